@@ -0,0 +1,33 @@
+-- =========================================================================
+-- MERCASTATS - Borradores de tickets (ingesta en dos fases)
+-- =========================================================================
+-- El OCR se guarda como borrador junto al archivo original. El usuario lo
+-- revisa y lo confirma después, sin volver a subir el archivo.
+-- =========================================================================
+
+CREATE TABLE tickets_borrador (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    usuario_email VARCHAR(255) NOT NULL,
+    resultado_ocr JSONB NOT NULL,
+    archivo BYTEA NOT NULL,
+    nombre_archivo VARCHAR(255) NOT NULL,
+    mime_type VARCHAR(100),
+    tamano_bytes INTEGER NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL,
+    expira_en TIMESTAMP NOT NULL,
+
+    -- Foreign Keys
+    CONSTRAINT fk_tickets_borrador_usuario FOREIGN KEY (usuario_email)
+        REFERENCES usuarios(email) ON DELETE CASCADE ON UPDATE CASCADE,
+
+    -- Constraints
+    CONSTRAINT borrador_tamano_valido CHECK (tamano_bytes > 0 AND tamano_bytes <= 10485760),
+    CONSTRAINT borrador_expiracion_valida CHECK (expira_en > created_at)
+);
+
+CREATE INDEX idx_tickets_borrador_usuario ON tickets_borrador(usuario_email, created_at DESC);
+CREATE INDEX idx_tickets_borrador_expira ON tickets_borrador(expira_en);
+
+COMMENT ON TABLE tickets_borrador IS 'Resultados de OCR pendientes de confirmación por el usuario';
+COMMENT ON COLUMN tickets_borrador.resultado_ocr IS 'Respuesta completa del servicio de OCR';
+COMMENT ON COLUMN tickets_borrador.expira_en IS 'Pasada esta fecha el borrador deja de poder confirmarse';
@@ -0,0 +1,41 @@
+-- =========================================================================
+-- MERCASTATS - Adjuntos de tickets (PDF e imágenes)
+-- =========================================================================
+-- Generaliza tickets_pdf para guardar también fotos de tickets. El tipo de
+-- archivo se registra como MIME y el límite de tamaño depende de él.
+-- =========================================================================
+
+ALTER TABLE tickets_pdf RENAME TO tickets_adjuntos;
+ALTER TABLE tickets_adjuntos RENAME COLUMN ticket_pdf TO contenido;
+ALTER TABLE tickets_adjuntos RENAME COLUMN ticket_nombre_archivo TO nombre_archivo;
+ALTER TABLE tickets_adjuntos RENAME COLUMN ticket_tamano_bytes TO tamano_bytes;
+
+-- Los archivos existentes son todos PDF
+ALTER TABLE tickets_adjuntos ADD COLUMN mime_type VARCHAR(100) NOT NULL DEFAULT 'application/pdf';
+ALTER TABLE tickets_adjuntos ALTER COLUMN mime_type DROP DEFAULT;
+
+ALTER TABLE tickets_adjuntos DROP CONSTRAINT extension_pdf;
+ALTER TABLE tickets_adjuntos DROP CONSTRAINT tamano_maximo;
+
+ALTER TABLE tickets_adjuntos ADD CONSTRAINT mime_type_soportado CHECK (
+    mime_type IN ('application/pdf', 'image/jpeg', 'image/png', 'image/webp', 'image/heic')
+);
+
+-- 10 MB para PDF, 20 MB para fotos
+ALTER TABLE tickets_adjuntos ADD CONSTRAINT tamano_maximo CHECK (
+    tamano_bytes <= CASE WHEN mime_type = 'application/pdf' THEN 10485760 ELSE 20971520 END
+);
+
+ALTER INDEX idx_tickets_tamano RENAME TO idx_tickets_adjuntos_tamano;
+ALTER INDEX idx_tickets_created_at RENAME TO idx_tickets_adjuntos_created_at;
+
+-- Los borradores admiten los mismos formatos
+ALTER TABLE tickets_borrador DROP CONSTRAINT borrador_tamano_valido;
+ALTER TABLE tickets_borrador ADD CONSTRAINT borrador_tamano_valido CHECK (
+    tamano_bytes > 0 AND tamano_bytes <= 20971520
+);
+
+COMMENT ON TABLE tickets_adjuntos IS 'Archivo original de cada ticket (PDF o imagen)';
+COMMENT ON COLUMN tickets_adjuntos.contenido IS 'Contenido binario del archivo del ticket';
+COMMENT ON COLUMN tickets_adjuntos.mime_type IS 'Tipo MIME canónico del archivo';
+COMMENT ON COLUMN tickets_adjuntos.tamano_bytes IS 'Tamaño del archivo en bytes (10MB PDF, 20MB imagen)';
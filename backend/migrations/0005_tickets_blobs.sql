@@ -0,0 +1,41 @@
+-- =========================================================================
+-- MERCASTATS - Almacenamiento intercambiable de archivos de tickets
+-- =========================================================================
+-- tickets_adjuntos pasa a guardar solo metadatos: en qué backend está el
+-- archivo y con qué clave. El backend Postgres guarda el contenido en
+-- tickets_blobs, fuera de la cascada de compras.
+-- =========================================================================
+
+CREATE TABLE tickets_blobs (
+    clave VARCHAR(255) PRIMARY KEY,
+    contenido BYTEA NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP NOT NULL
+);
+
+COMMENT ON TABLE tickets_blobs IS 'Contenido de los archivos de tickets con almacenamiento postgres';
+
+ALTER TABLE tickets_adjuntos ADD COLUMN almacenamiento VARCHAR(20) NOT NULL DEFAULT 'postgres';
+ALTER TABLE tickets_adjuntos ADD COLUMN clave_almacenamiento VARCHAR(255);
+
+-- Mismo esquema que new_storage_key: el número saneado solo agrupa, la
+-- unicidad la da el UUID (A/1 y A 1 se sanean igual)
+UPDATE tickets_adjuntos
+SET clave_almacenamiento =
+    'tickets/' || regexp_replace(numero_factura, '[^A-Za-z0-9_-]', '_', 'g') || '/' || gen_random_uuid();
+
+INSERT INTO tickets_blobs (clave, contenido, created_at)
+SELECT clave_almacenamiento, contenido, created_at
+FROM tickets_adjuntos;
+
+ALTER TABLE tickets_adjuntos ALTER COLUMN clave_almacenamiento SET NOT NULL;
+ALTER TABLE tickets_adjuntos ALTER COLUMN almacenamiento DROP DEFAULT;
+ALTER TABLE tickets_adjuntos DROP COLUMN contenido;
+
+ALTER TABLE tickets_adjuntos ADD CONSTRAINT almacenamiento_valido CHECK (
+    almacenamiento IN ('postgres', 'filesystem', 's3')
+);
+
+CREATE INDEX idx_tickets_adjuntos_almacenamiento ON tickets_adjuntos(almacenamiento);
+
+COMMENT ON COLUMN tickets_adjuntos.almacenamiento IS 'Backend que guarda el archivo (postgres, filesystem, s3)';
+COMMENT ON COLUMN tickets_adjuntos.clave_almacenamiento IS 'Clave del archivo dentro del backend';
@@ -0,0 +1,46 @@
+-- =========================================================================
+-- MERCASTATS - Cola de trabajos de OCR e ingesta asíncrona
+-- =========================================================================
+-- La petición HTTP solo encola el archivo. Un worker del backend lo procesa
+-- con el OCR, lo ingesta y deja aquí el estado para que el cliente lo
+-- consulte.
+-- =========================================================================
+
+CREATE TABLE trabajos_ocr (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    usuario_email VARCHAR(255) NOT NULL,
+    estado VARCHAR(20) NOT NULL DEFAULT 'queued',
+    archivo BYTEA,
+    nombre_archivo VARCHAR(255) NOT NULL,
+    mime_type VARCHAR(100),
+    tamano_bytes INTEGER NOT NULL,
+    reemplazar BOOLEAN NOT NULL DEFAULT FALSE,
+    intentos INTEGER NOT NULL DEFAULT 0,
+    siguiente_intento TIMESTAMP NOT NULL DEFAULT (NOW() AT TIME ZONE 'UTC'),
+    error_codigo VARCHAR(50),
+    error_mensaje TEXT,
+    resultado JSONB,
+    created_at TIMESTAMP DEFAULT (NOW() AT TIME ZONE 'UTC') NOT NULL,
+    updated_at TIMESTAMP DEFAULT (NOW() AT TIME ZONE 'UTC') NOT NULL,
+
+    -- Foreign Keys
+    CONSTRAINT fk_trabajos_ocr_usuario FOREIGN KEY (usuario_email)
+        REFERENCES usuarios(email) ON DELETE CASCADE ON UPDATE CASCADE,
+
+    -- Constraints
+    CONSTRAINT trabajo_estado_valido CHECK (
+        estado IN ('queued', 'ocr', 'validating', 'ingested', 'failed')
+    ),
+    CONSTRAINT trabajo_tamano_valido CHECK (tamano_bytes > 0),
+    CONSTRAINT trabajo_intentos_validos CHECK (intentos >= 0)
+);
+
+CREATE INDEX idx_trabajos_ocr_pendientes ON trabajos_ocr(siguiente_intento)
+    WHERE estado = 'queued';
+CREATE INDEX idx_trabajos_ocr_usuario ON trabajos_ocr(usuario_email, created_at DESC);
+
+COMMENT ON TABLE trabajos_ocr IS 'Trabajos de OCR e ingesta procesados en segundo plano';
+COMMENT ON COLUMN trabajos_ocr.archivo IS 'Archivo original; se borra al terminar el trabajo';
+COMMENT ON COLUMN trabajos_ocr.siguiente_intento IS 'Momento (UTC) a partir del cual el worker puede tomar el trabajo';
+COMMENT ON COLUMN trabajos_ocr.error_codigo IS 'Código del AppError que hizo fallar el trabajo';
+COMMENT ON COLUMN trabajos_ocr.resultado IS 'Resultado de la ingesta cuando el trabajo termina bien';
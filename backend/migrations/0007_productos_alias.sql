@@ -0,0 +1,26 @@
+-- =========================================================================
+-- MERCASTATS - Alias de productos
+-- =========================================================================
+-- Los tickets abrevian el mismo producto de formas distintas ("LECHE SEMI"
+-- y "LECHE SEMIDESNATADA"). Cada alias apunta al producto canónico y la
+-- ingesta lo resuelve antes de guardar las líneas. Al fusionar un producto
+-- en otro, su nombre queda registrado como alias del destino.
+-- =========================================================================
+
+CREATE TABLE productos_alias (
+    alias VARCHAR(255) PRIMARY KEY,
+    producto_nombre VARCHAR(255) NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT (NOW() AT TIME ZONE 'UTC'),
+
+    CONSTRAINT fk_productos_alias_producto
+        FOREIGN KEY (producto_nombre)
+        REFERENCES productos(nombre)
+        ON DELETE CASCADE
+        ON UPDATE CASCADE,
+
+    CONSTRAINT alias_distinto_producto CHECK (alias <> producto_nombre)
+);
+
+CREATE INDEX idx_productos_alias_producto ON productos_alias(producto_nombre);
+
+COMMENT ON TABLE productos_alias IS 'Nombres alternativos de un producto detectados en los tickets';
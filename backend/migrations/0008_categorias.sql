@@ -0,0 +1,63 @@
+-- =========================================================================
+-- MERCASTATS - Categorías de productos
+-- =========================================================================
+-- Los tickets de Mercadona no traen categoría. El backend asigna una a cada
+-- producto con las reglas de palabras clave de `data/category_rules.json`
+-- y cada usuario puede sobrescribirla para sus propias estadísticas.
+-- =========================================================================
+
+CREATE TABLE categorias (
+    id SERIAL PRIMARY KEY,
+    nombre VARCHAR(100) NOT NULL UNIQUE,
+    created_at TIMESTAMP NOT NULL DEFAULT (NOW() AT TIME ZONE 'UTC')
+);
+
+-- Categoría asignada automáticamente a cada producto del catálogo
+CREATE TABLE productos_categorias (
+    producto_nombre VARCHAR(255) PRIMARY KEY,
+    categoria_id INTEGER NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT (NOW() AT TIME ZONE 'UTC'),
+
+    CONSTRAINT fk_productos_categorias_producto
+        FOREIGN KEY (producto_nombre)
+        REFERENCES productos(nombre)
+        ON DELETE CASCADE
+        ON UPDATE CASCADE,
+
+    CONSTRAINT fk_productos_categorias_categoria
+        FOREIGN KEY (categoria_id)
+        REFERENCES categorias(id)
+        ON DELETE CASCADE
+);
+
+CREATE INDEX idx_productos_categorias_categoria ON productos_categorias(categoria_id);
+
+-- Categoría elegida por un usuario para un producto
+CREATE TABLE usuarios_productos_categorias (
+    usuario_email VARCHAR(255) NOT NULL,
+    producto_nombre VARCHAR(255) NOT NULL,
+    categoria_id INTEGER NOT NULL,
+    updated_at TIMESTAMP NOT NULL DEFAULT (NOW() AT TIME ZONE 'UTC'),
+
+    PRIMARY KEY (usuario_email, producto_nombre),
+
+    CONSTRAINT fk_usuarios_productos_categorias_usuario
+        FOREIGN KEY (usuario_email)
+        REFERENCES usuarios(email)
+        ON DELETE CASCADE
+        ON UPDATE CASCADE,
+
+    CONSTRAINT fk_usuarios_productos_categorias_producto
+        FOREIGN KEY (producto_nombre)
+        REFERENCES productos(nombre)
+        ON DELETE CASCADE
+        ON UPDATE CASCADE,
+
+    CONSTRAINT fk_usuarios_productos_categorias_categoria
+        FOREIGN KEY (categoria_id)
+        REFERENCES categorias(id)
+        ON DELETE CASCADE
+);
+
+COMMENT ON TABLE productos_categorias IS 'Categoría asignada por las reglas de palabras clave';
+COMMENT ON TABLE usuarios_productos_categorias IS 'Categoría elegida por el usuario; prevalece sobre la automática';
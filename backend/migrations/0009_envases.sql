@@ -0,0 +1,51 @@
+-- =========================================================================
+-- MERCASTATS - Tamaño de envase de los productos
+-- =========================================================================
+-- Los nombres de Mercadona incluyen el tamaño del envase ("AGUA 1,5L",
+-- "YOGUR 6X125G"). El backend lo extrae al guardar cada producto para poder
+-- comparar precios por kg o litro entre formatos distintos.
+-- =========================================================================
+
+CREATE TABLE productos_envases (
+    producto_nombre VARCHAR(255) PRIMARY KEY,
+    -- Nombre sin el tamaño: agrupa los formatos de un mismo producto
+    nombre_base VARCHAR(255) NOT NULL,
+    piezas INTEGER NOT NULL DEFAULT 1,
+    -- Contenido total del envase, en kg o litros
+    contenido NUMERIC(10, 3) NOT NULL,
+    unidad VARCHAR(2) NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT (NOW() AT TIME ZONE 'UTC'),
+
+    CONSTRAINT fk_productos_envases_producto
+        FOREIGN KEY (producto_nombre)
+        REFERENCES productos(nombre)
+        ON DELETE CASCADE
+        ON UPDATE CASCADE,
+
+    CONSTRAINT envase_piezas_positivas CHECK (piezas > 0),
+    CONSTRAINT envase_contenido_positivo CHECK (contenido > 0),
+    CONSTRAINT envase_unidad_valida CHECK (unidad IN ('kg', 'l'))
+);
+
+CREATE INDEX idx_productos_envases_nombre_base ON productos_envases(nombre_base);
+
+-- Precio actual de cada producto por kg o litro
+--   - Productos envasados: precio del envase entre su contenido
+--   - Productos a peso o volumen: el precio ya es por kg o litro
+CREATE OR REPLACE VIEW productos_precio_base AS
+SELECT
+    p.nombre,
+    COALESCE(e.nombre_base, p.nombre) AS nombre_base,
+    e.piezas,
+    e.contenido,
+    COALESCE(e.unidad, CASE WHEN p.unidad IN ('kg', 'l') THEN p.unidad END) AS unidad_base,
+    p.precio_actual,
+    CASE
+        WHEN e.contenido > 0 THEN ROUND(p.precio_actual / e.contenido, 2)
+        WHEN p.unidad IN ('kg', 'l') THEN p.precio_actual
+    END AS precio_por_unidad_base
+FROM productos p
+LEFT JOIN productos_envases e ON e.producto_nombre = p.nombre;
+
+COMMENT ON TABLE productos_envases IS 'Tamaño de envase extraído del nombre del producto';
+COMMENT ON VIEW productos_precio_base IS 'Precio actual normalizado por kg o litro';
@@ -0,0 +1,27 @@
+-- =========================================================================
+-- MERCASTATS - Alias de tarjetas bancarias
+-- =========================================================================
+-- Los tickets pagados con tarjeta guardan el número de operación impreso
+-- por el datáfono. Cada usuario puede asociar un alias ("Visa nómina") a un
+-- patrón de ese número para conciliar el gasto por tarjeta.
+-- =========================================================================
+
+CREATE TABLE tarjetas_usuario (
+    id SERIAL PRIMARY KEY,
+    usuario_email VARCHAR(255) NOT NULL,
+    alias VARCHAR(100) NOT NULL,
+    -- Letras y dígitos en mayúsculas; `*` equivale a cualquier secuencia
+    patron VARCHAR(50) NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT (NOW() AT TIME ZONE 'UTC'),
+
+    CONSTRAINT fk_tarjetas_usuario_usuario
+        FOREIGN KEY (usuario_email)
+        REFERENCES usuarios(email)
+        ON DELETE CASCADE
+        ON UPDATE CASCADE,
+
+    CONSTRAINT uq_tarjetas_usuario_patron UNIQUE (usuario_email, patron),
+    CONSTRAINT chk_tarjetas_usuario_patron CHECK (patron ~ '^[A-Z0-9*]+$')
+);
+
+COMMENT ON TABLE tarjetas_usuario IS 'Alias de tarjeta asociado a un patrón de numero_operacion';
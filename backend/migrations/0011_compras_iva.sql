@@ -0,0 +1,26 @@
+-- =========================================================================
+-- MERCASTATS - Desglose de IVA de los tickets
+-- =========================================================================
+-- El pie del ticket trae la base imponible y la cuota de cada tipo de IVA.
+-- Se guarda tal cual para los informes de IVA; las líneas de la compra
+-- siguen teniendo su propio `iva_importe`.
+-- =========================================================================
+
+CREATE TABLE compras_iva (
+    compra_numero_factura VARCHAR(50) NOT NULL,
+    iva_porcentaje NUMERIC(5, 2) NOT NULL,
+    base_imponible NUMERIC(10, 2) NOT NULL,
+    cuota NUMERIC(10, 2) NOT NULL,
+
+    PRIMARY KEY (compra_numero_factura, iva_porcentaje),
+
+    CONSTRAINT fk_compras_iva_compra
+        FOREIGN KEY (compra_numero_factura)
+        REFERENCES compras(numero_factura)
+        ON DELETE CASCADE
+        ON UPDATE CASCADE,
+
+    CONSTRAINT chk_compras_iva_importes CHECK (base_imponible >= 0 AND cuota >= 0)
+);
+
+COMMENT ON TABLE compras_iva IS 'Desglose de IVA impreso en el ticket (base y cuota por tipo)';
@@ -0,0 +1,59 @@
+-- =========================================================================
+-- MERCASTATS - Alertas de gasto e informes periódicos
+-- =========================================================================
+-- `preferencias_usuario.umbral_alerta_gasto` es el gasto mensual a partir del
+-- cual se avisa al usuario. Cada umbral superado en un mes genera una única
+-- alerta. Los informes resumen el gasto de cada periodo cerrado según
+-- `preferencias_usuario.frecuencia_reportes`.
+-- =========================================================================
+
+CREATE TABLE alertas_gasto (
+    id SERIAL PRIMARY KEY,
+    usuario_email VARCHAR(255) NOT NULL,
+    -- Primer día del mes en el que se superó el umbral
+    mes DATE NOT NULL,
+    umbral NUMERIC(10, 2) NOT NULL,
+    -- Gasto del mes en el momento de superar el umbral
+    gasto NUMERIC(12, 2) NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT (NOW() AT TIME ZONE 'UTC'),
+
+    CONSTRAINT fk_alertas_gasto_usuario
+        FOREIGN KEY (usuario_email)
+        REFERENCES usuarios(email)
+        ON DELETE CASCADE
+        ON UPDATE CASCADE,
+
+    CONSTRAINT uq_alertas_gasto_mes_umbral UNIQUE (usuario_email, mes, umbral)
+);
+
+COMMENT ON TABLE alertas_gasto IS 'Avisos de umbral de gasto mensual superado';
+
+CREATE TABLE informes_usuario (
+    id SERIAL PRIMARY KEY,
+    usuario_email VARCHAR(255) NOT NULL,
+    frecuencia VARCHAR(20) NOT NULL,
+    -- Periodo cubierto, ambos extremos incluidos
+    desde DATE NOT NULL,
+    hasta DATE NOT NULL,
+    total NUMERIC(12, 2) NOT NULL,
+    compras INTEGER NOT NULL,
+    -- Gasto del periodo anterior de la misma duración
+    total_anterior NUMERIC(12, 2) NOT NULL,
+    generado_en TIMESTAMP NOT NULL DEFAULT (NOW() AT TIME ZONE 'UTC'),
+
+    CONSTRAINT fk_informes_usuario_usuario
+        FOREIGN KEY (usuario_email)
+        REFERENCES usuarios(email)
+        ON DELETE CASCADE
+        ON UPDATE CASCADE,
+
+    CONSTRAINT uq_informes_usuario_periodo UNIQUE (usuario_email, desde, hasta),
+    CONSTRAINT chk_informes_usuario_frecuencia CHECK (
+        frecuencia IN ('diaria', 'semanal', 'mensual')
+    ),
+    CONSTRAINT chk_informes_usuario_periodo CHECK (desde <= hasta)
+);
+
+CREATE INDEX idx_informes_usuario_email ON informes_usuario(usuario_email, hasta DESC);
+
+COMMENT ON TABLE informes_usuario IS 'Resúmenes de gasto generados según frecuencia_reportes';
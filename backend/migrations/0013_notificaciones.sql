@@ -0,0 +1,37 @@
+-- =========================================================================
+-- MERCASTATS - Notificaciones dentro de la aplicación
+-- =========================================================================
+-- Avisos que el backend deja al usuario: umbral de gasto superado, logro
+-- desbloqueado, subida de precio o borrador a punto de caducar. `clave`
+-- identifica el hecho notificado para no avisar dos veces de lo mismo.
+-- =========================================================================
+
+CREATE TABLE notificaciones (
+    id SERIAL PRIMARY KEY,
+    usuario_email VARCHAR(255) NOT NULL,
+    tipo VARCHAR(30) NOT NULL,
+    titulo VARCHAR(200) NOT NULL,
+    mensaje TEXT NOT NULL,
+    -- Datos estructurados del aviso (logro, productos, borrador...)
+    datos JSON,
+    clave VARCHAR(150),
+    leida BOOLEAN NOT NULL DEFAULT FALSE,
+    leida_en TIMESTAMP,
+    created_at TIMESTAMP NOT NULL DEFAULT (NOW() AT TIME ZONE 'UTC'),
+
+    CONSTRAINT fk_notificaciones_usuario
+        FOREIGN KEY (usuario_email)
+        REFERENCES usuarios(email)
+        ON DELETE CASCADE
+        ON UPDATE CASCADE,
+
+    CONSTRAINT uq_notificaciones_clave UNIQUE (usuario_email, clave),
+    CONSTRAINT chk_notificaciones_tipo CHECK (
+        tipo IN ('alerta_gasto', 'logro', 'subida_precio', 'borrador_caduca')
+    )
+);
+
+CREATE INDEX idx_notificaciones_usuario ON notificaciones(usuario_email, created_at DESC);
+CREATE INDEX idx_notificaciones_no_leidas ON notificaciones(usuario_email) WHERE NOT leida;
+
+COMMENT ON TABLE notificaciones IS 'Avisos del backend al usuario, con estado de lectura';
@@ -0,0 +1,15 @@
+-- =========================================================================
+-- MERCASTATS - Compra creada por cada trabajo de OCR
+-- =========================================================================
+-- La ingesta guarda aquí el número de factura en la misma transacción que
+-- inserta la compra. Si el worker se interrumpe antes de marcar el trabajo
+-- como terminado, al retomarlo sabe que la compra ya existe y no vuelve a
+-- ingestarla.
+-- =========================================================================
+
+ALTER TABLE trabajos_ocr ADD COLUMN numero_factura VARCHAR(50);
+
+CREATE INDEX idx_trabajos_ocr_en_curso ON trabajos_ocr(updated_at)
+    WHERE estado IN ('ocr', 'validating');
+
+COMMENT ON COLUMN trabajos_ocr.numero_factura IS 'Compra creada por el trabajo (se rellena al confirmar la ingesta)';
@@ -0,0 +1,15 @@
+-- =========================================================================
+-- MERCASTATS - Cuota de IVA de las líneas con precios IVA incluido
+-- =========================================================================
+-- Antes, cuando el OCR no traía la cuota de una línea, se calculaba como si
+-- el precio fuese la base imponible (precio * tipo / 100). Los precios del
+-- ticket ya incluyen el IVA, así que la cuota correcta es
+-- precio * tipo / (100 + tipo). Se recalculan las líneas que tienen
+-- exactamente la cuota de la fórmula antigua; las que trajo el OCR no se
+-- tocan.
+-- =========================================================================
+
+UPDATE compras_productos
+SET iva_importe = ROUND(precio_total * iva_porcentaje / (100 + iva_porcentaje), 2)
+WHERE iva_porcentaje > 0
+    AND iva_importe = ROUND(precio_total * iva_porcentaje / 100, 2);
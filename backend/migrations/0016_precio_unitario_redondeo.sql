@@ -0,0 +1,30 @@
+-- =========================================================================
+-- MERCASTATS - Redondeo del precio unitario sin descuentos inventados
+-- =========================================================================
+-- Al sumar líneas del mismo producto o recalcular el precio por kg, el
+-- precio unitario al céntimo no siempre cuadra con el total (3,04 € entre
+-- 3 unidades). Hasta ahora se redondeaba hacia arriba y la diferencia se
+-- guardaba como descuento, un descuento que el ticket no tenía y que
+-- acababa en las estadísticas de precio medio.
+--
+-- Ahora el CHECK tolera el redondeo del precio unitario (medio céntimo por
+-- unidad) y las líneas con un descuento de ese tipo vuelven al precio
+-- redondeado sin descuento.
+-- =========================================================================
+
+ALTER TABLE compras_productos DROP CONSTRAINT precio_coherente;
+
+-- Líneas con el precio redondeado hacia arriba y un descuento menor que un
+-- céntimo por unidad: es el resto del redondeo, no un descuento del ticket
+UPDATE compras_productos
+SET precio_unitario = ROUND(precio_total / cantidad, 2),
+    descuento = 0
+WHERE descuento > 0
+    AND descuento < 0.01 * cantidad
+    AND precio_unitario = CEIL(precio_total * 100 / cantidad) / 100
+    AND precio_unitario <> ROUND(precio_total / cantidad, 2);
+
+ALTER TABLE compras_productos ADD CONSTRAINT precio_coherente CHECK (
+    abs(precio_total - (precio_unitario * cantidad - descuento)) < 0.01
+    OR abs(precio_total - (precio_unitario * cantidad - descuento)) <= 0.005 * cantidad
+);
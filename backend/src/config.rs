@@ -1,3 +1,53 @@
+use std::path::PathBuf;
+
+/// Backend donde se guardan los archivos originales de los tickets
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StorageBackend {
+    Postgres,
+    Filesystem,
+    S3,
+}
+
+impl StorageBackend {
+    /// Nombre del backend tal y como se guarda en `tickets_adjuntos.almacenamiento`
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Postgres => "postgres",
+            Self::Filesystem => "filesystem",
+            Self::S3 => "s3",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "postgres" => Some(Self::Postgres),
+            "filesystem" | "fs" => Some(Self::Filesystem),
+            "s3" => Some(Self::S3),
+            _ => None,
+        }
+    }
+}
+
+/// Conexión con un almacenamiento compatible con S3 (AWS, MinIO...)
+#[derive(Debug, Clone)]
+pub struct S3StorageConfig {
+    pub endpoint: String,
+    pub bucket: String,
+    pub region: String,
+    pub access_key: String,
+    pub secret_key: String,
+    /// URLs `endpoint/bucket/clave` en lugar de `bucket.endpoint/clave` (MinIO)
+    pub path_style: bool,
+}
+
+/// Configuración del almacenamiento de archivos de tickets
+#[derive(Debug, Clone)]
+pub struct TicketStorageConfig {
+    pub backend: StorageBackend,
+    pub filesystem_path: PathBuf,
+    pub s3: Option<S3StorageConfig>,
+}
+
 /// Configuración de la aplicación cargada desde variables de entorno
 #[derive(Debug, Clone)]
 pub struct AppConfig {
@@ -10,7 +60,37 @@ pub struct AppConfig {
     pub intelligence_timeout_secs: u64,
     pub intelligence_max_retries: u32,
     pub demo_user_email: Option<String>,
+    /// Cuentas que pueden modificar el catálogo compartido (p. ej. fusionar productos)
+    pub operator_emails: Vec<String>,
     pub cors_origins: Vec<String>,
+    /// Horas que un borrador de ticket puede confirmarse tras el OCR
+    pub ticket_draft_ttl_hours: i64,
+    pub ticket_storage: TicketStorageConfig,
+    /// Llamadas simultáneas al OCR durante una importación masiva
+    pub import_ocr_concurrency: usize,
+    /// Número máximo de archivos por importación masiva
+    pub import_max_files: usize,
+    /// Tamaño máximo de la petición de importación masiva, y del total de
+    /// tickets una vez descomprimidos los ZIP, en bytes
+    pub import_max_bytes: usize,
+    /// Intentos máximos de un trabajo de OCR ante fallos transitorios
+    pub ocr_job_max_attempts: i32,
+    /// Espera base entre reintentos de un trabajo de OCR (se duplica en cada intento)
+    pub ocr_job_retry_base_secs: i64,
+    /// Intervalo con el que el worker busca trabajos pendientes
+    pub ocr_job_poll_secs: u64,
+    /// Segundos sin avanzar tras los que un trabajo de OCR en curso vuelve a la cola
+    pub ocr_job_lease_secs: i64,
+    /// Inflación oficial anual (%) con la que se compara la inflación personal
+    pub reference_inflation_rate: f64,
+    /// Intervalo con el que se cierran los objetivos de ahorro de meses pasados
+    pub savings_goal_close_secs: u64,
+    /// Intervalo con el que se generan los informes periódicos pendientes
+    pub report_scheduler_secs: u64,
+    /// Intervalo con el que se buscan borradores a punto de caducar
+    pub draft_expiry_check_secs: u64,
+    /// Horas de antelación con las que se avisa de que un borrador caduca
+    pub draft_expiry_notice_hours: i64,
 }
 
 impl AppConfig {
@@ -56,6 +136,96 @@ impl AppConfig {
             .map(str::to_string)
             .collect::<Vec<_>>();
 
+        let operator_emails = std::env::var("OPERATOR_EMAILS")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|email| !email.is_empty())
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+
+        let ticket_draft_ttl_hours = std::env::var("TICKET_DRAFT_TTL_HOURS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .filter(|hours| *hours > 0)
+            .unwrap_or(24);
+
+        let ticket_storage = TicketStorageConfig::from_env()?;
+
+        let import_ocr_concurrency = std::env::var("IMPORT_OCR_CONCURRENCY")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .filter(|n| *n > 0)
+            .unwrap_or(3);
+
+        let import_max_files = std::env::var("IMPORT_MAX_FILES")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .filter(|n| *n > 0)
+            .unwrap_or(500);
+
+        let import_max_bytes = std::env::var("IMPORT_MAX_MB")
+            .ok()
+            .and_then(|v| v.parse::<usize>().ok())
+            .filter(|n| *n > 0)
+            .unwrap_or(200)
+            * 1024
+            * 1024;
+
+        let ocr_job_max_attempts = std::env::var("OCR_JOB_MAX_ATTEMPTS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .filter(|n| *n > 0)
+            .unwrap_or(5);
+
+        let ocr_job_retry_base_secs = std::env::var("OCR_JOB_RETRY_BASE_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .filter(|n| *n > 0)
+            .unwrap_or(10);
+
+        let ocr_job_poll_secs = std::env::var("OCR_JOB_POLL_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .filter(|n| *n > 0)
+            .unwrap_or(2);
+
+        let ocr_job_lease_secs = std::env::var("OCR_JOB_LEASE_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .filter(|n| *n > 0)
+            .unwrap_or(600);
+
+        let reference_inflation_rate = std::env::var("INFLATION_REFERENCE_RATE")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .filter(|rate: &f64| rate.is_finite())
+            .unwrap_or(3.0);
+
+        let savings_goal_close_secs = std::env::var("SAVINGS_GOAL_CLOSE_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .filter(|n| *n > 0)
+            .unwrap_or(3600);
+
+        let report_scheduler_secs = std::env::var("REPORT_SCHEDULER_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .filter(|n| *n > 0)
+            .unwrap_or(3600);
+
+        let draft_expiry_check_secs = std::env::var("DRAFT_EXPIRY_CHECK_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .filter(|n| *n > 0)
+            .unwrap_or(600);
+
+        let draft_expiry_notice_hours = std::env::var("DRAFT_EXPIRY_NOTICE_HOURS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .filter(|n| *n > 0)
+            .unwrap_or(6);
+
         if cors_origins.is_empty() {
             return Err("CORS_ORIGINS no contiene ningún origen válido".to_string());
         }
@@ -72,7 +242,22 @@ impl AppConfig {
             demo_user_email: std::env::var("DEMO_USER_EMAIL")
                 .ok()
                 .filter(|v| !v.is_empty()),
+            operator_emails,
             cors_origins,
+            ticket_draft_ttl_hours,
+            ticket_storage,
+            import_ocr_concurrency,
+            import_max_files,
+            import_max_bytes,
+            ocr_job_max_attempts,
+            ocr_job_retry_base_secs,
+            ocr_job_poll_secs,
+            ocr_job_lease_secs,
+            reference_inflation_rate,
+            savings_goal_close_secs,
+            report_scheduler_secs,
+            draft_expiry_check_secs,
+            draft_expiry_notice_hours,
         })
     }
 
@@ -81,3 +266,48 @@ impl AppConfig {
         format!("{}:{}", self.host, self.port)
     }
 }
+
+impl TicketStorageConfig {
+    /// Carga la configuración del almacenamiento de tickets
+    pub fn from_env() -> Result<Self, String> {
+        let backend = match std::env::var("TICKET_STORAGE") {
+            Ok(value) if !value.trim().is_empty() => StorageBackend::parse(&value)
+                .ok_or_else(|| format!("TICKET_STORAGE no soportado: {}", value))?,
+            _ => StorageBackend::Postgres,
+        };
+
+        let filesystem_path = std::env::var("TICKET_STORAGE_PATH")
+            .ok()
+            .filter(|v| !v.is_empty())
+            .unwrap_or_else(|| "./data/tickets".to_string())
+            .into();
+
+        let s3 = match (std::env::var("S3_ENDPOINT"), std::env::var("S3_BUCKET")) {
+            (Ok(endpoint), Ok(bucket)) if !endpoint.is_empty() && !bucket.is_empty() => {
+                Some(S3StorageConfig {
+                    endpoint,
+                    bucket,
+                    region: std::env::var("S3_REGION").unwrap_or_else(|_| "us-east-1".to_string()),
+                    access_key: std::env::var("S3_ACCESS_KEY")
+                        .map_err(|_| "S3_ACCESS_KEY no configurada".to_string())?,
+                    secret_key: std::env::var("S3_SECRET_KEY")
+                        .map_err(|_| "S3_SECRET_KEY no configurada".to_string())?,
+                    path_style: std::env::var("S3_PATH_STYLE")
+                        .map(|v| v != "false")
+                        .unwrap_or(true),
+                })
+            }
+            _ => None,
+        };
+
+        if backend == StorageBackend::S3 && s3.is_none() {
+            return Err("TICKET_STORAGE=s3 requiere S3_ENDPOINT y S3_BUCKET".to_string());
+        }
+
+        Ok(Self {
+            backend,
+            filesystem_path,
+            s3,
+        })
+    }
+}
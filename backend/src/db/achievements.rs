@@ -0,0 +1,179 @@
+use crate::models::{Achievement, AchievementUnlock};
+use chrono::NaiveDate;
+use sqlx::{types::Json, PgPool};
+
+/// Contadores de actividad con los que se evalúan los logros
+#[derive(Debug, Clone, Default, PartialEq, sqlx::FromRow)]
+pub struct AchievementCounters {
+    pub compras: i64,
+    pub objetivos_conseguidos: i64,
+}
+
+/// Catálogo completo de logros
+pub async fn list_achievements(pool: &PgPool) -> Result<Vec<Achievement>, sqlx::Error> {
+    sqlx::query_as!(
+        Achievement,
+        "SELECT id, codigo, nombre, descripcion, icono FROM logros ORDER BY id"
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Logros que el usuario ya ha desbloqueado
+pub async fn get_user_achievement_unlocks(
+    pool: &PgPool,
+    usuario_email: &str,
+) -> Result<Vec<AchievementUnlock>, sqlx::Error> {
+    sqlx::query_as!(
+        AchievementUnlock,
+        r#"
+        SELECT
+            logro_id,
+            desbloqueado_en,
+            metadata as "metadata: Json<serde_json::Value>"
+        FROM logros_usuario
+        WHERE usuario_email = $1
+        "#,
+        usuario_email
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Compras registradas y objetivos de ahorro conseguidos por el usuario
+pub async fn get_achievement_counters(
+    pool: &PgPool,
+    usuario_email: &str,
+) -> Result<AchievementCounters, sqlx::Error> {
+    sqlx::query_as!(
+        AchievementCounters,
+        r#"
+        SELECT
+            (SELECT COUNT(*) FROM compras WHERE usuario_email = $1) as "compras!",
+            (
+                SELECT COUNT(*) FROM objetivos_ahorro
+                WHERE usuario_email = $1 AND conseguido = TRUE
+            ) as "objetivos_conseguidos!"
+        "#,
+        usuario_email
+    )
+    .fetch_one(pool)
+    .await
+}
+
+/// Días distintos en los que el usuario tiene compras, en orden
+pub async fn get_purchase_dates(
+    pool: &PgPool,
+    usuario_email: &str,
+) -> Result<Vec<NaiveDate>, sqlx::Error> {
+    sqlx::query_scalar!(
+        r#"
+        SELECT DISTINCT fecha_hora::date as "fecha!"
+        FROM compras
+        WHERE usuario_email = $1
+        ORDER BY 1
+        "#,
+        usuario_email
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Registra un logro desbloqueado
+///
+/// Devuelve `false` si el usuario ya lo tenía.
+pub async fn insert_user_achievement(
+    pool: &PgPool,
+    usuario_email: &str,
+    logro_id: i32,
+    metadata: &serde_json::Value,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+        INSERT INTO logros_usuario (usuario_email, logro_id, metadata)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (usuario_email, logro_id) DO NOTHING
+        "#,
+        usuario_email,
+        logro_id,
+        Json(metadata) as _
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::insert_purchase;
+    use crate::models::PurchaseInsert;
+    use rust_decimal::Decimal;
+
+    #[sqlx::test(migrations = "./migrations")]
+    async fn test_user_achievements(pool: PgPool) -> sqlx::Result<()> {
+        sqlx::query!(
+            "INSERT INTO usuarios (email, password_hash, nombre) VALUES ($1, $2, $3)",
+            "logros@example.com",
+            "$2b$12$KpIEW.jQKvqXfN5nDwAXLub8RCRYjqNvCLKXfzHpFGK2FQJGmqQJi",
+            "Logros User"
+        )
+        .execute(&pool)
+        .await?;
+
+        let dia = |d: u32| NaiveDate::from_ymd_opt(2025, 3, d).unwrap();
+        for (factura, fecha, hora) in [
+            ("F-1", dia(1), 10),
+            ("F-2", dia(1), 19),
+            ("F-3", dia(3), 12),
+        ] {
+            insert_purchase(
+                &pool,
+                &PurchaseInsert {
+                    numero_factura: factura.to_string(),
+                    usuario_email: "logros@example.com".to_string(),
+                    fecha_hora: fecha.and_hms_opt(hora, 0, 0).unwrap(),
+                    total: Decimal::new(1000, 2),
+                    tienda: None,
+                    ubicacion: None,
+                    metodo_pago: None,
+                    numero_operacion: None,
+                },
+            )
+            .await?;
+        }
+
+        let counters = get_achievement_counters(&pool, "logros@example.com").await?;
+        assert_eq!(
+            counters,
+            AchievementCounters {
+                compras: 3,
+                objetivos_conseguidos: 0,
+            }
+        );
+        assert_eq!(
+            get_purchase_dates(&pool, "logros@example.com").await?,
+            vec![dia(1), dia(3)]
+        );
+
+        let catalogo = list_achievements(&pool).await?;
+        let primera = catalogo
+            .iter()
+            .find(|logro| logro.codigo == "PRIMERA_COMPRA")
+            .expect("logro sembrado");
+
+        let metadata = serde_json::json!({ "compras": 3 });
+        assert!(insert_user_achievement(&pool, "logros@example.com", primera.id, &metadata).await?);
+        assert!(
+            !insert_user_achievement(&pool, "logros@example.com", primera.id, &metadata).await?
+        );
+
+        let unlocks = get_user_achievement_unlocks(&pool, "logros@example.com").await?;
+        assert_eq!(unlocks.len(), 1);
+        assert_eq!(unlocks[0].logro_id, primera.id);
+        assert_eq!(unlocks[0].metadata.as_ref().map(|m| &m.0), Some(&metadata));
+
+        Ok(())
+    }
+}
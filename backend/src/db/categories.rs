@@ -0,0 +1,221 @@
+use crate::models::{Category, ProductCategory};
+use sqlx::{PgPool, Postgres};
+
+/// Crea las categorías que todavía no existen
+pub async fn insert_categories(pool: &PgPool, nombres: &[String]) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+        INSERT INTO categorias (nombre)
+        SELECT UNNEST($1::varchar[])
+        ON CONFLICT (nombre) DO NOTHING
+        "#,
+        nombres as _
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// Lista todas las categorías por orden alfabético
+pub async fn list_categories(pool: &PgPool) -> Result<Vec<Category>, sqlx::Error> {
+    sqlx::query_as!(
+        Category,
+        "SELECT id, nombre FROM categorias ORDER BY nombre"
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Productos sin categoría automática
+///
+/// Con `nombres` se limita a esos productos; sin él recorre todo el catálogo.
+pub async fn get_uncategorized_products<'c, E>(
+    executor: E,
+    nombres: Option<&[String]>,
+) -> Result<Vec<String>, sqlx::Error>
+where
+    E: sqlx::Executor<'c, Database = Postgres>,
+{
+    sqlx::query_scalar!(
+        r#"
+        SELECT p.nombre
+        FROM productos p
+        LEFT JOIN productos_categorias pc ON pc.producto_nombre = p.nombre
+        WHERE pc.producto_nombre IS NULL
+            AND ($1::text[] IS NULL OR p.nombre = ANY($1))
+        ORDER BY p.nombre
+        "#,
+        nombres as _
+    )
+    .fetch_all(executor)
+    .await
+}
+
+/// Asigna la categoría automática de un producto si aún no tiene
+///
+/// Devuelve `false` si el producto ya tenía categoría o la categoría no existe.
+pub async fn assign_product_category<'c, E>(
+    executor: E,
+    producto_nombre: &str,
+    categoria: &str,
+) -> Result<bool, sqlx::Error>
+where
+    E: sqlx::Executor<'c, Database = Postgres>,
+{
+    let result = sqlx::query!(
+        r#"
+        INSERT INTO productos_categorias (producto_nombre, categoria_id)
+        SELECT $1, id FROM categorias WHERE nombre = $2
+        ON CONFLICT (producto_nombre) DO NOTHING
+        "#,
+        producto_nombre,
+        categoria
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Guarda la categoría elegida por un usuario para un producto
+///
+/// Devuelve `false` si la categoría no existe.
+pub async fn set_user_product_category(
+    pool: &PgPool,
+    usuario_email: &str,
+    producto_nombre: &str,
+    categoria: &str,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+        INSERT INTO usuarios_productos_categorias (usuario_email, producto_nombre, categoria_id)
+        SELECT $1, $2, id FROM categorias WHERE nombre = $3
+        ON CONFLICT (usuario_email, producto_nombre)
+        DO UPDATE SET
+            categoria_id = EXCLUDED.categoria_id,
+            updated_at = NOW() AT TIME ZONE 'UTC'
+        "#,
+        usuario_email,
+        producto_nombre,
+        categoria
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Elimina la categoría elegida por el usuario y vuelve a la automática
+pub async fn delete_user_product_category(
+    pool: &PgPool,
+    usuario_email: &str,
+    producto_nombre: &str,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+        DELETE FROM usuarios_productos_categorias
+        WHERE usuario_email = $1 AND producto_nombre = $2
+        "#,
+        usuario_email,
+        producto_nombre
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Categoría efectiva de un producto: la del usuario o, si no hay, la automática
+pub async fn get_product_category(
+    pool: &PgPool,
+    usuario_email: &str,
+    producto_nombre: &str,
+) -> Result<ProductCategory, sqlx::Error> {
+    sqlx::query_as!(
+        ProductCategory,
+        r#"
+        SELECT
+            p.nombre as producto,
+            cat.nombre as "categoria?",
+            (upc.categoria_id IS NOT NULL) as "personalizada!"
+        FROM productos p
+        LEFT JOIN usuarios_productos_categorias upc
+            ON upc.producto_nombre = p.nombre AND upc.usuario_email = $1
+        LEFT JOIN productos_categorias pc ON pc.producto_nombre = p.nombre
+        LEFT JOIN categorias cat ON cat.id = COALESCE(upc.categoria_id, pc.categoria_id)
+        WHERE p.nombre = $2
+        "#,
+        usuario_email,
+        producto_nombre
+    )
+    .fetch_one(pool)
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::upsert_product;
+    use crate::models::ProductUpsert;
+
+    #[sqlx::test(migrations = "./migrations")]
+    async fn test_product_categories(pool: PgPool) -> sqlx::Result<()> {
+        sqlx::query!(
+            "INSERT INTO usuarios (email, password_hash, nombre) VALUES ($1, $2, $3)",
+            "test@example.com",
+            "$2b$12$KpIEW.jQKvqXfN5nDwAXLub8RCRYjqNvCLKXfzHpFGK2FQJGmqQJi",
+            "Test User"
+        )
+        .execute(&pool)
+        .await?;
+
+        let categorias = vec!["Lácteos".to_string(), "Bebidas".to_string()];
+        assert_eq!(insert_categories(&pool, &categorias).await?, 2);
+        assert_eq!(insert_categories(&pool, &categorias).await?, 0);
+
+        upsert_product(
+            &pool,
+            &ProductUpsert {
+                nombre: "BATIDO CACAO".to_string(),
+                marca: None,
+                unidad: "unidad".to_string(),
+                precio_actual: None,
+            },
+        )
+        .await?;
+
+        let pendientes = get_uncategorized_products(&pool, None).await?;
+        assert_eq!(pendientes, vec!["BATIDO CACAO".to_string()]);
+
+        assert!(assign_product_category(&pool, "BATIDO CACAO", "Lácteos").await?);
+        // La categoría automática no se sobrescribe
+        assert!(!assign_product_category(&pool, "BATIDO CACAO", "Bebidas").await?);
+        assert!(get_uncategorized_products(&pool, None).await?.is_empty());
+
+        let category = get_product_category(&pool, "test@example.com", "BATIDO CACAO").await?;
+        assert_eq!(category.categoria.as_deref(), Some("Lácteos"));
+        assert!(!category.personalizada);
+
+        // La elección del usuario prevalece
+        assert!(
+            !set_user_product_category(&pool, "test@example.com", "BATIDO CACAO", "Otra").await?
+        );
+        assert!(
+            set_user_product_category(&pool, "test@example.com", "BATIDO CACAO", "Bebidas").await?
+        );
+        let category = get_product_category(&pool, "test@example.com", "BATIDO CACAO").await?;
+        assert_eq!(category.categoria.as_deref(), Some("Bebidas"));
+        assert!(category.personalizada);
+
+        // Otros usuarios siguen viendo la automática
+        let other = get_product_category(&pool, "other@example.com", "BATIDO CACAO").await?;
+        assert_eq!(other.categoria.as_deref(), Some("Lácteos"));
+
+        assert!(delete_user_product_category(&pool, "test@example.com", "BATIDO CACAO").await?);
+        let category = get_product_category(&pool, "test@example.com", "BATIDO CACAO").await?;
+        assert_eq!(category.categoria.as_deref(), Some("Lácteos"));
+
+        Ok(())
+    }
+}
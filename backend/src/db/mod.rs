@@ -1,18 +1,81 @@
+pub mod achievements;
+pub mod categories;
+pub mod notifications;
+pub mod ocr_jobs;
+pub mod package_sizes;
+pub mod payment_cards;
+pub mod preferences;
+pub mod product_aliases;
 pub mod products;
 pub mod purchases;
+pub mod savings_goals;
 pub mod stats;
+pub mod ticket_blobs;
+pub mod ticket_drafts;
 pub mod ticket_history;
 pub mod tickets;
 pub mod users;
 
-pub use products::upsert_product;
-pub use purchases::{get_purchase, insert_purchase};
+pub use achievements::{
+    get_achievement_counters, get_purchase_dates, get_user_achievement_unlocks,
+    insert_user_achievement, list_achievements, AchievementCounters,
+};
+pub use categories::{
+    assign_product_category, delete_user_product_category, get_product_category,
+    get_uncategorized_products, insert_categories, list_categories, set_user_product_category,
+};
+pub use notifications::{
+    count_unread_notifications, insert_notification, list_notifications,
+    mark_all_notifications_read, mark_notification_read,
+};
+pub use ocr_jobs::{
+    claim_next_ocr_job, complete_ocr_job, fail_ocr_job, get_ocr_job, insert_ocr_job,
+    link_ocr_job_purchase, requeue_stalled_ocr_jobs, schedule_ocr_job_retry, update_ocr_job_status,
+};
+pub use package_sizes::{
+    get_equivalent_products, get_products_without_package, get_shrinkflation_candidates,
+    get_unit_price_ranking, insert_product_package, ShrinkflationCandidate, UnitPriceItem,
+};
+pub use payment_cards::{delete_payment_card, insert_payment_card, list_payment_cards};
+pub use preferences::{
+    generate_user_reports, get_user_preferences, insert_spending_alert, list_spending_alerts,
+    list_user_reports, upsert_user_preferences,
+};
+pub use product_aliases::{get_product_aliases, lock_products, merge_products, ProductMergeStats};
+pub use products::{
+    get_price_history, get_product, get_purchase_price_jumps, get_user_product_purchases,
+    search_products, upsert_price_history, upsert_product, PriceHistoryPoint, PriceJump,
+    ProductSearchResult, UserPricePoint,
+};
+pub use purchases::{
+    delete_purchase, delete_purchase_products, get_purchase, get_purchase_iva_breakdown,
+    get_purchase_iva_totals, get_purchase_products, insert_purchase, insert_purchase_iva,
+    insert_purchase_products, refresh_derived_prices, update_purchase_header,
+    update_purchase_product, PurchaseFilter, PurchaseIvaTotal,
+};
+pub use savings_goals::{
+    close_savings_goals, delete_savings_goal, get_month_spending, get_savings_goal_for_month,
+    insert_savings_goal, list_savings_goals, update_savings_goal,
+};
 pub use stats::{
-    get_current_year_total, get_hourly_distribution, get_month_comparison, get_monthly_spending,
-    get_spending_trend, get_top_products_by_quantity, get_top_products_by_spending,
-    get_weekly_distribution, DailySpendPoint, MonthlySpendPoint, TimeDistributionPoint,
-    TopProductItem,
+    get_category_monthly_spending, get_current_year_total, get_hourly_distribution,
+    get_month_comparison, get_monthly_iva, get_monthly_spending,
+    get_payment_method_monthly_spending, get_personal_inflation_basket, get_spending_by_card,
+    get_spending_by_category, get_spending_by_payment_method, get_spending_by_store,
+    get_spending_trend, get_store_price_differences, get_top_products_by_quantity,
+    get_top_products_by_spending, get_weekly_distribution, CardSpendItem, CategoryMonthlyPoint,
+    CategorySpendItem, DailySpendPoint, IvaMonthlyPoint, MonthlySpendPoint,
+    PaymentMethodMonthlyPoint, PaymentMethodSpendItem, PersonalInflationData, StorePriceDifference,
+    StoreSpendItem, TimeDistributionPoint, TopProductItem,
+};
+pub use ticket_blobs::{delete_ticket_blob, get_ticket_blob, upsert_ticket_blob};
+pub use ticket_drafts::{
+    delete_expired_ticket_drafts, delete_ticket_draft, get_expiring_ticket_drafts,
+    get_ticket_draft, get_user_ticket_drafts, insert_ticket_draft, ExpiringTicketDraft,
 };
 pub use ticket_history::{get_user_stats, get_user_ticket_history, TicketHistoryItem, UserStats};
-pub use tickets::insert_ticket_pdf;
+pub use tickets::{
+    get_ticket_attachment, get_ticket_attachments_by_storage, insert_ticket_attachment,
+    ticket_attachment_exists, update_ticket_attachment_storage,
+};
 pub use users::{create_user, find_user_by_email};
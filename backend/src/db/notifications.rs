@@ -0,0 +1,202 @@
+use crate::models::{Notification, NotificationInsert};
+use sqlx::{types::Json, PgPool};
+
+/// Guarda una notificación
+///
+/// Devuelve `None` si el usuario ya tenía una notificación con la misma clave.
+pub async fn insert_notification(
+    pool: &PgPool,
+    notification: &NotificationInsert,
+) -> Result<Option<Notification>, sqlx::Error> {
+    sqlx::query_as!(
+        Notification,
+        r#"
+        INSERT INTO notificaciones (usuario_email, tipo, titulo, mensaje, datos, clave)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        ON CONFLICT (usuario_email, clave) DO NOTHING
+        RETURNING
+            id,
+            tipo,
+            titulo,
+            mensaje,
+            datos as "datos: Json<serde_json::Value>",
+            leida,
+            leida_en,
+            created_at
+        "#,
+        notification.usuario_email,
+        notification.tipo.as_str(),
+        notification.titulo,
+        notification.mensaje,
+        notification.datos.as_ref().map(Json) as _,
+        notification.clave
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+/// Notificaciones del usuario, de la más reciente a la más antigua
+pub async fn list_notifications(
+    pool: &PgPool,
+    usuario_email: &str,
+    solo_no_leidas: bool,
+    limit: i64,
+) -> Result<Vec<Notification>, sqlx::Error> {
+    sqlx::query_as!(
+        Notification,
+        r#"
+        SELECT
+            id,
+            tipo,
+            titulo,
+            mensaje,
+            datos as "datos: Json<serde_json::Value>",
+            leida,
+            leida_en,
+            created_at
+        FROM notificaciones
+        WHERE usuario_email = $1 AND (NOT $2 OR NOT leida)
+        ORDER BY created_at DESC, id DESC
+        LIMIT $3
+        "#,
+        usuario_email,
+        solo_no_leidas,
+        limit
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Número de notificaciones sin leer del usuario
+pub async fn count_unread_notifications(
+    pool: &PgPool,
+    usuario_email: &str,
+) -> Result<i64, sqlx::Error> {
+    sqlx::query_scalar!(
+        r#"
+        SELECT COUNT(*) as "total!"
+        FROM notificaciones
+        WHERE usuario_email = $1 AND NOT leida
+        "#,
+        usuario_email
+    )
+    .fetch_one(pool)
+    .await
+}
+
+/// Marca una notificación como leída
+///
+/// Devuelve `false` si no existe o es de otro usuario.
+pub async fn mark_notification_read(
+    pool: &PgPool,
+    usuario_email: &str,
+    id: i32,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE notificaciones
+        SET leida = TRUE, leida_en = COALESCE(leida_en, NOW() AT TIME ZONE 'UTC')
+        WHERE id = $1 AND usuario_email = $2
+        "#,
+        id,
+        usuario_email
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Marca como leídas todas las notificaciones del usuario
+pub async fn mark_all_notifications_read(
+    pool: &PgPool,
+    usuario_email: &str,
+) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE notificaciones
+        SET leida = TRUE, leida_en = NOW() AT TIME ZONE 'UTC'
+        WHERE usuario_email = $1 AND NOT leida
+        "#,
+        usuario_email
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::NotificationKind;
+
+    fn notification(clave: Option<&str>) -> NotificationInsert {
+        NotificationInsert {
+            usuario_email: "notif@example.com".to_string(),
+            tipo: NotificationKind::Logro,
+            titulo: "Nuevo logro".to_string(),
+            mensaje: "Has desbloqueado un logro".to_string(),
+            datos: Some(serde_json::json!({ "codigo": "PRIMERA_COMPRA" })),
+            clave: clave.map(str::to_string),
+        }
+    }
+
+    #[sqlx::test(migrations = "./migrations")]
+    async fn test_notifications(pool: PgPool) -> sqlx::Result<()> {
+        sqlx::query!(
+            "INSERT INTO usuarios (email, password_hash, nombre) VALUES ($1, $2, $3)",
+            "notif@example.com",
+            "$2b$12$KpIEW.jQKvqXfN5nDwAXLub8RCRYjqNvCLKXfzHpFGK2FQJGmqQJi",
+            "Notif User"
+        )
+        .execute(&pool)
+        .await?;
+
+        let primera = insert_notification(&pool, &notification(Some("logro:PRIMERA_COMPRA")))
+            .await?
+            .expect("notificación nueva");
+        assert!(!primera.leida);
+        // La misma clave no se notifica dos veces; sin clave siempre se guarda
+        assert!(
+            insert_notification(&pool, &notification(Some("logro:PRIMERA_COMPRA")))
+                .await?
+                .is_none()
+        );
+        assert!(insert_notification(&pool, &notification(None))
+            .await?
+            .is_some());
+        assert!(insert_notification(&pool, &notification(None))
+            .await?
+            .is_some());
+
+        assert_eq!(
+            count_unread_notifications(&pool, "notif@example.com").await?,
+            3
+        );
+
+        assert!(mark_notification_read(&pool, "notif@example.com", primera.id).await?);
+        assert!(!mark_notification_read(&pool, "otro@example.com", primera.id).await?);
+        assert_eq!(
+            count_unread_notifications(&pool, "notif@example.com").await?,
+            2
+        );
+
+        let todas = list_notifications(&pool, "notif@example.com", false, 10).await?;
+        assert_eq!(todas.len(), 3);
+        let no_leidas = list_notifications(&pool, "notif@example.com", true, 10).await?;
+        assert_eq!(no_leidas.len(), 2);
+        assert!(no_leidas.iter().all(|n| !n.leida));
+
+        assert_eq!(
+            mark_all_notifications_read(&pool, "notif@example.com").await?,
+            2
+        );
+        assert_eq!(
+            count_unread_notifications(&pool, "notif@example.com").await?,
+            0
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,406 @@
+use crate::models::{OcrJob, OcrJobInsert, OcrJobStatus, OcrJobTask};
+use crate::services::TicketIngestionResponse;
+use chrono::NaiveDateTime;
+use sqlx::{types::Json, PgPool, Postgres};
+use uuid::Uuid;
+
+/// Encola un trabajo de OCR con el archivo original
+pub async fn insert_ocr_job(pool: &PgPool, job: &OcrJobInsert) -> Result<OcrJob, sqlx::Error> {
+    let size_bytes = job.archivo.len() as i32;
+
+    sqlx::query_as!(
+        OcrJob,
+        r#"
+        INSERT INTO trabajos_ocr (
+            usuario_email,
+            archivo,
+            nombre_archivo,
+            mime_type,
+            tamano_bytes,
+            reemplazar
+        )
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING
+            id,
+            estado,
+            nombre_archivo,
+            mime_type,
+            tamano_bytes,
+            intentos,
+            siguiente_intento,
+            error_codigo,
+            error_mensaje,
+            resultado as "resultado: Json<TicketIngestionResponse>",
+            created_at,
+            updated_at
+        "#,
+        job.usuario_email,
+        job.archivo,
+        job.nombre_archivo,
+        job.mime_type,
+        size_bytes,
+        job.reemplazar
+    )
+    .fetch_one(pool)
+    .await
+}
+
+/// Obtiene un trabajo de OCR de un usuario
+pub async fn get_ocr_job(
+    pool: &PgPool,
+    usuario_email: &str,
+    id: Uuid,
+) -> Result<Option<OcrJob>, sqlx::Error> {
+    sqlx::query_as!(
+        OcrJob,
+        r#"
+        SELECT
+            id,
+            estado,
+            nombre_archivo,
+            mime_type,
+            tamano_bytes,
+            intentos,
+            siguiente_intento,
+            error_codigo,
+            error_mensaje,
+            resultado as "resultado: Json<TicketIngestionResponse>",
+            created_at,
+            updated_at
+        FROM trabajos_ocr
+        WHERE id = $1 AND usuario_email = $2
+        "#,
+        id,
+        usuario_email
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+/// Reserva el siguiente trabajo pendiente y lo pasa al estado `ocr`
+///
+/// Usa `FOR UPDATE SKIP LOCKED` para que varias instancias del backend
+/// puedan compartir la cola sin procesar dos veces el mismo trabajo.
+pub async fn claim_next_ocr_job(
+    pool: &PgPool,
+    now: NaiveDateTime,
+) -> Result<Option<OcrJobTask>, sqlx::Error> {
+    sqlx::query_as!(
+        OcrJobTask,
+        r#"
+        UPDATE trabajos_ocr
+        SET estado = 'ocr', intentos = intentos + 1, updated_at = $1
+        WHERE id = (
+            SELECT id
+            FROM trabajos_ocr
+            WHERE estado = 'queued' AND siguiente_intento <= $1
+            ORDER BY siguiente_intento
+            LIMIT 1
+            FOR UPDATE SKIP LOCKED
+        )
+        RETURNING
+            id,
+            usuario_email,
+            archivo,
+            nombre_archivo,
+            mime_type,
+            reemplazar,
+            intentos,
+            numero_factura
+        "#,
+        now
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+/// Cambia el estado de un trabajo en curso
+pub async fn update_ocr_job_status(
+    pool: &PgPool,
+    id: Uuid,
+    estado: OcrJobStatus,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE trabajos_ocr
+        SET estado = $2, updated_at = NOW() AT TIME ZONE 'UTC'
+        WHERE id = $1
+        "#,
+        id,
+        estado.as_str()
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Devuelve un trabajo a la cola para reintentarlo más tarde
+pub async fn schedule_ocr_job_retry(
+    pool: &PgPool,
+    id: Uuid,
+    siguiente_intento: NaiveDateTime,
+    error_codigo: &str,
+    error_mensaje: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE trabajos_ocr
+        SET estado = 'queued',
+            siguiente_intento = $2,
+            error_codigo = $3,
+            error_mensaje = $4,
+            updated_at = NOW() AT TIME ZONE 'UTC'
+        WHERE id = $1
+        "#,
+        id,
+        siguiente_intento,
+        error_codigo,
+        error_mensaje
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Marca un trabajo como ingestado y libera el archivo
+pub async fn complete_ocr_job(
+    pool: &PgPool,
+    id: Uuid,
+    resultado: &TicketIngestionResponse,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE trabajos_ocr
+        SET estado = 'ingested',
+            archivo = NULL,
+            resultado = $2,
+            error_codigo = NULL,
+            error_mensaje = NULL,
+            updated_at = NOW() AT TIME ZONE 'UTC'
+        WHERE id = $1
+        "#,
+        id,
+        Json(resultado) as _
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Marca un trabajo como fallido y libera el archivo
+pub async fn fail_ocr_job(
+    pool: &PgPool,
+    id: Uuid,
+    error_codigo: &str,
+    error_mensaje: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE trabajos_ocr
+        SET estado = 'failed',
+            archivo = NULL,
+            error_codigo = $2,
+            error_mensaje = $3,
+            updated_at = NOW() AT TIME ZONE 'UTC'
+        WHERE id = $1
+        "#,
+        id,
+        error_codigo,
+        error_mensaje
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Asocia al trabajo la compra que acaba de crear su ingesta
+///
+/// Se ejecuta dentro de la transacción de la ingesta, así que el trabajo
+/// solo queda asociado si la compra se ha guardado.
+pub async fn link_ocr_job_purchase<'c, E>(
+    executor: E,
+    id: Uuid,
+    numero_factura: &str,
+) -> Result<(), sqlx::Error>
+where
+    E: sqlx::Executor<'c, Database = Postgres>,
+{
+    sqlx::query!(
+        r#"
+        UPDATE trabajos_ocr
+        SET numero_factura = $2, updated_at = NOW() AT TIME ZONE 'UTC'
+        WHERE id = $1
+        "#,
+        id,
+        numero_factura
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// Trabajos interrumpidos recuperados por `requeue_stalled_ocr_jobs`
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct StalledOcrJobs {
+    pub reencolados: u64,
+    pub fallidos: u64,
+}
+
+/// Devuelve a la cola los trabajos en curso que no avanzan desde `antes_de`
+///
+/// Son trabajos cuyo worker se detuvo (reinicio o caída). Los que siguen
+/// avanzando, en esta u otra instancia, actualizan `updated_at` y no se tocan.
+/// Los que ya agotaron `max_intentos` se marcan como fallidos (`stalled`) en
+/// lugar de reencolarse, para que un ticket que tumba al worker no se
+/// reintente sin fin.
+pub async fn requeue_stalled_ocr_jobs(
+    pool: &PgPool,
+    antes_de: NaiveDateTime,
+    max_intentos: i32,
+) -> Result<StalledOcrJobs, sqlx::Error> {
+    let estados = sqlx::query_scalar!(
+        r#"
+        UPDATE trabajos_ocr
+        SET estado = CASE WHEN intentos >= $2 THEN 'failed' ELSE 'queued' END,
+            archivo = CASE WHEN intentos >= $2 THEN NULL ELSE archivo END,
+            error_codigo = CASE WHEN intentos >= $2 THEN 'stalled' ELSE error_codigo END,
+            error_mensaje = CASE
+                WHEN intentos >= $2 THEN 'El procesamiento se interrumpió en todos los intentos'
+                ELSE error_mensaje
+            END,
+            updated_at = NOW() AT TIME ZONE 'UTC'
+        WHERE estado IN ('ocr', 'validating') AND updated_at < $1
+        RETURNING estado
+        "#,
+        antes_de,
+        max_intentos
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let fallidos = estados.iter().filter(|estado| *estado == "failed").count() as u64;
+    Ok(StalledOcrJobs {
+        reencolados: estados.len() as u64 - fallidos,
+        fallidos,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, Utc};
+
+    #[sqlx::test(migrations = "./migrations")]
+    async fn test_ocr_job_lifecycle(pool: PgPool) -> sqlx::Result<()> {
+        sqlx::query!(
+            "INSERT INTO usuarios (email, password_hash, nombre) VALUES ($1, $2, $3)",
+            "test@example.com",
+            "$2b$12$KpIEW.jQKvqXfN5nDwAXLub8RCRYjqNvCLKXfzHpFGK2FQJGmqQJi",
+            "Test User"
+        )
+        .execute(&pool)
+        .await?;
+
+        let job = insert_ocr_job(
+            &pool,
+            &OcrJobInsert {
+                usuario_email: "test@example.com".to_string(),
+                archivo: vec![0x25, 0x50, 0x44, 0x46],
+                nombre_archivo: "ticket.pdf".to_string(),
+                mime_type: Some("application/pdf".to_string()),
+                reemplazar: false,
+            },
+        )
+        .await?;
+        assert_eq!(job.status(), OcrJobStatus::Queued);
+        assert_eq!(job.tamano_bytes, 4);
+
+        // Otro usuario no puede consultar el trabajo
+        assert!(get_ocr_job(&pool, "other@example.com", job.id)
+            .await?
+            .is_none());
+
+        let now = Utc::now().naive_utc();
+        let task = claim_next_ocr_job(&pool, now)
+            .await?
+            .expect("job should be claimed");
+        assert_eq!(task.id, job.id);
+        assert_eq!(task.intentos, 1);
+        assert_eq!(task.archivo.as_deref(), Some(&[0x25, 0x50, 0x44, 0x46][..]));
+
+        // Un trabajo en curso no se vuelve a reservar
+        assert!(claim_next_ocr_job(&pool, now).await?.is_none());
+
+        // Un reintento programado no se toma antes de tiempo
+        schedule_ocr_job_retry(
+            &pool,
+            job.id,
+            now + Duration::seconds(30),
+            "service_unavailable",
+            "Servicio temporalmente no disponible",
+        )
+        .await?;
+        assert!(claim_next_ocr_job(&pool, now).await?.is_none());
+
+        let task = claim_next_ocr_job(&pool, now + Duration::seconds(31))
+            .await?
+            .expect("job should be claimed again");
+        assert_eq!(task.intentos, 2);
+
+        // Solo vuelve a la cola cuando deja de avanzar más allá del plazo
+        update_ocr_job_status(&pool, job.id, OcrJobStatus::Validating).await?;
+        let lease = Duration::minutes(10);
+        assert_eq!(
+            requeue_stalled_ocr_jobs(&pool, Utc::now().naive_utc() - lease, 3).await?,
+            StalledOcrJobs::default()
+        );
+        assert_eq!(
+            requeue_stalled_ocr_jobs(&pool, Utc::now().naive_utc() + Duration::seconds(1), 3)
+                .await?,
+            StalledOcrJobs {
+                reencolados: 1,
+                fallidos: 0
+            }
+        );
+
+        // La compra creada por un intento se conserva para los siguientes
+        link_ocr_job_purchase(&pool, job.id, "F-OCR-1").await?;
+        let task = claim_next_ocr_job(&pool, now + Duration::seconds(31))
+            .await?
+            .expect("job should be claimed again");
+        assert_eq!(task.numero_factura.as_deref(), Some("F-OCR-1"));
+        assert_eq!(task.intentos, 3);
+
+        // Si el último intento también se interrumpe, el trabajo falla
+        assert_eq!(
+            requeue_stalled_ocr_jobs(&pool, now + Duration::seconds(32), 3).await?,
+            StalledOcrJobs {
+                reencolados: 0,
+                fallidos: 1
+            }
+        );
+        let stalled = get_ocr_job(&pool, "test@example.com", job.id)
+            .await?
+            .expect("job should exist");
+        assert_eq!(stalled.status(), OcrJobStatus::Failed);
+        assert_eq!(stalled.error_codigo.as_deref(), Some("stalled"));
+        assert!(claim_next_ocr_job(&pool, now + Duration::seconds(31))
+            .await?
+            .is_none());
+
+        fail_ocr_job(&pool, job.id, "invalid_totals", "Totales incoherentes").await?;
+        let failed = get_ocr_job(&pool, "test@example.com", job.id)
+            .await?
+            .expect("job should exist");
+        assert_eq!(failed.status(), OcrJobStatus::Failed);
+        assert_eq!(failed.error_codigo.as_deref(), Some("invalid_totals"));
+        assert_eq!(failed.intentos, 3);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,358 @@
+use crate::models::ProductPackage;
+use chrono::{NaiveDate, NaiveDateTime};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, Postgres};
+
+/// Producto con su precio normalizado por kg o litro
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct UnitPriceItem {
+    pub nombre: String,
+    pub nombre_base: String,
+    pub piezas: Option<i32>,
+    /// Contenido total del envase en `unidad_base`
+    pub contenido: Option<Decimal>,
+    /// `kg` o `l`
+    pub unidad_base: Option<String>,
+    pub precio_actual: Option<Decimal>,
+    pub precio_por_unidad_base: Option<Decimal>,
+    pub ultima_compra: Option<NaiveDateTime>,
+}
+
+/// Dos formatos de un mismo producto en los que el nuevo tiene menos contenido
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct ShrinkflationCandidate {
+    pub nombre_base: String,
+    pub producto_anterior: String,
+    pub producto_nuevo: String,
+    pub unidad: String,
+    pub contenido_anterior: Decimal,
+    pub contenido_nuevo: Decimal,
+    /// Precio del formato anterior cuando apareció el nuevo
+    pub precio_anterior: Decimal,
+    /// Primer precio registrado del formato nuevo
+    pub precio_nuevo: Decimal,
+    pub fecha_cambio: NaiveDate,
+}
+
+/// Productos sin tamaño de envase registrado
+///
+/// Con `nombres` se limita a esos productos; sin él recorre todo el catálogo.
+pub async fn get_products_without_package<'c, E>(
+    executor: E,
+    nombres: Option<&[String]>,
+) -> Result<Vec<String>, sqlx::Error>
+where
+    E: sqlx::Executor<'c, Database = Postgres>,
+{
+    sqlx::query_scalar!(
+        r#"
+        SELECT p.nombre
+        FROM productos p
+        LEFT JOIN productos_envases e ON e.producto_nombre = p.nombre
+        WHERE e.producto_nombre IS NULL
+            AND ($1::text[] IS NULL OR p.nombre = ANY($1))
+        ORDER BY p.nombre
+        "#,
+        nombres as _
+    )
+    .fetch_all(executor)
+    .await
+}
+
+/// Registra el tamaño de envase de un producto si aún no lo tenía
+pub async fn insert_product_package<'c, E>(
+    executor: E,
+    producto_nombre: &str,
+    envase: &ProductPackage,
+) -> Result<bool, sqlx::Error>
+where
+    E: sqlx::Executor<'c, Database = Postgres>,
+{
+    let result = sqlx::query!(
+        r#"
+        INSERT INTO productos_envases (producto_nombre, nombre_base, piezas, contenido, unidad)
+        VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT (producto_nombre) DO NOTHING
+        "#,
+        producto_nombre,
+        envase.nombre_base,
+        envase.piezas,
+        envase.contenido,
+        envase.unidad.as_str()
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Productos comprados por el usuario ordenados por precio por kg o litro
+///
+/// Solo incluye productos con precio normalizado; `unidad` filtra por `kg`
+/// o `l`.
+pub async fn get_unit_price_ranking(
+    pool: &PgPool,
+    usuario_email: &str,
+    unidad: Option<&str>,
+    limit: i64,
+) -> Result<Vec<UnitPriceItem>, sqlx::Error> {
+    sqlx::query_as!(
+        UnitPriceItem,
+        r#"
+        SELECT
+            pb.nombre as "nombre!",
+            pb.nombre_base as "nombre_base!",
+            pb.piezas,
+            pb.contenido,
+            pb.unidad_base,
+            pb.precio_actual,
+            pb.precio_por_unidad_base,
+            MAX(c.fecha_hora) as ultima_compra
+        FROM productos_precio_base pb
+        INNER JOIN compras_productos cp ON cp.producto_nombre = pb.nombre
+        INNER JOIN compras c ON c.numero_factura = cp.compra_numero_factura
+        WHERE c.usuario_email = $1
+            AND pb.precio_por_unidad_base IS NOT NULL
+            AND ($2::text IS NULL OR pb.unidad_base = $2)
+        GROUP BY pb.nombre, pb.nombre_base, pb.piezas, pb.contenido, pb.unidad_base,
+            pb.precio_actual, pb.precio_por_unidad_base
+        ORDER BY pb.precio_por_unidad_base ASC, pb.nombre
+        LIMIT $3
+        "#,
+        usuario_email,
+        unidad,
+        limit
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Formatos equivalentes a un producto (mismo nombre base y unidad)
+///
+/// Incluye el propio producto y los formatos que el usuario ha comprado,
+/// ordenados por precio por kg o litro.
+pub async fn get_equivalent_products(
+    pool: &PgPool,
+    usuario_email: &str,
+    producto_nombre: &str,
+) -> Result<Vec<UnitPriceItem>, sqlx::Error> {
+    sqlx::query_as!(
+        UnitPriceItem,
+        r#"
+        SELECT
+            pb.nombre as "nombre!",
+            pb.nombre_base as "nombre_base!",
+            pb.piezas,
+            pb.contenido,
+            pb.unidad_base,
+            pb.precio_actual,
+            pb.precio_por_unidad_base,
+            compras_usuario.ultima_compra
+        FROM productos_precio_base ref
+        INNER JOIN productos_precio_base pb
+            ON pb.nombre_base = ref.nombre_base
+            AND pb.unidad_base IS NOT DISTINCT FROM ref.unidad_base
+        LEFT JOIN LATERAL (
+            SELECT MAX(c.fecha_hora) as ultima_compra
+            FROM compras_productos cp
+            INNER JOIN compras c ON c.numero_factura = cp.compra_numero_factura
+            WHERE cp.producto_nombre = pb.nombre AND c.usuario_email = $1
+        ) compras_usuario ON TRUE
+        WHERE ref.nombre = $2
+            AND (pb.nombre = ref.nombre OR compras_usuario.ultima_compra IS NOT NULL)
+        ORDER BY pb.precio_por_unidad_base ASC NULLS LAST, pb.nombre
+        "#,
+        usuario_email,
+        producto_nombre
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Parejas de formatos de un mismo producto en las que el envase se reduce
+///
+/// El formato nuevo es el que aparece más tarde en el histórico de precios;
+/// se compara su primer precio con el que tenía el anterior en esa fecha.
+/// Solo se consideran productos que el usuario ha comprado.
+pub async fn get_shrinkflation_candidates(
+    pool: &PgPool,
+    usuario_email: &str,
+) -> Result<Vec<ShrinkflationCandidate>, sqlx::Error> {
+    sqlx::query_as!(
+        ShrinkflationCandidate,
+        r#"
+        WITH comprados AS (
+            SELECT DISTINCT cp.producto_nombre
+            FROM compras_productos cp
+            INNER JOIN compras c ON c.numero_factura = cp.compra_numero_factura
+            WHERE c.usuario_email = $1
+        ),
+        formatos AS (
+            SELECT
+                e.producto_nombre,
+                e.nombre_base,
+                e.contenido,
+                e.unidad,
+                MIN(h.fecha_vigencia) as primera_fecha
+            FROM productos_envases e
+            INNER JOIN historico_precios h ON h.producto_nombre = e.producto_nombre
+            GROUP BY e.producto_nombre, e.nombre_base, e.contenido, e.unidad
+        )
+        SELECT
+            nuevo.nombre_base,
+            anterior.producto_nombre as producto_anterior,
+            nuevo.producto_nombre as producto_nuevo,
+            nuevo.unidad,
+            anterior.contenido as contenido_anterior,
+            nuevo.contenido as contenido_nuevo,
+            precio_anterior.precio as "precio_anterior!",
+            precio_nuevo.precio as "precio_nuevo!",
+            nuevo.primera_fecha as "fecha_cambio!"
+        FROM formatos nuevo
+        INNER JOIN formatos anterior
+            ON anterior.nombre_base = nuevo.nombre_base
+            AND anterior.unidad = nuevo.unidad
+            AND anterior.contenido > nuevo.contenido
+            AND anterior.primera_fecha < nuevo.primera_fecha
+        INNER JOIN LATERAL (
+            SELECT h.precio
+            FROM historico_precios h
+            WHERE h.producto_nombre = anterior.producto_nombre
+                AND h.fecha_vigencia <= nuevo.primera_fecha
+            ORDER BY h.fecha_vigencia DESC
+            LIMIT 1
+        ) precio_anterior ON TRUE
+        INNER JOIN historico_precios precio_nuevo
+            ON precio_nuevo.producto_nombre = nuevo.producto_nombre
+            AND precio_nuevo.fecha_vigencia = nuevo.primera_fecha
+        WHERE nuevo.producto_nombre IN (SELECT producto_nombre FROM comprados)
+            OR anterior.producto_nombre IN (SELECT producto_nombre FROM comprados)
+        ORDER BY nuevo.primera_fecha DESC, nuevo.nombre_base
+        "#,
+        usuario_email
+    )
+    .fetch_all(pool)
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{insert_purchase, insert_purchase_products, upsert_product};
+    use crate::models::{ProductUnit, ProductUpsert, PurchaseInsert, PurchaseProductInsert};
+    use chrono::{Duration, Utc};
+
+    fn line(nombre: &str, centimos: i64) -> PurchaseProductInsert {
+        PurchaseProductInsert {
+            producto_nombre: nombre.to_string(),
+            cantidad: Decimal::ONE,
+            precio_unitario: Decimal::new(centimos, 2),
+            precio_total: Decimal::new(centimos, 2),
+            descuento: Decimal::ZERO,
+            iva_porcentaje: Decimal::new(10, 0),
+            iva_importe: Decimal::ZERO,
+        }
+    }
+
+    #[sqlx::test(migrations = "./migrations")]
+    async fn test_unit_prices_and_shrinkflation(pool: PgPool) -> sqlx::Result<()> {
+        sqlx::query!(
+            "INSERT INTO usuarios (email, password_hash, nombre) VALUES ($1, $2, $3)",
+            "test@example.com",
+            "$2b$12$KpIEW.jQKvqXfN5nDwAXLub8RCRYjqNvCLKXfzHpFGK2FQJGmqQJi",
+            "Test User"
+        )
+        .execute(&pool)
+        .await?;
+
+        let envases = [
+            ("CAFE MOLIDO 250G", 250),
+            ("CAFE MOLIDO 220G", 220),
+            ("CAFE MOLIDO 500G", 500),
+        ];
+        for (nombre, gramos) in envases {
+            upsert_product(
+                &pool,
+                &ProductUpsert {
+                    nombre: nombre.to_string(),
+                    marca: None,
+                    unidad: "unidad".to_string(),
+                    precio_actual: None,
+                },
+            )
+            .await?;
+            let envase = ProductPackage {
+                nombre_base: "CAFE MOLIDO".to_string(),
+                piezas: 1,
+                contenido: Decimal::new(gramos, 3),
+                unidad: ProductUnit::Kg,
+            };
+            assert!(insert_product_package(&pool, nombre, &envase).await?);
+            assert!(!insert_product_package(&pool, nombre, &envase).await?);
+        }
+
+        let now = Utc::now().naive_utc() - Duration::hours(1);
+        let purchases = [
+            (
+                "F-1",
+                now - Duration::days(60),
+                line("CAFE MOLIDO 250G", 220),
+            ),
+            (
+                "F-2",
+                now - Duration::days(10),
+                line("CAFE MOLIDO 220G", 220),
+            ),
+        ];
+        for (factura, fecha_hora, linea) in purchases {
+            insert_purchase(
+                &pool,
+                &PurchaseInsert {
+                    numero_factura: factura.to_string(),
+                    usuario_email: "test@example.com".to_string(),
+                    fecha_hora,
+                    total: linea.precio_total,
+                    tienda: None,
+                    ubicacion: None,
+                    metodo_pago: None,
+                    numero_operacion: None,
+                },
+            )
+            .await?;
+
+            let mut conn = pool.acquire().await?;
+            insert_purchase_products(&mut conn, factura, &[linea]).await?;
+        }
+
+        let ranking = get_unit_price_ranking(&pool, "test@example.com", Some("kg"), 10).await?;
+        assert_eq!(ranking.len(), 2);
+        assert_eq!(ranking[0].nombre, "CAFE MOLIDO 250G");
+        assert_eq!(
+            ranking[0].precio_por_unidad_base,
+            Some(Decimal::new(880, 2))
+        );
+        assert_eq!(
+            ranking[1].precio_por_unidad_base,
+            Some(Decimal::new(1000, 2))
+        );
+
+        // El formato de 500G no se ha comprado y no aparece
+        let equivalents =
+            get_equivalent_products(&pool, "test@example.com", "CAFE MOLIDO 220G").await?;
+        let nombres: Vec<_> = equivalents.iter().map(|p| p.nombre.as_str()).collect();
+        assert_eq!(nombres, vec!["CAFE MOLIDO 250G", "CAFE MOLIDO 220G"]);
+
+        let candidates = get_shrinkflation_candidates(&pool, "test@example.com").await?;
+        assert_eq!(candidates.len(), 1);
+        assert_eq!(candidates[0].producto_anterior, "CAFE MOLIDO 250G");
+        assert_eq!(candidates[0].producto_nuevo, "CAFE MOLIDO 220G");
+        assert_eq!(candidates[0].precio_anterior, candidates[0].precio_nuevo);
+
+        assert!(get_shrinkflation_candidates(&pool, "otro@example.com")
+            .await?
+            .is_empty());
+
+        Ok(())
+    }
+}
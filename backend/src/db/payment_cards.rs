@@ -0,0 +1,153 @@
+use crate::models::PaymentCard;
+use sqlx::PgPool;
+
+/// Alias de tarjeta del usuario por orden alfabético
+pub async fn list_payment_cards(
+    pool: &PgPool,
+    usuario_email: &str,
+) -> Result<Vec<PaymentCard>, sqlx::Error> {
+    sqlx::query_as!(
+        PaymentCard,
+        r#"
+        SELECT id, alias, patron, created_at
+        FROM tarjetas_usuario
+        WHERE usuario_email = $1
+        ORDER BY alias, patron
+        "#,
+        usuario_email
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Asocia un alias a un patrón de número de operación
+///
+/// Devuelve `None` si el usuario ya tiene ese patrón.
+pub async fn insert_payment_card(
+    pool: &PgPool,
+    usuario_email: &str,
+    alias: &str,
+    patron: &str,
+) -> Result<Option<PaymentCard>, sqlx::Error> {
+    sqlx::query_as!(
+        PaymentCard,
+        r#"
+        INSERT INTO tarjetas_usuario (usuario_email, alias, patron)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (usuario_email, patron) DO NOTHING
+        RETURNING id, alias, patron, created_at
+        "#,
+        usuario_email,
+        alias,
+        patron
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+/// Elimina un alias de tarjeta del usuario
+pub async fn delete_payment_card(
+    pool: &PgPool,
+    usuario_email: &str,
+    id: i32,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!(
+        "DELETE FROM tarjetas_usuario WHERE id = $1 AND usuario_email = $2",
+        id,
+        usuario_email
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{
+        get_spending_by_card, get_spending_by_payment_method, insert_purchase, PurchaseFilter,
+    };
+    use crate::models::PurchaseInsert;
+    use chrono::{Duration, Utc};
+    use rust_decimal::Decimal;
+
+    #[sqlx::test(migrations = "./migrations")]
+    async fn test_payment_cards_and_card_spending(pool: PgPool) -> sqlx::Result<()> {
+        sqlx::query!(
+            "INSERT INTO usuarios (email, password_hash, nombre) VALUES ($1, $2, $3)",
+            "cards@example.com",
+            "$2b$12$KpIEW.jQKvqXfN5nDwAXLub8RCRYjqNvCLKXfzHpFGK2FQJGmqQJi",
+            "Cards User"
+        )
+        .execute(&pool)
+        .await?;
+
+        let nomina = insert_payment_card(&pool, "cards@example.com", "Visa nómina", "4539*")
+            .await?
+            .expect("alias creado");
+        insert_payment_card(&pool, "cards@example.com", "Visa gastos", "45391*").await?;
+        assert!(
+            insert_payment_card(&pool, "cards@example.com", "Repetido", "4539*")
+                .await?
+                .is_none()
+        );
+
+        let now = Utc::now().naive_utc() - Duration::hours(1);
+        let purchases = [
+            ("F-1", Some("TARJETA BANCARIA"), Some("453900"), 1000),
+            ("F-2", Some("TARJETA BANCARIA"), Some("453911"), 2000),
+            ("F-3", Some("TARJETA BANCARIA"), Some("111111"), 500),
+            ("F-4", Some("EFECTIVO"), None, 300),
+        ];
+        for (i, (factura, metodo, operacion, centimos)) in purchases.into_iter().enumerate() {
+            insert_purchase(
+                &pool,
+                &PurchaseInsert {
+                    numero_factura: factura.to_string(),
+                    usuario_email: "cards@example.com".to_string(),
+                    fecha_hora: now - Duration::minutes(i as i64),
+                    total: Decimal::new(centimos, 2),
+                    tienda: None,
+                    ubicacion: None,
+                    metodo_pago: metodo.map(str::to_string),
+                    numero_operacion: operacion.map(str::to_string),
+                },
+            )
+            .await?;
+        }
+
+        let filter = PurchaseFilter::default();
+        let metodos =
+            get_spending_by_payment_method(&pool, "cards@example.com", None, &filter).await?;
+        assert_eq!(metodos.len(), 2);
+        assert_eq!(metodos[0].metodo_pago, "TARJETA BANCARIA");
+        assert_eq!(metodos[0].tickets, 3);
+
+        // El patrón más específico gana sobre "4539*"
+        let tarjetas = get_spending_by_card(&pool, "cards@example.com", 1, &filter).await?;
+        let resumen: Vec<_> = tarjetas
+            .iter()
+            .map(|t| (t.alias.as_str(), t.tickets))
+            .collect();
+        assert_eq!(
+            resumen,
+            vec![("Visa gastos", 1), ("Visa nómina", 1), ("Sin alias", 1)]
+        );
+
+        // Otro usuario no puede borrar la tarjeta, que sigue existiendo
+        assert!(!delete_payment_card(&pool, "other@example.com", nomina.id).await?);
+        assert_eq!(
+            list_payment_cards(&pool, "cards@example.com").await?.len(),
+            2
+        );
+
+        assert!(delete_payment_card(&pool, "cards@example.com", nomina.id).await?);
+        assert_eq!(
+            list_payment_cards(&pool, "cards@example.com").await?.len(),
+            1
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,308 @@
+use crate::models::{
+    ReportFrequency, SpendingAlert, UserPreferences, UserPreferencesUpdate, UserReport,
+};
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use sqlx::{types::Json, PgPool};
+
+/// Preferencias guardadas por el usuario
+///
+/// Devuelve `None` si el usuario nunca las ha guardado.
+pub async fn get_user_preferences(
+    pool: &PgPool,
+    usuario_email: &str,
+) -> Result<Option<UserPreferences>, sqlx::Error> {
+    sqlx::query_as!(
+        UserPreferences,
+        r#"
+        SELECT
+            COALESCE(alertas_gasto_activas, TRUE) as "alertas_gasto_activas!",
+            umbral_alerta_gasto,
+            COALESCE(notif_nuevos_logros, TRUE) as "notif_nuevos_logros!",
+            COALESCE(notif_inflacion, TRUE) as "notif_inflacion!",
+            COALESCE(frecuencia_reportes, 'semanal') as "frecuencia_reportes!",
+            configuracion_extra as "configuracion_extra: Json<serde_json::Value>",
+            updated_at as "updated_at?"
+        FROM preferencias_usuario
+        WHERE usuario_email = $1
+        "#,
+        usuario_email
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+/// Crea o sustituye las preferencias del usuario
+pub async fn upsert_user_preferences(
+    pool: &PgPool,
+    usuario_email: &str,
+    preferences: &UserPreferencesUpdate,
+) -> Result<UserPreferences, sqlx::Error> {
+    sqlx::query_as!(
+        UserPreferences,
+        r#"
+        INSERT INTO preferencias_usuario (
+            usuario_email, alertas_gasto_activas, umbral_alerta_gasto,
+            notif_nuevos_logros, notif_inflacion, frecuencia_reportes, configuracion_extra
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        ON CONFLICT (usuario_email) DO UPDATE SET
+            alertas_gasto_activas = EXCLUDED.alertas_gasto_activas,
+            umbral_alerta_gasto = EXCLUDED.umbral_alerta_gasto,
+            notif_nuevos_logros = EXCLUDED.notif_nuevos_logros,
+            notif_inflacion = EXCLUDED.notif_inflacion,
+            frecuencia_reportes = EXCLUDED.frecuencia_reportes,
+            configuracion_extra = EXCLUDED.configuracion_extra,
+            updated_at = CURRENT_TIMESTAMP
+        RETURNING
+            COALESCE(alertas_gasto_activas, TRUE) as "alertas_gasto_activas!",
+            umbral_alerta_gasto,
+            COALESCE(notif_nuevos_logros, TRUE) as "notif_nuevos_logros!",
+            COALESCE(notif_inflacion, TRUE) as "notif_inflacion!",
+            COALESCE(frecuencia_reportes, 'semanal') as "frecuencia_reportes!",
+            configuracion_extra as "configuracion_extra: Json<serde_json::Value>",
+            updated_at as "updated_at?"
+        "#,
+        usuario_email,
+        preferences.alertas_gasto_activas,
+        preferences.umbral_alerta_gasto,
+        preferences.notif_nuevos_logros,
+        preferences.notif_inflacion,
+        preferences.frecuencia_reportes.as_str(),
+        preferences.configuracion_extra.as_ref().map(Json) as _
+    )
+    .fetch_one(pool)
+    .await
+}
+
+/// Registra que el gasto de `mes` ha superado `umbral`
+///
+/// Devuelve `None` si ese umbral ya había generado una alerta ese mes.
+pub async fn insert_spending_alert(
+    pool: &PgPool,
+    usuario_email: &str,
+    mes: NaiveDate,
+    umbral: Decimal,
+    gasto: Decimal,
+) -> Result<Option<SpendingAlert>, sqlx::Error> {
+    sqlx::query_as!(
+        SpendingAlert,
+        r#"
+        INSERT INTO alertas_gasto (usuario_email, mes, umbral, gasto)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (usuario_email, mes, umbral) DO NOTHING
+        RETURNING id, mes, umbral, gasto, created_at
+        "#,
+        usuario_email,
+        mes,
+        umbral,
+        gasto
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+/// Alertas de gasto del usuario, de la más reciente a la más antigua
+pub async fn list_spending_alerts(
+    pool: &PgPool,
+    usuario_email: &str,
+    limit: i64,
+) -> Result<Vec<SpendingAlert>, sqlx::Error> {
+    sqlx::query_as!(
+        SpendingAlert,
+        r#"
+        SELECT id, mes, umbral, gasto, created_at
+        FROM alertas_gasto
+        WHERE usuario_email = $1
+        ORDER BY created_at DESC, id DESC
+        LIMIT $2
+        "#,
+        usuario_email,
+        limit
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Genera el informe del periodo `desde..=hasta` para los usuarios con esa frecuencia
+///
+/// `anterior_desde` es el inicio del periodo anterior, que termina el día
+/// antes de `desde`. Solo se crean informes para usuarios con compras en el
+/// periodo y los ya generados no se tocan.
+pub async fn generate_user_reports(
+    pool: &PgPool,
+    frecuencia: ReportFrequency,
+    desde: NaiveDate,
+    hasta: NaiveDate,
+    anterior_desde: NaiveDate,
+) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+        INSERT INTO informes_usuario (
+            usuario_email, frecuencia, desde, hasta, total, compras, total_anterior
+        )
+        SELECT
+            c.usuario_email,
+            $1::varchar,
+            $2::date,
+            $3::date,
+            COALESCE(SUM(c.total) FILTER (WHERE c.fecha_hora >= $2::date), 0),
+            COUNT(*) FILTER (WHERE c.fecha_hora >= $2::date)::int,
+            COALESCE(SUM(c.total) FILTER (WHERE c.fecha_hora < $2::date), 0)
+        FROM compras c
+        LEFT JOIN preferencias_usuario p ON p.usuario_email = c.usuario_email
+        WHERE COALESCE(p.frecuencia_reportes, 'semanal') = $1::varchar
+            AND c.fecha_hora >= $4::date
+            AND c.fecha_hora < $3::date + 1
+        GROUP BY c.usuario_email
+        HAVING COUNT(*) FILTER (WHERE c.fecha_hora >= $2::date) > 0
+        ON CONFLICT (usuario_email, desde, hasta) DO NOTHING
+        "#,
+        frecuencia.as_str(),
+        desde,
+        hasta,
+        anterior_desde
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// Informes del usuario, del más reciente al más antiguo
+pub async fn list_user_reports(
+    pool: &PgPool,
+    usuario_email: &str,
+    limit: i64,
+) -> Result<Vec<UserReport>, sqlx::Error> {
+    sqlx::query_as!(
+        UserReport,
+        r#"
+        SELECT id, frecuencia, desde, hasta, total, compras, total_anterior, generado_en
+        FROM informes_usuario
+        WHERE usuario_email = $1
+        ORDER BY hasta DESC, id DESC
+        LIMIT $2
+        "#,
+        usuario_email,
+        limit
+    )
+    .fetch_all(pool)
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::insert_purchase;
+    use crate::models::PurchaseInsert;
+
+    #[sqlx::test(migrations = "./migrations")]
+    async fn test_preferences_alerts_and_reports(pool: PgPool) -> sqlx::Result<()> {
+        for email in ["prefs@example.com", "mensual@example.com"] {
+            sqlx::query!(
+                "INSERT INTO usuarios (email, password_hash, nombre) VALUES ($1, $2, $3)",
+                email,
+                "$2b$12$KpIEW.jQKvqXfN5nDwAXLub8RCRYjqNvCLKXfzHpFGK2FQJGmqQJi",
+                "Prefs User"
+            )
+            .execute(&pool)
+            .await?;
+        }
+
+        assert!(get_user_preferences(&pool, "prefs@example.com")
+            .await?
+            .is_none());
+
+        let update = UserPreferencesUpdate {
+            alertas_gasto_activas: true,
+            umbral_alerta_gasto: Some(Decimal::new(20000, 2)),
+            notif_nuevos_logros: false,
+            notif_inflacion: true,
+            frecuencia_reportes: ReportFrequency::Mensual,
+            configuracion_extra: Some(serde_json::json!({ "tema": "oscuro" })),
+        };
+        let saved = upsert_user_preferences(&pool, "mensual@example.com", &update).await?;
+        assert_eq!(saved.frecuencia_reportes, "mensual");
+        assert!(!saved.notif_nuevos_logros);
+        assert!(saved.updated_at.is_some());
+        assert_eq!(
+            get_user_preferences(&pool, "mensual@example.com").await?,
+            Some(saved)
+        );
+
+        let marzo = NaiveDate::from_ymd_opt(2025, 3, 1).unwrap();
+        let alerta = insert_spending_alert(
+            &pool,
+            "prefs@example.com",
+            marzo,
+            Decimal::new(20000, 2),
+            Decimal::new(21000, 2),
+        )
+        .await?;
+        assert!(alerta.is_some());
+        assert!(insert_spending_alert(
+            &pool,
+            "prefs@example.com",
+            marzo,
+            Decimal::new(20000, 2),
+            Decimal::new(25000, 2)
+        )
+        .await?
+        .is_none());
+        assert_eq!(
+            list_spending_alerts(&pool, "prefs@example.com", 10)
+                .await?
+                .len(),
+            1
+        );
+
+        // Semana del 10 al 16 de marzo y la anterior
+        let dia = |d: u32| NaiveDate::from_ymd_opt(2025, 3, d).unwrap();
+        for (factura, email, fecha, centimos) in [
+            ("F-1", "prefs@example.com", dia(5), 3000),
+            ("F-2", "prefs@example.com", dia(10), 4000),
+            ("F-3", "prefs@example.com", dia(16), 1500),
+            ("F-4", "prefs@example.com", dia(17), 9900),
+            ("F-5", "mensual@example.com", dia(12), 5000),
+        ] {
+            insert_purchase(
+                &pool,
+                &PurchaseInsert {
+                    numero_factura: factura.to_string(),
+                    usuario_email: email.to_string(),
+                    fecha_hora: fecha.and_hms_opt(20, 0, 0).unwrap(),
+                    total: Decimal::new(centimos, 2),
+                    tienda: None,
+                    ubicacion: None,
+                    metodo_pago: None,
+                    numero_operacion: None,
+                },
+            )
+            .await?;
+        }
+
+        // Solo el usuario sin preferencias usa la frecuencia semanal por defecto
+        assert_eq!(
+            generate_user_reports(&pool, ReportFrequency::Semanal, dia(10), dia(16), dia(3))
+                .await?,
+            1
+        );
+        assert_eq!(
+            generate_user_reports(&pool, ReportFrequency::Semanal, dia(10), dia(16), dia(3))
+                .await?,
+            0
+        );
+
+        let reports = list_user_reports(&pool, "prefs@example.com", 10).await?;
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].total, Decimal::new(5500, 2));
+        assert_eq!(reports[0].compras, 2);
+        assert_eq!(reports[0].total_anterior, Decimal::new(3000, 2));
+        assert!(list_user_reports(&pool, "mensual@example.com", 10)
+            .await?
+            .is_empty());
+
+        Ok(())
+    }
+}
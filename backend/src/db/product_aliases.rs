@@ -0,0 +1,418 @@
+use serde::Serialize;
+use sqlx::{PgConnection, PgPool};
+use std::collections::HashMap;
+
+/// Filas afectadas al fusionar un producto en otro
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ProductMergeStats {
+    /// Líneas de compra que pasan al producto destino
+    pub lineas_movidas: u64,
+    /// Líneas sumadas a una línea del destino en la misma compra
+    pub lineas_fusionadas: u64,
+    /// Precios del histórico que pasan al producto destino
+    pub precios_movidos: u64,
+}
+
+/// Resuelve los alias de una lista de nombres normalizados
+///
+/// Devuelve solo los nombres que son alias, junto al producto canónico.
+pub async fn get_product_aliases(
+    pool: &PgPool,
+    nombres: &[String],
+) -> Result<HashMap<String, String>, sqlx::Error> {
+    let rows = sqlx::query!(
+        r#"
+        SELECT alias, producto_nombre
+        FROM productos_alias
+        WHERE alias = ANY($1)
+        "#,
+        nombres
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| (row.alias, row.producto_nombre))
+        .collect())
+}
+
+/// Bloquea los productos indicados y devuelve los que existen
+///
+/// Debe llamarse dentro de la transacción de la fusión para que ninguna
+/// ingesta añada líneas al producto origen mientras se reescribe.
+pub async fn lock_products(
+    conn: &mut PgConnection,
+    nombres: &[String],
+) -> Result<Vec<String>, sqlx::Error> {
+    let rows = sqlx::query_scalar!(
+        r#"
+        SELECT nombre
+        FROM productos
+        WHERE nombre = ANY($1)
+        ORDER BY nombre
+        FOR UPDATE
+        "#,
+        nombres
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(rows)
+}
+
+/// Fusiona el producto `origen` en `destino`
+///
+/// - Las líneas de compra pasan al destino; si la compra ya tenía una línea
+///   del destino, ambas se suman en una sola
+/// - El histórico de precios pasa al destino salvo los días en que el destino
+///   ya tenía precio, que se conservan
+/// - `origen` y sus alias quedan como alias de `destino` y el producto
+///   `origen` se elimina del catálogo
+pub async fn merge_products(
+    conn: &mut PgConnection,
+    origen: &str,
+    destino: &str,
+) -> Result<ProductMergeStats, sqlx::Error> {
+    // Líneas de compras que ya tienen el producto destino: se suman. El precio
+    // unitario se deriva del total como en `PurchaseProductInsert::absorb`; el
+    // redondeo al céntimo entra en la tolerancia de `precio_coherente`
+    let lineas_fusionadas = sqlx::query!(
+        r#"
+        WITH sumas AS (
+            SELECT
+                d.compra_numero_factura,
+                d.cantidad + o.cantidad AS cantidad,
+                d.precio_total + o.precio_total AS precio_total,
+                COALESCE(d.descuento, 0) + COALESCE(o.descuento, 0) AS descuento,
+                d.iva_importe + o.iva_importe AS iva_importe
+            FROM compras_productos d
+            JOIN compras_productos o ON o.compra_numero_factura = d.compra_numero_factura
+            WHERE o.producto_nombre = $1
+                AND d.producto_nombre = $2
+        )
+        UPDATE compras_productos d
+        SET
+            cantidad = s.cantidad,
+            precio_unitario = ROUND((s.precio_total + s.descuento) / s.cantidad, 2),
+            precio_total = s.precio_total,
+            descuento = s.descuento,
+            iva_importe = s.iva_importe
+        FROM sumas s
+        WHERE d.producto_nombre = $2
+            AND d.compra_numero_factura = s.compra_numero_factura
+        "#,
+        origen,
+        destino
+    )
+    .execute(&mut *conn)
+    .await?
+    .rows_affected();
+
+    sqlx::query!(
+        r#"
+        DELETE FROM compras_productos o
+        USING compras_productos d
+        WHERE o.producto_nombre = $1
+            AND d.producto_nombre = $2
+            AND d.compra_numero_factura = o.compra_numero_factura
+        "#,
+        origen,
+        destino
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    let lineas_movidas = sqlx::query!(
+        "UPDATE compras_productos SET producto_nombre = $2 WHERE producto_nombre = $1",
+        origen,
+        destino
+    )
+    .execute(&mut *conn)
+    .await?
+    .rows_affected();
+
+    // Histórico: el precio del destino prevalece en los días coincidentes
+    sqlx::query!(
+        r#"
+        DELETE FROM historico_precios o
+        USING historico_precios d
+        WHERE o.producto_nombre = $1
+            AND d.producto_nombre = $2
+            AND d.fecha_vigencia = o.fecha_vigencia
+        "#,
+        origen,
+        destino
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    let precios_movidos = sqlx::query!(
+        "UPDATE historico_precios SET producto_nombre = $2 WHERE producto_nombre = $1",
+        origen,
+        destino
+    )
+    .execute(&mut *conn)
+    .await?
+    .rows_affected();
+
+    // El destino conserva sus datos y toma el precio más reciente de ambos
+    sqlx::query!(
+        r#"
+        UPDATE productos d
+        SET
+            marca = COALESCE(d.marca, o.marca),
+            unidad = COALESCE(d.unidad, o.unidad),
+            precio_actual = CASE
+                WHEN o.precio_actualizado_en > d.precio_actualizado_en
+                     OR d.precio_actualizado_en IS NULL
+                THEN o.precio_actual
+                ELSE d.precio_actual
+            END,
+            precio_actualizado_en = GREATEST(d.precio_actualizado_en, o.precio_actualizado_en)
+        FROM productos o
+        WHERE d.nombre = $2 AND o.nombre = $1
+        "#,
+        origen,
+        destino
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    // Categorías elegidas por los usuarios: se conservan si el destino no tenía
+    sqlx::query!(
+        r#"
+        UPDATE usuarios_productos_categorias o
+        SET producto_nombre = $2
+        WHERE o.producto_nombre = $1
+            AND NOT EXISTS (
+                SELECT 1
+                FROM usuarios_productos_categorias d
+                WHERE d.usuario_email = o.usuario_email AND d.producto_nombre = $2
+            )
+        "#,
+        origen,
+        destino
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    // Alias: los del origen pasan al destino y el origen se convierte en alias
+    sqlx::query!(
+        "UPDATE productos_alias SET producto_nombre = $2 WHERE producto_nombre = $1",
+        origen,
+        destino
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query!("DELETE FROM productos WHERE nombre = $1", origen)
+        .execute(&mut *conn)
+        .await?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO productos_alias (alias, producto_nombre)
+        VALUES ($1, $2)
+        ON CONFLICT (alias) DO UPDATE SET producto_nombre = EXCLUDED.producto_nombre
+        "#,
+        origen,
+        destino
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(ProductMergeStats {
+        lineas_movidas,
+        lineas_fusionadas,
+        precios_movidos,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{
+        get_price_history, get_product, get_purchase_products, insert_purchase,
+        insert_purchase_products, upsert_product,
+    };
+    use crate::models::{ProductUpsert, PurchaseInsert, PurchaseProductInsert};
+    use chrono::{Duration, Utc};
+    use rust_decimal::Decimal;
+
+    fn line(nombre: &str, centimos: i64) -> PurchaseProductInsert {
+        PurchaseProductInsert {
+            producto_nombre: nombre.to_string(),
+            cantidad: Decimal::ONE,
+            precio_unitario: Decimal::new(centimos, 2),
+            precio_total: Decimal::new(centimos, 2),
+            descuento: Decimal::ZERO,
+            iva_porcentaje: Decimal::new(4, 0),
+            iva_importe: Decimal::ZERO,
+        }
+    }
+
+    #[sqlx::test(migrations = "./migrations")]
+    async fn test_merge_products(pool: PgPool) -> sqlx::Result<()> {
+        sqlx::query!(
+            "INSERT INTO usuarios (email, password_hash, nombre) VALUES ($1, $2, $3)",
+            "test@example.com",
+            "$2b$12$KpIEW.jQKvqXfN5nDwAXLub8RCRYjqNvCLKXfzHpFGK2FQJGmqQJi",
+            "Test User"
+        )
+        .execute(&pool)
+        .await?;
+
+        for nombre in ["LECHE SEMI", "LECHE SEMIDESNATADA"] {
+            upsert_product(
+                &pool,
+                &ProductUpsert {
+                    nombre: nombre.to_string(),
+                    marca: None,
+                    unidad: "unidad".to_string(),
+                    precio_actual: None,
+                },
+            )
+            .await?;
+        }
+
+        let now = Utc::now().naive_utc() - Duration::hours(1);
+        let purchases = [
+            (
+                "F-1",
+                now - Duration::days(10),
+                vec![line("LECHE SEMI", 90)],
+            ),
+            (
+                "F-2",
+                now,
+                vec![line("LECHE SEMI", 100), line("LECHE SEMIDESNATADA", 100)],
+            ),
+        ];
+
+        for (factura, fecha_hora, lineas) in purchases {
+            let total = lineas.iter().map(|l| l.precio_total).sum();
+            insert_purchase(
+                &pool,
+                &PurchaseInsert {
+                    numero_factura: factura.to_string(),
+                    usuario_email: "test@example.com".to_string(),
+                    fecha_hora,
+                    total,
+                    tienda: None,
+                    ubicacion: None,
+                    metodo_pago: None,
+                    numero_operacion: None,
+                },
+            )
+            .await?;
+
+            let mut conn = pool.acquire().await?;
+            insert_purchase_products(&mut conn, factura, &lineas).await?;
+        }
+
+        let mut tx = pool.begin().await?;
+        let locked = lock_products(
+            &mut tx,
+            &["LECHE SEMI".to_string(), "LECHE SEMIDESNATADA".to_string()],
+        )
+        .await?;
+        assert_eq!(locked.len(), 2);
+
+        let stats = merge_products(&mut tx, "LECHE SEMI", "LECHE SEMIDESNATADA").await?;
+        tx.commit().await?;
+
+        assert_eq!(
+            stats,
+            ProductMergeStats {
+                lineas_movidas: 1,
+                lineas_fusionadas: 1,
+                precios_movidos: 1,
+            }
+        );
+
+        // Las dos líneas de la misma compra quedan sumadas
+        let lineas = get_purchase_products(&pool, "F-2").await?;
+        assert_eq!(lineas.len(), 1);
+        assert_eq!(lineas[0].producto_nombre, "LECHE SEMIDESNATADA");
+        assert_eq!(lineas[0].cantidad, Decimal::new(2, 0));
+        assert_eq!(lineas[0].precio_total, Decimal::new(200, 2));
+
+        let history = get_price_history(&pool, "LECHE SEMIDESNATADA").await?;
+        assert_eq!(history.len(), 2);
+
+        assert!(get_product(&pool, "LECHE SEMI").await?.is_none());
+
+        let aliases = get_product_aliases(&pool, &["LECHE SEMI".to_string()]).await?;
+        assert_eq!(
+            aliases.get("LECHE SEMI").map(String::as_str),
+            Some("LECHE SEMIDESNATADA")
+        );
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrations = "./migrations")]
+    async fn test_merge_products_unequal_unit_prices(pool: PgPool) -> sqlx::Result<()> {
+        sqlx::query!(
+            "INSERT INTO usuarios (email, password_hash, nombre) VALUES ($1, $2, $3)",
+            "test@example.com",
+            "$2b$12$KpIEW.jQKvqXfN5nDwAXLub8RCRYjqNvCLKXfzHpFGK2FQJGmqQJi",
+            "Test User"
+        )
+        .execute(&pool)
+        .await?;
+
+        for nombre in ["YOGUR NAT", "YOGUR NATURAL"] {
+            upsert_product(
+                &pool,
+                &ProductUpsert {
+                    nombre: nombre.to_string(),
+                    marca: None,
+                    unidad: "unidad".to_string(),
+                    precio_actual: None,
+                },
+            )
+            .await?;
+        }
+
+        // 1 x 1.00 + 2 x 1.01: la media redondeada (1.01) da 3.03 para 3.02
+        let mut destino = line("YOGUR NATURAL", 101);
+        destino.cantidad = Decimal::new(2, 0);
+        destino.precio_total = Decimal::new(202, 2);
+        let lineas = [line("YOGUR NAT", 100), destino];
+
+        insert_purchase(
+            &pool,
+            &PurchaseInsert {
+                numero_factura: "F-1".to_string(),
+                usuario_email: "test@example.com".to_string(),
+                fecha_hora: Utc::now().naive_utc() - Duration::hours(1),
+                total: Decimal::new(302, 2),
+                tienda: None,
+                ubicacion: None,
+                metodo_pago: None,
+                numero_operacion: None,
+            },
+        )
+        .await?;
+        let mut conn = pool.acquire().await?;
+        insert_purchase_products(&mut conn, "F-1", &lineas).await?;
+
+        let mut tx = pool.begin().await?;
+        let stats = merge_products(&mut tx, "YOGUR NAT", "YOGUR NATURAL").await?;
+        tx.commit().await?;
+        assert_eq!(stats.lineas_fusionadas, 1);
+
+        let lineas = get_purchase_products(&pool, "F-1").await?;
+        assert_eq!(lineas.len(), 1);
+        let linea = &lineas[0];
+        assert_eq!(linea.cantidad, Decimal::new(3, 0));
+        assert_eq!(linea.precio_total, Decimal::new(302, 2));
+        assert_eq!(linea.precio_unitario, Decimal::new(101, 2));
+        // El resto del redondeo no se guarda como descuento
+        assert_eq!(linea.descuento, Decimal::ZERO);
+
+        Ok(())
+    }
+}
@@ -1,6 +1,48 @@
 use crate::models::{Product, ProductUpsert};
+use chrono::{NaiveDate, NaiveDateTime};
+use rust_decimal::Decimal;
+use serde::Serialize;
 use sqlx::{PgPool, Postgres};
 
+/// Precio de un producto vigente desde una fecha
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct PriceHistoryPoint {
+    pub fecha: NaiveDate,
+    pub precio: Decimal,
+    pub fuente: Option<String>,
+}
+
+/// Precio pagado por el usuario en una de sus compras
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct UserPricePoint {
+    pub fecha_hora: NaiveDateTime,
+    pub numero_factura: String,
+    pub cantidad: Decimal,
+    pub precio_unitario: Decimal,
+}
+
+/// Producto que el usuario ha pagado más caro que en su compra anterior
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct PriceJump {
+    pub producto_nombre: String,
+    pub precio_anterior: Decimal,
+    pub precio_actual: Decimal,
+    pub variacion_porcentaje: Decimal,
+}
+
+/// Producto encontrado en la búsqueda por nombre
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct ProductSearchResult {
+    pub nombre: String,
+    pub marca: Option<String>,
+    pub unidad: Option<String>,
+    pub precio_actual: Option<Decimal>,
+    /// Última compra del producto hecha por el usuario
+    pub ultima_compra: Option<NaiveDateTime>,
+    /// Parecido con el texto buscado, entre 0 y 1
+    pub similitud: f32,
+}
+
 /// Busca un producto por su nombre (normalizado)
 pub async fn get_product(pool: &PgPool, nombre: &str) -> Result<Option<Product>, sqlx::Error> {
     let product = sqlx::query_as!(
@@ -27,7 +69,9 @@ pub async fn get_product(pool: &PgPool, nombre: &str) -> Result<Option<Product>,
 /// Inserta o actualiza un producto en el catálogo.
 /// - Si el producto no existe, lo crea con todos los datos proporcionados.
 /// - Si el producto existe:
-///   - Actualiza marca y unidad solo si estaban vacíos antes
+///   - Actualiza marca solo si estaba vacía antes
+///   - Actualiza la unidad si estaba vacía o era `unidad` (productos a peso
+///     registrados antes de leer la unidad del OCR)
 ///   - Actualiza precio_actual solo si el nuevo precio es más reciente
 ///
 /// Puede usarse tanto con un pool como con una transacción
@@ -46,7 +90,11 @@ where
         ON CONFLICT (nombre)
         DO UPDATE SET
             marca = COALESCE(productos.marca, EXCLUDED.marca),
-            unidad = COALESCE(productos.unidad, EXCLUDED.unidad),
+            unidad = CASE
+                WHEN productos.unidad IS NULL OR productos.unidad = 'unidad'
+                THEN EXCLUDED.unidad
+                ELSE productos.unidad
+            END,
             precio_actual = CASE
                 WHEN productos.precio_actualizado_en IS NULL
                      OR productos.precio_actualizado_en < CURRENT_TIMESTAMP
@@ -78,6 +126,180 @@ where
     Ok(result)
 }
 
+/// Registra un precio en el histórico con el origen indicado.
+/// Si ya existe una entrada para ese día se sobrescribe precio y origen.
+pub async fn upsert_price_history<'c, E>(
+    executor: E,
+    producto_nombre: &str,
+    fecha_vigencia: NaiveDate,
+    precio: Decimal,
+    fuente: &str,
+) -> Result<(), sqlx::Error>
+where
+    E: sqlx::Executor<'c, Database = Postgres>,
+{
+    sqlx::query!(
+        r#"
+        INSERT INTO historico_precios (producto_nombre, fecha_vigencia, precio, fuente)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (producto_nombre, fecha_vigencia)
+        DO UPDATE SET
+            precio = EXCLUDED.precio,
+            fuente = EXCLUDED.fuente,
+            created_at = CURRENT_TIMESTAMP
+        "#,
+        producto_nombre,
+        fecha_vigencia,
+        precio,
+        fuente
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+/// Serie histórica de precios de un producto, de la más antigua a la más reciente
+pub async fn get_price_history(
+    pool: &PgPool,
+    producto_nombre: &str,
+) -> Result<Vec<PriceHistoryPoint>, sqlx::Error> {
+    sqlx::query_as!(
+        PriceHistoryPoint,
+        r#"
+        SELECT
+            fecha_vigencia as fecha,
+            precio,
+            fuente
+        FROM historico_precios
+        WHERE producto_nombre = $1
+        ORDER BY fecha_vigencia ASC
+        "#,
+        producto_nombre
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Compras de un producto hechas por un usuario, en orden cronológico
+pub async fn get_user_product_purchases(
+    pool: &PgPool,
+    usuario_email: &str,
+    producto_nombre: &str,
+) -> Result<Vec<UserPricePoint>, sqlx::Error> {
+    sqlx::query_as!(
+        UserPricePoint,
+        r#"
+        SELECT
+            c.fecha_hora,
+            c.numero_factura,
+            cp.cantidad,
+            cp.precio_unitario
+        FROM compras_productos cp
+        INNER JOIN compras c ON c.numero_factura = cp.compra_numero_factura
+        WHERE c.usuario_email = $1 AND cp.producto_nombre = $2
+        ORDER BY c.fecha_hora ASC
+        "#,
+        usuario_email,
+        producto_nombre
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Productos de una compra cuyo precio unitario ha subido al menos `min_pct` %
+///
+/// Cada línea se compara con la compra anterior del mismo producto hecha por
+/// el usuario. Se ordenan de mayor a menor subida.
+pub async fn get_purchase_price_jumps(
+    pool: &PgPool,
+    usuario_email: &str,
+    numero_factura: &str,
+    min_pct: Decimal,
+) -> Result<Vec<PriceJump>, sqlx::Error> {
+    sqlx::query_as!(
+        PriceJump,
+        r#"
+        SELECT
+            cp.producto_nombre,
+            prev.precio_unitario as "precio_anterior!",
+            cp.precio_unitario as precio_actual,
+            ROUND((cp.precio_unitario - prev.precio_unitario) * 100 / prev.precio_unitario, 2)
+                as "variacion_porcentaje!"
+        FROM compras_productos cp
+        INNER JOIN compras c ON c.numero_factura = cp.compra_numero_factura
+        INNER JOIN LATERAL (
+            SELECT cp2.precio_unitario
+            FROM compras_productos cp2
+            INNER JOIN compras c2 ON c2.numero_factura = cp2.compra_numero_factura
+            WHERE c2.usuario_email = c.usuario_email
+                AND cp2.producto_nombre = cp.producto_nombre
+                AND c2.fecha_hora < c.fecha_hora
+            ORDER BY c2.fecha_hora DESC
+            LIMIT 1
+        ) prev ON TRUE
+        WHERE c.numero_factura = $2
+            AND c.usuario_email = $1
+            AND prev.precio_unitario > 0
+            AND cp.precio_unitario >= prev.precio_unitario * (1 + $3::numeric / 100)
+        ORDER BY 4 DESC, cp.producto_nombre
+        "#,
+        usuario_email,
+        numero_factura,
+        min_pct
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Búsqueda aproximada de productos por nombre usando trigramas (`pg_trgm`)
+///
+/// Con `whole_catalog = false` solo devuelve productos que el usuario ha
+/// comprado alguna vez. Los resultados se ordenan por parecido y, a igualdad,
+/// por la compra más reciente.
+pub async fn search_products(
+    pool: &PgPool,
+    usuario_email: &str,
+    query: &str,
+    whole_catalog: bool,
+    limit: i64,
+) -> Result<Vec<ProductSearchResult>, sqlx::Error> {
+    sqlx::query_as!(
+        ProductSearchResult,
+        r#"
+        SELECT
+            p.nombre,
+            p.marca,
+            p.unidad,
+            p.precio_actual,
+            compras_usuario.ultima_compra,
+            GREATEST(similarity(p.nombre, $2), word_similarity($2, p.nombre)) as "similitud!"
+        FROM productos p
+        LEFT JOIN LATERAL (
+            SELECT MAX(c.fecha_hora) as ultima_compra
+            FROM compras_productos cp
+            INNER JOIN compras c ON c.numero_factura = cp.compra_numero_factura
+            WHERE cp.producto_nombre = p.nombre AND c.usuario_email = $1
+        ) compras_usuario ON TRUE
+        WHERE (
+                p.nombre % $2
+                OR $2 <% p.nombre
+                -- Subcadena literal: se escapan los comodines de LIKE de la consulta
+                OR p.nombre ILIKE '%' || replace(replace(replace($2, '\', '\\'), '%', '\%'), '_', '\_') || '%'
+            )
+            AND ($3 OR compras_usuario.ultima_compra IS NOT NULL)
+        ORDER BY "similitud!" DESC, compras_usuario.ultima_compra DESC NULLS LAST, p.nombre
+        LIMIT $4
+        "#,
+        usuario_email,
+        query,
+        whole_catalog,
+        limit
+    )
+    .fetch_all(pool)
+    .await
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -127,4 +349,198 @@ mod tests {
 
         Ok(())
     }
+
+    #[sqlx::test(migrations = "./migrations")]
+    async fn test_price_history_and_user_purchases(pool: PgPool) -> sqlx::Result<()> {
+        use crate::db::{insert_purchase, insert_purchase_products};
+        use crate::models::{PurchaseInsert, PurchaseProductInsert};
+        use chrono::{Duration, Utc};
+
+        for email in ["test@example.com", "other@example.com"] {
+            sqlx::query!(
+                "INSERT INTO usuarios (email, password_hash, nombre) VALUES ($1, $2, $3)",
+                email,
+                "$2b$12$KpIEW.jQKvqXfN5nDwAXLub8RCRYjqNvCLKXfzHpFGK2FQJGmqQJi",
+                "Test User"
+            )
+            .execute(&pool)
+            .await?;
+        }
+
+        upsert_product(
+            &pool,
+            &ProductUpsert {
+                nombre: "LECHE ENTERA".to_string(),
+                marca: None,
+                unidad: "unidad".to_string(),
+                precio_actual: Some(Decimal::new(95, 2)),
+            },
+        )
+        .await?;
+
+        let now = Utc::now().naive_utc() - Duration::hours(1);
+        let purchases = [
+            ("F-1", "test@example.com", now - Duration::days(60), 95),
+            ("F-2", "other@example.com", now - Duration::days(30), 99),
+            ("F-3", "test@example.com", now, 105),
+        ];
+
+        for (factura, email, fecha_hora, centimos) in purchases {
+            let precio = Decimal::new(centimos, 2);
+            insert_purchase(
+                &pool,
+                &PurchaseInsert {
+                    numero_factura: factura.to_string(),
+                    usuario_email: email.to_string(),
+                    fecha_hora,
+                    total: precio,
+                    tienda: None,
+                    ubicacion: None,
+                    metodo_pago: None,
+                    numero_operacion: None,
+                },
+            )
+            .await?;
+
+            let mut conn = pool.acquire().await?;
+            insert_purchase_products(
+                &mut conn,
+                factura,
+                &[PurchaseProductInsert {
+                    producto_nombre: "LECHE ENTERA".to_string(),
+                    cantidad: Decimal::ONE,
+                    precio_unitario: precio,
+                    precio_total: precio,
+                    descuento: Decimal::ZERO,
+                    iva_porcentaje: Decimal::new(4, 0),
+                    iva_importe: Decimal::ZERO,
+                }],
+            )
+            .await?;
+        }
+
+        // El trigger registra el precio de todas las compras
+        let history = get_price_history(&pool, "LECHE ENTERA").await?;
+        let prices: Vec<_> = history.iter().map(|p| p.precio).collect();
+        assert_eq!(
+            prices,
+            vec![
+                Decimal::new(95, 2),
+                Decimal::new(99, 2),
+                Decimal::new(105, 2)
+            ]
+        );
+
+        // Solo las compras propias aparecen como puntos del usuario
+        let own = get_user_product_purchases(&pool, "test@example.com", "LECHE ENTERA").await?;
+        let facturas: Vec<_> = own.iter().map(|p| p.numero_factura.as_str()).collect();
+        assert_eq!(facturas, vec!["F-1", "F-3"]);
+
+        // La subida se mide contra la compra anterior del mismo usuario (0,95 -> 1,05)
+        let jumps =
+            get_purchase_price_jumps(&pool, "test@example.com", "F-3", Decimal::new(10, 0)).await?;
+        assert_eq!(jumps.len(), 1);
+        assert_eq!(jumps[0].precio_anterior, Decimal::new(95, 2));
+        assert_eq!(jumps[0].variacion_porcentaje, Decimal::new(1053, 2));
+        assert!(
+            get_purchase_price_jumps(&pool, "test@example.com", "F-3", Decimal::new(20, 0))
+                .await?
+                .is_empty()
+        );
+        assert!(
+            get_purchase_price_jumps(&pool, "test@example.com", "F-1", Decimal::new(10, 0))
+                .await?
+                .is_empty()
+        );
+
+        Ok(())
+    }
+    #[sqlx::test(migrations = "./migrations")]
+    async fn test_search_products(pool: PgPool) -> sqlx::Result<()> {
+        use crate::db::{insert_purchase, insert_purchase_products};
+        use crate::models::{PurchaseInsert, PurchaseProductInsert};
+        use chrono::{Duration, Utc};
+
+        sqlx::query!(
+            "INSERT INTO usuarios (email, password_hash, nombre) VALUES ($1, $2, $3)",
+            "test@example.com",
+            "$2b$12$KpIEW.jQKvqXfN5nDwAXLub8RCRYjqNvCLKXfzHpFGK2FQJGmqQJi",
+            "Test User"
+        )
+        .execute(&pool)
+        .await?;
+
+        for nombre in [
+            "LECHE ENTERA",
+            "LECHE DESNATADA",
+            "PAN INTEGRAL",
+            "ZUMO NARANJA 100%",
+        ] {
+            upsert_product(
+                &pool,
+                &ProductUpsert {
+                    nombre: nombre.to_string(),
+                    marca: None,
+                    unidad: "unidad".to_string(),
+                    precio_actual: Some(Decimal::new(95, 2)),
+                },
+            )
+            .await?;
+        }
+
+        let fecha_hora = Utc::now().naive_utc() - Duration::hours(1);
+        insert_purchase(
+            &pool,
+            &PurchaseInsert {
+                numero_factura: "F-1".to_string(),
+                usuario_email: "test@example.com".to_string(),
+                fecha_hora,
+                total: Decimal::new(95, 2),
+                tienda: None,
+                ubicacion: None,
+                metodo_pago: None,
+                numero_operacion: None,
+            },
+        )
+        .await?;
+
+        let mut conn = pool.acquire().await?;
+        insert_purchase_products(
+            &mut conn,
+            "F-1",
+            &[PurchaseProductInsert {
+                producto_nombre: "LECHE ENTERA".to_string(),
+                cantidad: Decimal::ONE,
+                precio_unitario: Decimal::new(95, 2),
+                precio_total: Decimal::new(95, 2),
+                descuento: Decimal::ZERO,
+                iva_porcentaje: Decimal::new(4, 0),
+                iva_importe: Decimal::ZERO,
+            }],
+        )
+        .await?;
+
+        // Por defecto solo aparecen los productos comprados por el usuario
+        let own = search_products(&pool, "test@example.com", "leche", false, 10).await?;
+        assert_eq!(own.len(), 1);
+        assert_eq!(own[0].nombre, "LECHE ENTERA");
+        assert!(own[0].ultima_compra.is_some());
+
+        // En todo el catálogo se toleran errores de escritura
+        let all = search_products(&pool, "test@example.com", "leche desnatda", true, 10).await?;
+        assert_eq!(all[0].nombre, "LECHE DESNATADA");
+        assert!(all[0].ultima_compra.is_none());
+        assert!(all.iter().all(|p| p.nombre != "PAN INTEGRAL"));
+
+        // Los comodines de LIKE se buscan literalmente
+        let percent = search_products(&pool, "test@example.com", "%%", true, 10).await?;
+        assert!(percent.is_empty());
+        let percent = search_products(&pool, "test@example.com", "100%", true, 10).await?;
+        assert_eq!(percent.len(), 1);
+        assert_eq!(percent[0].nombre, "ZUMO NARANJA 100%");
+        let underscore = search_products(&pool, "test@example.com", "_", true, 10).await?;
+        assert!(underscore.is_empty());
+
+        Ok(())
+    }
 }
@@ -1,11 +1,46 @@
-use crate::models::{Purchase, PurchaseInsert, PurchaseProductInsert};
-use sqlx::{PgPool, Postgres};
+use crate::models::{
+    Purchase, PurchaseInsert, PurchaseIvaInsert, PurchaseProduct, PurchaseProductInsert,
+};
+use chrono::{NaiveDate, NaiveDateTime};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::{PgConnection, PgPool, Postgres};
+
+/// Filtro de compras para las estadísticas y el histórico
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PurchaseFilter {
+    /// Solo compras de esta tienda (`compras.tienda`)
+    pub tienda: Option<String>,
+    /// Primer día incluido
+    pub from: Option<NaiveDate>,
+    /// Último día incluido
+    pub to: Option<NaiveDate>,
+}
+
+impl PurchaseFilter {
+    /// Indica si el filtro acota las fechas
+    pub fn has_range(&self) -> bool {
+        self.from.is_some() || self.to.is_some()
+    }
+}
+
+/// Totales de IVA de una compra agrupados por tipo impositivo
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct PurchaseIvaTotal {
+    pub iva_porcentaje: Decimal,
+    pub base_imponible: Decimal,
+    pub cuota: Decimal,
+    pub total: Decimal,
+}
 
 /// Busca una compra por número de factura
-pub async fn get_purchase(
-    pool: &PgPool,
+pub async fn get_purchase<'c, E>(
+    executor: E,
     numero_factura: &str,
-) -> Result<Option<Purchase>, sqlx::Error> {
+) -> Result<Option<Purchase>, sqlx::Error>
+where
+    E: sqlx::Executor<'c, Database = Postgres>,
+{
     let purchase = sqlx::query_as!(
         Purchase,
         r#"
@@ -24,12 +59,71 @@ pub async fn get_purchase(
         "#,
         numero_factura
     )
-    .fetch_optional(pool)
+    .fetch_optional(executor)
     .await?;
 
     Ok(purchase)
 }
 
+/// Obtiene las líneas de producto de una compra
+pub async fn get_purchase_products<'c, E>(
+    executor: E,
+    numero_factura: &str,
+) -> Result<Vec<PurchaseProduct>, sqlx::Error>
+where
+    E: sqlx::Executor<'c, Database = Postgres>,
+{
+    let productos = sqlx::query_as!(
+        PurchaseProduct,
+        r#"
+        SELECT
+            compra_numero_factura,
+            producto_nombre,
+            cantidad,
+            precio_unitario,
+            precio_total,
+            COALESCE(descuento, 0) as "descuento!",
+            iva_porcentaje,
+            iva_importe
+        FROM compras_productos
+        WHERE compra_numero_factura = $1
+        ORDER BY producto_nombre
+        "#,
+        numero_factura
+    )
+    .fetch_all(executor)
+    .await?;
+
+    Ok(productos)
+}
+
+/// Agrega el IVA de las líneas de una compra por tipo impositivo.
+/// Los precios del ticket incluyen IVA, por lo que la base es el importe menos la cuota.
+pub async fn get_purchase_iva_totals(
+    pool: &PgPool,
+    numero_factura: &str,
+) -> Result<Vec<PurchaseIvaTotal>, sqlx::Error> {
+    let totals = sqlx::query_as!(
+        PurchaseIvaTotal,
+        r#"
+        SELECT
+            iva_porcentaje,
+            SUM(precio_total - iva_importe)::numeric as "base_imponible!",
+            SUM(iva_importe)::numeric as "cuota!",
+            SUM(precio_total)::numeric as "total!"
+        FROM compras_productos
+        WHERE compra_numero_factura = $1
+        GROUP BY iva_porcentaje
+        ORDER BY iva_porcentaje
+        "#,
+        numero_factura
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(totals)
+}
+
 /// Inserta una nueva compra
 pub async fn insert_purchase<'c, E>(
     executor: E,
@@ -78,10 +172,172 @@ where
     Ok(result)
 }
 
+/// Resumen del borrado de una compra
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PurchaseDeletion {
+    pub lineas_eliminadas: u64,
+    pub productos_eliminados: u64,
+}
+
+/// Elimina una compra y deshace los efectos que solo ella había producido.
+///
+/// El borrado de `compras` arrastra en cascada `compras_productos`, `compras_iva`
+/// y los metadatos de `tickets_adjuntos`; después se corrigen precios e histórico
+/// con `refresh_derived_prices`. El archivo del ticket está en el almacenamiento
+/// configurado, fuera de la cascada: quien llama lo borra con `remove_stored_file`
+/// tras confirmar la transacción.
+///
+/// NOTA: Debe llamarse dentro de una transacción
+pub async fn delete_purchase(
+    conn: &mut PgConnection,
+    numero_factura: &str,
+) -> Result<Option<PurchaseDeletion>, sqlx::Error> {
+    let Some(purchase) = sqlx::query!(
+        r#"
+        SELECT fecha_hora::date as "fecha!"
+        FROM compras
+        WHERE numero_factura = $1
+        FOR UPDATE
+        "#,
+        numero_factura
+    )
+    .fetch_optional(&mut *conn)
+    .await?
+    else {
+        return Ok(None);
+    };
+
+    let productos: Vec<String> = sqlx::query_scalar!(
+        r#"
+        SELECT producto_nombre
+        FROM compras_productos
+        WHERE compra_numero_factura = $1
+        "#,
+        numero_factura
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    sqlx::query!(
+        "DELETE FROM compras WHERE numero_factura = $1",
+        numero_factura
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    let productos_eliminados = refresh_derived_prices(conn, &productos, purchase.fecha).await?;
+
+    Ok(Some(PurchaseDeletion {
+        lineas_eliminadas: productos.len() as u64,
+        productos_eliminados,
+    }))
+}
+
+/// Recalcula los datos que los triggers de ingesta derivan de `compras_productos`
+/// tras eliminar o modificar líneas de los productos indicados:
+/// - `historico_precios`: las entradas de origen `ticket` de `fecha` se recalculan
+///   con las líneas restantes de ese día o se eliminan si ya no queda ninguna
+/// - `productos.precio_actual`: pasa a ser el de la compra restante más reciente
+/// - Los productos sin ninguna compra ni histórico se eliminan del catálogo
+///
+/// Devuelve el número de productos eliminados del catálogo
+pub async fn refresh_derived_prices(
+    conn: &mut PgConnection,
+    productos: &[String],
+    fecha: NaiveDate,
+) -> Result<u64, sqlx::Error> {
+    // Histórico del día: recalcular con otras compras del mismo día o eliminar
+    sqlx::query!(
+        r#"
+        UPDATE historico_precios h
+        SET precio = restante.precio_unitario
+        FROM (
+            SELECT DISTINCT ON (cp.producto_nombre)
+                cp.producto_nombre,
+                cp.precio_unitario
+            FROM compras_productos cp
+            INNER JOIN compras c ON c.numero_factura = cp.compra_numero_factura
+            WHERE cp.producto_nombre = ANY($1)
+                AND c.fecha_hora::date = $2
+            ORDER BY cp.producto_nombre, c.created_at DESC
+        ) restante
+        WHERE h.producto_nombre = restante.producto_nombre
+            AND h.fecha_vigencia = $2
+            AND h.fuente = 'ticket'
+        "#,
+        productos,
+        fecha
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        DELETE FROM historico_precios h
+        WHERE h.producto_nombre = ANY($1)
+            AND h.fecha_vigencia = $2
+            AND h.fuente = 'ticket'
+            AND NOT EXISTS (
+                SELECT 1
+                FROM compras_productos cp
+                INNER JOIN compras c ON c.numero_factura = cp.compra_numero_factura
+                WHERE cp.producto_nombre = h.producto_nombre
+                    AND c.fecha_hora::date = h.fecha_vigencia
+            )
+        "#,
+        productos,
+        fecha
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    // Precio actual: el de la compra restante más reciente
+    sqlx::query!(
+        r#"
+        UPDATE productos p
+        SET
+            precio_actual = ultima.precio_unitario,
+            precio_actualizado_en = CURRENT_TIMESTAMP
+        FROM (
+            SELECT DISTINCT ON (cp.producto_nombre)
+                cp.producto_nombre,
+                cp.precio_unitario
+            FROM compras_productos cp
+            INNER JOIN compras c ON c.numero_factura = cp.compra_numero_factura
+            WHERE cp.producto_nombre = ANY($1)
+            ORDER BY cp.producto_nombre, c.fecha_hora DESC, c.created_at DESC
+        ) ultima
+        WHERE p.nombre = ultima.producto_nombre
+        "#,
+        productos
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    // Productos que ya no tienen compras ni histórico
+    let huerfanos = sqlx::query!(
+        r#"
+        DELETE FROM productos p
+        WHERE p.nombre = ANY($1)
+            AND NOT EXISTS (
+                SELECT 1 FROM compras_productos cp WHERE cp.producto_nombre = p.nombre
+            )
+            AND NOT EXISTS (
+                SELECT 1 FROM historico_precios h WHERE h.producto_nombre = p.nombre
+            )
+        "#,
+        productos
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(huerfanos.rows_affected())
+}
+
 /// Inserta múltiples productos asociados a una compra
 /// NOTA: Esta función debe llamarse dentro de una transacción junto con insert_purchase
 pub async fn insert_purchase_products(
-    pool: &PgPool,
+    conn: &mut PgConnection,
     numero_factura: &str,
     items: &[PurchaseProductInsert],
 ) -> Result<u64, sqlx::Error> {
@@ -111,7 +367,7 @@ pub async fn insert_purchase_products(
             item.iva_porcentaje,
             item.iva_importe
         )
-        .execute(pool)
+        .execute(&mut *conn)
         .await?;
 
         total_inserted += result.rows_affected();
@@ -120,6 +376,164 @@ pub async fn insert_purchase_products(
     Ok(total_inserted)
 }
 
+/// Guarda el desglose de IVA impreso en el ticket
+pub async fn insert_purchase_iva(
+    conn: &mut PgConnection,
+    numero_factura: &str,
+    desglose: &[PurchaseIvaInsert],
+) -> Result<u64, sqlx::Error> {
+    let mut total_inserted = 0u64;
+
+    for fila in desglose {
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO compras_iva (
+                compra_numero_factura,
+                iva_porcentaje,
+                base_imponible,
+                cuota
+            )
+            VALUES ($1, $2, $3, $4)
+            "#,
+            numero_factura,
+            fila.iva_porcentaje,
+            fila.base_imponible,
+            fila.cuota
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        total_inserted += result.rows_affected();
+    }
+
+    Ok(total_inserted)
+}
+
+/// Desglose de IVA impreso en el ticket, tal y como se guardó al ingestarlo
+pub async fn get_purchase_iva_breakdown<'c, E>(
+    executor: E,
+    numero_factura: &str,
+) -> Result<Vec<PurchaseIvaInsert>, sqlx::Error>
+where
+    E: sqlx::Executor<'c, Database = Postgres>,
+{
+    sqlx::query_as!(
+        PurchaseIvaInsert,
+        r#"
+        SELECT iva_porcentaje, base_imponible, cuota
+        FROM compras_iva
+        WHERE compra_numero_factura = $1
+        ORDER BY iva_porcentaje
+        "#,
+        numero_factura
+    )
+    .fetch_all(executor)
+    .await
+}
+
+/// Actualiza cantidades, precios e IVA de una línea existente de una compra
+pub async fn update_purchase_product<'c, E>(
+    executor: E,
+    numero_factura: &str,
+    item: &PurchaseProductInsert,
+) -> Result<u64, sqlx::Error>
+where
+    E: sqlx::Executor<'c, Database = Postgres>,
+{
+    let result = sqlx::query!(
+        r#"
+        UPDATE compras_productos
+        SET
+            cantidad = $3,
+            precio_unitario = $4,
+            precio_total = $5,
+            descuento = $6,
+            iva_porcentaje = $7,
+            iva_importe = $8
+        WHERE compra_numero_factura = $1
+            AND producto_nombre = $2
+        "#,
+        numero_factura,
+        item.producto_nombre,
+        item.cantidad,
+        item.precio_unitario,
+        item.precio_total,
+        item.descuento,
+        item.iva_porcentaje,
+        item.iva_importe
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// Elimina líneas concretas de una compra
+pub async fn delete_purchase_products<'c, E>(
+    executor: E,
+    numero_factura: &str,
+    productos: &[String],
+) -> Result<u64, sqlx::Error>
+where
+    E: sqlx::Executor<'c, Database = Postgres>,
+{
+    let result = sqlx::query!(
+        r#"
+        DELETE FROM compras_productos
+        WHERE compra_numero_factura = $1
+            AND producto_nombre = ANY($2)
+        "#,
+        numero_factura,
+        productos
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// Actualiza los campos de cabecera editables de una compra
+pub async fn update_purchase_header<'c, E>(
+    executor: E,
+    numero_factura: &str,
+    fecha_hora: NaiveDateTime,
+    tienda: Option<&str>,
+    metodo_pago: Option<&str>,
+) -> Result<Purchase, sqlx::Error>
+where
+    E: sqlx::Executor<'c, Database = Postgres>,
+{
+    let result = sqlx::query_as!(
+        Purchase,
+        r#"
+        UPDATE compras
+        SET
+            fecha_hora = $2,
+            tienda = $3,
+            metodo_pago = $4
+        WHERE numero_factura = $1
+        RETURNING
+            numero_factura,
+            usuario_email,
+            fecha_hora,
+            total,
+            tienda,
+            ubicacion,
+            metodo_pago,
+            numero_operacion,
+            created_at
+        "#,
+        numero_factura,
+        fecha_hora,
+        tienda,
+        metodo_pago
+    )
+    .fetch_one(executor)
+    .await?;
+
+    Ok(result)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -160,4 +574,194 @@ mod tests {
 
         Ok(())
     }
+
+    #[sqlx::test(migrations = "./migrations")]
+    async fn test_get_purchase_products_and_iva_totals(pool: PgPool) -> sqlx::Result<()> {
+        sqlx::query!(
+            "INSERT INTO usuarios (email, password_hash, nombre) VALUES ($1, $2, $3)",
+            "detail@example.com",
+            "$2b$12$KpIEW.jQKvqXfN5nDwAXLub8RCRYjqNvCLKXfzHpFGK2FQJGmqQJi",
+            "Detail User"
+        )
+        .execute(&pool)
+        .await?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO compras (numero_factura, usuario_email, fecha_hora, total)
+            VALUES ($1, $2, $3, $4)
+            "#,
+            "0001-001-000010",
+            "detail@example.com",
+            NaiveDate::from_ymd_opt(2025, 1, 15)
+                .unwrap()
+                .and_hms_opt(10, 30, 0)
+                .unwrap(),
+            Decimal::new(1320, 2)
+        )
+        .execute(&pool)
+        .await?;
+
+        // (producto, cantidad, precio_unitario, precio_total, iva %, cuota)
+        let lineas = [
+            ("LECHE ENTERA", 2, 110, 220, 1000, 20),
+            ("PAN DE MOLDE", 1, 100, 100, 400, 4),
+            ("DETERGENTE", 1, 1000, 1000, 2100, 174),
+        ];
+
+        for (producto, cantidad, unitario, total, iva, cuota) in lineas {
+            sqlx::query!(
+                "INSERT INTO productos (nombre, unidad) VALUES ($1, 'unidad')",
+                producto
+            )
+            .execute(&pool)
+            .await?;
+
+            sqlx::query!(
+                r#"
+                INSERT INTO compras_productos (
+                    compra_numero_factura,
+                    producto_nombre,
+                    cantidad,
+                    precio_unitario,
+                    precio_total,
+                    descuento,
+                    iva_porcentaje,
+                    iva_importe
+                )
+                VALUES ($1, $2, $3, $4, $5, 0, $6, $7)
+                "#,
+                "0001-001-000010",
+                producto,
+                Decimal::from(cantidad),
+                Decimal::new(unitario, 2),
+                Decimal::new(total, 2),
+                Decimal::new(iva, 2),
+                Decimal::new(cuota, 2)
+            )
+            .execute(&pool)
+            .await?;
+        }
+
+        let productos = get_purchase_products(&pool, "0001-001-000010").await?;
+        assert_eq!(productos.len(), 3);
+        assert_eq!(productos[0].producto_nombre, "DETERGENTE");
+        assert_eq!(productos[1].cantidad, Decimal::from(2));
+
+        let iva = get_purchase_iva_totals(&pool, "0001-001-000010").await?;
+        assert_eq!(iva.len(), 3);
+        assert_eq!(iva[2].iva_porcentaje, Decimal::new(2100, 2));
+        assert_eq!(iva[2].cuota, Decimal::new(174, 2));
+        assert_eq!(iva[2].base_imponible, Decimal::new(826, 2));
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrations = "./migrations")]
+    async fn test_delete_purchase_fixes_derived_prices(pool: PgPool) -> sqlx::Result<()> {
+        sqlx::query!(
+            "INSERT INTO usuarios (email, password_hash, nombre) VALUES ($1, $2, $3)",
+            "delete@example.com",
+            "$2b$12$KpIEW.jQKvqXfN5nDwAXLub8RCRYjqNvCLKXfzHpFGK2FQJGmqQJi",
+            "Delete User"
+        )
+        .execute(&pool)
+        .await?;
+
+        for nombre in ["LECHE ENTERA", "PAN DE MOLDE"] {
+            sqlx::query!(
+                "INSERT INTO productos (nombre, unidad) VALUES ($1, 'unidad')",
+                nombre
+            )
+            .execute(&pool)
+            .await?;
+        }
+
+        // (factura, dia, [(producto, precio)])
+        let compras = [
+            (
+                "0001-001-000020",
+                10,
+                vec![("LECHE ENTERA", 100), ("PAN DE MOLDE", 200)],
+            ),
+            ("0001-001-000021", 20, vec![("LECHE ENTERA", 120)]),
+        ];
+
+        for (numero_factura, dia, lineas) in &compras {
+            let total: i64 = lineas.iter().map(|(_, precio)| precio).sum();
+            sqlx::query!(
+                r#"
+                INSERT INTO compras (numero_factura, usuario_email, fecha_hora, total)
+                VALUES ($1, $2, $3, $4)
+                "#,
+                numero_factura,
+                "delete@example.com",
+                NaiveDate::from_ymd_opt(2025, 1, *dia)
+                    .unwrap()
+                    .and_hms_opt(10, 0, 0)
+                    .unwrap(),
+                Decimal::new(total, 2)
+            )
+            .execute(&pool)
+            .await?;
+
+            for (producto, precio) in lineas {
+                sqlx::query!(
+                    r#"
+                    INSERT INTO compras_productos (
+                        compra_numero_factura,
+                        producto_nombre,
+                        cantidad,
+                        precio_unitario,
+                        precio_total,
+                        descuento,
+                        iva_porcentaje,
+                        iva_importe
+                    )
+                    VALUES ($1, $2, 1, $3, $3, 0, 10, 0)
+                    "#,
+                    numero_factura,
+                    producto,
+                    Decimal::new(*precio, 2)
+                )
+                .execute(&pool)
+                .await?;
+            }
+        }
+
+        // Borrar la compra más reciente: LECHE vuelve al precio anterior
+        let mut conn = pool.acquire().await?;
+        let deletion = delete_purchase(&mut conn, "0001-001-000021")
+            .await?
+            .expect("la compra existe");
+        assert_eq!(deletion.lineas_eliminadas, 1);
+        assert_eq!(deletion.productos_eliminados, 0);
+
+        let precio = sqlx::query_scalar!(
+            "SELECT precio_actual FROM productos WHERE nombre = 'LECHE ENTERA'"
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+        assert_eq!(precio, Some(Decimal::new(100, 2)));
+
+        let historico = sqlx::query_scalar!(
+            r#"SELECT COUNT(*) as "count!" FROM historico_precios WHERE producto_nombre = 'LECHE ENTERA'"#
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+        assert_eq!(historico, 1);
+
+        // Borrar la última compra: el catálogo queda vacío
+        let deletion = delete_purchase(&mut conn, "0001-001-000020")
+            .await?
+            .expect("la compra existe");
+        assert_eq!(deletion.lineas_eliminadas, 2);
+        assert_eq!(deletion.productos_eliminados, 2);
+
+        assert!(delete_purchase(&mut conn, "0001-001-000020")
+            .await?
+            .is_none());
+
+        Ok(())
+    }
 }
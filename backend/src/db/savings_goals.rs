@@ -0,0 +1,274 @@
+use crate::models::SavingsGoal;
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use sqlx::PgPool;
+
+/// Objetivos de ahorro del usuario, del más reciente al más antiguo
+pub async fn list_savings_goals(
+    pool: &PgPool,
+    usuario_email: &str,
+) -> Result<Vec<SavingsGoal>, sqlx::Error> {
+    sqlx::query_as!(
+        SavingsGoal,
+        r#"
+        SELECT
+            id,
+            mes,
+            objetivo_mensual,
+            COALESCE(conseguido, FALSE) as "conseguido!",
+            ahorro_real,
+            created_at
+        FROM objetivos_ahorro
+        WHERE usuario_email = $1
+        ORDER BY mes DESC
+        "#,
+        usuario_email
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Objetivo de ahorro del usuario para un mes
+pub async fn get_savings_goal_for_month(
+    pool: &PgPool,
+    usuario_email: &str,
+    mes: NaiveDate,
+) -> Result<Option<SavingsGoal>, sqlx::Error> {
+    sqlx::query_as!(
+        SavingsGoal,
+        r#"
+        SELECT
+            id,
+            mes,
+            objetivo_mensual,
+            COALESCE(conseguido, FALSE) as "conseguido!",
+            ahorro_real,
+            created_at
+        FROM objetivos_ahorro
+        WHERE usuario_email = $1 AND mes = $2
+        "#,
+        usuario_email,
+        mes
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+/// Crea el objetivo de un mes
+///
+/// Devuelve `None` si el usuario ya tiene objetivo para ese mes.
+pub async fn insert_savings_goal(
+    pool: &PgPool,
+    usuario_email: &str,
+    mes: NaiveDate,
+    objetivo_mensual: Decimal,
+) -> Result<Option<SavingsGoal>, sqlx::Error> {
+    sqlx::query_as!(
+        SavingsGoal,
+        r#"
+        INSERT INTO objetivos_ahorro (usuario_email, mes, objetivo_mensual)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (usuario_email, mes) DO NOTHING
+        RETURNING
+            id,
+            mes,
+            objetivo_mensual,
+            COALESCE(conseguido, FALSE) as "conseguido!",
+            ahorro_real,
+            created_at
+        "#,
+        usuario_email,
+        mes,
+        objetivo_mensual
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+/// Cambia el importe de un objetivo
+///
+/// El resultado del mes se borra para que el siguiente cierre lo recalcule.
+pub async fn update_savings_goal(
+    pool: &PgPool,
+    usuario_email: &str,
+    id: i32,
+    objetivo_mensual: Decimal,
+) -> Result<Option<SavingsGoal>, sqlx::Error> {
+    sqlx::query_as!(
+        SavingsGoal,
+        r#"
+        UPDATE objetivos_ahorro
+        SET objetivo_mensual = $3, conseguido = FALSE, ahorro_real = NULL
+        WHERE id = $1 AND usuario_email = $2
+        RETURNING
+            id,
+            mes,
+            objetivo_mensual,
+            COALESCE(conseguido, FALSE) as "conseguido!",
+            ahorro_real,
+            created_at
+        "#,
+        id,
+        usuario_email,
+        objetivo_mensual
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+/// Elimina un objetivo del usuario
+pub async fn delete_savings_goal(
+    pool: &PgPool,
+    usuario_email: &str,
+    id: i32,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!(
+        "DELETE FROM objetivos_ahorro WHERE id = $1 AND usuario_email = $2",
+        id,
+        usuario_email
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Gasto total del usuario en el mes que empieza en `mes`
+pub async fn get_month_spending(
+    pool: &PgPool,
+    usuario_email: &str,
+    mes: NaiveDate,
+) -> Result<Decimal, sqlx::Error> {
+    sqlx::query_scalar!(
+        r#"
+        SELECT COALESCE(SUM(total), 0)::numeric as "total!"
+        FROM compras
+        WHERE usuario_email = $1
+            AND fecha_hora >= $2::date
+            AND fecha_hora < ($2::date + INTERVAL '1 month')
+        "#,
+        usuario_email,
+        mes
+    )
+    .fetch_one(pool)
+    .await
+}
+
+/// Cierra los objetivos de los meses anteriores a `current_month`
+///
+/// Calcula `ahorro_real` (lo que sobró del objetivo, nunca negativo) y
+/// `conseguido` a partir de las compras del mes. Se recalculan también los
+/// meses ya cerrados para recoger tickets subidos o borrados más tarde; solo
+/// se escriben las filas que cambian.
+pub async fn close_savings_goals(
+    pool: &PgPool,
+    current_month: NaiveDate,
+) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE objetivos_ahorro o
+        SET
+            ahorro_real = GREATEST(o.objetivo_mensual - g.gasto, 0),
+            conseguido = g.gasto <= o.objetivo_mensual
+        FROM (
+            SELECT o2.id, COALESCE(SUM(c.total), 0) as gasto
+            FROM objetivos_ahorro o2
+            LEFT JOIN compras c
+                ON c.usuario_email = o2.usuario_email
+                AND c.fecha_hora >= o2.mes
+                AND c.fecha_hora < o2.mes + INTERVAL '1 month'
+            WHERE o2.mes < $1
+            GROUP BY o2.id
+        ) g
+        WHERE o.id = g.id
+            AND (
+                o.ahorro_real IS DISTINCT FROM GREATEST(o.objetivo_mensual - g.gasto, 0)
+                OR o.conseguido IS DISTINCT FROM (g.gasto <= o.objetivo_mensual)
+            )
+        "#,
+        current_month
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::insert_purchase;
+    use crate::models::PurchaseInsert;
+
+    #[sqlx::test(migrations = "./migrations")]
+    async fn test_close_savings_goals(pool: PgPool) -> sqlx::Result<()> {
+        sqlx::query!(
+            "INSERT INTO usuarios (email, password_hash, nombre) VALUES ($1, $2, $3)",
+            "goals@example.com",
+            "$2b$12$KpIEW.jQKvqXfN5nDwAXLub8RCRYjqNvCLKXfzHpFGK2FQJGmqQJi",
+            "Goals User"
+        )
+        .execute(&pool)
+        .await?;
+
+        let enero = NaiveDate::from_ymd_opt(2025, 1, 1).unwrap();
+        let febrero = NaiveDate::from_ymd_opt(2025, 2, 1).unwrap();
+        let marzo = NaiveDate::from_ymd_opt(2025, 3, 1).unwrap();
+
+        for (factura, fecha, centimos) in [("F-1", enero, 15000), ("F-2", febrero, 25000)] {
+            insert_purchase(
+                &pool,
+                &PurchaseInsert {
+                    numero_factura: factura.to_string(),
+                    usuario_email: "goals@example.com".to_string(),
+                    fecha_hora: fecha.and_hms_opt(18, 0, 0).unwrap(),
+                    total: Decimal::new(centimos, 2),
+                    tienda: None,
+                    ubicacion: None,
+                    metodo_pago: None,
+                    numero_operacion: None,
+                },
+            )
+            .await?;
+        }
+
+        let objetivo = Decimal::new(20000, 2);
+        for mes in [enero, febrero, marzo] {
+            assert!(
+                insert_savings_goal(&pool, "goals@example.com", mes, objetivo)
+                    .await?
+                    .is_some()
+            );
+        }
+        assert!(
+            insert_savings_goal(&pool, "goals@example.com", enero, objetivo)
+                .await?
+                .is_none()
+        );
+
+        // Marzo es el mes en curso y queda abierto
+        assert_eq!(close_savings_goals(&pool, marzo).await?, 2);
+        assert_eq!(close_savings_goals(&pool, marzo).await?, 0);
+
+        let goals = list_savings_goals(&pool, "goals@example.com").await?;
+        let resumen: Vec<_> = goals
+            .iter()
+            .map(|g| (g.mes, g.conseguido, g.ahorro_real))
+            .collect();
+        assert_eq!(
+            resumen,
+            vec![
+                (marzo, false, None),
+                (febrero, false, Some(Decimal::ZERO)),
+                (enero, true, Some(Decimal::new(5000, 2))),
+            ]
+        );
+
+        assert_eq!(
+            get_month_spending(&pool, "goals@example.com", febrero).await?,
+            Decimal::new(25000, 2)
+        );
+
+        Ok(())
+    }
+}
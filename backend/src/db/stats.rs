@@ -1,8 +1,10 @@
-// use chrono::NaiveDate;
+use chrono::{NaiveDate, NaiveDateTime};
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
 
+use super::PurchaseFilter;
+
 /// Punto de data para la tendencia de gasto (serie temporal)
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
 pub struct DailySpendPoint {
@@ -14,19 +16,29 @@ pub struct DailySpendPoint {
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
 pub struct TopProductItem {
     pub nombre: String,
-    pub cantidad_total: Option<i64>,
+    /// Unidades, o kg/litros en los productos a peso o volumen
+    pub cantidad_total: Option<Decimal>,
     pub gasto_total: Option<Decimal>,
     pub precio_medio: Option<Decimal>,
     pub precio_actual: Option<Decimal>,
+    pub unidad: Option<String>,
+    /// Precio medio pagado por kg o litro; `None` en productos por unidades
+    pub precio_unidad_medida: Option<Decimal>,
+    /// `kg` o `l` según el envase o la unidad del producto
+    pub unidad_base: Option<String>,
+    /// Precio medio pagado por kg o litro, también en productos envasados
+    pub precio_por_unidad_base: Option<Decimal>,
 }
 
-/// Estadísticas de inflación personal (comparación de precios de productos favoritos)
+/// Producto de la cesta de inflación personal con sus precios de referencia
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
 pub struct PersonalInflationData {
     pub producto: String,
-    pub precio_medio_actual: Option<Decimal>,
-    pub precio_medio_anterior: Option<Decimal>,
-    pub variacion_porcentaje: Option<f64>,
+    /// Gasto del usuario en el producto durante los últimos 12 meses
+    pub gasto: Decimal,
+    pub precio_actual: Option<Decimal>,
+    pub precio_mes_anterior: Option<Decimal>,
+    pub precio_anio_anterior: Option<Decimal>,
 }
 
 /// Serie mensual de gasto agregada
@@ -38,10 +50,13 @@ pub struct MonthlySpendPoint {
 }
 
 /// Obtiene la tendencia de gasto diaria para los últimos N días
+///
+/// Si el filtro trae `from` o `to`, el rango sustituye a los últimos N días.
 pub async fn get_spending_trend(
     pool: &PgPool,
     usuario_email: &str,
     days: i64,
+    filter: &PurchaseFilter,
 ) -> Result<Vec<DailySpendPoint>, sqlx::Error> {
     let trend = sqlx::query_as!(
         DailySpendPoint,
@@ -51,12 +66,22 @@ pub async fn get_spending_trend(
             SUM(c.total)::numeric as "total!"
         FROM compras c
         WHERE c.usuario_email = $1
-            AND c.fecha_hora >= NOW() - INTERVAL '1 day' * $2::int
+            AND (
+                c.fecha_hora >= NOW() - INTERVAL '1 day' * $2::int
+                OR $4::date IS NOT NULL
+                OR $5::date IS NOT NULL
+            )
+            AND ($3::text IS NULL OR c.tienda = $3)
+            AND ($4::date IS NULL OR c.fecha_hora >= $4)
+            AND ($5::date IS NULL OR c.fecha_hora < $5::date + 1)
         GROUP BY DATE(c.fecha_hora)
         ORDER BY DATE(c.fecha_hora) ASC
         "#,
         usuario_email,
-        days as i32
+        days as i32,
+        filter.tienda.as_deref(),
+        filter.from,
+        filter.to
     )
     .fetch_all(pool)
     .await?;
@@ -69,26 +94,57 @@ pub async fn get_top_products_by_quantity(
     pool: &PgPool,
     usuario_email: &str,
     limit: i64,
+    filter: &PurchaseFilter,
 ) -> Result<Vec<TopProductItem>, sqlx::Error> {
     let products = sqlx::query_as!(
         TopProductItem,
         r#"
         SELECT
             p.nombre,
-            SUM(cp.cantidad)::bigint as "cantidad_total?",
+            SUM(cp.cantidad)::numeric as "cantidad_total?",
             SUM(cp.precio_total)::numeric as "gasto_total?",
             ROUND(AVG(cp.precio_unitario)::numeric, 2)::numeric as "precio_medio?",
-            p.precio_actual as "precio_actual?"
+            p.precio_actual as "precio_actual?",
+            p.unidad as "unidad?",
+            CASE
+                WHEN p.unidad IN ('kg', 'l') AND SUM(cp.cantidad) > 0
+                THEN ROUND(
+                    SUM(cp.precio_total + COALESCE(cp.descuento, 0)) / SUM(cp.cantidad),
+                    2
+                )
+            END as "precio_unidad_medida?",
+            COALESCE(e.unidad, CASE WHEN p.unidad IN ('kg', 'l') THEN p.unidad END)
+                as "unidad_base?",
+            CASE
+                WHEN e.contenido > 0 AND SUM(cp.cantidad) > 0
+                THEN ROUND(
+                    SUM(cp.precio_total + COALESCE(cp.descuento, 0))
+                        / (SUM(cp.cantidad) * e.contenido),
+                    2
+                )
+                WHEN p.unidad IN ('kg', 'l') AND SUM(cp.cantidad) > 0
+                THEN ROUND(
+                    SUM(cp.precio_total + COALESCE(cp.descuento, 0)) / SUM(cp.cantidad),
+                    2
+                )
+            END as "precio_por_unidad_base?"
         FROM compras c
         INNER JOIN compras_productos cp ON c.numero_factura = cp.compra_numero_factura
         INNER JOIN productos p ON cp.producto_nombre = p.nombre
+        LEFT JOIN productos_envases e ON e.producto_nombre = p.nombre
         WHERE c.usuario_email = $1
-        GROUP BY p.nombre, p.precio_actual
+            AND ($3::text IS NULL OR c.tienda = $3)
+            AND ($4::date IS NULL OR c.fecha_hora >= $4)
+            AND ($5::date IS NULL OR c.fecha_hora < $5::date + 1)
+        GROUP BY p.nombre, p.precio_actual, p.unidad, e.contenido, e.unidad
         ORDER BY SUM(cp.cantidad) DESC
         LIMIT $2
         "#,
         usuario_email,
-        limit
+        limit,
+        filter.tienda.as_deref(),
+        filter.from,
+        filter.to
     )
     .fetch_all(pool)
     .await?;
@@ -101,26 +157,57 @@ pub async fn get_top_products_by_spending(
     pool: &PgPool,
     usuario_email: &str,
     limit: i64,
+    filter: &PurchaseFilter,
 ) -> Result<Vec<TopProductItem>, sqlx::Error> {
     let products = sqlx::query_as!(
         TopProductItem,
         r#"
         SELECT
             p.nombre,
-            SUM(cp.cantidad)::bigint as "cantidad_total?",
+            SUM(cp.cantidad)::numeric as "cantidad_total?",
             SUM(cp.precio_total)::numeric as "gasto_total?",
             ROUND(AVG(cp.precio_unitario)::numeric, 2)::numeric as "precio_medio?",
-            p.precio_actual as "precio_actual?"
+            p.precio_actual as "precio_actual?",
+            p.unidad as "unidad?",
+            CASE
+                WHEN p.unidad IN ('kg', 'l') AND SUM(cp.cantidad) > 0
+                THEN ROUND(
+                    SUM(cp.precio_total + COALESCE(cp.descuento, 0)) / SUM(cp.cantidad),
+                    2
+                )
+            END as "precio_unidad_medida?",
+            COALESCE(e.unidad, CASE WHEN p.unidad IN ('kg', 'l') THEN p.unidad END)
+                as "unidad_base?",
+            CASE
+                WHEN e.contenido > 0 AND SUM(cp.cantidad) > 0
+                THEN ROUND(
+                    SUM(cp.precio_total + COALESCE(cp.descuento, 0))
+                        / (SUM(cp.cantidad) * e.contenido),
+                    2
+                )
+                WHEN p.unidad IN ('kg', 'l') AND SUM(cp.cantidad) > 0
+                THEN ROUND(
+                    SUM(cp.precio_total + COALESCE(cp.descuento, 0)) / SUM(cp.cantidad),
+                    2
+                )
+            END as "precio_por_unidad_base?"
         FROM compras c
         INNER JOIN compras_productos cp ON c.numero_factura = cp.compra_numero_factura
         INNER JOIN productos p ON cp.producto_nombre = p.nombre
+        LEFT JOIN productos_envases e ON e.producto_nombre = p.nombre
         WHERE c.usuario_email = $1
-        GROUP BY p.nombre, p.precio_actual
+            AND ($3::text IS NULL OR c.tienda = $3)
+            AND ($4::date IS NULL OR c.fecha_hora >= $4)
+            AND ($5::date IS NULL OR c.fecha_hora < $5::date + 1)
+        GROUP BY p.nombre, p.precio_actual, p.unidad, e.contenido, e.unidad
         ORDER BY SUM(cp.precio_total) DESC
         LIMIT $2
         "#,
         usuario_email,
-        limit
+        limit,
+        filter.tienda.as_deref(),
+        filter.from,
+        filter.to
     )
     .fetch_all(pool)
     .await?;
@@ -129,22 +216,32 @@ pub async fn get_top_products_by_spending(
 }
 
 /// Serie de gasto mensual agregada (últimos `months` meses)
+///
+/// Con `from` o `to` en el filtro la serie cubre los meses del rango.
 pub async fn get_monthly_spending(
     pool: &PgPool,
     usuario_email: &str,
     months: i32,
+    filter: &PurchaseFilter,
 ) -> Result<Vec<MonthlySpendPoint>, sqlx::Error> {
-    if months > 100 {
-        // "All Time" mode: dynamic range from first purchase
+    if months > 100 || filter.has_range() {
+        // "All Time" mode: dynamic range from first purchase (or the filter range)
         let monthly = sqlx::query_as!(
             MonthlySpendPoint,
             r#"
             WITH bounds AS (
                 SELECT 
-                    COALESCE(MIN(DATE_TRUNC('month', fecha_hora)), DATE_TRUNC('month', CURRENT_DATE)) as first_month,
-                    DATE_TRUNC('month', CURRENT_DATE) as last_month
+                    COALESCE(
+                        DATE_TRUNC('month', $3::date),
+                        MIN(DATE_TRUNC('month', fecha_hora)),
+                        DATE_TRUNC('month', CURRENT_DATE)
+                    ) as first_month,
+                    DATE_TRUNC('month', COALESCE($4::date, CURRENT_DATE)) as last_month
                 FROM compras
                 WHERE usuario_email = $1
+                    AND ($2::text IS NULL OR tienda = $2)
+                    AND ($3::date IS NULL OR fecha_hora >= $3)
+                    AND ($4::date IS NULL OR fecha_hora < $4::date + 1)
             ),
             months_series AS (
                 SELECT generate_series(first_month, last_month, '1 month') as month_start
@@ -158,10 +255,16 @@ pub async fn get_monthly_spending(
             LEFT JOIN compras c
                 ON DATE_TRUNC('month', c.fecha_hora) = ms.month_start
                 AND c.usuario_email = $1
+                AND ($2::text IS NULL OR c.tienda = $2)
+                AND ($3::date IS NULL OR c.fecha_hora >= $3)
+                AND ($4::date IS NULL OR c.fecha_hora < $4::date + 1)
             GROUP BY ms.month_start
             ORDER BY ms.month_start
             "#,
-            usuario_email
+            usuario_email,
+            filter.tienda.as_deref(),
+            filter.from,
+            filter.to
         )
         .fetch_all(pool)
         .await?;
@@ -184,11 +287,17 @@ pub async fn get_monthly_spending(
             LEFT JOIN compras c
                 ON DATE_TRUNC('month', c.fecha_hora) = months.month_start
                 AND c.usuario_email = $1
+                AND ($3::text IS NULL OR c.tienda = $3)
+                AND ($4::date IS NULL OR c.fecha_hora >= $4)
+                AND ($5::date IS NULL OR c.fecha_hora < $5::date + 1)
             GROUP BY months.month_start
             ORDER BY months.month_start
             "#,
             usuario_email,
-            months
+            months,
+            filter.tienda.as_deref(),
+            filter.from,
+            filter.to
         )
         .fetch_all(pool)
         .await?;
@@ -206,9 +315,11 @@ pub struct MonthComparisonData {
     pub days_in_current_month: Option<i32>,
 }
 
+/// El mes actual es el de `to` si el filtro lo trae
 pub async fn get_month_comparison(
     pool: &PgPool,
     usuario_email: &str,
+    filter: &PurchaseFilter,
 ) -> Result<MonthComparisonData, sqlx::Error> {
     let result = sqlx::query!(
         r#"
@@ -218,14 +329,20 @@ pub async fn get_month_comparison(
                 COUNT(DISTINCT DATE(fecha_hora))::int as days_with_purchases
             FROM compras
             WHERE usuario_email = $1
-                AND DATE_TRUNC('month', fecha_hora) = DATE_TRUNC('month', CURRENT_DATE)
+                AND ($2::text IS NULL OR tienda = $2)
+                AND ($3::date IS NULL OR fecha_hora >= $3)
+                AND ($4::date IS NULL OR fecha_hora < $4::date + 1)
+                AND DATE_TRUNC('month', fecha_hora) = DATE_TRUNC('month', COALESCE($4::date, CURRENT_DATE))
         ),
         previous_month AS (
             SELECT
                 COALESCE(SUM(total), 0)::numeric as total
             FROM compras
             WHERE usuario_email = $1
-                AND DATE_TRUNC('month', fecha_hora) = DATE_TRUNC('month', CURRENT_DATE - INTERVAL '1 month')
+                AND ($2::text IS NULL OR tienda = $2)
+                AND ($3::date IS NULL OR fecha_hora >= $3)
+                AND ($4::date IS NULL OR fecha_hora < $4::date + 1)
+                AND DATE_TRUNC('month', fecha_hora) = DATE_TRUNC('month', COALESCE($4::date, CURRENT_DATE) - INTERVAL '1 month')
         )
         SELECT
             current_month.total as "current_total!",
@@ -233,7 +350,10 @@ pub async fn get_month_comparison(
             current_month.days_with_purchases as "days_with_purchases?"
         FROM current_month, previous_month
         "#,
-        usuario_email
+        usuario_email,
+        filter.tienda.as_deref(),
+        filter.from,
+        filter.to
     )
     .fetch_one(pool)
     .await?;
@@ -260,9 +380,12 @@ pub async fn get_month_comparison(
 }
 
 /// Obtiene el gasto total acumulado del año actual (YTD)
+///
+/// El año es el de `to` si el filtro lo trae.
 pub async fn get_current_year_total(
     pool: &PgPool,
     usuario_email: &str,
+    filter: &PurchaseFilter,
 ) -> Result<Decimal, sqlx::Error> {
     let result = sqlx::query!(
         r#"
@@ -270,9 +393,15 @@ pub async fn get_current_year_total(
             COALESCE(SUM(total), 0)::numeric as "total!"
         FROM compras
         WHERE usuario_email = $1
-            AND EXTRACT(YEAR FROM fecha_hora) = EXTRACT(YEAR FROM CURRENT_DATE)
+            AND ($2::text IS NULL OR tienda = $2)
+            AND ($3::date IS NULL OR fecha_hora >= $3)
+            AND ($4::date IS NULL OR fecha_hora < $4::date + 1)
+            AND EXTRACT(YEAR FROM fecha_hora) = EXTRACT(YEAR FROM COALESCE($4::date, CURRENT_DATE))
         "#,
-        usuario_email
+        usuario_email,
+        filter.tienda.as_deref(),
+        filter.from,
+        filter.to
     )
     .fetch_one(pool)
     .await?;
@@ -292,6 +421,7 @@ pub struct TimeDistributionPoint {
 pub async fn get_weekly_distribution(
     pool: &PgPool,
     usuario_email: &str,
+    filter: &PurchaseFilter,
 ) -> Result<Vec<TimeDistributionPoint>, sqlx::Error> {
     let distribution = sqlx::query_as!(
         TimeDistributionPoint,
@@ -302,10 +432,16 @@ pub async fn get_weekly_distribution(
             COUNT(*)::bigint as "cantidad_tickets!"
         FROM compras
         WHERE usuario_email = $1
+            AND ($2::text IS NULL OR tienda = $2)
+            AND ($3::date IS NULL OR fecha_hora >= $3)
+            AND ($4::date IS NULL OR fecha_hora < $4::date + 1)
         GROUP BY EXTRACT(DOW FROM fecha_hora), TO_CHAR(fecha_hora, 'Day')
         ORDER BY EXTRACT(DOW FROM fecha_hora)
         "#,
-        usuario_email
+        usuario_email,
+        filter.tienda.as_deref(),
+        filter.from,
+        filter.to
     )
     .fetch_all(pool)
     .await?;
@@ -317,6 +453,7 @@ pub async fn get_weekly_distribution(
 pub async fn get_hourly_distribution(
     pool: &PgPool,
     usuario_email: &str,
+    filter: &PurchaseFilter,
 ) -> Result<Vec<TimeDistributionPoint>, sqlx::Error> {
     let distribution = sqlx::query_as!(
         TimeDistributionPoint,
@@ -327,10 +464,16 @@ pub async fn get_hourly_distribution(
             COUNT(*)::bigint as "cantidad_tickets!"
         FROM compras
         WHERE usuario_email = $1
+            AND ($2::text IS NULL OR tienda = $2)
+            AND ($3::date IS NULL OR fecha_hora >= $3)
+            AND ($4::date IS NULL OR fecha_hora < $4::date + 1)
         GROUP BY EXTRACT(HOUR FROM fecha_hora)
         ORDER BY EXTRACT(HOUR FROM fecha_hora)
         "#,
-        usuario_email
+        usuario_email,
+        filter.tienda.as_deref(),
+        filter.from,
+        filter.to
     )
     .fetch_all(pool)
     .await?;
@@ -338,6 +481,561 @@ pub async fn get_hourly_distribution(
     Ok(distribution)
 }
 
+/// Gasto del usuario en una categoría de productos
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct CategorySpendItem {
+    pub categoria: String,
+    pub total: Decimal,
+    pub porcentaje: f64,
+    pub productos_distintos: i64,
+}
+
+/// Gasto mensual de una categoría
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct CategoryMonthlyPoint {
+    pub month: String,
+    pub categoria: String,
+    pub total: Decimal,
+}
+
+/// Gasto por categoría en los últimos `months` meses (incluido el actual)
+///
+/// Usa la categoría elegida por el usuario y, si no hay, la automática. Los
+/// productos sin categoría se agrupan en "Sin categoría". Un rango `from`/`to`
+/// en el filtro sustituye a los últimos meses.
+pub async fn get_spending_by_category(
+    pool: &PgPool,
+    usuario_email: &str,
+    months: i32,
+    filter: &PurchaseFilter,
+) -> Result<Vec<CategorySpendItem>, sqlx::Error> {
+    sqlx::query_as!(
+        CategorySpendItem,
+        r#"
+        SELECT
+            COALESCE(cat.nombre, 'Sin categoría') as "categoria!",
+            SUM(cp.precio_total)::numeric as "total!",
+            COALESCE(
+                ROUND(SUM(cp.precio_total) * 100 / NULLIF(SUM(SUM(cp.precio_total)) OVER (), 0), 2),
+                0
+            )::float8 as "porcentaje!",
+            COUNT(DISTINCT cp.producto_nombre)::bigint as "productos_distintos!"
+        FROM compras_productos cp
+        INNER JOIN compras c ON c.numero_factura = cp.compra_numero_factura
+        LEFT JOIN usuarios_productos_categorias upc
+            ON upc.usuario_email = c.usuario_email AND upc.producto_nombre = cp.producto_nombre
+        LEFT JOIN productos_categorias pc ON pc.producto_nombre = cp.producto_nombre
+        LEFT JOIN categorias cat ON cat.id = COALESCE(upc.categoria_id, pc.categoria_id)
+        WHERE c.usuario_email = $1
+            AND ($3::text IS NULL OR c.tienda = $3)
+            AND ($4::date IS NULL OR c.fecha_hora >= $4)
+            AND ($5::date IS NULL OR c.fecha_hora < $5::date + 1)
+            AND (
+                c.fecha_hora >= DATE_TRUNC('month', CURRENT_DATE) - INTERVAL '1 month' * ($2::int - 1)
+                OR $4::date IS NOT NULL
+                OR $5::date IS NOT NULL
+            )
+        GROUP BY 1
+        ORDER BY 2 DESC
+        "#,
+        usuario_email,
+        months,
+        filter.tienda.as_deref(),
+        filter.from,
+        filter.to
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Serie mensual de gasto por categoría en los últimos `months` meses
+pub async fn get_category_monthly_spending(
+    pool: &PgPool,
+    usuario_email: &str,
+    months: i32,
+    filter: &PurchaseFilter,
+) -> Result<Vec<CategoryMonthlyPoint>, sqlx::Error> {
+    sqlx::query_as!(
+        CategoryMonthlyPoint,
+        r#"
+        SELECT
+            TO_CHAR(DATE_TRUNC('month', c.fecha_hora), 'YYYY-MM') as "month!",
+            COALESCE(cat.nombre, 'Sin categoría') as "categoria!",
+            SUM(cp.precio_total)::numeric as "total!"
+        FROM compras_productos cp
+        INNER JOIN compras c ON c.numero_factura = cp.compra_numero_factura
+        LEFT JOIN usuarios_productos_categorias upc
+            ON upc.usuario_email = c.usuario_email AND upc.producto_nombre = cp.producto_nombre
+        LEFT JOIN productos_categorias pc ON pc.producto_nombre = cp.producto_nombre
+        LEFT JOIN categorias cat ON cat.id = COALESCE(upc.categoria_id, pc.categoria_id)
+        WHERE c.usuario_email = $1
+            AND ($3::text IS NULL OR c.tienda = $3)
+            AND ($4::date IS NULL OR c.fecha_hora >= $4)
+            AND ($5::date IS NULL OR c.fecha_hora < $5::date + 1)
+            AND (
+                c.fecha_hora >= DATE_TRUNC('month', CURRENT_DATE) - INTERVAL '1 month' * ($2::int - 1)
+                OR $4::date IS NOT NULL
+                OR $5::date IS NOT NULL
+            )
+        GROUP BY 1, 2
+        ORDER BY 1, 3 DESC
+        "#,
+        usuario_email,
+        months,
+        filter.tienda.as_deref(),
+        filter.from,
+        filter.to
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Obtiene la cesta de la compra del usuario (compras entre `basket_from` y
+/// `today`) con el precio vigente de cada producto hoy, hace un mes y hace un año
+pub async fn get_personal_inflation_basket(
+    pool: &PgPool,
+    usuario_email: &str,
+    today: NaiveDate,
+    month_ago: NaiveDate,
+    year_ago: NaiveDate,
+    basket_from: NaiveDate,
+) -> Result<Vec<PersonalInflationData>, sqlx::Error> {
+    sqlx::query_as!(
+        PersonalInflationData,
+        r#"
+        WITH cesta AS (
+            SELECT
+                cp.producto_nombre,
+                SUM(cp.precio_total) as gasto
+            FROM compras_productos cp
+            INNER JOIN compras c ON c.numero_factura = cp.compra_numero_factura
+            WHERE c.usuario_email = $1
+                AND c.fecha_hora >= $5::date
+                AND c.fecha_hora < $2::date + 1
+            GROUP BY cp.producto_nombre
+        )
+        SELECT
+            cesta.producto_nombre as "producto!",
+            cesta.gasto as "gasto!",
+            (
+                SELECT h.precio FROM historico_precios h
+                WHERE h.producto_nombre = cesta.producto_nombre AND h.fecha_vigencia <= $2
+                ORDER BY h.fecha_vigencia DESC LIMIT 1
+            ) as precio_actual,
+            (
+                SELECT h.precio FROM historico_precios h
+                WHERE h.producto_nombre = cesta.producto_nombre AND h.fecha_vigencia <= $3
+                ORDER BY h.fecha_vigencia DESC LIMIT 1
+            ) as precio_mes_anterior,
+            (
+                SELECT h.precio FROM historico_precios h
+                WHERE h.producto_nombre = cesta.producto_nombre AND h.fecha_vigencia <= $4
+                ORDER BY h.fecha_vigencia DESC LIMIT 1
+            ) as precio_anio_anterior
+        FROM cesta
+        ORDER BY cesta.gasto DESC
+        "#,
+        usuario_email,
+        today,
+        month_ago,
+        year_ago,
+        basket_from
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Gasto y visitas del usuario en una tienda
+///
+/// Una tienda es la combinación de `tienda` y `ubicacion` del ticket; los
+/// tickets sin tienda se agrupan en "Sin tienda".
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct StoreSpendItem {
+    pub tienda: String,
+    pub ubicacion: Option<String>,
+    pub total: Decimal,
+    pub visitas: i64,
+    pub ticket_medio: Decimal,
+    pub porcentaje: f64,
+    pub ultima_visita: NaiveDateTime,
+}
+
+/// Diferencia de precio de un mismo producto entre tiendas
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct StorePriceDifference {
+    pub producto: String,
+    /// Número de tiendas en las que el usuario ha comprado el producto
+    pub tiendas: i64,
+    pub tienda_mas_barata: String,
+    pub ubicacion_mas_barata: Option<String>,
+    pub precio_minimo: Decimal,
+    pub tienda_mas_cara: String,
+    pub ubicacion_mas_cara: Option<String>,
+    pub precio_maximo: Decimal,
+    pub diferencia_porcentaje: f64,
+}
+
+/// Gasto por tienda en los últimos `months` meses (incluido el actual)
+pub async fn get_spending_by_store(
+    pool: &PgPool,
+    usuario_email: &str,
+    months: i32,
+    filter: &PurchaseFilter,
+) -> Result<Vec<StoreSpendItem>, sqlx::Error> {
+    sqlx::query_as!(
+        StoreSpendItem,
+        r#"
+        SELECT
+            COALESCE(c.tienda, 'Sin tienda') as "tienda!",
+            c.ubicacion,
+            SUM(c.total)::numeric as "total!",
+            COUNT(*)::bigint as "visitas!",
+            ROUND(SUM(c.total) / COUNT(*), 2)::numeric as "ticket_medio!",
+            COALESCE(
+                ROUND(SUM(c.total) * 100 / NULLIF(SUM(SUM(c.total)) OVER (), 0), 2),
+                0
+            )::float8 as "porcentaje!",
+            MAX(c.fecha_hora) as "ultima_visita!"
+        FROM compras c
+        WHERE c.usuario_email = $1
+            AND ($3::text IS NULL OR c.tienda = $3)
+            AND ($4::date IS NULL OR c.fecha_hora >= $4)
+            AND ($5::date IS NULL OR c.fecha_hora < $5::date + 1)
+            AND (
+                c.fecha_hora >= DATE_TRUNC('month', CURRENT_DATE) - INTERVAL '1 month' * ($2::int - 1)
+                OR $4::date IS NOT NULL
+                OR $5::date IS NOT NULL
+            )
+        GROUP BY 1, 2
+        ORDER BY 3 DESC
+        "#,
+        usuario_email,
+        months,
+        filter.tienda.as_deref(),
+        filter.from,
+        filter.to
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Productos que el usuario compra en varias tiendas con precios distintos
+///
+/// Compara el precio unitario medio pagado en cada tienda en los últimos
+/// `months` meses y ordena por la mayor diferencia.
+pub async fn get_store_price_differences(
+    pool: &PgPool,
+    usuario_email: &str,
+    months: i32,
+    limit: i64,
+    filter: &PurchaseFilter,
+) -> Result<Vec<StorePriceDifference>, sqlx::Error> {
+    sqlx::query_as!(
+        StorePriceDifference,
+        r#"
+        WITH precios AS (
+            SELECT
+                cp.producto_nombre,
+                COALESCE(c.tienda, 'Sin tienda') as tienda,
+                c.ubicacion,
+                ROUND(AVG(cp.precio_unitario), 2) as precio
+            FROM compras_productos cp
+            INNER JOIN compras c ON c.numero_factura = cp.compra_numero_factura
+            WHERE c.usuario_email = $1
+                AND ($4::text IS NULL OR c.tienda = $4)
+                AND ($5::date IS NULL OR c.fecha_hora >= $5)
+                AND ($6::date IS NULL OR c.fecha_hora < $6::date + 1)
+                AND (
+                    c.fecha_hora >= DATE_TRUNC('month', CURRENT_DATE) - INTERVAL '1 month' * ($2::int - 1)
+                    OR $5::date IS NOT NULL
+                    OR $6::date IS NOT NULL
+                )
+            GROUP BY 1, 2, 3
+        ),
+        rangos AS (
+            SELECT
+                producto_nombre,
+                COUNT(*) as tiendas,
+                MIN(precio) as precio_minimo,
+                MAX(precio) as precio_maximo
+            FROM precios
+            GROUP BY producto_nombre
+            HAVING COUNT(*) > 1 AND MIN(precio) > 0 AND MAX(precio) > MIN(precio)
+        )
+        SELECT
+            r.producto_nombre as "producto!",
+            r.tiendas::bigint as "tiendas!",
+            barata.tienda as "tienda_mas_barata!",
+            barata.ubicacion as ubicacion_mas_barata,
+            r.precio_minimo as "precio_minimo!",
+            cara.tienda as "tienda_mas_cara!",
+            cara.ubicacion as ubicacion_mas_cara,
+            r.precio_maximo as "precio_maximo!",
+            ROUND((r.precio_maximo - r.precio_minimo) * 100 / r.precio_minimo, 2)::float8
+                as "diferencia_porcentaje!"
+        FROM rangos r
+        INNER JOIN LATERAL (
+            SELECT p.tienda, p.ubicacion
+            FROM precios p
+            WHERE p.producto_nombre = r.producto_nombre
+            ORDER BY p.precio ASC, p.tienda, p.ubicacion
+            LIMIT 1
+        ) barata ON TRUE
+        INNER JOIN LATERAL (
+            SELECT p.tienda, p.ubicacion
+            FROM precios p
+            WHERE p.producto_nombre = r.producto_nombre
+            ORDER BY p.precio DESC, p.tienda, p.ubicacion
+            LIMIT 1
+        ) cara ON TRUE
+        ORDER BY "diferencia_porcentaje!" DESC, r.producto_nombre
+        LIMIT $3
+        "#,
+        usuario_email,
+        months,
+        limit,
+        filter.tienda.as_deref(),
+        filter.from,
+        filter.to
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Gasto del usuario con un método de pago
+///
+/// Los tickets sin método de pago reconocido se agrupan en "Sin registrar".
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct PaymentMethodSpendItem {
+    pub metodo_pago: String,
+    pub total: Decimal,
+    pub tickets: i64,
+    pub porcentaje: f64,
+}
+
+/// Gasto mensual con un método de pago
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct PaymentMethodMonthlyPoint {
+    pub month: String,
+    pub metodo_pago: String,
+    pub total: Decimal,
+    pub tickets: i64,
+}
+
+/// Gasto con tarjeta agrupado por el alias que le ha dado el usuario
+///
+/// Los pagos con tarjeta cuyo número de operación no encaja con ningún
+/// patrón se agrupan en "Sin alias".
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct CardSpendItem {
+    pub alias: String,
+    pub total: Decimal,
+    pub tickets: i64,
+    pub ultima_compra: NaiveDateTime,
+}
+
+/// Reparto del gasto por método de pago
+///
+/// Con `months` se limita a los últimos meses (incluido el actual); sin él
+/// cuenta todas las compras del filtro.
+pub async fn get_spending_by_payment_method(
+    pool: &PgPool,
+    usuario_email: &str,
+    months: Option<i32>,
+    filter: &PurchaseFilter,
+) -> Result<Vec<PaymentMethodSpendItem>, sqlx::Error> {
+    sqlx::query_as!(
+        PaymentMethodSpendItem,
+        r#"
+        SELECT
+            COALESCE(c.metodo_pago, 'Sin registrar') as "metodo_pago!",
+            SUM(c.total)::numeric as "total!",
+            COUNT(*)::bigint as "tickets!",
+            COALESCE(
+                ROUND(SUM(c.total) * 100 / NULLIF(SUM(SUM(c.total)) OVER (), 0), 2),
+                0
+            )::float8 as "porcentaje!"
+        FROM compras c
+        WHERE c.usuario_email = $1
+            AND ($3::text IS NULL OR c.tienda = $3)
+            AND ($4::date IS NULL OR c.fecha_hora >= $4)
+            AND ($5::date IS NULL OR c.fecha_hora < $5::date + 1)
+            AND (
+                $2::int IS NULL
+                OR c.fecha_hora >= DATE_TRUNC('month', CURRENT_DATE) - INTERVAL '1 month' * ($2::int - 1)
+                OR $4::date IS NOT NULL
+                OR $5::date IS NOT NULL
+            )
+        GROUP BY 1
+        ORDER BY 2 DESC
+        "#,
+        usuario_email,
+        months,
+        filter.tienda.as_deref(),
+        filter.from,
+        filter.to
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Serie mensual de gasto por método de pago en los últimos `months` meses
+pub async fn get_payment_method_monthly_spending(
+    pool: &PgPool,
+    usuario_email: &str,
+    months: i32,
+    filter: &PurchaseFilter,
+) -> Result<Vec<PaymentMethodMonthlyPoint>, sqlx::Error> {
+    sqlx::query_as!(
+        PaymentMethodMonthlyPoint,
+        r#"
+        SELECT
+            TO_CHAR(DATE_TRUNC('month', c.fecha_hora), 'YYYY-MM') as "month!",
+            COALESCE(c.metodo_pago, 'Sin registrar') as "metodo_pago!",
+            SUM(c.total)::numeric as "total!",
+            COUNT(*)::bigint as "tickets!"
+        FROM compras c
+        WHERE c.usuario_email = $1
+            AND ($3::text IS NULL OR c.tienda = $3)
+            AND ($4::date IS NULL OR c.fecha_hora >= $4)
+            AND ($5::date IS NULL OR c.fecha_hora < $5::date + 1)
+            AND (
+                c.fecha_hora >= DATE_TRUNC('month', CURRENT_DATE) - INTERVAL '1 month' * ($2::int - 1)
+                OR $4::date IS NOT NULL
+                OR $5::date IS NOT NULL
+            )
+        GROUP BY 1, 2
+        ORDER BY 1, 3 DESC
+        "#,
+        usuario_email,
+        months,
+        filter.tienda.as_deref(),
+        filter.from,
+        filter.to
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Gasto con tarjeta por alias en los últimos `months` meses
+///
+/// Si varios patrones encajan con un número de operación gana el que tiene
+/// más caracteres fijos.
+pub async fn get_spending_by_card(
+    pool: &PgPool,
+    usuario_email: &str,
+    months: i32,
+    filter: &PurchaseFilter,
+) -> Result<Vec<CardSpendItem>, sqlx::Error> {
+    sqlx::query_as!(
+        CardSpendItem,
+        r#"
+        SELECT
+            COALESCE(t.alias, 'Sin alias') as "alias!",
+            SUM(c.total)::numeric as "total!",
+            COUNT(*)::bigint as "tickets!",
+            MAX(c.fecha_hora) as "ultima_compra!"
+        FROM compras c
+        LEFT JOIN LATERAL (
+            SELECT tu.alias
+            FROM tarjetas_usuario tu
+            WHERE tu.usuario_email = c.usuario_email
+                AND UPPER(c.numero_operacion) LIKE REPLACE(tu.patron, '*', '%')
+            ORDER BY LENGTH(REPLACE(tu.patron, '*', '')) DESC, tu.id
+            LIMIT 1
+        ) t ON TRUE
+        WHERE c.usuario_email = $1
+            AND (c.metodo_pago = 'TARJETA BANCARIA' OR t.alias IS NOT NULL)
+            AND ($3::text IS NULL OR c.tienda = $3)
+            AND ($4::date IS NULL OR c.fecha_hora >= $4)
+            AND ($5::date IS NULL OR c.fecha_hora < $5::date + 1)
+            AND (
+                c.fecha_hora >= DATE_TRUNC('month', CURRENT_DATE) - INTERVAL '1 month' * ($2::int - 1)
+                OR $4::date IS NOT NULL
+                OR $5::date IS NOT NULL
+            )
+        GROUP BY 1
+        ORDER BY 2 DESC
+        "#,
+        usuario_email,
+        months,
+        filter.tienda.as_deref(),
+        filter.from,
+        filter.to
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Base imponible y cuota de IVA pagadas en un mes con un tipo impositivo
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct IvaMonthlyPoint {
+    pub month: String,
+    pub iva_porcentaje: Decimal,
+    pub base_imponible: Decimal,
+    pub cuota: Decimal,
+    pub total: Decimal,
+    pub tickets: i64,
+}
+
+/// IVA pagado por mes y tipo impositivo
+///
+/// Usa el desglose impreso en el ticket cuando se guardó y, para los tickets
+/// sin él, la cuota de las líneas (los precios incluyen IVA).
+pub async fn get_monthly_iva(
+    pool: &PgPool,
+    usuario_email: &str,
+    filter: &PurchaseFilter,
+) -> Result<Vec<IvaMonthlyPoint>, sqlx::Error> {
+    sqlx::query_as!(
+        IvaMonthlyPoint,
+        r#"
+        WITH compras_filtradas AS (
+            SELECT c.numero_factura, c.fecha_hora
+            FROM compras c
+            WHERE c.usuario_email = $1
+                AND ($2::text IS NULL OR c.tienda = $2)
+                AND ($3::date IS NULL OR c.fecha_hora >= $3)
+                AND ($4::date IS NULL OR c.fecha_hora < $4::date + 1)
+        ),
+        desglose AS (
+            SELECT c.numero_factura, c.fecha_hora, ci.iva_porcentaje, ci.base_imponible, ci.cuota
+            FROM compras_filtradas c
+            INNER JOIN compras_iva ci ON ci.compra_numero_factura = c.numero_factura
+            UNION ALL
+            SELECT
+                c.numero_factura,
+                c.fecha_hora,
+                cp.iva_porcentaje,
+                SUM(cp.precio_total - cp.iva_importe),
+                SUM(cp.iva_importe)
+            FROM compras_filtradas c
+            INNER JOIN compras_productos cp ON cp.compra_numero_factura = c.numero_factura
+            WHERE NOT EXISTS (
+                SELECT 1 FROM compras_iva ci WHERE ci.compra_numero_factura = c.numero_factura
+            )
+            GROUP BY 1, 2, 3
+        )
+        SELECT
+            TO_CHAR(DATE_TRUNC('month', fecha_hora), 'YYYY-MM') as "month!",
+            iva_porcentaje as "iva_porcentaje!",
+            SUM(base_imponible)::numeric as "base_imponible!",
+            SUM(cuota)::numeric as "cuota!",
+            SUM(base_imponible + cuota)::numeric as "total!",
+            COUNT(DISTINCT numero_factura)::bigint as "tickets!"
+        FROM desglose
+        GROUP BY 1, 2
+        ORDER BY 1, 2
+        "#,
+        usuario_email,
+        filter.tienda.as_deref(),
+        filter.from,
+        filter.to
+    )
+    .fetch_all(pool)
+    .await
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -375,7 +1073,8 @@ mod tests {
         }
 
         // Test
-        let trend = get_spending_trend(&pool, "trend@example.com", 10).await?;
+        let trend =
+            get_spending_trend(&pool, "trend@example.com", 10, &PurchaseFilter::default()).await?;
 
         assert!(!trend.is_empty());
         assert_eq!(trend.len(), 5);
@@ -416,10 +1115,386 @@ mod tests {
         .await?;
 
         // Test
-        let comparison = get_month_comparison(&pool, "month@example.com").await?;
+        let comparison =
+            get_month_comparison(&pool, "month@example.com", &PurchaseFilter::default()).await?;
 
         assert!(comparison.current_month_spend > Decimal::ZERO);
 
+        Ok(())
+    }
+    #[sqlx::test(migrations = "./migrations")]
+    async fn test_get_personal_inflation_basket(pool: PgPool) -> sqlx::Result<()> {
+        use crate::db::{insert_purchase, insert_purchase_products, upsert_product};
+        use crate::models::{ProductUpsert, PurchaseInsert, PurchaseProductInsert};
+
+        sqlx::query!(
+            "INSERT INTO usuarios (email, password_hash, nombre) VALUES ($1, $2, $3)",
+            "inflation@example.com",
+            "$2b$12$KpIEW.jQKvqXfN5nDwAXLub8RCRYjqNvCLKXfzHpFGK2FQJGmqQJi",
+            "Inflation User"
+        )
+        .execute(&pool)
+        .await?;
+
+        for nombre in ["LECHE ENTERA", "PAN"] {
+            upsert_product(
+                &pool,
+                &ProductUpsert {
+                    nombre: nombre.to_string(),
+                    marca: None,
+                    unidad: "unidad".to_string(),
+                    precio_actual: None,
+                },
+            )
+            .await?;
+        }
+
+        let now = Utc::now().naive_utc() - Duration::hours(1);
+        let purchases = [
+            ("F-1", now - Duration::days(45), "LECHE ENTERA", 100),
+            ("F-2", now, "LECHE ENTERA", 110),
+            ("F-3", now, "PAN", 50),
+        ];
+
+        for (factura, fecha_hora, producto, centimos) in purchases {
+            let precio = Decimal::new(centimos, 2);
+            insert_purchase(
+                &pool,
+                &PurchaseInsert {
+                    numero_factura: factura.to_string(),
+                    usuario_email: "inflation@example.com".to_string(),
+                    fecha_hora,
+                    total: precio,
+                    tienda: None,
+                    ubicacion: None,
+                    metodo_pago: None,
+                    numero_operacion: None,
+                },
+            )
+            .await?;
+
+            let mut conn = pool.acquire().await?;
+            insert_purchase_products(
+                &mut conn,
+                factura,
+                &[PurchaseProductInsert {
+                    producto_nombre: producto.to_string(),
+                    cantidad: Decimal::ONE,
+                    precio_unitario: precio,
+                    precio_total: precio,
+                    descuento: Decimal::ZERO,
+                    iva_porcentaje: Decimal::new(4, 0),
+                    iva_importe: Decimal::ZERO,
+                }],
+            )
+            .await?;
+        }
+
+        let today = now.date();
+        let basket = get_personal_inflation_basket(
+            &pool,
+            "inflation@example.com",
+            today,
+            today - Duration::days(30),
+            today - Duration::days(365),
+            today - Duration::days(365),
+        )
+        .await?;
+
+        assert_eq!(basket.len(), 2);
+
+        // La cesta se ordena por gasto
+        let leche = &basket[0];
+        assert_eq!(leche.producto, "LECHE ENTERA");
+        assert_eq!(leche.gasto, Decimal::new(210, 2));
+        assert_eq!(leche.precio_actual, Some(Decimal::new(110, 2)));
+        assert_eq!(leche.precio_mes_anterior, Some(Decimal::new(100, 2)));
+        assert_eq!(leche.precio_anio_anterior, None);
+
+        // Sin histórico anterior no hay precio de referencia
+        let pan = &basket[1];
+        assert_eq!(pan.precio_actual, Some(Decimal::new(50, 2)));
+        assert_eq!(pan.precio_mes_anterior, None);
+
+        Ok(())
+    }
+    #[sqlx::test(migrations = "./migrations")]
+    async fn test_get_spending_by_category(pool: PgPool) -> sqlx::Result<()> {
+        use crate::db::{
+            assign_product_category, insert_categories, insert_purchase, insert_purchase_products,
+            set_user_product_category, upsert_product,
+        };
+        use crate::models::{ProductUpsert, PurchaseInsert, PurchaseProductInsert};
+
+        sqlx::query!(
+            "INSERT INTO usuarios (email, password_hash, nombre) VALUES ($1, $2, $3)",
+            "category@example.com",
+            "$2b$12$KpIEW.jQKvqXfN5nDwAXLub8RCRYjqNvCLKXfzHpFGK2FQJGmqQJi",
+            "Category User"
+        )
+        .execute(&pool)
+        .await?;
+
+        insert_categories(&pool, &["Lácteos".to_string(), "Bebidas".to_string()]).await?;
+
+        let lineas = [("LECHE ENTERA", 300), ("BATIDO CACAO", 100), ("XYZ", 100)];
+        for (nombre, _) in lineas {
+            upsert_product(
+                &pool,
+                &ProductUpsert {
+                    nombre: nombre.to_string(),
+                    marca: None,
+                    unidad: "unidad".to_string(),
+                    precio_actual: None,
+                },
+            )
+            .await?;
+        }
+        assign_product_category(&pool, "LECHE ENTERA", "Lácteos").await?;
+        assign_product_category(&pool, "BATIDO CACAO", "Lácteos").await?;
+        set_user_product_category(&pool, "category@example.com", "BATIDO CACAO", "Bebidas").await?;
+
+        insert_purchase(
+            &pool,
+            &PurchaseInsert {
+                numero_factura: "F-1".to_string(),
+                usuario_email: "category@example.com".to_string(),
+                fecha_hora: Utc::now().naive_utc() - Duration::hours(1),
+                total: Decimal::new(500, 2),
+                tienda: None,
+                ubicacion: None,
+                metodo_pago: None,
+                numero_operacion: None,
+            },
+        )
+        .await?;
+
+        let productos: Vec<_> = lineas
+            .iter()
+            .map(|(nombre, centimos)| PurchaseProductInsert {
+                producto_nombre: nombre.to_string(),
+                cantidad: Decimal::ONE,
+                precio_unitario: Decimal::new(*centimos, 2),
+                precio_total: Decimal::new(*centimos, 2),
+                descuento: Decimal::ZERO,
+                iva_porcentaje: Decimal::new(4, 0),
+                iva_importe: Decimal::ZERO,
+            })
+            .collect();
+        let mut conn = pool.acquire().await?;
+        insert_purchase_products(&mut conn, "F-1", &productos).await?;
+
+        let categorias =
+            get_spending_by_category(&pool, "category@example.com", 1, &PurchaseFilter::default())
+                .await?;
+        let resumen: Vec<_> = categorias
+            .iter()
+            .map(|c| (c.categoria.as_str(), c.total, c.porcentaje))
+            .collect();
+
+        assert_eq!(resumen[0], ("Lácteos", Decimal::new(300, 2), 60.0));
+        // La categoría elegida por el usuario prevalece sobre la automática
+        assert!(resumen.contains(&("Bebidas", Decimal::new(100, 2), 20.0)));
+        assert!(resumen.contains(&("Sin categoría", Decimal::new(100, 2), 20.0)));
+
+        let evolucion = get_category_monthly_spending(
+            &pool,
+            "category@example.com",
+            1,
+            &PurchaseFilter::default(),
+        )
+        .await?;
+        assert_eq!(evolucion.len(), 3);
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrations = "./migrations")]
+    async fn test_get_spending_by_store(pool: PgPool) -> sqlx::Result<()> {
+        use crate::db::{
+            get_user_stats, insert_purchase, insert_purchase_products, upsert_product,
+        };
+        use crate::models::{ProductUpsert, PurchaseInsert, PurchaseProductInsert};
+
+        sqlx::query!(
+            "INSERT INTO usuarios (email, password_hash, nombre) VALUES ($1, $2, $3)",
+            "store@example.com",
+            "$2b$12$KpIEW.jQKvqXfN5nDwAXLub8RCRYjqNvCLKXfzHpFGK2FQJGmqQJi",
+            "Store User"
+        )
+        .execute(&pool)
+        .await?;
+
+        for nombre in ["LECHE ENTERA", "PAN"] {
+            upsert_product(
+                &pool,
+                &ProductUpsert {
+                    nombre: nombre.to_string(),
+                    marca: None,
+                    unidad: "unidad".to_string(),
+                    precio_actual: None,
+                },
+            )
+            .await?;
+        }
+
+        let now = Utc::now().naive_utc() - Duration::hours(1);
+        let purchases = [
+            (
+                "F-1",
+                Some("MERCADONA"),
+                Some("CALLE A 1"),
+                "LECHE ENTERA",
+                100,
+            ),
+            (
+                "F-2",
+                Some("MERCADONA"),
+                Some("CALLE B 2"),
+                "LECHE ENTERA",
+                120,
+            ),
+            ("F-3", None, None, "PAN", 50),
+        ];
+        for (i, (factura, tienda, ubicacion, producto, centimos)) in
+            purchases.into_iter().enumerate()
+        {
+            insert_purchase(
+                &pool,
+                &PurchaseInsert {
+                    numero_factura: factura.to_string(),
+                    usuario_email: "store@example.com".to_string(),
+                    fecha_hora: now - Duration::minutes(i as i64),
+                    total: Decimal::new(centimos, 2),
+                    tienda: tienda.map(str::to_string),
+                    ubicacion: ubicacion.map(str::to_string),
+                    metodo_pago: None,
+                    numero_operacion: None,
+                },
+            )
+            .await?;
+
+            let linea = PurchaseProductInsert {
+                producto_nombre: producto.to_string(),
+                cantidad: Decimal::ONE,
+                precio_unitario: Decimal::new(centimos, 2),
+                precio_total: Decimal::new(centimos, 2),
+                descuento: Decimal::ZERO,
+                iva_porcentaje: Decimal::new(4, 0),
+                iva_importe: Decimal::ZERO,
+            };
+            let mut conn = pool.acquire().await?;
+            insert_purchase_products(&mut conn, factura, &[linea]).await?;
+        }
+
+        let sin_filtro = PurchaseFilter::default();
+        let tiendas = get_spending_by_store(&pool, "store@example.com", 1, &sin_filtro).await?;
+        assert_eq!(tiendas.len(), 3);
+        assert_eq!(tiendas[0].ubicacion.as_deref(), Some("CALLE B 2"));
+        assert_eq!(tiendas[0].visitas, 1);
+        assert!(tiendas.iter().any(|t| t.tienda == "Sin tienda"));
+
+        let diferencias =
+            get_store_price_differences(&pool, "store@example.com", 1, 10, &sin_filtro).await?;
+        assert_eq!(diferencias.len(), 1);
+        assert_eq!(diferencias[0].producto, "LECHE ENTERA");
+        assert_eq!(
+            diferencias[0].ubicacion_mas_barata.as_deref(),
+            Some("CALLE A 1")
+        );
+        assert_eq!(diferencias[0].diferencia_porcentaje, 20.0);
+
+        // Filtro por tienda en las estadísticas existentes
+        let mercadona = PurchaseFilter {
+            tienda: Some("MERCADONA".to_string()),
+            ..Default::default()
+        };
+        let stats = get_user_stats(&pool, "store@example.com", &mercadona).await?;
+        assert_eq!(stats.total_tickets, Some(2));
+
+        let top = get_top_products_by_spending(&pool, "store@example.com", 10, &mercadona).await?;
+        assert_eq!(top.len(), 1);
+        assert_eq!(top[0].gasto_total, Some(Decimal::new(220, 2)));
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrations = "./migrations")]
+    async fn test_get_monthly_iva(pool: PgPool) -> sqlx::Result<()> {
+        use crate::db::{insert_purchase, insert_purchase_iva, insert_purchase_products};
+        use crate::models::{PurchaseInsert, PurchaseIvaInsert, PurchaseProductInsert};
+
+        sqlx::query!(
+            "INSERT INTO usuarios (email, password_hash, nombre) VALUES ($1, $2, $3)",
+            "iva@example.com",
+            "$2b$12$KpIEW.jQKvqXfN5nDwAXLub8RCRYjqNvCLKXfzHpFGK2FQJGmqQJi",
+            "Iva User"
+        )
+        .execute(&pool)
+        .await?;
+        sqlx::query!("INSERT INTO productos (nombre, unidad) VALUES ('DETERGENTE', 'unidad')")
+            .execute(&pool)
+            .await?;
+
+        let fechas = [(2025, 1, 10), (2025, 1, 20), (2025, 2, 5)];
+        for (i, (year, month, day)) in fechas.into_iter().enumerate() {
+            let factura = format!("F-IVA-{}", i);
+            insert_purchase(
+                &pool,
+                &PurchaseInsert {
+                    numero_factura: factura.clone(),
+                    usuario_email: "iva@example.com".to_string(),
+                    fecha_hora: NaiveDate::from_ymd_opt(year, month, day)
+                        .unwrap()
+                        .and_hms_opt(12, 0, 0)
+                        .unwrap(),
+                    total: Decimal::new(1210, 2),
+                    tienda: None,
+                    ubicacion: None,
+                    metodo_pago: None,
+                    numero_operacion: None,
+                },
+            )
+            .await?;
+
+            let linea = PurchaseProductInsert {
+                producto_nombre: "DETERGENTE".to_string(),
+                cantidad: Decimal::ONE,
+                precio_unitario: Decimal::new(1210, 2),
+                precio_total: Decimal::new(1210, 2),
+                descuento: Decimal::ZERO,
+                iva_porcentaje: Decimal::new(21, 0),
+                iva_importe: Decimal::new(210, 2),
+            };
+            let mut conn = pool.acquire().await?;
+            insert_purchase_products(&mut conn, &factura, &[linea]).await?;
+
+            // El primer ticket trae desglose y prevalece sobre las líneas
+            if i == 0 {
+                let desglose = PurchaseIvaInsert {
+                    iva_porcentaje: Decimal::new(21, 0),
+                    base_imponible: Decimal::new(1000, 2),
+                    cuota: Decimal::new(209, 2),
+                };
+                insert_purchase_iva(&mut conn, &factura, &[desglose]).await?;
+            }
+        }
+
+        let mensual = get_monthly_iva(&pool, "iva@example.com", &PurchaseFilter::default()).await?;
+        assert_eq!(mensual.len(), 2);
+        assert_eq!(mensual[0].month, "2025-01");
+        assert_eq!(mensual[0].tickets, 2);
+        assert_eq!(mensual[0].cuota, Decimal::new(419, 2));
+        assert_eq!(mensual[0].base_imponible, Decimal::new(2000, 2));
+        assert_eq!(mensual[1].month, "2025-02");
+
+        let febrero = PurchaseFilter {
+            from: NaiveDate::from_ymd_opt(2025, 2, 1),
+            ..Default::default()
+        };
+        let mensual = get_monthly_iva(&pool, "iva@example.com", &febrero).await?;
+        assert_eq!(mensual.len(), 1);
+
         Ok(())
     }
 }
@@ -0,0 +1,43 @@
+use sqlx::PgPool;
+
+/// Guarda (o sustituye) el contenido de un archivo de ticket
+pub async fn upsert_ticket_blob(
+    pool: &PgPool,
+    clave: &str,
+    contenido: &[u8],
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO tickets_blobs (clave, contenido)
+        VALUES ($1, $2)
+        ON CONFLICT (clave) DO UPDATE SET contenido = EXCLUDED.contenido
+        "#,
+        clave,
+        contenido
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+/// Obtiene el contenido de un archivo de ticket
+pub async fn get_ticket_blob(pool: &PgPool, clave: &str) -> Result<Option<Vec<u8>>, sqlx::Error> {
+    let blob = sqlx::query_scalar!(
+        "SELECT contenido FROM tickets_blobs WHERE clave = $1",
+        clave
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(blob)
+}
+
+/// Elimina el contenido de un archivo de ticket
+pub async fn delete_ticket_blob(pool: &PgPool, clave: &str) -> Result<(), sqlx::Error> {
+    sqlx::query!("DELETE FROM tickets_blobs WHERE clave = $1", clave)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
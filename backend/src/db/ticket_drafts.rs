@@ -0,0 +1,236 @@
+use crate::models::{TicketDraft, TicketDraftInsert, TicketDraftSummary};
+use crate::services::OcrProcessTicketResponse;
+use chrono::NaiveDateTime;
+use sqlx::{types::Json, PgPool, Postgres};
+use uuid::Uuid;
+
+/// Guarda un borrador de ticket con el resultado del OCR y el archivo original
+pub async fn insert_ticket_draft(
+    pool: &PgPool,
+    draft: &TicketDraftInsert,
+) -> Result<TicketDraftSummary, sqlx::Error> {
+    let size_bytes = draft.archivo.len() as i32;
+
+    let result = sqlx::query_as!(
+        TicketDraftSummary,
+        r#"
+        INSERT INTO tickets_borrador (
+            usuario_email,
+            resultado_ocr,
+            archivo,
+            nombre_archivo,
+            mime_type,
+            tamano_bytes,
+            expira_en
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        RETURNING
+            id,
+            nombre_archivo,
+            mime_type,
+            tamano_bytes,
+            resultado_ocr->>'numero_factura' as numero_factura,
+            created_at,
+            expira_en
+        "#,
+        draft.usuario_email,
+        Json(&draft.resultado_ocr) as _,
+        draft.archivo,
+        draft.nombre_archivo,
+        draft.mime_type,
+        size_bytes,
+        draft.expira_en
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(result)
+}
+
+/// Obtiene un borrador completo (incluido el archivo) de un usuario
+pub async fn get_ticket_draft(
+    pool: &PgPool,
+    usuario_email: &str,
+    id: Uuid,
+) -> Result<Option<TicketDraft>, sqlx::Error> {
+    let draft = sqlx::query_as!(
+        TicketDraft,
+        r#"
+        SELECT
+            id,
+            resultado_ocr as "resultado_ocr: Json<OcrProcessTicketResponse>",
+            archivo,
+            nombre_archivo,
+            mime_type,
+            tamano_bytes,
+            created_at,
+            expira_en
+        FROM tickets_borrador
+        WHERE id = $1 AND usuario_email = $2
+        "#,
+        id,
+        usuario_email
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(draft)
+}
+
+/// Lista los borradores vigentes de un usuario, del más reciente al más antiguo
+pub async fn get_user_ticket_drafts(
+    pool: &PgPool,
+    usuario_email: &str,
+) -> Result<Vec<TicketDraftSummary>, sqlx::Error> {
+    let drafts = sqlx::query_as!(
+        TicketDraftSummary,
+        r#"
+        SELECT
+            id,
+            nombre_archivo,
+            mime_type,
+            tamano_bytes,
+            resultado_ocr->>'numero_factura' as numero_factura,
+            created_at,
+            expira_en
+        FROM tickets_borrador
+        WHERE usuario_email = $1
+          AND expira_en > (NOW() AT TIME ZONE 'UTC')
+        ORDER BY created_at DESC
+        "#,
+        usuario_email
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(drafts)
+}
+
+/// Elimina un borrador. Devuelve `false` si no existía
+pub async fn delete_ticket_draft<'c, E>(executor: E, id: Uuid) -> Result<bool, sqlx::Error>
+where
+    E: sqlx::Executor<'c, Database = Postgres>,
+{
+    let result = sqlx::query!("DELETE FROM tickets_borrador WHERE id = $1", id)
+        .execute(executor)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Elimina los borradores caducados de todos los usuarios
+pub async fn delete_expired_ticket_drafts(pool: &PgPool) -> Result<u64, sqlx::Error> {
+    let result =
+        sqlx::query!("DELETE FROM tickets_borrador WHERE expira_en <= (NOW() AT TIME ZONE 'UTC')")
+            .execute(pool)
+            .await?;
+
+    Ok(result.rows_affected())
+}
+
+/// Borrador vigente que caduca pronto
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct ExpiringTicketDraft {
+    pub id: Uuid,
+    pub usuario_email: String,
+    pub nombre_archivo: String,
+    pub expira_en: NaiveDateTime,
+}
+
+/// Borradores de todos los usuarios que caducan antes de `hasta`
+pub async fn get_expiring_ticket_drafts(
+    pool: &PgPool,
+    hasta: NaiveDateTime,
+) -> Result<Vec<ExpiringTicketDraft>, sqlx::Error> {
+    sqlx::query_as!(
+        ExpiringTicketDraft,
+        r#"
+        SELECT id, usuario_email, nombre_archivo, expira_en
+        FROM tickets_borrador
+        WHERE expira_en > (NOW() AT TIME ZONE 'UTC')
+          AND expira_en <= $1
+        ORDER BY expira_en
+        "#,
+        hasta
+    )
+    .fetch_all(pool)
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, Utc};
+
+    fn ocr_response() -> OcrProcessTicketResponse {
+        serde_json::from_value(serde_json::json!({
+            "ticket_id": "ticket_1",
+            "raw_text": "MERCADONA",
+            "numero_factura": "0001-001-000001",
+            "fecha": "2025-01-15",
+            "fecha_hora": "2025-01-15 10:30:00",
+            "total": 2.5,
+            "tienda": "MERCADONA",
+            "ubicacion": null,
+            "metodo_pago": null,
+            "numero_operacion": null
+        }))
+        .unwrap()
+    }
+
+    #[sqlx::test(migrations = "./migrations")]
+    async fn test_ticket_draft_lifecycle(pool: PgPool) -> sqlx::Result<()> {
+        sqlx::query!(
+            "INSERT INTO usuarios (email, password_hash, nombre) VALUES ($1, $2, $3)",
+            "test@example.com",
+            "$2b$12$KpIEW.jQKvqXfN5nDwAXLub8RCRYjqNvCLKXfzHpFGK2FQJGmqQJi",
+            "Test User"
+        )
+        .execute(&pool)
+        .await?;
+
+        let now = Utc::now().naive_utc();
+        let draft = TicketDraftInsert {
+            usuario_email: "test@example.com".to_string(),
+            resultado_ocr: ocr_response(),
+            archivo: vec![0x25, 0x50, 0x44, 0x46],
+            nombre_archivo: "ticket.pdf".to_string(),
+            mime_type: Some("application/pdf".to_string()),
+            expira_en: now + Duration::hours(24),
+        };
+
+        let summary = insert_ticket_draft(&pool, &draft).await?;
+        assert_eq!(summary.tamano_bytes, 4);
+        assert_eq!(summary.numero_factura.as_deref(), Some("0001-001-000001"));
+
+        // Otro usuario no puede ver el borrador
+        assert!(get_ticket_draft(&pool, "other@example.com", summary.id)
+            .await?
+            .is_none());
+
+        let stored = get_ticket_draft(&pool, "test@example.com", summary.id)
+            .await?
+            .expect("draft should exist");
+        assert_eq!(stored.archivo, draft.archivo);
+        assert_eq!(stored.resultado_ocr.total, Some(2.5));
+        assert!(!stored.is_expired(now));
+
+        // Un borrador caducado no se lista y se purga
+        sqlx::query!(
+            "UPDATE tickets_borrador SET created_at = $2, expira_en = $3 WHERE id = $1",
+            summary.id,
+            now - Duration::hours(48),
+            now - Duration::hours(1)
+        )
+        .execute(&pool)
+        .await?;
+
+        assert!(get_user_ticket_drafts(&pool, "test@example.com")
+            .await?
+            .is_empty());
+        assert_eq!(delete_expired_ticket_drafts(&pool).await?, 1);
+        assert!(!delete_ticket_draft(&pool, summary.id).await?);
+
+        Ok(())
+    }
+}
@@ -3,6 +3,8 @@ use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 use sqlx::PgPool;
 
+use super::PurchaseFilter;
+
 /// Resumen de un ticket para el histórico del usuario
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
 pub struct TicketHistoryItem {
@@ -21,6 +23,7 @@ pub async fn get_user_ticket_history(
     usuario_email: &str,
     limit: Option<i64>,
     offset: Option<i64>,
+    filter: &PurchaseFilter,
 ) -> Result<Vec<TicketHistoryItem>, sqlx::Error> {
     let limit = limit.unwrap_or(50);
     let offset = offset.unwrap_or(0);
@@ -39,13 +42,19 @@ pub async fn get_user_ticket_history(
         FROM compras c
         LEFT JOIN compras_productos cp ON c.numero_factura = cp.compra_numero_factura
         WHERE c.usuario_email = $1
+            AND ($4::text IS NULL OR c.tienda = $4)
+            AND ($5::date IS NULL OR c.fecha_hora >= $5)
+            AND ($6::date IS NULL OR c.fecha_hora < $6::date + 1)
         GROUP BY c.numero_factura, c.fecha_hora, c.total, c.tienda, c.ubicacion, c.created_at
         ORDER BY c.fecha_hora DESC, c.created_at DESC
         LIMIT $2 OFFSET $3
         "#,
         usuario_email,
         limit,
-        offset
+        offset,
+        filter.tienda.as_deref(),
+        filter.from,
+        filter.to
     )
     .fetch_all(pool)
     .await?;
@@ -62,7 +71,11 @@ pub struct UserStats {
     pub productos_unicos: Option<i64>,
 }
 
-pub async fn get_user_stats(pool: &PgPool, usuario_email: &str) -> Result<UserStats, sqlx::Error> {
+pub async fn get_user_stats(
+    pool: &PgPool,
+    usuario_email: &str,
+    filter: &PurchaseFilter,
+) -> Result<UserStats, sqlx::Error> {
     let stats = sqlx::query_as!(
         UserStats,
         r#"
@@ -76,6 +89,9 @@ pub async fn get_user_stats(pool: &PgPool, usuario_email: &str) -> Result<UserSt
                 END AS gasto_medio
             FROM compras
             WHERE usuario_email = $1
+                AND ($2::text IS NULL OR tienda = $2)
+                AND ($3::date IS NULL OR fecha_hora >= $3)
+                AND ($4::date IS NULL OR fecha_hora < $4::date + 1)
         ),
         productos_stats AS (
             SELECT
@@ -83,6 +99,9 @@ pub async fn get_user_stats(pool: &PgPool, usuario_email: &str) -> Result<UserSt
             FROM compras c
             LEFT JOIN compras_productos cp ON c.numero_factura = cp.compra_numero_factura
             WHERE c.usuario_email = $1
+                AND ($2::text IS NULL OR c.tienda = $2)
+                AND ($3::date IS NULL OR c.fecha_hora >= $3)
+                AND ($4::date IS NULL OR c.fecha_hora < $4::date + 1)
         )
         SELECT
             compras_stats.total_tickets as "total_tickets?",
@@ -91,7 +110,10 @@ pub async fn get_user_stats(pool: &PgPool, usuario_email: &str) -> Result<UserSt
             productos_stats.productos_unicos as "productos_unicos?"
         FROM compras_stats, productos_stats
         "#,
-        usuario_email
+        usuario_email,
+        filter.tienda.as_deref(),
+        filter.from,
+        filter.to
     )
     .fetch_one(pool)
     .await?;
@@ -137,7 +159,14 @@ mod tests {
         }
 
         // Test: obtener histórico
-        let history = get_user_ticket_history(&pool, "test@example.com", None, None).await?;
+        let history = get_user_ticket_history(
+            &pool,
+            "test@example.com",
+            None,
+            None,
+            &PurchaseFilter::default(),
+        )
+        .await?;
 
         assert_eq!(history.len(), 3);
         // Verificar que están ordenados por fecha descendente
@@ -145,6 +174,17 @@ mod tests {
         assert_eq!(history[1].numero_factura, "0001-001-000002");
         assert_eq!(history[2].numero_factura, "0001-001-000001");
 
+        // El último día del rango está incluido
+        let filter = PurchaseFilter {
+            from: NaiveDate::from_ymd_opt(2025, 1, 2),
+            to: NaiveDate::from_ymd_opt(2025, 1, 3),
+            ..Default::default()
+        };
+        let history =
+            get_user_ticket_history(&pool, "test@example.com", None, None, &filter).await?;
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[1].numero_factura, "0001-001-000002");
+
         Ok(())
     }
 
@@ -240,7 +280,7 @@ mod tests {
         }
 
         // Test
-        let stats = get_user_stats(&pool, "stats@example.com").await?;
+        let stats = get_user_stats(&pool, "stats@example.com", &PurchaseFilter::default()).await?;
 
         assert_eq!(stats.total_tickets, Some(2));
         assert_eq!(stats.total_gastado, Some(Decimal::new(12000, 2)));
@@ -1,37 +1,47 @@
-use crate::models::{TicketPdf, TicketPdfInsert};
+use crate::models::{TicketAttachment, TicketAttachmentInsert};
 use sqlx::{PgPool, Postgres};
 
-/// Inserta el PDF de un ticket en la base de datos
-pub async fn insert_ticket_pdf<'c, E>(
+/// Registra los metadatos del archivo original de un ticket
+///
+/// El contenido se guarda aparte, a través de `TicketStorage`.
+pub async fn insert_ticket_attachment<'c, E>(
     executor: E,
-    ticket: &TicketPdfInsert,
-) -> Result<TicketPdf, sqlx::Error>
+    ticket: &TicketAttachmentInsert,
+    almacenamiento: &str,
+    clave_almacenamiento: &str,
+) -> Result<TicketAttachment, sqlx::Error>
 where
     E: sqlx::Executor<'c, Database = Postgres>,
 {
     let size_bytes = ticket.calculate_size();
 
     let result = sqlx::query_as!(
-        TicketPdf,
+        TicketAttachment,
         r#"
-        INSERT INTO tickets_pdf (
+        INSERT INTO tickets_adjuntos (
             numero_factura,
-            ticket_pdf,
-            ticket_nombre_archivo,
-            ticket_tamano_bytes
+            nombre_archivo,
+            mime_type,
+            tamano_bytes,
+            almacenamiento,
+            clave_almacenamiento
         )
-        VALUES ($1, $2, $3, $4)
+        VALUES ($1, $2, $3, $4, $5, $6)
         RETURNING
             numero_factura,
-            ticket_pdf,
-            ticket_nombre_archivo,
-            ticket_tamano_bytes,
+            nombre_archivo,
+            mime_type,
+            tamano_bytes,
+            almacenamiento,
+            clave_almacenamiento,
             created_at
         "#,
         ticket.numero_factura,
-        ticket.ticket_pdf,
-        ticket.ticket_nombre_archivo,
-        size_bytes
+        ticket.normalized_file_name(),
+        ticket.kind.mime_type(),
+        size_bytes,
+        almacenamiento,
+        clave_almacenamiento
     )
     .fetch_one(executor)
     .await?;
@@ -39,39 +49,115 @@ where
     Ok(result)
 }
 
-/// Obtiene el PDF de un ticket
-pub async fn get_ticket_pdf(
-    pool: &PgPool,
+/// Obtiene los metadatos del archivo original de un ticket
+pub async fn get_ticket_attachment<'c, E>(
+    executor: E,
     numero_factura: &str,
-) -> Result<Option<TicketPdf>, sqlx::Error> {
+) -> Result<Option<TicketAttachment>, sqlx::Error>
+where
+    E: sqlx::Executor<'c, Database = Postgres>,
+{
     let ticket = sqlx::query_as!(
-        TicketPdf,
+        TicketAttachment,
         r#"
         SELECT
             numero_factura,
-            ticket_pdf,
-            ticket_nombre_archivo,
-            ticket_tamano_bytes,
+            nombre_archivo,
+            mime_type,
+            tamano_bytes,
+            almacenamiento,
+            clave_almacenamiento,
             created_at
-        FROM tickets_pdf
+        FROM tickets_adjuntos
         WHERE numero_factura = $1
         "#,
         numero_factura
     )
-    .fetch_optional(pool)
+    .fetch_optional(executor)
     .await?;
 
     Ok(ticket)
 }
 
+/// Indica si una compra tiene el archivo original almacenado
+pub async fn ticket_attachment_exists(
+    pool: &PgPool,
+    numero_factura: &str,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+        SELECT EXISTS(
+            SELECT 1 FROM tickets_adjuntos WHERE numero_factura = $1
+        ) as "exists!"
+        "#,
+        numero_factura
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(result.exists)
+}
+
+/// Lista los archivos guardados en un backend de almacenamiento
+pub async fn get_ticket_attachments_by_storage(
+    pool: &PgPool,
+    almacenamiento: &str,
+) -> Result<Vec<TicketAttachment>, sqlx::Error> {
+    let tickets = sqlx::query_as!(
+        TicketAttachment,
+        r#"
+        SELECT
+            numero_factura,
+            nombre_archivo,
+            mime_type,
+            tamano_bytes,
+            almacenamiento,
+            clave_almacenamiento,
+            created_at
+        FROM tickets_adjuntos
+        WHERE almacenamiento = $1
+        ORDER BY created_at
+        "#,
+        almacenamiento
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(tickets)
+}
+
+/// Cambia el backend en el que está guardado el archivo de un ticket
+pub async fn update_ticket_attachment_storage(
+    pool: &PgPool,
+    numero_factura: &str,
+    almacenamiento: &str,
+    clave_almacenamiento: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE tickets_adjuntos
+        SET almacenamiento = $2, clave_almacenamiento = $3
+        WHERE numero_factura = $1
+        "#,
+        numero_factura,
+        almacenamiento,
+        clave_almacenamiento
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::models::AttachmentKind;
     use chrono::NaiveDate;
     use rust_decimal::Decimal;
 
     #[sqlx::test(migrations = "./migrations")]
-    async fn test_insert_and_get_ticket_pdf(pool: PgPool) -> sqlx::Result<()> {
+    async fn test_insert_and_get_ticket_attachment(pool: PgPool) -> sqlx::Result<()> {
         // Setup: crear usuario y compra
         sqlx::query!(
             "INSERT INTO usuarios (email, password_hash, nombre) VALUES ($1, $2, $3)",
@@ -100,25 +186,96 @@ mod tests {
 
         // Test: insertar PDF
         let fake_pdf = vec![0x25, 0x50, 0x44, 0x46]; // "%PDF" magic bytes
-        let ticket = TicketPdfInsert {
+        let ticket = TicketAttachmentInsert {
             numero_factura: "0001-001-000001".to_string(),
-            ticket_pdf: fake_pdf.clone(),
-            ticket_nombre_archivo: "ticket_test.pdf".to_string(),
+            contenido: fake_pdf,
+            nombre_archivo: "ticket_test.pdf".to_string(),
+            kind: AttachmentKind::Pdf,
         };
 
-        let inserted = insert_ticket_pdf(&pool, &ticket).await?;
+        let inserted =
+            insert_ticket_attachment(&pool, &ticket, "postgres", "tickets/0001-001-000001/a")
+                .await?;
 
         assert_eq!(inserted.numero_factura, "0001-001-000001");
-        assert_eq!(inserted.ticket_pdf, fake_pdf);
-        assert_eq!(inserted.ticket_nombre_archivo, "ticket_test.pdf");
-        assert_eq!(inserted.ticket_tamano_bytes, 4);
+        assert_eq!(inserted.nombre_archivo, "ticket_test.pdf");
+        assert_eq!(inserted.mime_type, "application/pdf");
+        assert_eq!(inserted.tamano_bytes, 4);
 
         // Test: recuperar PDF
-        let retrieved = get_ticket_pdf(&pool, "0001-001-000001")
+        let retrieved = get_ticket_attachment(&pool, "0001-001-000001")
             .await?
             .expect("PDF should exist");
 
-        assert_eq!(retrieved.ticket_pdf, fake_pdf);
+        assert_eq!(retrieved.clave_almacenamiento, "tickets/0001-001-000001/a");
+        assert!(ticket_attachment_exists(&pool, "0001-001-000001").await?);
+        assert!(!ticket_attachment_exists(&pool, "0001-001-000002").await?);
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrations = "./migrations")]
+    async fn test_insert_image_attachment(pool: PgPool) -> sqlx::Result<()> {
+        sqlx::query!(
+            "INSERT INTO usuarios (email, password_hash, nombre) VALUES ($1, $2, $3)",
+            "test@example.com",
+            "$2b$12$KpIEW.jQKvqXfN5nDwAXLub8RCRYjqNvCLKXfzHpFGK2FQJGmqQJi",
+            "Test User"
+        )
+        .execute(&pool)
+        .await?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO compras (numero_factura, usuario_email, fecha_hora, total)
+            VALUES ($1, $2, $3, $4)
+            "#,
+            "0001-001-000001",
+            "test@example.com",
+            NaiveDate::from_ymd_opt(2025, 1, 15)
+                .unwrap()
+                .and_hms_opt(10, 30, 0)
+                .unwrap(),
+            Decimal::new(4565, 2)
+        )
+        .execute(&pool)
+        .await?;
+
+        // Foto sin extension: se guarda con la extension del formato detectado
+        let photo = vec![0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x10];
+        let kind =
+            AttachmentKind::detect("IMG_1234", Some("image/jpeg"), &photo).expect("valid jpeg");
+        let ticket = TicketAttachmentInsert {
+            numero_factura: "0001-001-000001".to_string(),
+            contenido: photo,
+            nombre_archivo: "IMG_1234".to_string(),
+            kind,
+        };
+
+        let inserted =
+            insert_ticket_attachment(&pool, &ticket, "filesystem", "tickets/0001-001-000001/b")
+                .await?;
+
+        assert_eq!(inserted.nombre_archivo, "IMG_1234.jpg");
+        assert_eq!(inserted.mime_type, "image/jpeg");
+
+        // El contenido debe coincidir con el formato declarado
+        assert!(
+            AttachmentKind::detect("ticket.png", Some("image/png"), &ticket.contenido).is_err()
+        );
+        assert!(
+            AttachmentKind::detect("ticket.gif", Some("image/gif"), &ticket.contenido).is_err()
+        );
+
+        // Cambio de backend tras una migracion
+        update_ticket_attachment_storage(&pool, "0001-001-000001", "s3", "tickets/x").await?;
+        assert!(get_ticket_attachments_by_storage(&pool, "filesystem")
+            .await?
+            .is_empty());
+        assert_eq!(
+            get_ticket_attachments_by_storage(&pool, "s3").await?[0].clave_almacenamiento,
+            "tickets/x"
+        );
 
         Ok(())
     }
@@ -10,6 +10,7 @@ pub enum AppError {
     DatabaseError(String),
     NotFound(String),
     Unauthorized(String),
+    Forbidden(String),
     BadRequest(String),
     InternalError(String),
     ServiceUnavailable(String),
@@ -25,63 +26,95 @@ pub enum AppError {
 #[derive(Debug, Serialize)]
 pub struct ErrorResponse {
     pub error: String,
+    pub code: &'static str,
 }
 
-impl IntoResponse for AppError {
-    fn into_response(self) -> Response {
-        let (status, error_message) = match self {
-            AppError::DatabaseError(msg) => {
-                tracing::error!("Database error: {}", msg);
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    "Error en la base de datos".to_string(),
-                )
+impl AppError {
+    /// Código estable del error, compartido por la API y los trabajos en segundo plano
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::DatabaseError(_) => "database_error",
+            AppError::NotFound(_) => "not_found",
+            AppError::Unauthorized(_) => "unauthorized",
+            AppError::Forbidden(_) => "forbidden",
+            AppError::BadRequest(_) => "bad_request",
+            AppError::InternalError(_) => "internal_error",
+            AppError::ServiceUnavailable(_) => "service_unavailable",
+            AppError::MissingInvoiceNumber => "missing_invoice_number",
+            AppError::InvalidTotals(_) => "invalid_totals",
+            AppError::DuplicatePurchase(_) => "duplicate_purchase",
+            AppError::DatabaseIntegrity(_) => "database_integrity",
+            AppError::InvalidTicketData(_) => "invalid_ticket_data",
+            AppError::DemoUserRestriction => "demo_user_restriction",
+        }
+    }
+
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            AppError::DatabaseError(_) | AppError::InternalError(_) => {
+                StatusCode::INTERNAL_SERVER_ERROR
             }
-            AppError::NotFound(msg) => (StatusCode::NOT_FOUND, msg),
-            AppError::Unauthorized(msg) => (StatusCode::UNAUTHORIZED, msg),
-            AppError::BadRequest(msg) => (StatusCode::BAD_REQUEST, msg),
-            AppError::InternalError(msg) => {
-                tracing::error!("Internal error: {}", msg);
-                (
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    "Error interno del servidor".to_string(),
-                )
+            AppError::NotFound(_) => StatusCode::NOT_FOUND,
+            AppError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            AppError::Forbidden(_) => StatusCode::FORBIDDEN,
+            AppError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            AppError::ServiceUnavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+            AppError::MissingInvoiceNumber
+            | AppError::InvalidTotals(_)
+            | AppError::DatabaseIntegrity(_)
+            | AppError::InvalidTicketData(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            AppError::DuplicatePurchase(_) => StatusCode::CONFLICT,
+            AppError::DemoUserRestriction => StatusCode::FORBIDDEN,
+        }
+    }
+
+    /// Mensaje que puede mostrarse al usuario (sin detalles internos)
+    pub fn public_message(&self) -> String {
+        match self {
+            AppError::DatabaseError(_) => "Error en la base de datos".to_string(),
+            AppError::InternalError(_) => "Error interno del servidor".to_string(),
+            AppError::ServiceUnavailable(_) => "Servicio temporalmente no disponible".to_string(),
+            AppError::NotFound(msg)
+            | AppError::Unauthorized(msg)
+            | AppError::Forbidden(msg)
+            | AppError::BadRequest(msg)
+            | AppError::InvalidTotals(msg)
+            | AppError::InvalidTicketData(msg) => msg.clone(),
+            AppError::MissingInvoiceNumber => "El ticket no contiene numero de factura".to_string(),
+            AppError::DuplicatePurchase(invoice) => {
+                format!("La compra con numero de factura {} ya existe", invoice)
             }
+            AppError::DatabaseIntegrity(msg) => format!("Error de integridad: {}", msg),
+            AppError::DemoUserRestriction => "Acción no permitida para el usuario demo".to_string(),
+        }
+    }
+
+    /// Registra en el log los errores que no son culpa del usuario
+    pub fn log(&self) {
+        match self {
+            AppError::DatabaseError(msg) => tracing::error!("Database error: {}", msg),
+            AppError::InternalError(msg) => tracing::error!("Internal error: {}", msg),
             AppError::ServiceUnavailable(msg) => {
-                tracing::warn!("Servicio externo no disponible: {}", msg);
-                (
-                    StatusCode::SERVICE_UNAVAILABLE,
-                    "Servicio temporalmente no disponible".to_string(),
-                )
+                tracing::warn!("Servicio externo no disponible: {}", msg)
             }
-            AppError::MissingInvoiceNumber => (
-                StatusCode::UNPROCESSABLE_ENTITY,
-                "El ticket no contiene numero de factura".to_string(),
-            ),
-            AppError::InvalidTotals(msg) => (StatusCode::UNPROCESSABLE_ENTITY, msg),
-            AppError::DuplicatePurchase(invoice) => (
-                StatusCode::CONFLICT,
-                format!("La compra con numero de factura {} ya existe", invoice),
-            ),
             AppError::DatabaseIntegrity(msg) => {
-                tracing::error!("Database integrity error: {}", msg);
-                (
-                    StatusCode::UNPROCESSABLE_ENTITY,
-                    format!("Error de integridad: {}", msg),
-                )
+                tracing::error!("Database integrity error: {}", msg)
             }
-            AppError::InvalidTicketData(msg) => (StatusCode::UNPROCESSABLE_ENTITY, msg),
-            AppError::DemoUserRestriction => (
-                StatusCode::FORBIDDEN,
-                "Acción no permitida para el usuario demo".to_string(),
-            ),
-        };
+            _ => {}
+        }
+    }
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        self.log();
 
         let body = Json(ErrorResponse {
-            error: error_message,
+            error: self.public_message(),
+            code: self.code(),
         });
 
-        (status, body).into_response()
+        (self.status_code(), body).into_response()
     }
 }
 
@@ -122,6 +155,44 @@ impl From<bcrypt::BcryptError> for AppError {
     }
 }
 
+impl From<crate::services::StorageError> for AppError {
+    fn from(err: crate::services::StorageError) -> Self {
+        AppError::InternalError(format!("Error de almacenamiento de tickets: {}", err))
+    }
+}
+
+impl From<crate::services::IntelligenceClientError> for AppError {
+    fn from(err: crate::services::IntelligenceClientError) -> Self {
+        use crate::services::IntelligenceClientError;
+        use reqwest::StatusCode as ReqStatusCode;
+
+        match err {
+            IntelligenceClientError::Timeout | IntelligenceClientError::ServiceUnavailable => {
+                AppError::ServiceUnavailable("Servicio de inteligencia no disponible".to_string())
+            }
+            IntelligenceClientError::UnexpectedStatus { status, .. } => {
+                if status == ReqStatusCode::BAD_REQUEST
+                    || status == ReqStatusCode::UNPROCESSABLE_ENTITY
+                    || status == ReqStatusCode::UNSUPPORTED_MEDIA_TYPE
+                {
+                    AppError::BadRequest("No se pudo procesar el ticket".to_string())
+                } else {
+                    AppError::InternalError(format!(
+                        "Fallo en el servicio de inteligencia ({})",
+                        status
+                    ))
+                }
+            }
+            IntelligenceClientError::Deserialize(_) => {
+                AppError::InternalError("Respuesta OCR invalida".to_string())
+            }
+            IntelligenceClientError::Request(_) => AppError::InternalError(
+                "No se pudo contactar con el servicio de inteligencia".to_string(),
+            ),
+        }
+    }
+}
+
 impl From<jsonwebtoken::errors::Error> for AppError {
     fn from(err: jsonwebtoken::errors::Error) -> Self {
         AppError::InternalError(format!("Error de JWT: {}", err))
@@ -16,15 +16,58 @@ use sqlx::postgres::PgPoolOptions;
 use std::net::SocketAddr;
 use tower_http::cors::{AllowOrigin, CorsLayer};
 
-use config::AppConfig;
+use config::{AppConfig, StorageBackend};
 use routes::auth::AppState;
-use services::IntelligenceClient;
+use services::{
+    backfill_package_sizes, build_ticket_storage, migrate_ticket_files, seed_categories,
+    DraftExpiryNotifier, IntelligenceClient, OcrJobWorker, ReportScheduler, SavingsGoalCloser,
+    TicketEventBus, TicketStorages,
+};
 
 /// Health check endpoint
 async fn health() -> &'static str {
     "OK"
 }
 
+/// Subcomando `migrate-storage --from <backend> --to <backend>`
+///
+/// Mueve los archivos de tickets existentes entre backends de almacenamiento.
+async fn run_storage_migration(
+    args: &[String],
+    config: &AppConfig,
+    pool: &sqlx::PgPool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut from = None;
+    let mut to = config.ticket_storage.backend;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let value = args
+            .next()
+            .and_then(|v| StorageBackend::parse(v))
+            .ok_or_else(|| format!("{} requiere postgres, filesystem o s3", arg))?;
+
+        match arg.as_str() {
+            "--from" => from = Some(value),
+            "--to" => to = value,
+            _ => return Err(format!("Argumento desconocido: {}", arg).into()),
+        }
+    }
+
+    let from = from.ok_or("Uso: migrate-storage --from <backend> [--to <backend>]")?;
+    let source = build_ticket_storage(from, &config.ticket_storage, pool)?;
+    let target = build_ticket_storage(to, &config.ticket_storage, pool)?;
+
+    let report = migrate_ticket_files(pool, source.as_ref(), target.as_ref()).await?;
+
+    println!(
+        "Archivos migrados: {}, no encontrados: {}, fallidos: {}",
+        report.migrados, report.no_encontrados, report.fallidos
+    );
+
+    Ok(())
+}
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     tracing_subscriber::fmt()
@@ -49,6 +92,36 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     tracing::info!("Conectado a la base de datos");
 
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("migrate-storage") {
+        return run_storage_migration(&args[1..], &config, &pool).await;
+    }
+
+    // Categorías de productos a partir de las reglas incluidas
+    match seed_categories(&pool).await {
+        Ok(asignados) => tracing::info!("Categorias sincronizadas ({} productos)", asignados),
+        Err(err) => {
+            err.log();
+            tracing::warn!("No se pudieron sincronizar las categorias");
+        }
+    }
+
+    // Tamaño de envase de los productos que aún no lo tienen
+    match backfill_package_sizes(&pool).await {
+        Ok(registrados) => tracing::info!("Envases extraidos ({} productos)", registrados),
+        Err(err) => {
+            err.log();
+            tracing::warn!("No se pudieron extraer los tamaños de envase");
+        }
+    }
+
+    // Almacenamiento de los archivos originales de los tickets
+    let ticket_storage = TicketStorages::build(&config.ticket_storage, &pool)?;
+    tracing::info!(
+        "Archivos de tickets en el backend {}",
+        config.ticket_storage.backend.as_str()
+    );
+
     // Cliente HTTP para el servicio externo de inteligencia (OCR/ML)
     let intelligence_client = IntelligenceClient::new(
         config.intelligence_service_url.clone(),
@@ -66,11 +139,51 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         tracing::info!("Servicio de inteligencia disponible");
     }
 
+    // Eventos de progreso de los tickets (SSE)
+    let ticket_events = TicketEventBus::new();
+
+    // Worker de la cola de trabajos de OCR
+    OcrJobWorker {
+        pool: pool.clone(),
+        storage: ticket_storage.clone(),
+        client: intelligence_client.clone(),
+        events: ticket_events.clone(),
+        max_attempts: config.ocr_job_max_attempts,
+        retry_base_secs: config.ocr_job_retry_base_secs,
+        poll_interval: std::time::Duration::from_secs(config.ocr_job_poll_secs),
+        lease: chrono::Duration::seconds(config.ocr_job_lease_secs),
+    }
+    .spawn();
+
+    // Cierre de los objetivos de ahorro de los meses terminados
+    SavingsGoalCloser {
+        pool: pool.clone(),
+        interval: std::time::Duration::from_secs(config.savings_goal_close_secs),
+    }
+    .spawn();
+
+    // Informes de gasto según la frecuencia elegida por cada usuario
+    ReportScheduler {
+        pool: pool.clone(),
+        interval: std::time::Duration::from_secs(config.report_scheduler_secs),
+    }
+    .spawn();
+
+    // Avisos de borradores de ticket a punto de caducar
+    DraftExpiryNotifier {
+        pool: pool.clone(),
+        interval: std::time::Duration::from_secs(config.draft_expiry_check_secs),
+        notice: chrono::Duration::hours(config.draft_expiry_notice_hours),
+    }
+    .spawn();
+
     // Crear estado de la aplicacion
     let state = AppState {
         db_pool: pool,
         config: config.clone(),
         intelligence_client: intelligence_client.clone(),
+        ticket_storage,
+        ticket_events,
     };
 
     let allowed_origins = config
@@ -80,8 +193,26 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .collect::<Result<Vec<_>, _>>()?;
     let cors = CorsLayer::new()
         .allow_origin(AllowOrigin::list(allowed_origins))
-        .allow_methods([Method::GET, Method::POST, Method::DELETE, Method::OPTIONS])
-        .allow_headers([header::AUTHORIZATION, header::CONTENT_TYPE]);
+        .allow_methods([
+            Method::GET,
+            Method::POST,
+            Method::PUT,
+            Method::PATCH,
+            Method::DELETE,
+            Method::OPTIONS,
+        ])
+        .allow_headers([
+            header::AUTHORIZATION,
+            header::CONTENT_TYPE,
+            header::RANGE,
+            header::IF_NONE_MATCH,
+            header::IF_RANGE,
+        ])
+        .expose_headers([
+            header::CONTENT_DISPOSITION,
+            header::CONTENT_RANGE,
+            header::ETAG,
+        ]);
 
     // Construir el router
     let app = Router::new()
@@ -89,7 +220,25 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .nest("/api/auth", routes::auth_router(state.clone()))
         .nest("/api/ocr", routes::ocr_router(state.clone()))
         .nest("/api/tickets", routes::tickets_router(state.clone()))
+        .nest("/api/products", routes::products_router(state.clone()))
         .nest("/api/stats", routes::stats_router(state.clone()))
+        .nest(
+            "/api/savings-goals",
+            routes::savings_goals_router(state.clone()),
+        )
+        .nest(
+            "/api/payment-cards",
+            routes::payment_cards_router(state.clone()),
+        )
+        .nest("/api/me", routes::me_router(state.clone()))
+        .nest(
+            "/api/notifications",
+            routes::notifications_router(state.clone()),
+        )
+        .nest(
+            "/api/achievements",
+            routes::achievements_router(state.clone()),
+        )
         .nest(
             "/api/predict",
             routes::intelligence::intelligence_router(state.clone()),
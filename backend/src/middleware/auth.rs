@@ -4,13 +4,19 @@ use axum::{
     http::{header::AUTHORIZATION, request::Parts},
 };
 
-use crate::{error::AppError, routes::auth::AppState, services::verify_jwt};
+use crate::{
+    error::AppError,
+    routes::auth::AppState,
+    services::{verify_events_token, verify_jwt},
+};
 
 /// Usuario autenticado extraido desde el token JWT
 #[derive(Debug, Clone)]
 pub struct AuthenticatedUser {
     pub email: String,
     pub is_demo: bool,
+    /// Puede modificar el catálogo compartido por todos los usuarios
+    pub is_operator: bool,
 }
 
 #[async_trait]
@@ -36,26 +42,47 @@ impl FromRequestParts<AppState> for AuthenticatedUser {
             .filter(|value| !value.is_empty())
             .ok_or_else(|| AppError::Unauthorized("Formato de token invalido".to_string()))?;
 
-        match verify_jwt(token, &state.config.jwt_secret) {
-            Ok(claims) => {
-                let is_demo = state
-                    .config
-                    .demo_user_email
-                    .as_ref()
-                    .map(|demo_email| demo_email == &claims.sub)
-                    .unwrap_or(false);
-
-                Ok(AuthenticatedUser {
-                    email: claims.sub,
-                    is_demo,
-                })
-            }
-            Err(err) => {
-                tracing::warn!("Intento de acceso con JWT invalido: {:?}", err);
-                Err(AppError::Unauthorized(
-                    "Token invalido o expirado".to_string(),
-                ))
-            }
+        authenticate_token(token, state)
+    }
+}
+
+/// Valida un JWT de sesión y devuelve el usuario autenticado
+pub fn authenticate_token(token: &str, state: &AppState) -> Result<AuthenticatedUser, AppError> {
+    match verify_jwt(token, &state.config.jwt_secret) {
+        Ok(claims) => Ok(authenticated_user(claims.sub, state)),
+        Err(err) => {
+            tracing::warn!("Intento de acceso con JWT invalido: {:?}", err);
+            Err(AppError::Unauthorized(
+                "Token invalido o expirado".to_string(),
+            ))
         }
     }
 }
+
+/// Valida el token de eventos que llega en la URL
+///
+/// `EventSource` no permite enviar cabeceras; el token de sesión nunca se
+/// acepta aquí para que no acabe en logs ni en el historial del navegador.
+pub fn authenticate_events_token(
+    token: &str,
+    state: &AppState,
+) -> Result<AuthenticatedUser, AppError> {
+    let claims = verify_events_token(token, &state.config.jwt_secret)?;
+    Ok(authenticated_user(claims.sub, state))
+}
+
+fn authenticated_user(email: String, state: &AppState) -> AuthenticatedUser {
+    let is_demo = state
+        .config
+        .demo_user_email
+        .as_ref()
+        .map(|demo_email| demo_email == &email)
+        .unwrap_or(false);
+    let is_operator = !is_demo && state.config.operator_emails.contains(&email);
+
+    AuthenticatedUser {
+        email,
+        is_demo,
+        is_operator,
+    }
+}
@@ -1,3 +1,3 @@
 pub mod auth;
 
-pub use auth::AuthenticatedUser;
+pub use auth::{authenticate_events_token, AuthenticatedUser};
@@ -0,0 +1,21 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use sqlx::types::Json;
+
+/// Logro del catálogo (`logros`)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct Achievement {
+    pub id: i32,
+    pub codigo: String,
+    pub nombre: String,
+    pub descripcion: Option<String>,
+    pub icono: Option<String>,
+}
+
+/// Logro desbloqueado por un usuario (`logros_usuario`)
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct AchievementUnlock {
+    pub logro_id: i32,
+    pub desbloqueado_en: NaiveDateTime,
+    pub metadata: Option<Json<serde_json::Value>>,
+}
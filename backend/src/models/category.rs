@@ -0,0 +1,17 @@
+use serde::Serialize;
+
+/// Categoría de productos
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct Category {
+    pub id: i32,
+    pub nombre: String,
+}
+
+/// Categoría efectiva de un producto para un usuario
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct ProductCategory {
+    pub producto: String,
+    pub categoria: Option<String>,
+    /// Indica si la categoría la ha elegido el usuario
+    pub personalizada: bool,
+}
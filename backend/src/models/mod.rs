@@ -1,11 +1,29 @@
+pub mod achievement;
+pub mod category;
+pub mod notification;
+pub mod ocr_job;
+pub mod payment_card;
+pub mod preferences;
 pub mod product;
 pub mod purchase;
 pub mod purchase_product;
-pub mod ticket_pdf;
+pub mod savings_goal;
+pub mod ticket_attachment;
+pub mod ticket_draft;
 pub mod user;
 
-pub use product::{Product, ProductUpsert};
-pub use purchase::{Purchase, PurchaseInsert};
-pub use purchase_product::PurchaseProductInsert;
-pub use ticket_pdf::{TicketPdf, TicketPdfInsert};
+pub use achievement::{Achievement, AchievementUnlock};
+pub use category::{Category, ProductCategory};
+pub use notification::{Notification, NotificationInsert, NotificationKind};
+pub use ocr_job::{OcrJob, OcrJobInsert, OcrJobStatus, OcrJobTask};
+pub use payment_card::PaymentCard;
+pub use preferences::{
+    ReportFrequency, SpendingAlert, UserPreferences, UserPreferencesUpdate, UserReport,
+};
+pub use product::{Product, ProductPackage, ProductUnit, ProductUpsert};
+pub use purchase::{Purchase, PurchaseInsert, PurchaseIvaInsert};
+pub use purchase_product::{PurchaseProduct, PurchaseProductInsert};
+pub use savings_goal::SavingsGoal;
+pub use ticket_attachment::{AttachmentKind, TicketAttachment, TicketAttachmentInsert};
+pub use ticket_draft::{TicketDraft, TicketDraftInsert, TicketDraftSummary};
 pub use user::User;
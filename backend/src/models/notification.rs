@@ -0,0 +1,49 @@
+use chrono::NaiveDateTime;
+use serde::Serialize;
+use sqlx::types::Json;
+
+/// Tipo de notificación
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationKind {
+    AlertaGasto,
+    Logro,
+    SubidaPrecio,
+    BorradorCaduca,
+}
+
+impl NotificationKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NotificationKind::AlertaGasto => "alerta_gasto",
+            NotificationKind::Logro => "logro",
+            NotificationKind::SubidaPrecio => "subida_precio",
+            NotificationKind::BorradorCaduca => "borrador_caduca",
+        }
+    }
+}
+
+/// Notificación tal y como la consulta el usuario (`notificaciones`)
+#[derive(Debug, Clone, PartialEq, Serialize, sqlx::FromRow)]
+pub struct Notification {
+    pub id: i32,
+    pub tipo: String,
+    pub titulo: String,
+    pub mensaje: String,
+    pub datos: Option<Json<serde_json::Value>>,
+    pub leida: bool,
+    pub leida_en: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+}
+
+/// DTO para encolar una notificación
+#[derive(Debug, Clone, PartialEq)]
+pub struct NotificationInsert {
+    pub usuario_email: String,
+    pub tipo: NotificationKind,
+    pub titulo: String,
+    pub mensaje: String,
+    pub datos: Option<serde_json::Value>,
+    /// Identifica el hecho notificado; con la misma clave solo se avisa una vez
+    pub clave: Option<String>,
+}
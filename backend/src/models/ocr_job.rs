@@ -0,0 +1,87 @@
+use chrono::NaiveDateTime;
+use serde::Serialize;
+use sqlx::types::Json;
+use uuid::Uuid;
+
+use crate::services::TicketIngestionResponse;
+
+/// Estado de un trabajo de OCR en segundo plano
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OcrJobStatus {
+    Queued,
+    Ocr,
+    Validating,
+    Ingested,
+    Failed,
+}
+
+impl OcrJobStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OcrJobStatus::Queued => "queued",
+            OcrJobStatus::Ocr => "ocr",
+            OcrJobStatus::Validating => "validating",
+            OcrJobStatus::Ingested => "ingested",
+            OcrJobStatus::Failed => "failed",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "queued" => Some(OcrJobStatus::Queued),
+            "ocr" => Some(OcrJobStatus::Ocr),
+            "validating" => Some(OcrJobStatus::Validating),
+            "ingested" => Some(OcrJobStatus::Ingested),
+            "failed" => Some(OcrJobStatus::Failed),
+            _ => None,
+        }
+    }
+}
+
+/// Trabajo de OCR tal y como lo consulta el usuario (sin el archivo)
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct OcrJob {
+    pub id: Uuid,
+    pub estado: String,
+    pub nombre_archivo: String,
+    pub mime_type: Option<String>,
+    pub tamano_bytes: i32,
+    pub intentos: i32,
+    pub siguiente_intento: NaiveDateTime,
+    pub error_codigo: Option<String>,
+    pub error_mensaje: Option<String>,
+    pub resultado: Option<Json<TicketIngestionResponse>>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+impl OcrJob {
+    pub fn status(&self) -> OcrJobStatus {
+        OcrJobStatus::parse(&self.estado).unwrap_or(OcrJobStatus::Failed)
+    }
+}
+
+/// Trabajo reservado por el worker, con todo lo necesario para procesarlo
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct OcrJobTask {
+    pub id: Uuid,
+    pub usuario_email: String,
+    pub archivo: Option<Vec<u8>>,
+    pub nombre_archivo: String,
+    pub mime_type: Option<String>,
+    pub reemplazar: bool,
+    pub intentos: i32,
+    /// Compra que un intento anterior ya llegó a crear
+    pub numero_factura: Option<String>,
+}
+
+/// DTO para encolar un trabajo de OCR
+#[derive(Debug, Clone)]
+pub struct OcrJobInsert {
+    pub usuario_email: String,
+    pub archivo: Vec<u8>,
+    pub nombre_archivo: String,
+    pub mime_type: Option<String>,
+    pub reemplazar: bool,
+}
@@ -0,0 +1,28 @@
+use chrono::NaiveDateTime;
+use serde::Serialize;
+
+/// Alias que el usuario asocia a un patrón de número de operación
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct PaymentCard {
+    pub id: i32,
+    pub alias: String,
+    /// Patrón de `numero_operacion`; `*` equivale a cualquier secuencia
+    pub patron: String,
+    pub created_at: NaiveDateTime,
+}
+
+impl PaymentCard {
+    /// Normaliza un patrón (trim y uppercase), o None si no es válido
+    ///
+    /// Solo admite letras, dígitos y `*`, y necesita al menos un carácter
+    /// que no sea comodín.
+    pub fn normalize_pattern(pattern: &str) -> Option<String> {
+        let normalized = pattern.trim().to_uppercase();
+        let valid = normalized
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '*');
+        let has_literal = normalized.chars().any(|c| c != '*');
+
+        (valid && has_literal).then_some(normalized)
+    }
+}
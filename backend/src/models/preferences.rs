@@ -0,0 +1,99 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::types::Json;
+
+/// Frecuencia con la que se generan los informes de gasto
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReportFrequency {
+    Diaria,
+    Semanal,
+    Mensual,
+    Nunca,
+}
+
+impl ReportFrequency {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReportFrequency::Diaria => "diaria",
+            ReportFrequency::Semanal => "semanal",
+            ReportFrequency::Mensual => "mensual",
+            ReportFrequency::Nunca => "nunca",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "diaria" => Some(ReportFrequency::Diaria),
+            "semanal" => Some(ReportFrequency::Semanal),
+            "mensual" => Some(ReportFrequency::Mensual),
+            "nunca" => Some(ReportFrequency::Nunca),
+            _ => None,
+        }
+    }
+}
+
+/// Preferencias del usuario (`preferencias_usuario`)
+///
+/// Los usuarios sin fila usan los valores por defecto de la tabla.
+#[derive(Debug, Clone, PartialEq, Serialize, sqlx::FromRow)]
+pub struct UserPreferences {
+    pub alertas_gasto_activas: bool,
+    /// Gasto mensual a partir del cual se crea una alerta
+    pub umbral_alerta_gasto: Option<Decimal>,
+    pub notif_nuevos_logros: bool,
+    pub notif_inflacion: bool,
+    pub frecuencia_reportes: String,
+    pub configuracion_extra: Option<Json<serde_json::Value>>,
+    /// `None` mientras el usuario no haya guardado sus preferencias
+    pub updated_at: Option<NaiveDateTime>,
+}
+
+impl Default for UserPreferences {
+    fn default() -> Self {
+        Self {
+            alertas_gasto_activas: true,
+            umbral_alerta_gasto: None,
+            notif_nuevos_logros: true,
+            notif_inflacion: true,
+            frecuencia_reportes: ReportFrequency::Semanal.as_str().to_string(),
+            configuracion_extra: None,
+            updated_at: None,
+        }
+    }
+}
+
+/// DTO para guardar las preferencias del usuario
+#[derive(Debug, Clone)]
+pub struct UserPreferencesUpdate {
+    pub alertas_gasto_activas: bool,
+    pub umbral_alerta_gasto: Option<Decimal>,
+    pub notif_nuevos_logros: bool,
+    pub notif_inflacion: bool,
+    pub frecuencia_reportes: ReportFrequency,
+    pub configuracion_extra: Option<serde_json::Value>,
+}
+
+/// Alerta de umbral de gasto mensual superado (`alertas_gasto`)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, sqlx::FromRow)]
+pub struct SpendingAlert {
+    pub id: i32,
+    pub mes: NaiveDate,
+    pub umbral: Decimal,
+    pub gasto: Decimal,
+    pub created_at: NaiveDateTime,
+}
+
+/// Informe periódico de gasto (`informes_usuario`)
+#[derive(Debug, Clone, PartialEq, Serialize, sqlx::FromRow)]
+pub struct UserReport {
+    pub id: i32,
+    pub frecuencia: String,
+    pub desde: NaiveDate,
+    pub hasta: NaiveDate,
+    pub total: Decimal,
+    pub compras: i32,
+    pub total_anterior: Decimal,
+    pub generado_en: NaiveDateTime,
+}
@@ -27,9 +27,65 @@ impl ProductUpsert {
     pub fn normalize_name(name: &str) -> String {
         name.trim().to_uppercase()
     }
+}
+
+/// Unidad de medida de un producto (columna `productos.unidad`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProductUnit {
+    Unidad,
+    Kg,
+    G,
+    L,
+    Ml,
+}
 
-    /// Valida que la unidad sea una de las permitidas
-    pub fn validate_unit(unit: &str) -> bool {
-        matches!(unit, "unidad" | "kg" | "g" | "l" | "ml")
+impl ProductUnit {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Unidad => "unidad",
+            Self::Kg => "kg",
+            Self::G => "g",
+            Self::L => "l",
+            Self::Ml => "ml",
+        }
     }
+
+    /// Interpreta la unidad tal y como la devuelve el OCR o aparece en el ticket
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().trim_end_matches('.').to_lowercase().as_str() {
+            "" | "u" | "ud" | "uds" | "un" | "unidad" | "unidades" => Some(Self::Unidad),
+            "kg" | "kgs" | "kilo" | "kilos" | "kilogramo" | "kilogramos" => Some(Self::Kg),
+            "g" | "gr" | "grs" | "gramo" | "gramos" => Some(Self::G),
+            "l" | "lt" | "lts" | "litro" | "litros" => Some(Self::L),
+            "ml" | "mililitro" | "mililitros" => Some(Self::Ml),
+            _ => None,
+        }
+    }
+
+    /// Unidad en la que se guardan las cantidades (kg o l para peso y volumen)
+    /// y factor para convertir a ella
+    pub fn base(self) -> (Self, Decimal) {
+        match self {
+            Self::G => (Self::Kg, Decimal::new(1, 3)),
+            Self::Ml => (Self::L, Decimal::new(1, 3)),
+            unit => (unit, Decimal::ONE),
+        }
+    }
+
+    /// Indica si la cantidad es un peso o un volumen y no un número de unidades
+    pub fn is_measured(self) -> bool {
+        self != Self::Unidad
+    }
+}
+
+/// Tamaño de envase extraído del nombre de un producto
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProductPackage {
+    /// Nombre del producto sin el tamaño
+    pub nombre_base: String,
+    pub piezas: i32,
+    /// Contenido total del envase en `unidad`
+    pub contenido: Decimal,
+    /// Kg o litros
+    pub unidad: ProductUnit,
 }
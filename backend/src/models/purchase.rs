@@ -29,6 +29,14 @@ pub struct PurchaseInsert {
     pub numero_operacion: Option<String>,
 }
 
+/// DTO para guardar una fila del desglose de IVA del ticket
+#[derive(Debug, Clone, PartialEq)]
+pub struct PurchaseIvaInsert {
+    pub iva_porcentaje: Decimal,
+    pub base_imponible: Decimal,
+    pub cuota: Decimal,
+}
+
 impl PurchaseInsert {
     /// Normaliza el número de factura (trim y uppercase)
     pub fn normalize_invoice_number(invoice: &str) -> String {
@@ -28,11 +28,26 @@ pub struct PurchaseProductInsert {
 
 impl PurchaseProductInsert {
     /// Valida que los precios sean coherentes (precio_total ≈ precio_unitario * cantidad - descuento)
-    /// Permite una tolerancia de 0.01€ para redondeos
+    /// Por debajo de 0.01€ de diferencia o del redondeo del precio unitario al
+    /// céntimo (medio céntimo por unidad), igual que el CHECK `precio_coherente`
     pub fn validate_price_coherence(&self) -> bool {
         let expected = self.precio_unitario * self.cantidad - self.descuento;
         let diff = (self.precio_total - expected).abs();
-        diff <= Decimal::new(1, 2) // 0.01
+        diff < Decimal::new(1, 2) // 0.01
+            || diff <= self.cantidad * Decimal::new(5, 3) // 0.005 por unidad
+    }
+
+    /// Recalcula el precio unitario a partir del total, el descuento y la cantidad
+    ///
+    /// El precio se redondea al céntimo; la diferencia de redondeo con el
+    /// total (cantidades grandes o a granel) entra en la tolerancia de
+    /// `validate_price_coherence` y el descuento no se toca.
+    pub fn derive_unit_price(&mut self) {
+        if self.cantidad.is_zero() {
+            return;
+        }
+
+        self.precio_unitario = ((self.precio_total + self.descuento) / self.cantidad).round_dp(2);
     }
 
     /// Normaliza el porcentaje de IVA a los valores estándar de España
@@ -49,8 +64,66 @@ impl PurchaseProductInsert {
         }
     }
 
+    /// Acumula otra línea del mismo producto en esta
+    ///
+    /// Los importes se suman y el precio unitario se deriva del total, de modo
+    /// que la línea resultante sigue cumpliendo `precio_coherente`.
+    pub fn absorb(&mut self, other: &PurchaseProductInsert) {
+        self.cantidad += other.cantidad;
+        self.precio_total += other.precio_total;
+        self.descuento += other.descuento;
+        self.iva_importe += other.iva_importe;
+        self.derive_unit_price();
+    }
+
     /// Calcula el IVA importe si no está presente
-    pub fn calculate_iva_importe(base: Decimal, percentage: Decimal) -> Decimal {
-        base * percentage / Decimal::new(100, 0)
+    ///
+    /// Los precios del ticket incluyen IVA, así que la cuota es la parte del
+    /// importe que corresponde al impuesto, redondeada al céntimo.
+    pub fn calculate_iva_importe(importe: Decimal, percentage: Decimal) -> Decimal {
+        (importe * percentage / (Decimal::new(100, 0) + percentage)).round_dp(2)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn linea(cantidad: i64, precio_unitario: i64) -> PurchaseProductInsert {
+        let cantidad = Decimal::new(cantidad, 0);
+        let precio_unitario = Decimal::new(precio_unitario, 2);
+        PurchaseProductInsert {
+            producto_nombre: "LECHE".to_string(),
+            cantidad,
+            precio_unitario,
+            precio_total: precio_unitario * cantidad,
+            descuento: Decimal::ZERO,
+            iva_porcentaje: Decimal::new(4, 0),
+            iva_importe: Decimal::ZERO,
+        }
+    }
+
+    #[test]
+    fn test_absorb_unequal_unit_prices() {
+        // 1 x 1.00 + 2 x 1.01: la media redondeada (1.01) da 3.03 para 3.02
+        let mut producto = linea(1, 100);
+        producto.absorb(&linea(2, 101));
+        assert_eq!(producto.cantidad, Decimal::new(3, 0));
+        assert_eq!(producto.precio_total, Decimal::new(302, 2));
+        assert_eq!(producto.precio_unitario, Decimal::new(101, 2));
+        assert!(producto.validate_price_coherence());
+
+        // 1 x 1.00 + 2 x 1.02: el resto del redondeo no se convierte en descuento
+        let mut producto = linea(1, 100);
+        producto.absorb(&linea(2, 102));
+        assert_eq!(producto.precio_total, Decimal::new(304, 2));
+        assert_eq!(producto.precio_unitario, Decimal::new(101, 2));
+        assert_eq!(producto.descuento, Decimal::ZERO);
+        assert!(producto.validate_price_coherence());
+
+        // Una diferencia mayor que el redondeo sigue siendo incoherente
+        let mut producto = linea(3, 101);
+        producto.precio_total = Decimal::new(305, 2);
+        assert!(!producto.validate_price_coherence());
     }
 }
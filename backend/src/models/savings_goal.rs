@@ -0,0 +1,24 @@
+use chrono::{Datelike, NaiveDate, NaiveDateTime};
+use rust_decimal::Decimal;
+use serde::Serialize;
+
+/// Objetivo de gasto máximo de un usuario para un mes
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct SavingsGoal {
+    pub id: i32,
+    /// Primer día del mes del objetivo
+    pub mes: NaiveDate,
+    pub objetivo_mensual: Decimal,
+    /// Se cumple si el gasto del mes no supera el objetivo; se fija al cerrar el mes
+    pub conseguido: bool,
+    /// Diferencia entre el objetivo y el gasto del mes; `None` hasta cerrarlo
+    pub ahorro_real: Option<Decimal>,
+    pub created_at: NaiveDateTime,
+}
+
+impl SavingsGoal {
+    /// Primer día del mes de una fecha
+    pub fn month_start(date: NaiveDate) -> NaiveDate {
+        date.with_day(1).expect("el día 1 siempre existe")
+    }
+}
@@ -0,0 +1,192 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+/// Tamaño máximo de un ticket en PDF (10 MB)
+pub const MAX_PDF_SIZE: usize = 10_485_760;
+
+/// Tamaño máximo de una foto de ticket (20 MB)
+pub const MAX_IMAGE_SIZE: usize = 20_971_520;
+
+/// Metadatos del archivo original de un ticket (PDF o imagen)
+///
+/// El contenido vive en el backend de almacenamiento indicado, bajo
+/// `clave_almacenamiento`.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct TicketAttachment {
+    pub numero_factura: String,
+    pub nombre_archivo: String,
+    pub mime_type: String,
+    pub tamano_bytes: i32,
+    pub almacenamiento: String,
+    pub clave_almacenamiento: String,
+    pub created_at: NaiveDateTime,
+}
+
+/// DTO para insertar el archivo original de un ticket
+#[derive(Debug, Clone)]
+pub struct TicketAttachmentInsert {
+    pub numero_factura: String,
+    pub contenido: Vec<u8>,
+    pub nombre_archivo: String,
+    pub kind: AttachmentKind,
+}
+
+/// Formatos de archivo admitidos para los tickets
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttachmentKind {
+    Pdf,
+    Jpeg,
+    Png,
+    Webp,
+    Heic,
+}
+
+impl AttachmentKind {
+    /// Interpreta un MIME declarado por el cliente
+    pub fn from_mime(mime: &str) -> Option<Self> {
+        let mime = mime.split(';').next().unwrap_or_default().trim();
+
+        match mime.to_lowercase().as_str() {
+            "application/pdf" => Some(Self::Pdf),
+            "image/jpeg" | "image/jpg" | "image/pjpeg" => Some(Self::Jpeg),
+            "image/png" => Some(Self::Png),
+            "image/webp" => Some(Self::Webp),
+            "image/heic" | "image/heif" | "image/heic-sequence" | "image/heif-sequence" => {
+                Some(Self::Heic)
+            }
+            _ => None,
+        }
+    }
+
+    /// Deduce el formato a partir de la extensión del nombre de archivo
+    pub fn from_file_name(file_name: &str) -> Option<Self> {
+        let extension = file_name.rsplit_once('.')?.1.to_lowercase();
+
+        match extension.as_str() {
+            "pdf" => Some(Self::Pdf),
+            "jpg" | "jpeg" => Some(Self::Jpeg),
+            "png" => Some(Self::Png),
+            "webp" => Some(Self::Webp),
+            "heic" | "heif" => Some(Self::Heic),
+            _ => None,
+        }
+    }
+
+    /// Deduce el formato a partir de la firma (magic bytes) del contenido
+    pub fn sniff(content: &[u8]) -> Option<Self> {
+        if content.starts_with(b"%PDF") {
+            Some(Self::Pdf)
+        } else if content.starts_with(&[0xFF, 0xD8, 0xFF]) {
+            Some(Self::Jpeg)
+        } else if content.starts_with(&[0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A]) {
+            Some(Self::Png)
+        } else if content.len() >= 12 && &content[0..4] == b"RIFF" && &content[8..12] == b"WEBP" {
+            Some(Self::Webp)
+        } else if content.len() >= 12
+            && &content[4..8] == b"ftyp"
+            && matches!(
+                &content[8..12],
+                b"heic" | b"heix" | b"hevc" | b"hevx" | b"heim" | b"heis" | b"mif1" | b"msf1"
+            )
+        {
+            Some(Self::Heic)
+        } else {
+            None
+        }
+    }
+
+    /// Determina el formato de un archivo recibido
+    ///
+    /// El MIME declarado (o en su defecto la extensión) debe coincidir con la
+    /// firma del contenido; así no se almacenan archivos con un tipo falso.
+    pub fn detect(file_name: &str, mime: Option<&str>, content: &[u8]) -> Result<Self, String> {
+        let declared = match mime.filter(|m| !m.trim().is_empty()) {
+            Some(mime) => Some(
+                Self::from_mime(mime).ok_or_else(|| format!("Formato no soportado: {}", mime))?,
+            ),
+            None => Self::from_file_name(file_name),
+        };
+
+        let detected = Self::sniff(content).ok_or_else(|| {
+            "El archivo no es un PDF ni una imagen JPEG, PNG, WEBP o HEIC válida".to_string()
+        })?;
+
+        match declared {
+            Some(declared) if declared != detected => Err(format!(
+                "El contenido del archivo no corresponde con el formato declarado ({})",
+                declared.mime_type()
+            )),
+            _ => Ok(detected),
+        }
+    }
+
+    /// MIME canónico con el que se almacena y se sirve el archivo
+    pub fn mime_type(self) -> &'static str {
+        match self {
+            Self::Pdf => "application/pdf",
+            Self::Jpeg => "image/jpeg",
+            Self::Png => "image/png",
+            Self::Webp => "image/webp",
+            Self::Heic => "image/heic",
+        }
+    }
+
+    /// Extensión por defecto del formato
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Pdf => "pdf",
+            Self::Jpeg => "jpg",
+            Self::Png => "png",
+            Self::Webp => "webp",
+            Self::Heic => "heic",
+        }
+    }
+
+    /// Tamaño máximo admitido para el formato
+    pub fn max_size(self) -> usize {
+        match self {
+            Self::Pdf => MAX_PDF_SIZE,
+            _ => MAX_IMAGE_SIZE,
+        }
+    }
+}
+
+impl TicketAttachmentInsert {
+    /// Calcula el tamaño en bytes
+    pub fn calculate_size(&self) -> i32 {
+        self.contenido.len() as i32
+    }
+
+    /// Nombre de archivo con una extensión coherente con el formato
+    pub fn normalized_file_name(&self) -> String {
+        let name = self.nombre_archivo.trim();
+
+        if AttachmentKind::from_file_name(name) == Some(self.kind) {
+            name.to_string()
+        } else if name.is_empty() {
+            format!("ticket.{}", self.kind.extension())
+        } else {
+            format!("{}.{}", name, self.kind.extension())
+        }
+    }
+
+    /// Valida el archivo completo
+    pub fn validate(&self) -> Result<(), String> {
+        let size = self.contenido.len();
+
+        if size == 0 {
+            return Err("El archivo está vacío".to_string());
+        }
+
+        if size > self.kind.max_size() {
+            return Err(format!(
+                "El archivo excede el tamaño máximo de {}MB para {} (tamaño actual: {} bytes)",
+                self.kind.max_size() / 1_048_576,
+                self.kind.mime_type(),
+                size
+            ));
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,49 @@
+use chrono::NaiveDateTime;
+use serde::Serialize;
+use sqlx::types::Json;
+use uuid::Uuid;
+
+use crate::services::OcrProcessTicketResponse;
+
+/// Borrador de ticket: resultado del OCR pendiente de confirmación
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct TicketDraft {
+    pub id: Uuid,
+    pub resultado_ocr: Json<OcrProcessTicketResponse>,
+    pub archivo: Vec<u8>,
+    pub nombre_archivo: String,
+    pub mime_type: Option<String>,
+    pub tamano_bytes: i32,
+    pub created_at: NaiveDateTime,
+    pub expira_en: NaiveDateTime,
+}
+
+/// Datos de un borrador sin el archivo ni el resultado completo del OCR
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct TicketDraftSummary {
+    pub id: Uuid,
+    pub nombre_archivo: String,
+    pub mime_type: Option<String>,
+    pub tamano_bytes: i32,
+    pub numero_factura: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub expira_en: NaiveDateTime,
+}
+
+/// DTO para insertar un borrador de ticket
+#[derive(Debug, Clone)]
+pub struct TicketDraftInsert {
+    pub usuario_email: String,
+    pub resultado_ocr: OcrProcessTicketResponse,
+    pub archivo: Vec<u8>,
+    pub nombre_archivo: String,
+    pub mime_type: Option<String>,
+    pub expira_en: NaiveDateTime,
+}
+
+impl TicketDraft {
+    /// Indica si el borrador ya no puede confirmarse
+    pub fn is_expired(&self, now: NaiveDateTime) -> bool {
+        self.expira_en <= now
+    }
+}
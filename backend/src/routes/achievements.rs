@@ -0,0 +1,28 @@
+use axum::{extract::State, routing::get, Json, Router};
+
+use super::auth::AppState;
+use crate::{
+    error::AppResult,
+    middleware::AuthenticatedUser,
+    services::{evaluate_achievements, get_user_achievements, AchievementProgress},
+};
+
+/// Handler para listar los logros del usuario con su progreso
+///
+/// Antes de responder se evalúan las reglas, así se recogen también los logros
+/// que no dependen de subir un ticket (p. ej. el cierre de un objetivo de ahorro).
+pub async fn list_achievements_handler(
+    State(state): State<AppState>,
+    auth_user: AuthenticatedUser,
+) -> AppResult<Json<Vec<AchievementProgress>>> {
+    evaluate_achievements(&state.db_pool, &auth_user.email).await?;
+    let logros = get_user_achievements(&state.db_pool, &auth_user.email).await?;
+    Ok(Json(logros))
+}
+
+/// Router para los logros
+pub fn achievements_router(state: AppState) -> Router {
+    Router::new()
+        .route("/", get(list_achievements_handler))
+        .with_state(state)
+}
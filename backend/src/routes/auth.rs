@@ -6,7 +6,10 @@ use crate::{
     db,
     error::AppResult,
     schema::{AuthResponse, LoginRequest, RegisterRequest, UserInfo},
-    services::{generate_jwt, hash_password, verify_password, IntelligenceClient},
+    services::{
+        generate_jwt, hash_password, verify_password, IntelligenceClient, TicketEventBus,
+        TicketStorages,
+    },
 };
 
 /// Estado compartido del servidor
@@ -15,6 +18,8 @@ pub struct AppState {
     pub db_pool: PgPool,
     pub config: AppConfig,
     pub intelligence_client: IntelligenceClient,
+    pub ticket_storage: TicketStorages,
+    pub ticket_events: TicketEventBus,
 }
 
 /// Handler para registro de usuario
@@ -129,3 +134,36 @@ pub fn auth_router(state: AppState) -> Router {
         .route("/login", post(login))
         .with_state(state)
 }
+
+#[cfg(test)]
+impl AppState {
+    /// Estado para probar handlers contra la base de datos de test
+    ///
+    /// Los archivos se guardan en Postgres y el servicio de inteligencia no
+    /// se contacta.
+    pub(crate) fn for_tests(pool: PgPool) -> Self {
+        if std::env::var("JWT_SECRET").is_err() {
+            std::env::set_var("JWT_SECRET", "test-secret");
+        }
+
+        let mut config = AppConfig::from_env().expect("configuración de test");
+        config.ticket_storage.backend = crate::config::StorageBackend::Postgres;
+
+        let intelligence_client = IntelligenceClient::new(
+            config.intelligence_service_url.clone(),
+            None,
+            config.intelligence_timeout_secs,
+            0,
+        )
+        .expect("cliente de inteligencia de test");
+
+        Self {
+            ticket_storage: TicketStorages::build(&config.ticket_storage, &pool)
+                .expect("almacenamiento de test"),
+            db_pool: pool,
+            config,
+            intelligence_client,
+            ticket_events: TicketEventBus::new(),
+        }
+    }
+}
@@ -0,0 +1,123 @@
+use axum::{
+    extract::{Query, State},
+    routing::get,
+    Json, Router,
+};
+use rust_decimal::Decimal;
+use serde::Deserialize;
+
+use super::auth::AppState;
+use crate::{
+    db::{list_spending_alerts, list_user_reports, upsert_user_preferences},
+    error::{AppError, AppResult},
+    middleware::AuthenticatedUser,
+    models::{ReportFrequency, SpendingAlert, UserPreferences, UserPreferencesUpdate, UserReport},
+    schema::UserPreferencesPayload,
+    services::get_preferences,
+};
+
+/// Importe máximo que admite `preferencias_usuario.umbral_alerta_gasto` (NUMERIC(10, 2))
+const MAX_THRESHOLD: Decimal = Decimal::from_parts(99_999_999, 0, 0, false, 0);
+
+#[derive(Debug, Deserialize)]
+pub struct MeListQuery {
+    #[serde(default = "default_list_limit")]
+    pub limit: i64,
+}
+
+fn default_list_limit() -> i64 {
+    20
+}
+
+/// Handler para consultar las preferencias del usuario
+pub async fn get_preferences_handler(
+    State(state): State<AppState>,
+    auth_user: AuthenticatedUser,
+) -> AppResult<Json<UserPreferences>> {
+    let preferences = get_preferences(&state.db_pool, &auth_user.email).await?;
+    Ok(Json(preferences))
+}
+
+/// Handler para guardar las preferencias del usuario
+pub async fn update_preferences_handler(
+    State(state): State<AppState>,
+    auth_user: AuthenticatedUser,
+    Json(payload): Json<UserPreferencesPayload>,
+) -> AppResult<Json<UserPreferences>> {
+    if auth_user.is_demo {
+        return Err(AppError::DemoUserRestriction);
+    }
+
+    let update = validate_preferences(payload)?;
+    let preferences = upsert_user_preferences(&state.db_pool, &auth_user.email, &update).await?;
+    Ok(Json(preferences))
+}
+
+/// Handler para listar las alertas de gasto del usuario
+pub async fn list_alerts_handler(
+    State(state): State<AppState>,
+    auth_user: AuthenticatedUser,
+    Query(params): Query<MeListQuery>,
+) -> AppResult<Json<Vec<SpendingAlert>>> {
+    let alerts =
+        list_spending_alerts(&state.db_pool, &auth_user.email, params.limit.clamp(1, 100)).await?;
+    Ok(Json(alerts))
+}
+
+/// Handler para listar los informes periódicos del usuario
+pub async fn list_reports_handler(
+    State(state): State<AppState>,
+    auth_user: AuthenticatedUser,
+    Query(params): Query<MeListQuery>,
+) -> AppResult<Json<Vec<UserReport>>> {
+    let reports =
+        list_user_reports(&state.db_pool, &auth_user.email, params.limit.clamp(1, 100)).await?;
+    Ok(Json(reports))
+}
+
+/// Aplica las mismas restricciones que `preferencias_usuario`
+fn validate_preferences(payload: UserPreferencesPayload) -> AppResult<UserPreferencesUpdate> {
+    let frecuencia_reportes = ReportFrequency::parse(payload.frecuencia_reportes.trim())
+        .ok_or_else(|| {
+            AppError::BadRequest(
+                "frecuencia_reportes debe ser diaria, semanal, mensual o nunca".to_string(),
+            )
+        })?;
+
+    let umbral_alerta_gasto = match payload.umbral_alerta_gasto.map(|u| u.round_dp(2)) {
+        Some(umbral) if umbral <= Decimal::ZERO || umbral > MAX_THRESHOLD => {
+            return Err(AppError::BadRequest(
+                "umbral_alerta_gasto debe ser mayor que 0".to_string(),
+            ));
+        }
+        umbral => umbral,
+    };
+
+    if matches!(&payload.configuracion_extra, Some(extra) if !extra.is_object() && !extra.is_null())
+    {
+        return Err(AppError::BadRequest(
+            "configuracion_extra debe ser un objeto JSON".to_string(),
+        ));
+    }
+
+    Ok(UserPreferencesUpdate {
+        alertas_gasto_activas: payload.alertas_gasto_activas,
+        umbral_alerta_gasto,
+        notif_nuevos_logros: payload.notif_nuevos_logros,
+        notif_inflacion: payload.notif_inflacion,
+        frecuencia_reportes,
+        configuracion_extra: payload.configuracion_extra.filter(|extra| !extra.is_null()),
+    })
+}
+
+/// Router para los datos del usuario autenticado
+pub fn me_router(state: AppState) -> Router {
+    Router::new()
+        .route(
+            "/preferences",
+            get(get_preferences_handler).put(update_preferences_handler),
+        )
+        .route("/alerts", get(list_alerts_handler))
+        .route("/reports", get(list_reports_handler))
+        .with_state(state)
+}
@@ -1,10 +1,22 @@
+pub mod achievements;
 pub mod auth;
 pub mod intelligence;
+pub mod me;
+pub mod notifications;
 pub mod ocr;
+pub mod payment_cards;
+pub mod products;
+pub mod savings_goals;
 pub mod stats;
 pub mod tickets;
 
+pub use achievements::achievements_router;
 pub use auth::auth_router;
+pub use me::me_router;
+pub use notifications::notifications_router;
 pub use ocr::ocr_router;
+pub use payment_cards::payment_cards_router;
+pub use products::products_router;
+pub use savings_goals::savings_goals_router;
 pub use stats::stats_router;
 pub use tickets::tickets_router;
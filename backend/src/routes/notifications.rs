@@ -0,0 +1,101 @@
+use axum::{
+    extract::{Path, Query, State},
+    routing::{get, post},
+    Json, Router,
+};
+use serde::Deserialize;
+
+use super::auth::AppState;
+use crate::{
+    db::{
+        count_unread_notifications, list_notifications, mark_all_notifications_read,
+        mark_notification_read,
+    },
+    error::{AppError, AppResult},
+    middleware::AuthenticatedUser,
+    schema::{NotificationListResponse, UnreadCountResponse},
+};
+
+#[derive(Debug, Deserialize)]
+pub struct NotificationQueryParams {
+    /// Return only unread notifications
+    #[serde(default)]
+    pub unread: bool,
+    #[serde(default = "default_notifications_limit")]
+    pub limit: i64,
+}
+
+fn default_notifications_limit() -> i64 {
+    20
+}
+
+/// Handler para listar las notificaciones del usuario
+pub async fn list_notifications_handler(
+    State(state): State<AppState>,
+    auth_user: AuthenticatedUser,
+    Query(params): Query<NotificationQueryParams>,
+) -> AppResult<Json<NotificationListResponse>> {
+    let notificaciones = list_notifications(
+        &state.db_pool,
+        &auth_user.email,
+        params.unread,
+        params.limit.clamp(1, 100),
+    )
+    .await?;
+    let no_leidas = count_unread_notifications(&state.db_pool, &auth_user.email).await?;
+
+    Ok(Json(NotificationListResponse {
+        notificaciones,
+        no_leidas,
+    }))
+}
+
+/// Handler para consultar el número de notificaciones sin leer
+pub async fn unread_count_handler(
+    State(state): State<AppState>,
+    auth_user: AuthenticatedUser,
+) -> AppResult<Json<UnreadCountResponse>> {
+    let no_leidas = count_unread_notifications(&state.db_pool, &auth_user.email).await?;
+    Ok(Json(UnreadCountResponse { no_leidas }))
+}
+
+/// Handler para marcar una notificación como leída
+pub async fn mark_read_handler(
+    State(state): State<AppState>,
+    auth_user: AuthenticatedUser,
+    Path(id): Path<i32>,
+) -> AppResult<Json<UnreadCountResponse>> {
+    if auth_user.is_demo {
+        return Err(AppError::DemoUserRestriction);
+    }
+
+    if !mark_notification_read(&state.db_pool, &auth_user.email, id).await? {
+        return Err(AppError::NotFound("Notificación no encontrada".to_string()));
+    }
+
+    let no_leidas = count_unread_notifications(&state.db_pool, &auth_user.email).await?;
+    Ok(Json(UnreadCountResponse { no_leidas }))
+}
+
+/// Handler para marcar todas las notificaciones como leídas
+pub async fn mark_all_read_handler(
+    State(state): State<AppState>,
+    auth_user: AuthenticatedUser,
+) -> AppResult<Json<UnreadCountResponse>> {
+    if auth_user.is_demo {
+        return Err(AppError::DemoUserRestriction);
+    }
+
+    mark_all_notifications_read(&state.db_pool, &auth_user.email).await?;
+    Ok(Json(UnreadCountResponse { no_leidas: 0 }))
+}
+
+/// Router para las notificaciones
+pub fn notifications_router(state: AppState) -> Router {
+    Router::new()
+        .route("/", get(list_notifications_handler))
+        .route("/unread-count", get(unread_count_handler))
+        .route("/read-all", post(mark_all_read_handler))
+        .route("/:id/read", post(mark_read_handler))
+        .with_state(state)
+}
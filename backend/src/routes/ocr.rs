@@ -1,19 +1,48 @@
-use axum::{extract::State, routing::post, Json, Router};
-use reqwest::StatusCode as ReqStatusCode;
-use serde::Serialize;
+use axum::{
+    extract::{DefaultBodyLimit, Path, Query, State},
+    handler::Handler,
+    http::StatusCode,
+    response::sse::{Event, KeepAlive, Sse},
+    routing::{get, post, MethodRouter},
+    Json, Router,
+};
+use chrono::NaiveDateTime;
+use futures::{stream, Stream};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
 use validator::Validate;
 
 use super::auth::AppState;
 use crate::{
+    db::get_ocr_job,
     error::{AppError, AppResult},
-    middleware::AuthenticatedUser,
-    schema::TicketProcessPayload,
+    middleware::{authenticate_events_token, AuthenticatedUser},
+    models::{
+        ticket_attachment::{MAX_IMAGE_SIZE, MAX_PDF_SIZE},
+        AttachmentKind, OcrJob, OcrJobStatus, TicketDraftSummary,
+    },
+    schema::{OcrJobPayload, TicketProcessPayload},
     services::{
-        ingest_ticket, IntelligenceClientError, OcrProcessTicketResponse, TicketIngestionResponse,
-        TicketProduct,
+        create_ticket_draft, enqueue_ocr_job, generate_events_token, ingest_ticket,
+        OcrProcessTicketResponse, TicketEventMessage, TicketFile, TicketIngestionResponse,
+        TicketProduct, TicketStage, EVENTS_TOKEN_SECS,
     },
 };
 
+/// Tamaño máximo del cuerpo de `/process` y `/jobs`, en bytes
+///
+/// El archivo llega en base64 (4/3 del tamaño original), así que el límite
+/// es el del archivo más grande admitido codificado, más margen para el resto
+/// de campos del JSON.
+pub const OCR_BODY_LIMIT: usize = {
+    let max_file = if MAX_IMAGE_SIZE > MAX_PDF_SIZE {
+        MAX_IMAGE_SIZE
+    } else {
+        MAX_PDF_SIZE
+    };
+    max_file.div_ceil(3) * 4 + 64 * 1024
+};
+
 /// Respuesta combinada del procesamiento y la ingesta del ticket.
 #[derive(Debug, Clone, Serialize)]
 pub struct TicketProcessAndIngestResponse {
@@ -21,6 +50,8 @@ pub struct TicketProcessAndIngestResponse {
     pub ocr: OcrResponseSummary,
     /// Resultado de la ingesta en base de datos (si se solicito)
     pub ingestion: Option<TicketIngestionResponse>,
+    /// Borrador pendiente de confirmar (si no se solicito la ingesta)
+    pub draft: Option<TicketDraftSummary>,
 }
 
 /// Resumen del OCR devuelto al frontend.
@@ -56,6 +87,7 @@ pub async fn process_ticket(
     let AuthenticatedUser {
         email: authenticated_email,
         is_demo,
+        ..
     } = auth_user;
 
     if is_demo {
@@ -63,10 +95,9 @@ pub async fn process_ticket(
     }
 
     if let Some(ref mime) = payload.mime_type {
-        let allowed = mime.starts_with("image/") || mime == "application/pdf";
-        if !allowed {
+        if AttachmentKind::from_mime(mime).is_none() {
             return Err(AppError::BadRequest(format!(
-                "Formato no soportado: {}. Solo se aceptan PDF o imagen.",
+                "Formato no soportado: {}. Solo se aceptan PDF o imagen JPEG, PNG, WEBP o HEIC.",
                 mime
             )));
         }
@@ -85,33 +116,48 @@ pub async fn process_ticket(
     // Clonar datos necesarios antes de consumir el payload
     let file_content_b64 = payload.file_content_b64.clone();
     let file_name = payload.file_name.clone();
+    let mime_type = payload.mime_type.clone();
     let usuario_email = payload.usuario_email.clone();
+    let replace = payload.replace;
+    let progress =
+        state
+            .ticket_events
+            .progress(&authenticated_email, &payload.ticket_id, &file_name);
 
     // 1. Ejecutar OCR
     let request = payload.into();
-    let ocr_result = state
-        .intelligence_client
-        .process_ticket(request)
-        .await
-        .map_err(map_intelligence_error)?;
+    progress.stage(TicketStage::Ocr);
+    let ocr_result = match state.intelligence_client.process_ticket(request).await {
+        Ok(result) => result,
+        Err(err) => {
+            let err = AppError::from(err);
+            progress.failed(&err, false);
+            return Err(err);
+        }
+    };
     log_ocr_result(&ocr_result);
+    progress.ocr_completed(&ocr_result.warnings);
 
-    // 2. Ingestar ticket si el usuario lo solicito
-    let ingestion_result = if let Some(email) = usuario_email {
+    // 2. Ingestar ticket si el usuario lo solicito; si no, guardar borrador
+    let (ingestion_result, draft) = if let Some(email) = usuario_email {
         tracing::info!("Ingesta de ticket solicitada");
 
+        let file = TicketFile::from_base64(&file_content_b64, &file_name, mime_type.as_deref())?;
+
         match ingest_ticket(
             &state.db_pool,
+            &state.ticket_storage,
             &email,
-            &file_content_b64,
-            &file_name,
+            file,
             ocr_result.clone(),
+            replace,
+            &progress,
         )
         .await
         {
             Ok(ingestion) => {
                 tracing::info!("Ticket ingestado correctamente");
-                Some(ingestion)
+                (Some(ingestion), None)
             }
             Err(err) => {
                 tracing::error!("Fallo al ingerir ticket");
@@ -119,15 +165,39 @@ pub async fn process_ticket(
             }
         }
     } else {
-        tracing::info!("No se solicito ingesta (usuario_email ausente)");
-        None
+        tracing::info!("No se solicito ingesta; se guarda el resultado como borrador");
+
+        let draft = create_ticket_draft(
+            &state.db_pool,
+            &authenticated_email,
+            &file_content_b64,
+            &file_name,
+            mime_type,
+            ocr_result.clone(),
+            state.config.ticket_draft_ttl_hours,
+        )
+        .await
+        .inspect_err(|err| progress.failed(err, false))?;
+        progress.stage(TicketStage::Draft);
+
+        (None, Some(draft))
     };
 
     // 3. Construir respuesta
-    let raw_preview = ocr_result.raw_text.chars().take(320).collect::<String>();
-
     let response = TicketProcessAndIngestResponse {
-        ocr: OcrResponseSummary {
+        ocr: OcrResponseSummary::from(&ocr_result),
+        ingestion: ingestion_result,
+        draft,
+    };
+
+    Ok(Json(response))
+}
+
+impl From<&OcrProcessTicketResponse> for OcrResponseSummary {
+    fn from(ocr_result: &OcrProcessTicketResponse) -> Self {
+        let raw_preview = ocr_result.raw_text.chars().take(320).collect::<String>();
+
+        OcrResponseSummary {
             ticket_id: ocr_result.ticket_id.clone(),
             numero_factura: ocr_result.numero_factura.clone(),
             fecha: ocr_result.fecha.clone(),
@@ -138,11 +208,8 @@ pub async fn process_ticket(
             processing_profile: ocr_result.processing_profile.clone(),
             warnings: ocr_result.warnings.clone(),
             raw_text_preview: Some(raw_preview),
-        },
-        ingestion: ingestion_result,
-    };
-
-    Ok(Json(response))
+        }
+    }
 }
 
 /// Logging estructurado del resultado del OCR para facilitar depuracion.
@@ -158,36 +225,249 @@ fn log_ocr_result(result: &OcrProcessTicketResponse) {
     );
 }
 
-fn map_intelligence_error(err: IntelligenceClientError) -> AppError {
-    match err {
-        IntelligenceClientError::Timeout | IntelligenceClientError::ServiceUnavailable => {
-            AppError::ServiceUnavailable("Servicio de inteligencia no disponible".to_string())
-        }
-        IntelligenceClientError::UnexpectedStatus { status, .. } => {
-            if status == ReqStatusCode::BAD_REQUEST
-                || status == ReqStatusCode::UNPROCESSABLE_ENTITY
-                || status == ReqStatusCode::UNSUPPORTED_MEDIA_TYPE
-            {
-                AppError::BadRequest("No se pudo procesar el ticket".to_string())
-            } else {
-                AppError::InternalError(format!(
-                    "Fallo en el servicio de inteligencia ({})",
-                    status
-                ))
-            }
-        }
-        IntelligenceClientError::Deserialize(_) => {
-            AppError::InternalError("Respuesta OCR invalida".to_string())
+/// Error de un trabajo de OCR, con el mismo código que la API
+#[derive(Debug, Clone, Serialize)]
+pub struct OcrJobError {
+    pub code: String,
+    pub message: String,
+}
+
+/// Estado de un trabajo de OCR en segundo plano
+#[derive(Debug, Clone, Serialize)]
+pub struct OcrJobResponse {
+    pub id: Uuid,
+    pub status: OcrJobStatus,
+    pub file_name: String,
+    pub mime_type: Option<String>,
+    pub size_bytes: i32,
+    pub attempts: i32,
+    /// Próximo intento programado mientras el trabajo está en cola
+    pub next_attempt_at: Option<NaiveDateTime>,
+    /// Último error; en cola indica el fallo transitorio que se reintentará
+    pub error: Option<OcrJobError>,
+    pub ingestion: Option<TicketIngestionResponse>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+impl From<OcrJob> for OcrJobResponse {
+    fn from(job: OcrJob) -> Self {
+        let status = job.status();
+
+        let error = job.error_codigo.map(|code| OcrJobError {
+            code,
+            message: job.error_mensaje.unwrap_or_default(),
+        });
+
+        OcrJobResponse {
+            id: job.id,
+            status,
+            file_name: job.nombre_archivo,
+            mime_type: job.mime_type,
+            size_bytes: job.tamano_bytes,
+            attempts: job.intentos,
+            next_attempt_at: (status == OcrJobStatus::Queued).then_some(job.siguiente_intento),
+            error,
+            ingestion: job.resultado.map(|r| r.0),
+            created_at: job.created_at,
+            updated_at: job.updated_at,
         }
-        IntelligenceClientError::Request(_) => AppError::InternalError(
-            "No se pudo contactar con el servicio de inteligencia".to_string(),
-        ),
     }
 }
 
+/// Encola un ticket para procesarlo en segundo plano y devuelve el trabajo.
+pub async fn create_ocr_job(
+    State(state): State<AppState>,
+    auth_user: AuthenticatedUser,
+    Json(payload): Json<OcrJobPayload>,
+) -> AppResult<(StatusCode, Json<OcrJobResponse>)> {
+    if auth_user.is_demo {
+        return Err(AppError::DemoUserRestriction);
+    }
+
+    payload
+        .validate()
+        .map_err(|err| AppError::BadRequest(format!("Validacion fallida: {}", err)))?;
+
+    let file = TicketFile::from_base64(
+        &payload.file_content_b64,
+        &payload.file_name,
+        payload.mime_type.as_deref(),
+    )?;
+
+    let job = enqueue_ocr_job(&state.db_pool, &auth_user.email, file, payload.replace).await?;
+
+    Ok((StatusCode::ACCEPTED, Json(job.into())))
+}
+
+/// Consulta el estado de un trabajo de OCR del usuario.
+pub async fn get_ocr_job_status(
+    State(state): State<AppState>,
+    auth_user: AuthenticatedUser,
+    Path(id): Path<Uuid>,
+) -> AppResult<Json<OcrJobResponse>> {
+    let job = get_ocr_job(&state.db_pool, &auth_user.email, id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Trabajo no encontrado".to_string()))?;
+
+    Ok(Json(job.into()))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct EventsQueryParams {
+    /// Token from `POST /ocr/events/token`, for clients that cannot send the
+    /// Authorization header. Session JWTs are not accepted here.
+    #[serde(default)]
+    pub token: Option<String>,
+}
+
+/// Token de corta duración para abrir el canal de eventos
+#[derive(Debug, Serialize)]
+pub struct EventsTokenResponse {
+    pub token: String,
+    /// Segundos durante los que se puede usar para conectarse
+    pub expira_en: i64,
+}
+
+/// Emite el token con el que el navegador abre `/ocr/events`
+///
+/// El token de sesión no debe viajar en la URL (acaba en logs de proxies y
+/// en el historial), así que el cliente pide antes este token de corta duración.
+pub async fn create_events_token(
+    State(state): State<AppState>,
+    auth_user: AuthenticatedUser,
+) -> AppResult<Json<EventsTokenResponse>> {
+    let token = generate_events_token(&auth_user.email, &state.config.jwt_secret)?;
+
+    Ok(Json(EventsTokenResponse {
+        token,
+        expira_en: EVENTS_TOKEN_SECS,
+    }))
+}
+
+/// Flujo SSE con el progreso de los tickets del usuario autenticado.
+pub async fn ticket_events(
+    State(state): State<AppState>,
+    auth_user: Option<AuthenticatedUser>,
+    Query(params): Query<EventsQueryParams>,
+) -> AppResult<Sse<impl Stream<Item = Result<Event, axum::Error>>>> {
+    let user = match (auth_user, params.token) {
+        (Some(user), _) => user,
+        (None, Some(token)) => authenticate_events_token(&token, &state)?,
+        (None, None) => {
+            return Err(AppError::Unauthorized(
+                "Falta cabecera Authorization".to_string(),
+            ))
+        }
+    };
+
+    tracing::debug!("Cliente suscrito a los eventos de tickets");
+
+    let subscription = state.ticket_events.subscribe(&user.email);
+    let stream = stream::unfold(subscription, |mut subscription| async move {
+        let sse_event = match subscription.recv().await? {
+            TicketEventMessage::Progress(event) => {
+                Event::default().event("ticket").json_data(&event)
+            }
+            // Se han perdido eventos: el cliente vuelve a consultar el estado
+            TicketEventMessage::Resync { skipped } => Event::default()
+                .event("resync")
+                .json_data(serde_json::json!({ "eventos_perdidos": skipped })),
+        };
+        Some((sse_event, subscription))
+    });
+
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+/// Ruta `POST` que recibe un ticket en base64, con el límite `OCR_BODY_LIMIT`
+fn upload_route<H, T, S>(handler: H) -> MethodRouter<S>
+where
+    H: Handler<T, S>,
+    T: 'static,
+    S: Clone + Send + Sync + 'static,
+{
+    post(handler).layer(DefaultBodyLimit::max(OCR_BODY_LIMIT))
+}
+
 /// Router para los endpoints relacionados con OCR.
 pub fn ocr_router(state: AppState) -> Router {
     Router::new()
-        .route("/process", post(process_ticket))
+        .route("/process", upload_route(process_ticket))
+        .route("/jobs", upload_route(create_ocr_job))
+        .route("/jobs/:id", get(get_ocr_job_status))
+        .route("/events", get(ticket_events))
+        .route("/events/token", post(create_events_token))
         .with_state(state)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, http::Request};
+    use tower::Service;
+
+    /// Cuerpo JSON de `/process` con un archivo de `size` bytes en base64
+    fn process_body(size: usize) -> String {
+        serde_json::json!({
+            "ticket_id": "ticket",
+            "file_name": "ticket.jpg",
+            "file_content_b64": "A".repeat(size.div_ceil(3) * 4),
+            "mime_type": "image/jpeg",
+        })
+        .to_string()
+    }
+
+    async fn post_status(mut router: Router, body: String) -> StatusCode {
+        let request = Request::post("/process")
+            .header("content-type", "application/json")
+            .body(Body::from(body))
+            .unwrap();
+        router.call(request).await.unwrap().status()
+    }
+
+    #[tokio::test]
+    async fn test_ocr_body_limit() {
+        let router = || {
+            Router::new().route(
+                "/process",
+                upload_route(|Json(_): Json<TicketProcessPayload>| async { StatusCode::OK }),
+            )
+        };
+
+        // Una foto de 3 MB supera el límite por defecto de axum (2 MB)
+        assert_eq!(
+            post_status(router(), process_body(3 * 1024 * 1024)).await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            post_status(router(), process_body(MAX_IMAGE_SIZE)).await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            post_status(router(), process_body(MAX_IMAGE_SIZE + 1024 * 1024)).await,
+            StatusCode::PAYLOAD_TOO_LARGE
+        );
+    }
+
+    #[sqlx::test(migrations = "./migrations")]
+    async fn test_events_accept_only_events_token(pool: sqlx::PgPool) {
+        let state = AppState::for_tests(pool);
+        let secret = state.config.jwt_secret.clone();
+        let status = |token: String| {
+            let mut router = ocr_router(state.clone());
+            async move {
+                let request = Request::get(format!("/events?token={}", token))
+                    .body(Body::empty())
+                    .unwrap();
+                router.call(request).await.unwrap().status()
+            }
+        };
+
+        let session = crate::services::generate_jwt("test@example.com", &secret).unwrap();
+        assert_eq!(status(session).await, StatusCode::UNAUTHORIZED);
+
+        let events = generate_events_token("test@example.com", &secret).unwrap();
+        assert_eq!(status(events).await, StatusCode::OK);
+    }
+}
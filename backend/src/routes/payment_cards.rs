@@ -0,0 +1,90 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    routing::{delete, get},
+    Json, Router,
+};
+
+use super::auth::AppState;
+use crate::{
+    db::{delete_payment_card, insert_payment_card, list_payment_cards},
+    error::{AppError, AppResult},
+    middleware::AuthenticatedUser,
+    models::PaymentCard,
+    schema::PaymentCardPayload,
+};
+use validator::Validate;
+
+/// Handler para listar los alias de tarjeta del usuario
+pub async fn list_payment_cards_handler(
+    State(state): State<AppState>,
+    auth_user: AuthenticatedUser,
+) -> AppResult<Json<Vec<PaymentCard>>> {
+    let cards = list_payment_cards(&state.db_pool, &auth_user.email).await?;
+    Ok(Json(cards))
+}
+
+/// Handler para asociar un alias a un patrón de número de operación
+///
+/// El patrón se compara con el `numero_operacion` de los tickets pagados
+/// con tarjeta; `*` equivale a cualquier secuencia ("4539*", "*1234").
+pub async fn create_payment_card(
+    State(state): State<AppState>,
+    auth_user: AuthenticatedUser,
+    Json(payload): Json<PaymentCardPayload>,
+) -> AppResult<(StatusCode, Json<PaymentCard>)> {
+    if auth_user.is_demo {
+        return Err(AppError::DemoUserRestriction);
+    }
+
+    payload
+        .validate()
+        .map_err(|e| AppError::BadRequest(format!("Datos inválidos: {}", e)))?;
+
+    let alias = payload.alias.trim();
+    if alias.is_empty() {
+        return Err(AppError::BadRequest(
+            "El alias no puede estar vacío".to_string(),
+        ));
+    }
+    let patron = PaymentCard::normalize_pattern(&payload.patron).ok_or_else(|| {
+        AppError::BadRequest(
+            "El patrón solo admite letras, dígitos y '*' y necesita algún carácter fijo"
+                .to_string(),
+        )
+    })?;
+
+    let card = insert_payment_card(&state.db_pool, &auth_user.email, alias, &patron)
+        .await?
+        .ok_or_else(|| AppError::BadRequest("Ya existe un alias para ese patrón".to_string()))?;
+
+    Ok((StatusCode::CREATED, Json(card)))
+}
+
+/// Handler para eliminar un alias de tarjeta
+pub async fn delete_payment_card_handler(
+    State(state): State<AppState>,
+    auth_user: AuthenticatedUser,
+    Path(id): Path<i32>,
+) -> AppResult<StatusCode> {
+    if auth_user.is_demo {
+        return Err(AppError::DemoUserRestriction);
+    }
+
+    if !delete_payment_card(&state.db_pool, &auth_user.email, id).await? {
+        return Err(AppError::NotFound("Tarjeta no encontrada".to_string()));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Router para los alias de tarjeta
+pub fn payment_cards_router(state: AppState) -> Router {
+    Router::new()
+        .route(
+            "/",
+            get(list_payment_cards_handler).post(create_payment_card),
+        )
+        .route("/:id", delete(delete_payment_card_handler))
+        .with_state(state)
+}
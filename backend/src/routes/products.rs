@@ -0,0 +1,315 @@
+use axum::{
+    extract::{Path, Query, State},
+    routing::{get, patch, post},
+    Json, Router,
+};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+
+use super::auth::AppState;
+use crate::{
+    db::{
+        delete_user_product_category, get_price_history, get_product, get_product_aliases,
+        get_product_category, get_unit_price_ranking, get_user_product_purchases, list_categories,
+        search_products, set_user_product_category, PriceHistoryPoint, ProductSearchResult,
+        UnitPriceItem, UserPricePoint,
+    },
+    error::{AppError, AppResult},
+    middleware::AuthenticatedUser,
+    models::{Category, Product, ProductCategory, ProductUpsert},
+    schema::{ProductCategoryPayload, ProductMergePayload},
+    services::{
+        package_sizes, product_merge, summarize_price_history, PriceSummary, ProductEquivalents,
+        ProductMergeResponse, ShrinkflationAlert,
+    },
+};
+use sqlx::PgPool;
+use validator::Validate;
+
+/// Busca el producto de la URL, resolviendo los nombres fusionados
+///
+/// Tras una fusión el nombre antiguo solo existe como alias, pero sigue en
+/// enlaces y favoritos del usuario.
+async fn find_product(pool: &PgPool, nombre: &str) -> AppResult<Product> {
+    let nombre = ProductUpsert::normalize_name(nombre);
+    let nombre = get_product_aliases(pool, std::slice::from_ref(&nombre))
+        .await?
+        .remove(&nombre)
+        .unwrap_or(nombre);
+
+    get_product(pool, &nombre)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Producto no encontrado".to_string()))
+}
+
+/// Evolución del precio de un producto
+#[derive(Debug, Serialize)]
+pub struct ProductPriceHistoryResponse {
+    pub producto: String,
+    pub unidad: Option<String>,
+    /// Serie de precios registrados para el producto
+    pub historico: Vec<PriceHistoryPoint>,
+    /// Precios pagados por el usuario en sus compras
+    pub compras_usuario: Vec<UserPricePoint>,
+    pub resumen: PriceSummary,
+}
+
+/// Handler para obtener el histórico de precios de un producto
+pub async fn get_product_prices(
+    State(state): State<AppState>,
+    auth_user: AuthenticatedUser,
+    Path(nombre): Path<String>,
+) -> AppResult<Json<ProductPriceHistoryResponse>> {
+    let product = find_product(&state.db_pool, &nombre).await?;
+
+    let historico = get_price_history(&state.db_pool, &product.nombre).await?;
+    let compras_usuario =
+        get_user_product_purchases(&state.db_pool, &auth_user.email, &product.nombre).await?;
+
+    let resumen = summarize_price_history(&historico, Utc::now().date_naive());
+
+    Ok(Json(ProductPriceHistoryResponse {
+        producto: product.nombre,
+        unidad: product.unidad,
+        historico,
+        compras_usuario,
+        resumen,
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ProductSearchQuery {
+    /// Texto a buscar en el nombre del producto
+    pub q: String,
+    /// Buscar en todo el catálogo y no solo en los productos comprados
+    #[serde(default)]
+    pub all: bool,
+    #[serde(default = "default_search_limit")]
+    pub limit: i64,
+}
+
+fn default_search_limit() -> i64 {
+    20
+}
+
+/// Handler para la búsqueda aproximada de productos por nombre
+pub async fn search_products_handler(
+    State(state): State<AppState>,
+    auth_user: AuthenticatedUser,
+    Query(params): Query<ProductSearchQuery>,
+) -> AppResult<Json<Vec<ProductSearchResult>>> {
+    let query = params.q.trim();
+    if query.chars().count() < 2 {
+        return Err(AppError::BadRequest(
+            "La búsqueda debe tener al menos 2 caracteres".to_string(),
+        ));
+    }
+
+    let results = search_products(
+        &state.db_pool,
+        &auth_user.email,
+        query,
+        params.all,
+        params.limit.clamp(1, 100),
+    )
+    .await?;
+
+    Ok(Json(results))
+}
+
+/// Handler para fusionar un producto en otro
+///
+/// Reescribe las compras y el histórico de `from` sobre `into` y deja `from`
+/// como alias para que la ingesta lo resuelva en los próximos tickets.
+/// Afecta a todos los usuarios, así que solo está disponible para las cuentas
+/// de `OPERATOR_EMAILS`.
+pub async fn merge_products_handler(
+    State(state): State<AppState>,
+    auth_user: AuthenticatedUser,
+    Json(payload): Json<ProductMergePayload>,
+) -> AppResult<Json<ProductMergeResponse>> {
+    if auth_user.is_demo {
+        return Err(AppError::DemoUserRestriction);
+    }
+
+    // El catálogo es común a todos los usuarios: solo los operadores lo modifican
+    if !auth_user.is_operator {
+        return Err(AppError::Forbidden(
+            "Solo un operador puede fusionar productos".to_string(),
+        ));
+    }
+
+    payload
+        .validate()
+        .map_err(|e| AppError::BadRequest(format!("Datos inválidos: {}", e)))?;
+
+    let response =
+        product_merge::merge_products(&state.db_pool, &payload.from, &payload.into).await?;
+
+    Ok(Json(response))
+}
+
+/// Handler para listar las categorías de productos
+pub async fn list_categories_handler(
+    State(state): State<AppState>,
+    _auth_user: AuthenticatedUser,
+) -> AppResult<Json<Vec<Category>>> {
+    let categories = list_categories(&state.db_pool).await?;
+    Ok(Json(categories))
+}
+
+/// Handler para elegir la categoría de un producto
+///
+/// La elección solo afecta a las estadísticas del usuario; con `categoria`
+/// a `null` se vuelve a la categoría automática.
+pub async fn update_product_category(
+    State(state): State<AppState>,
+    auth_user: AuthenticatedUser,
+    Path(nombre): Path<String>,
+    Json(payload): Json<ProductCategoryPayload>,
+) -> AppResult<Json<ProductCategory>> {
+    if auth_user.is_demo {
+        return Err(AppError::DemoUserRestriction);
+    }
+
+    payload
+        .validate()
+        .map_err(|e| AppError::BadRequest(format!("Datos inválidos: {}", e)))?;
+
+    let product = find_product(&state.db_pool, &nombre).await?;
+
+    match payload.categoria.as_deref().map(str::trim) {
+        Some(categoria) => {
+            let updated = set_user_product_category(
+                &state.db_pool,
+                &auth_user.email,
+                &product.nombre,
+                categoria,
+            )
+            .await?;
+            if !updated {
+                return Err(AppError::NotFound("Categoría no encontrada".to_string()));
+            }
+        }
+        None => {
+            delete_user_product_category(&state.db_pool, &auth_user.email, &product.nombre).await?;
+        }
+    }
+
+    let category = get_product_category(&state.db_pool, &auth_user.email, &product.nombre).await?;
+
+    Ok(Json(category))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UnitPriceQuery {
+    /// `kg` o `l`; sin filtro devuelve ambos
+    pub unidad: Option<String>,
+    #[serde(default = "default_search_limit")]
+    pub limit: i64,
+}
+
+/// Handler para el ranking de productos por precio por kg o litro
+pub async fn get_unit_price_ranking_handler(
+    State(state): State<AppState>,
+    auth_user: AuthenticatedUser,
+    Query(params): Query<UnitPriceQuery>,
+) -> AppResult<Json<Vec<UnitPriceItem>>> {
+    let unidad = params.unidad.as_deref().map(str::to_lowercase);
+    if let Some(unidad) = unidad.as_deref() {
+        if unidad != "kg" && unidad != "l" {
+            return Err(AppError::BadRequest(
+                "La unidad debe ser 'kg' o 'l'".to_string(),
+            ));
+        }
+    }
+
+    let ranking = get_unit_price_ranking(
+        &state.db_pool,
+        &auth_user.email,
+        unidad.as_deref(),
+        params.limit.clamp(1, 100),
+    )
+    .await?;
+
+    Ok(Json(ranking))
+}
+
+/// Handler para detectar reducciones de envase sin bajada de precio
+pub async fn get_shrinkflation_handler(
+    State(state): State<AppState>,
+    auth_user: AuthenticatedUser,
+) -> AppResult<Json<Vec<ShrinkflationAlert>>> {
+    let alerts = package_sizes::get_shrinkflation_alerts(&state.db_pool, &auth_user.email).await?;
+    Ok(Json(alerts))
+}
+
+/// Handler para comparar un producto con sus formatos equivalentes
+pub async fn get_product_equivalents_handler(
+    State(state): State<AppState>,
+    auth_user: AuthenticatedUser,
+    Path(nombre): Path<String>,
+) -> AppResult<Json<ProductEquivalents>> {
+    let product = find_product(&state.db_pool, &nombre).await?;
+
+    let equivalents =
+        package_sizes::get_product_equivalents(&state.db_pool, &auth_user.email, &product.nombre)
+            .await?;
+
+    Ok(Json(equivalents))
+}
+
+/// Router para los endpoints de productos
+pub fn products_router(state: AppState) -> Router {
+    Router::new()
+        .route("/search", get(search_products_handler))
+        .route("/merge", post(merge_products_handler))
+        .route("/categories", get(list_categories_handler))
+        .route("/unit-prices", get(get_unit_price_ranking_handler))
+        .route("/shrinkflation", get(get_shrinkflation_handler))
+        .route("/:nombre/equivalents", get(get_product_equivalents_handler))
+        .route("/:nombre/category", patch(update_product_category))
+        .route("/:nombre/prices", get(get_product_prices))
+        .with_state(state)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::upsert_product;
+
+    #[sqlx::test(migrations = "./migrations")]
+    async fn test_find_product_resolves_merged_names(pool: PgPool) -> sqlx::Result<()> {
+        for nombre in ["YOGUR NAT", "YOGUR NATURAL"] {
+            upsert_product(
+                &pool,
+                &ProductUpsert {
+                    nombre: nombre.to_string(),
+                    marca: None,
+                    unidad: "unidad".to_string(),
+                    precio_actual: None,
+                },
+            )
+            .await?;
+        }
+
+        // Antes de la fusión cada nombre es su propio producto
+        let product = find_product(&pool, " yogur nat ").await.expect("producto");
+        assert_eq!(product.nombre, "YOGUR NAT");
+
+        product_merge::merge_products(&pool, "YOGUR NAT", "YOGUR NATURAL")
+            .await
+            .expect("fusión");
+
+        // El nombre fusionado lleva al producto que se conserva
+        let product = find_product(&pool, "yogur nat").await.expect("producto");
+        assert_eq!(product.nombre, "YOGUR NATURAL");
+
+        assert!(matches!(
+            find_product(&pool, "KEFIR").await,
+            Err(AppError::NotFound(_))
+        ));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,111 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    routing::{get, patch},
+    Json, Router,
+};
+use rust_decimal::Decimal;
+
+use super::auth::AppState;
+use crate::{
+    db::{delete_savings_goal, insert_savings_goal, list_savings_goals, update_savings_goal},
+    error::{AppError, AppResult},
+    middleware::AuthenticatedUser,
+    models::SavingsGoal,
+    schema::{SavingsGoalCreatePayload, SavingsGoalUpdatePayload},
+};
+
+/// Importe máximo que admite `objetivos_ahorro.objetivo_mensual` (NUMERIC(10, 2))
+const MAX_GOAL_AMOUNT: Decimal = Decimal::from_parts(99_999_999, 0, 0, false, 0);
+
+/// Handler para listar los objetivos de ahorro del usuario
+pub async fn list_savings_goals_handler(
+    State(state): State<AppState>,
+    auth_user: AuthenticatedUser,
+) -> AppResult<Json<Vec<SavingsGoal>>> {
+    let goals = list_savings_goals(&state.db_pool, &auth_user.email).await?;
+    Ok(Json(goals))
+}
+
+/// Handler para crear el objetivo de gasto de un mes
+pub async fn create_savings_goal(
+    State(state): State<AppState>,
+    auth_user: AuthenticatedUser,
+    Json(payload): Json<SavingsGoalCreatePayload>,
+) -> AppResult<(StatusCode, Json<SavingsGoal>)> {
+    if auth_user.is_demo {
+        return Err(AppError::DemoUserRestriction);
+    }
+
+    let objetivo = validate_goal_amount(payload.objetivo_mensual)?;
+    let mes = SavingsGoal::month_start(payload.mes);
+
+    let goal = insert_savings_goal(&state.db_pool, &auth_user.email, mes, objetivo)
+        .await?
+        .ok_or_else(|| AppError::BadRequest("Ya existe un objetivo para ese mes".to_string()))?;
+
+    Ok((StatusCode::CREATED, Json(goal)))
+}
+
+/// Handler para cambiar el importe de un objetivo
+///
+/// Si el mes ya estaba cerrado, el resultado se recalcula en el siguiente cierre.
+pub async fn update_savings_goal_handler(
+    State(state): State<AppState>,
+    auth_user: AuthenticatedUser,
+    Path(id): Path<i32>,
+    Json(payload): Json<SavingsGoalUpdatePayload>,
+) -> AppResult<Json<SavingsGoal>> {
+    if auth_user.is_demo {
+        return Err(AppError::DemoUserRestriction);
+    }
+
+    let objetivo = validate_goal_amount(payload.objetivo_mensual)?;
+
+    let goal = update_savings_goal(&state.db_pool, &auth_user.email, id, objetivo)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Objetivo no encontrado".to_string()))?;
+
+    Ok(Json(goal))
+}
+
+/// Handler para eliminar un objetivo
+pub async fn delete_savings_goal_handler(
+    State(state): State<AppState>,
+    auth_user: AuthenticatedUser,
+    Path(id): Path<i32>,
+) -> AppResult<StatusCode> {
+    if auth_user.is_demo {
+        return Err(AppError::DemoUserRestriction);
+    }
+
+    if !delete_savings_goal(&state.db_pool, &auth_user.email, id).await? {
+        return Err(AppError::NotFound("Objetivo no encontrado".to_string()));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+fn validate_goal_amount(objetivo: Decimal) -> AppResult<Decimal> {
+    let objetivo = objetivo.round_dp(2);
+    if objetivo <= Decimal::ZERO || objetivo > MAX_GOAL_AMOUNT {
+        return Err(AppError::BadRequest(
+            "El objetivo mensual debe ser mayor que 0".to_string(),
+        ));
+    }
+    Ok(objetivo)
+}
+
+/// Router para los objetivos de ahorro
+pub fn savings_goals_router(state: AppState) -> Router {
+    Router::new()
+        .route(
+            "/",
+            get(list_savings_goals_handler).post(create_savings_goal),
+        )
+        .route(
+            "/:id",
+            patch(update_savings_goal_handler).delete(delete_savings_goal_handler),
+        )
+        .with_state(state)
+}
@@ -3,19 +3,30 @@ use axum::{
     routing::get,
     Json, Router,
 };
+use chrono::{NaiveDate, Utc};
 use rust_decimal::Decimal;
 use serde::Deserialize;
 
 use super::auth::AppState;
 use crate::{
     db::{
-        get_current_year_total, get_hourly_distribution, get_month_comparison,
-        get_monthly_spending, get_spending_trend, get_top_products_by_quantity,
-        get_top_products_by_spending, get_user_stats, get_weekly_distribution,
+        get_category_monthly_spending, get_current_year_total, get_hourly_distribution,
+        get_month_comparison, get_monthly_iva, get_monthly_spending,
+        get_payment_method_monthly_spending, get_spending_by_card, get_spending_by_category,
+        get_spending_by_payment_method, get_spending_by_store, get_spending_trend,
+        get_store_price_differences, get_top_products_by_quantity, get_top_products_by_spending,
+        get_user_stats, get_weekly_distribution, PurchaseFilter,
     },
-    error::AppResult,
+    error::{AppError, AppResult},
     middleware::AuthenticatedUser,
-    schema::{DashboardStatsResponse, MonthlyEvolutionResponse},
+    schema::{
+        CategoryStatsResponse, DashboardStatsResponse, IvaStatsResponse, MonthlyEvolutionResponse,
+        PaymentMethodStatsResponse, StoreStatsResponse,
+    },
+    services::{
+        compare_periods, get_current_goal_progress, get_personal_inflation, summarize_iva_by_year,
+        PeriodComparison, PersonalInflationIndex,
+    },
 };
 
 #[derive(Debug, Deserialize)]
@@ -27,6 +38,15 @@ pub struct DashboardQueryParams {
     /// Limit for top products (default: 5)
     #[serde(default = "default_limit")]
     pub limit: i64,
+
+    /// Only count purchases from this store (`compras.tienda`)
+    pub tienda: Option<String>,
+
+    /// First day included (YYYY-MM-DD); with `to`, replaces the relative window
+    pub from: Option<NaiveDate>,
+
+    /// Last day included (YYYY-MM-DD)
+    pub to: Option<NaiveDate>,
 }
 
 fn default_days() -> i64 {
@@ -42,6 +62,15 @@ pub struct MonthlyEvolutionQueryParams {
     /// Months to retrieve (default 12, max 24)
     #[serde(default = "default_months")]
     pub months: i64,
+
+    /// Only count purchases from this store (`compras.tienda`)
+    pub tienda: Option<String>,
+
+    /// First day included (YYYY-MM-DD); with `to`, replaces the relative window
+    pub from: Option<NaiveDate>,
+
+    /// Last day included (YYYY-MM-DD)
+    pub to: Option<NaiveDate>,
 }
 
 fn default_months() -> i64 {
@@ -61,15 +90,29 @@ pub async fn get_dashboard_stats(
         user_email
     );
 
-    let month_comparison = get_month_comparison(&state.db_pool, &user_email).await?;
-    let user_stats = get_user_stats(&state.db_pool, &user_email).await?;
-    let daily_trend = get_spending_trend(&state.db_pool, &user_email, params.days).await?;
+    let filter = purchase_filter(params.tienda, params.from, params.to)?;
+
+    let month_comparison = get_month_comparison(&state.db_pool, &user_email, &filter).await?;
+    let user_stats = get_user_stats(&state.db_pool, &user_email, &filter).await?;
+    let daily_trend = get_spending_trend(&state.db_pool, &user_email, params.days, &filter).await?;
     let top_by_qty =
-        get_top_products_by_quantity(&state.db_pool, &user_email, params.limit).await?;
+        get_top_products_by_quantity(&state.db_pool, &user_email, params.limit, &filter).await?;
     let top_by_spending =
-        get_top_products_by_spending(&state.db_pool, &user_email, params.limit).await?;
-    let weekly_dist = get_weekly_distribution(&state.db_pool, &user_email).await?;
-    let hourly_dist = get_hourly_distribution(&state.db_pool, &user_email).await?;
+        get_top_products_by_spending(&state.db_pool, &user_email, params.limit, &filter).await?;
+    let weekly_dist = get_weekly_distribution(&state.db_pool, &user_email, &filter).await?;
+    let hourly_dist = get_hourly_distribution(&state.db_pool, &user_email, &filter).await?;
+    let payment_methods =
+        get_spending_by_payment_method(&state.db_pool, &user_email, None, &filter).await?;
+    let savings_goal =
+        get_current_goal_progress(&state.db_pool, &user_email, Utc::now().date_naive()).await?;
+    let personal_inflation = get_personal_inflation(
+        &state.db_pool,
+        &user_email,
+        filter.to.unwrap_or_else(|| Utc::now().date_naive()),
+        filter.from,
+        state.config.reference_inflation_rate,
+    )
+    .await?;
 
     let response = DashboardStatsResponse {
         current_month_spend: month_comparison.current_month_spend,
@@ -83,6 +126,9 @@ pub async fn get_dashboard_stats(
         top_products_spending: top_by_spending,
         weekly_distribution: weekly_dist,
         hourly_distribution: hourly_dist,
+        payment_methods,
+        personal_inflation,
+        savings_goal,
     };
 
     tracing::info!("Dashboard de estadisticas obtenido exitosamente");
@@ -98,8 +144,9 @@ pub async fn get_monthly_evolution(
 ) -> AppResult<Json<MonthlyEvolutionResponse>> {
     let user_email = auth_user.email;
     let months = params.months.clamp(3, 1000) as i32;
+    let filter = purchase_filter(params.tienda, params.from, params.to)?;
 
-    let months_data = get_monthly_spending(&state.db_pool, &user_email, months).await?;
+    let months_data = get_monthly_spending(&state.db_pool, &user_email, months, &filter).await?;
 
     let current_total = months_data.last().map(|m| m.total).unwrap_or(Decimal::ZERO);
     let previous_total = months_data
@@ -129,7 +176,7 @@ pub async fn get_monthly_evolution(
 
     let _current_year = chrono::Utc::now().format("%Y").to_string();
     // Obtener el total real del año desde la BD (no depende de months_data)
-    let year_to_date_total = get_current_year_total(&state.db_pool, &user_email).await?;
+    let year_to_date_total = get_current_year_total(&state.db_pool, &user_email, &filter).await?;
 
     let response = MonthlyEvolutionResponse {
         months: months_data,
@@ -148,6 +195,9 @@ pub struct ProductsQueryParams {
     #[serde(default = "default_limit_products")]
     pub limit: i64,
     pub sort_by: String, // "quantity" or "spending"
+    pub tienda: Option<String>,
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
 }
 
 fn default_limit_products() -> i64 {
@@ -162,20 +212,253 @@ pub async fn get_all_products_stats(
 ) -> AppResult<Json<Vec<crate::db::TopProductItem>>> {
     let user_email = auth_user.email;
     let limit = params.limit.clamp(1, 1000);
+    let filter = purchase_filter(params.tienda, params.from, params.to)?;
 
     let products = match params.sort_by.as_str() {
-        "spending" => get_top_products_by_spending(&state.db_pool, &user_email, limit).await?,
-        _ => get_top_products_by_quantity(&state.db_pool, &user_email, limit).await?,
+        "spending" => {
+            get_top_products_by_spending(&state.db_pool, &user_email, limit, &filter).await?
+        }
+        _ => get_top_products_by_quantity(&state.db_pool, &user_email, limit, &filter).await?,
     };
 
     Ok(Json(products))
 }
 
+/// Handler: spend by category and its monthly evolution
+pub async fn get_category_stats(
+    State(state): State<AppState>,
+    auth_user: AuthenticatedUser,
+    Query(params): Query<MonthlyEvolutionQueryParams>,
+) -> AppResult<Json<CategoryStatsResponse>> {
+    let months = params.months.clamp(1, 120) as i32;
+    let filter = purchase_filter(params.tienda, params.from, params.to)?;
+
+    let categorias =
+        get_spending_by_category(&state.db_pool, &auth_user.email, months, &filter).await?;
+    let evolucion =
+        get_category_monthly_spending(&state.db_pool, &auth_user.email, months, &filter).await?;
+
+    Ok(Json(CategoryStatsResponse {
+        categorias,
+        evolucion,
+    }))
+}
+
+/// Handler: spend by payment method, its monthly evolution and spend per card
+pub async fn get_payment_method_stats(
+    State(state): State<AppState>,
+    auth_user: AuthenticatedUser,
+    Query(params): Query<MonthlyEvolutionQueryParams>,
+) -> AppResult<Json<PaymentMethodStatsResponse>> {
+    let months = params.months.clamp(1, 120) as i32;
+    let filter = purchase_filter(params.tienda, params.from, params.to)?;
+
+    let metodos =
+        get_spending_by_payment_method(&state.db_pool, &auth_user.email, Some(months), &filter)
+            .await?;
+    let evolucion =
+        get_payment_method_monthly_spending(&state.db_pool, &auth_user.email, months, &filter)
+            .await?;
+    let tarjetas = get_spending_by_card(&state.db_pool, &auth_user.email, months, &filter).await?;
+
+    Ok(Json(PaymentMethodStatsResponse {
+        metodos,
+        evolucion,
+        tarjetas,
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct IvaQueryParams {
+    /// Calendar year to report; cannot be combined with `from`/`to`
+    pub year: Option<i32>,
+
+    /// Only count purchases from this store (`compras.tienda`)
+    pub tienda: Option<String>,
+
+    /// First day included (YYYY-MM-DD)
+    pub from: Option<NaiveDate>,
+
+    /// Last day included (YYYY-MM-DD)
+    pub to: Option<NaiveDate>,
+}
+
+/// Handler: taxable base and VAT paid per rate, by month and year
+pub async fn get_iva_stats(
+    State(state): State<AppState>,
+    auth_user: AuthenticatedUser,
+    Query(params): Query<IvaQueryParams>,
+) -> AppResult<Json<IvaStatsResponse>> {
+    let (from, to) = match params.year {
+        Some(_) if params.from.is_some() || params.to.is_some() => {
+            return Err(AppError::BadRequest(
+                "'year' no se puede combinar con 'from' y 'to'".to_string(),
+            ));
+        }
+        Some(year) => {
+            let from = NaiveDate::from_ymd_opt(year, 1, 1)
+                .ok_or_else(|| AppError::BadRequest("Año inválido".to_string()))?;
+            let to = NaiveDate::from_ymd_opt(year, 12, 31)
+                .ok_or_else(|| AppError::BadRequest("Año inválido".to_string()))?;
+            (Some(from), Some(to))
+        }
+        None => (params.from, params.to),
+    };
+    let filter = purchase_filter(params.tienda, from, to)?;
+
+    let mensual = get_monthly_iva(&state.db_pool, &auth_user.email, &filter).await?;
+    let anual = summarize_iva_by_year(&mensual);
+
+    Ok(Json(IvaStatsResponse { mensual, anual }))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct InflationQueryParams {
+    /// First day of the basket (default: 12 months before `to`)
+    pub from: Option<NaiveDate>,
+
+    /// Reference date for current prices (default: today)
+    pub to: Option<NaiveDate>,
+}
+
+/// Handler: personal inflation index weighted by the user's basket
+pub async fn get_inflation_stats(
+    State(state): State<AppState>,
+    auth_user: AuthenticatedUser,
+    Query(params): Query<InflationQueryParams>,
+) -> AppResult<Json<PersonalInflationIndex>> {
+    let filter = purchase_filter(None, params.from, params.to)?;
+
+    let index = get_personal_inflation(
+        &state.db_pool,
+        &auth_user.email,
+        filter.to.unwrap_or_else(|| Utc::now().date_naive()),
+        filter.from,
+        state.config.reference_inflation_rate,
+    )
+    .await?;
+
+    Ok(Json(index))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StoreStatsQueryParams {
+    /// Months to include (default 12)
+    #[serde(default = "default_months")]
+    pub months: i64,
+
+    /// Limit for products with price differences (default 20)
+    #[serde(default = "default_limit_store_products")]
+    pub limit: i64,
+
+    pub tienda: Option<String>,
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+}
+
+fn default_limit_store_products() -> i64 {
+    20
+}
+
+/// Handler: spend per store and price differences between stores
+pub async fn get_store_stats(
+    State(state): State<AppState>,
+    auth_user: AuthenticatedUser,
+    Query(params): Query<StoreStatsQueryParams>,
+) -> AppResult<Json<StoreStatsResponse>> {
+    let months = params.months.clamp(1, 120) as i32;
+    let limit = params.limit.clamp(1, 100);
+    let filter = purchase_filter(params.tienda, params.from, params.to)?;
+
+    let tiendas = get_spending_by_store(&state.db_pool, &auth_user.email, months, &filter).await?;
+    let diferencias_precio =
+        get_store_price_differences(&state.db_pool, &auth_user.email, months, limit, &filter)
+            .await?;
+
+    Ok(Json(StoreStatsResponse {
+        tiendas,
+        diferencias_precio,
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CompareQueryParams {
+    /// First day of period A (YYYY-MM-DD)
+    pub a_from: NaiveDate,
+    /// Last day of period A (YYYY-MM-DD)
+    pub a_to: NaiveDate,
+    /// First day of period B (YYYY-MM-DD)
+    pub b_from: NaiveDate,
+    /// Last day of period B (YYYY-MM-DD)
+    pub b_to: NaiveDate,
+
+    /// Only count purchases from this store (`compras.tienda`)
+    pub tienda: Option<String>,
+
+    /// Limit for compared products (default 10)
+    #[serde(default = "default_limit_compare_products")]
+    pub limit: i64,
+}
+
+fn default_limit_compare_products() -> i64 {
+    10
+}
+
+/// Handler: side-by-side comparison of two date ranges
+pub async fn get_period_comparison(
+    State(state): State<AppState>,
+    auth_user: AuthenticatedUser,
+    Query(params): Query<CompareQueryParams>,
+) -> AppResult<Json<PeriodComparison>> {
+    let a = purchase_filter(
+        params.tienda.clone(),
+        Some(params.a_from),
+        Some(params.a_to),
+    )?;
+    let b = purchase_filter(params.tienda, Some(params.b_from), Some(params.b_to))?;
+    let limit = params.limit.clamp(1, 100) as usize;
+
+    let comparison = compare_periods(&state.db_pool, &auth_user.email, &a, &b, limit).await?;
+
+    Ok(Json(comparison))
+}
+
+/// Builds the purchase filter shared by the stats handlers
+///
+/// Empty `tienda` values mean no filter; `from` must not be after `to`.
+pub(crate) fn purchase_filter(
+    tienda: Option<String>,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+) -> AppResult<PurchaseFilter> {
+    if let (Some(from), Some(to)) = (from, to) {
+        if from > to {
+            return Err(AppError::BadRequest(
+                "La fecha 'from' no puede ser posterior a 'to'".to_string(),
+            ));
+        }
+    }
+
+    Ok(PurchaseFilter {
+        tienda: tienda
+            .map(|t| t.trim().to_string())
+            .filter(|t| !t.is_empty()),
+        from,
+        to,
+    })
+}
+
 /// Router para los endpoints de estadisticas
 pub fn stats_router(state: AppState) -> Router {
     Router::new()
         .route("/dashboard", get(get_dashboard_stats))
         .route("/monthly", get(get_monthly_evolution))
         .route("/products", get(get_all_products_stats))
+        .route("/inflation", get(get_inflation_stats))
+        .route("/categories", get(get_category_stats))
+        .route("/payment-methods", get(get_payment_method_stats))
+        .route("/iva", get(get_iva_stats))
+        .route("/stores", get(get_store_stats))
+        .route("/compare", get(get_period_comparison))
         .with_state(state)
 }
@@ -1,15 +1,33 @@
 use axum::{
-    extract::{Query, State},
-    routing::get,
+    body::{Body, Bytes},
+    extract::{DefaultBodyLimit, Multipart, Path, Query, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{get, post},
     Json, Router,
 };
+use chrono::{NaiveDate, NaiveDateTime, Utc};
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use validator::Validate;
 
-use super::auth::AppState;
+use super::{auth::AppState, ocr::OcrResponseSummary, stats::purchase_filter};
 use crate::{
-    db::{get_user_stats, get_user_ticket_history, TicketHistoryItem, UserStats},
+    db::{
+        delete_purchase, delete_ticket_draft, get_purchase, get_purchase_iva_totals,
+        get_purchase_products, get_ticket_attachment, get_ticket_draft, get_user_stats,
+        get_user_ticket_drafts, get_user_ticket_history, ticket_attachment_exists,
+        PurchaseIvaTotal, TicketHistoryItem, UserStats,
+    },
     error::{AppError, AppResult},
     middleware::AuthenticatedUser,
+    models::{Purchase, PurchaseInsert, PurchaseProduct, TicketAttachment, TicketDraftSummary},
+    schema::{TicketDraftConfirmPayload, TicketUpdatePayload},
+    services::{
+        confirm_ticket_draft, correct_ticket, expand_import_files, import_tickets,
+        ticket_storage::remove_stored_file, ImportFile, ImportLimits, IvaMismatch,
+        TicketImportReport, TicketIngestionResponse,
+    },
 };
 
 #[derive(Debug, Deserialize)]
@@ -20,6 +38,12 @@ pub struct HistoryQueryParams {
     pub limit: Option<i64>,
     #[serde(default)]
     pub offset: Option<i64>,
+    /// Primer día incluido (YYYY-MM-DD)
+    #[serde(default)]
+    pub from: Option<NaiveDate>,
+    /// Último día incluido (YYYY-MM-DD)
+    #[serde(default)]
+    pub to: Option<NaiveDate>,
 }
 
 #[derive(Debug, Serialize)]
@@ -28,6 +52,23 @@ pub struct TicketHistoryResponse {
     pub stats: UserStats,
 }
 
+/// Detalle completo de un ticket con sus lineas de producto
+#[derive(Debug, Serialize)]
+pub struct TicketDetailResponse {
+    pub compra: Purchase,
+    pub productos: Vec<PurchaseProduct>,
+    pub iva_desglose: Vec<PurchaseIvaTotal>,
+    pub tiene_archivo: bool,
+}
+
+/// Ticket corregido junto a los descuadres de IVA que dejan las correcciones
+#[derive(Debug, Serialize)]
+pub struct TicketCorrectionResponse {
+    #[serde(flatten)]
+    pub detalle: TicketDetailResponse,
+    pub iva_descuadres: Vec<IvaMismatch>,
+}
+
 /// Handler para obtener el historico de tickets de un usuario
 pub async fn get_user_tickets(
     State(state): State<AppState>,
@@ -46,19 +87,692 @@ pub async fn get_user_tickets(
 
     tracing::info!("Obteniendo historico de tickets para usuario autenticado");
 
-    let tickets =
-        get_user_ticket_history(&state.db_pool, &user_email, params.limit, params.offset).await?;
+    let filter = purchase_filter(None, params.from, params.to)?;
 
-    let stats = get_user_stats(&state.db_pool, &user_email).await?;
+    let tickets = get_user_ticket_history(
+        &state.db_pool,
+        &user_email,
+        params.limit,
+        params.offset,
+        &filter,
+    )
+    .await?;
+
+    let stats = get_user_stats(&state.db_pool, &user_email, &filter).await?;
 
     tracing::info!("Historico obtenido: {} tickets encontrados", tickets.len());
 
     Ok(Json(TicketHistoryResponse { tickets, stats }))
 }
 
+/// Handler para obtener el detalle de un ticket del usuario
+pub async fn get_ticket_detail(
+    State(state): State<AppState>,
+    auth_user: AuthenticatedUser,
+    Path(numero_factura): Path<String>,
+) -> AppResult<Json<TicketDetailResponse>> {
+    let compra = find_user_purchase(&state, &auth_user.email, &numero_factura).await?;
+    let detail = build_ticket_detail(&state, compra).await?;
+
+    tracing::info!(
+        "Detalle de ticket obtenido: {} lineas",
+        detail.productos.len()
+    );
+
+    Ok(Json(detail))
+}
+
+/// Handler para corregir manualmente un ticket ya ingestado
+///
+/// Devuelve el detalle actualizado del ticket tras aplicar la corrección.
+pub async fn update_ticket(
+    State(state): State<AppState>,
+    auth_user: AuthenticatedUser,
+    Path(numero_factura): Path<String>,
+    Json(payload): Json<TicketUpdatePayload>,
+) -> AppResult<Json<TicketCorrectionResponse>> {
+    if auth_user.is_demo {
+        return Err(AppError::DemoUserRestriction);
+    }
+
+    payload
+        .validate()
+        .map_err(|e| AppError::BadRequest(format!("Datos inválidos: {}", e)))?;
+
+    let correccion =
+        correct_ticket(&state.db_pool, &auth_user.email, &numero_factura, payload).await?;
+
+    let compra = find_user_purchase(&state, &auth_user.email, &numero_factura).await?;
+    let detalle = build_ticket_detail(&state, compra).await?;
+
+    Ok(Json(TicketCorrectionResponse {
+        detalle,
+        iva_descuadres: correccion.iva_descuadres,
+    }))
+}
+
+/// Carga las líneas, el desglose de IVA y la existencia de archivo de una compra
+async fn build_ticket_detail(
+    state: &AppState,
+    compra: Purchase,
+) -> AppResult<TicketDetailResponse> {
+    let productos = get_purchase_products(&state.db_pool, &compra.numero_factura).await?;
+    let iva_desglose = get_purchase_iva_totals(&state.db_pool, &compra.numero_factura).await?;
+    let tiene_archivo = ticket_attachment_exists(&state.db_pool, &compra.numero_factura).await?;
+
+    Ok(TicketDetailResponse {
+        compra,
+        productos,
+        iva_desglose,
+        tiene_archivo,
+    })
+}
+
+/// Resultado del borrado de un ticket
+#[derive(Debug, Serialize)]
+pub struct TicketDeletionResponse {
+    pub deleted: bool,
+    pub numero_factura: String,
+    pub lineas_eliminadas: u64,
+    pub productos_eliminados: u64,
+}
+
+/// Handler para eliminar un ticket del usuario y sus datos derivados
+pub async fn delete_ticket(
+    State(state): State<AppState>,
+    auth_user: AuthenticatedUser,
+    Path(numero_factura): Path<String>,
+) -> AppResult<Json<TicketDeletionResponse>> {
+    if auth_user.is_demo {
+        return Err(AppError::DemoUserRestriction);
+    }
+
+    let compra = find_user_purchase(&state, &auth_user.email, &numero_factura).await?;
+
+    let attachment = get_ticket_attachment(&state.db_pool, &compra.numero_factura).await?;
+
+    let mut tx = state.db_pool.begin().await?;
+    let deletion = delete_purchase(&mut tx, &compra.numero_factura)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Ticket no encontrado".to_string()))?;
+    tx.commit().await?;
+
+    // El archivo vive fuera de la cascada de compras
+    if let Some(attachment) = attachment {
+        remove_stored_file(&state.ticket_storage, &attachment).await;
+    }
+
+    tracing::info!(
+        "Ticket eliminado ({} lineas, {} productos huerfanos)",
+        deletion.lineas_eliminadas,
+        deletion.productos_eliminados
+    );
+
+    Ok(Json(TicketDeletionResponse {
+        deleted: true,
+        numero_factura: compra.numero_factura,
+        lineas_eliminadas: deletion.lineas_eliminadas,
+        productos_eliminados: deletion.productos_eliminados,
+    }))
+}
+
+/// Listado de borradores pendientes de confirmar
+#[derive(Debug, Serialize)]
+pub struct TicketDraftListResponse {
+    pub borradores: Vec<TicketDraftSummary>,
+}
+
+/// Borrador con el resultado del OCR para revisarlo
+#[derive(Debug, Serialize)]
+pub struct TicketDraftResponse {
+    pub id: Uuid,
+    pub nombre_archivo: String,
+    pub mime_type: Option<String>,
+    pub tamano_bytes: i32,
+    pub created_at: NaiveDateTime,
+    pub expira_en: NaiveDateTime,
+    pub ocr: OcrResponseSummary,
+}
+
+/// Handler para listar los borradores vigentes del usuario
+pub async fn list_ticket_drafts(
+    State(state): State<AppState>,
+    auth_user: AuthenticatedUser,
+) -> AppResult<Json<TicketDraftListResponse>> {
+    let borradores = get_user_ticket_drafts(&state.db_pool, &auth_user.email).await?;
+
+    Ok(Json(TicketDraftListResponse { borradores }))
+}
+
+/// Handler para recuperar un borrador (por ejemplo tras recargar la pagina)
+pub async fn get_ticket_draft_detail(
+    State(state): State<AppState>,
+    auth_user: AuthenticatedUser,
+    Path(id): Path<Uuid>,
+) -> AppResult<Json<TicketDraftResponse>> {
+    let draft = get_ticket_draft(&state.db_pool, &auth_user.email, id)
+        .await?
+        .filter(|d| !d.is_expired(Utc::now().naive_utc()))
+        .ok_or_else(|| AppError::NotFound("Borrador no encontrado o caducado".to_string()))?;
+
+    Ok(Json(TicketDraftResponse {
+        id: draft.id,
+        nombre_archivo: draft.nombre_archivo,
+        mime_type: draft.mime_type,
+        tamano_bytes: draft.tamano_bytes,
+        created_at: draft.created_at,
+        expira_en: draft.expira_en,
+        ocr: OcrResponseSummary::from(&draft.resultado_ocr.0),
+    }))
+}
+
+/// Handler para confirmar un borrador e ingestarlo
+pub async fn confirm_draft(
+    State(state): State<AppState>,
+    auth_user: AuthenticatedUser,
+    Path(id): Path<Uuid>,
+    body: Bytes,
+) -> AppResult<Json<TicketIngestionResponse>> {
+    if auth_user.is_demo {
+        return Err(AppError::DemoUserRestriction);
+    }
+
+    let payload = parse_draft_edits(&body)?;
+    payload
+        .validate()
+        .map_err(|e| AppError::BadRequest(format!("Datos inválidos: {}", e)))?;
+
+    let ingestion = confirm_ticket_draft(
+        &state.db_pool,
+        &state.ticket_storage,
+        &auth_user.email,
+        id,
+        payload,
+        &state.ticket_events,
+    )
+    .await?;
+
+    Ok(Json(ingestion))
+}
+
+/// Correcciones enviadas al confirmar un borrador
+///
+/// Solo un cuerpo vacío equivale a confirmar sin cambios; cualquier JSON
+/// inválido se rechaza para no ingestar el OCR descartando las correcciones.
+fn parse_draft_edits(body: &[u8]) -> AppResult<TicketDraftConfirmPayload> {
+    if body.iter().all(u8::is_ascii_whitespace) {
+        return Ok(TicketDraftConfirmPayload::default());
+    }
+
+    serde_json::from_slice(body)
+        .map_err(|e| AppError::BadRequest(format!("Correcciones del borrador inválidas: {}", e)))
+}
+
+/// Handler para descartar un borrador
+pub async fn discard_draft(
+    State(state): State<AppState>,
+    auth_user: AuthenticatedUser,
+    Path(id): Path<Uuid>,
+) -> AppResult<StatusCode> {
+    if get_ticket_draft(&state.db_pool, &auth_user.email, id)
+        .await?
+        .is_none()
+    {
+        return Err(AppError::NotFound("Borrador no encontrado".to_string()));
+    }
+
+    delete_ticket_draft(&state.db_pool, id).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TicketFileQueryParams {
+    /// Fuerza la descarga (`attachment`) en lugar de la vista previa en linea
+    #[serde(default)]
+    pub download: bool,
+}
+
+/// Handler para importar tickets en bloque
+///
+/// Acepta varios archivos en un formulario multipart o un ZIP con los
+/// tickets. Devuelve el resultado individual de cada archivo.
+pub async fn import_tickets_batch(
+    State(state): State<AppState>,
+    auth_user: AuthenticatedUser,
+    mut multipart: Multipart,
+) -> AppResult<Json<TicketImportReport>> {
+    if auth_user.is_demo {
+        return Err(AppError::DemoUserRestriction);
+    }
+
+    let mut files = Vec::new();
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|e| AppError::BadRequest(format!("Formulario multipart inválido: {}", e)))?
+    {
+        let Some(nombre_archivo) = field.file_name().map(str::to_string) else {
+            continue;
+        };
+        let mime_type = field.content_type().map(str::to_string);
+        let contenido = field
+            .bytes()
+            .await
+            .map_err(|e| AppError::BadRequest(format!("No se pudo leer el archivo: {}", e)))?;
+
+        files.push(ImportFile {
+            nombre_archivo,
+            contenido: contenido.to_vec(),
+            mime_type,
+        });
+    }
+
+    if files.is_empty() {
+        return Err(AppError::BadRequest(
+            "No se ha recibido ningún archivo".to_string(),
+        ));
+    }
+
+    let limits = ImportLimits {
+        max_files: state.config.import_max_files,
+        max_bytes: state.config.import_max_bytes,
+    };
+    let files = tokio::task::spawn_blocking(move || expand_import_files(files, limits))
+        .await
+        .map_err(|e| AppError::InternalError(format!("Fallo al descomprimir: {}", e)))?
+        .map_err(AppError::BadRequest)?;
+
+    if files.is_empty() {
+        return Err(AppError::BadRequest(
+            "El ZIP no contiene tickets en un formato soportado".to_string(),
+        ));
+    }
+
+    let report = import_tickets(
+        state.db_pool.clone(),
+        state.ticket_storage.clone(),
+        state.intelligence_client.clone(),
+        &auth_user.email,
+        files,
+        state.config.import_ocr_concurrency,
+        state.ticket_events.clone(),
+    )
+    .await;
+
+    Ok(Json(report))
+}
+
+/// Handler para descargar el archivo original de un ticket.
+///
+/// Soporta peticiones condicionales (`If-None-Match`) y rangos de bytes
+/// (`Range` / `If-Range`) para que el navegador pueda previsualizar el PDF o la imagen.
+pub async fn download_ticket_file(
+    State(state): State<AppState>,
+    auth_user: AuthenticatedUser,
+    Path(numero_factura): Path<String>,
+    Query(params): Query<TicketFileQueryParams>,
+    headers: HeaderMap,
+) -> AppResult<Response> {
+    let purchase = find_user_purchase(&state, &auth_user.email, &numero_factura).await?;
+
+    let ticket = get_ticket_attachment(&state.db_pool, &purchase.numero_factura)
+        .await?
+        .ok_or_else(|| {
+            AppError::NotFound("El ticket no tiene archivo original almacenado".to_string())
+        })?;
+
+    let storage = state
+        .ticket_storage
+        .for_attachment(&ticket)
+        .ok_or_else(|| {
+            AppError::InternalError(format!(
+                "Archivo guardado en el backend {}, que no está configurado",
+                ticket.almacenamiento
+            ))
+        })?;
+
+    let content = storage
+        .get(&ticket.clave_almacenamiento)
+        .await?
+        .ok_or_else(|| {
+            AppError::NotFound("El ticket no tiene archivo original almacenado".to_string())
+        })?;
+
+    tracing::info!("Sirviendo archivo original de ticket");
+
+    Ok(build_file_response(
+        ticket,
+        content,
+        &headers,
+        params.download,
+    ))
+}
+
+/// Busca una compra y verifica que pertenezca al usuario autenticado.
+///
+/// Si la compra es de otro usuario se responde igual que si no existiera para
+/// no revelar numeros de factura ajenos.
+async fn find_user_purchase(
+    state: &AppState,
+    user_email: &str,
+    numero_factura: &str,
+) -> AppResult<Purchase> {
+    let numero_factura = PurchaseInsert::normalize_invoice_number(numero_factura);
+
+    match get_purchase(&state.db_pool, &numero_factura).await? {
+        Some(purchase) if purchase.usuario_email == user_email => Ok(purchase),
+        _ => Err(AppError::NotFound("Ticket no encontrado".to_string())),
+    }
+}
+
+/// Construye la respuesta HTTP del archivo aplicando ETag y rangos.
+fn build_file_response(
+    ticket: TicketAttachment,
+    content: Vec<u8>,
+    headers: &HeaderMap,
+    download: bool,
+) -> Response {
+    let etag = ticket_etag(&ticket);
+    let total_len = content.len() as u64;
+
+    let mut response_headers = HeaderMap::new();
+    response_headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_str(&ticket.mime_type)
+            .unwrap_or_else(|_| HeaderValue::from_static("application/octet-stream")),
+    );
+    if let Ok(value) = HeaderValue::from_str(&content_disposition(&ticket.nombre_archivo, download))
+    {
+        response_headers.insert(header::CONTENT_DISPOSITION, value);
+    }
+    if let Ok(value) = HeaderValue::from_str(&etag) {
+        response_headers.insert(header::ETAG, value);
+    }
+    response_headers.insert(header::ACCEPT_RANGES, HeaderValue::from_static("bytes"));
+    response_headers.insert(
+        header::CACHE_CONTROL,
+        HeaderValue::from_static("private, no-cache"),
+    );
+
+    if etag_matches(headers.get(header::IF_NONE_MATCH), &etag) {
+        return (StatusCode::NOT_MODIFIED, response_headers).into_response();
+    }
+
+    // Si `If-Range` no coincide con la version actual se ignora el rango
+    let range_header = headers.get(header::RANGE).filter(|_| {
+        headers
+            .get(header::IF_RANGE)
+            .map(|value| value.to_str().map(|v| v == etag).unwrap_or(false))
+            .unwrap_or(true)
+    });
+
+    let range = match range_header {
+        Some(value) => match value.to_str().ok().map(|v| parse_range(v, total_len)) {
+            Some(ByteRange::Satisfiable(start, end)) => Some((start, end)),
+            Some(ByteRange::Unsatisfiable) => {
+                if let Ok(value) = HeaderValue::from_str(&format!("bytes */{}", total_len)) {
+                    response_headers.insert(header::CONTENT_RANGE, value);
+                }
+                return (StatusCode::RANGE_NOT_SATISFIABLE, response_headers).into_response();
+            }
+            Some(ByteRange::Ignored) | None => None,
+        },
+        None => None,
+    };
+
+    match range {
+        Some((start, end)) => {
+            let body = content[start as usize..=end as usize].to_vec();
+            if let Ok(value) =
+                HeaderValue::from_str(&format!("bytes {}-{}/{}", start, end, total_len))
+            {
+                response_headers.insert(header::CONTENT_RANGE, value);
+            }
+            response_headers.insert(header::CONTENT_LENGTH, HeaderValue::from(body.len()));
+            (
+                StatusCode::PARTIAL_CONTENT,
+                response_headers,
+                Body::from(body),
+            )
+                .into_response()
+        }
+        None => {
+            response_headers.insert(header::CONTENT_LENGTH, HeaderValue::from(total_len));
+            (StatusCode::OK, response_headers, Body::from(content)).into_response()
+        }
+    }
+}
+
+/// ETag fuerte derivado de la version almacenada del archivo.
+///
+/// El archivo de un ticket no se modifica nunca en sitio, por lo que el
+/// instante de creacion y el tamano identifican su contenido.
+fn ticket_etag(ticket: &TicketAttachment) -> String {
+    format!(
+        "\"{:x}-{:x}\"",
+        ticket.created_at.and_utc().timestamp_micros(),
+        ticket.tamano_bytes
+    )
+}
+
+/// Comprueba si alguna de las ETags de `If-None-Match` coincide con la actual
+fn etag_matches(header_value: Option<&HeaderValue>, etag: &str) -> bool {
+    let Some(value) = header_value.and_then(|v| v.to_str().ok()) else {
+        return false;
+    };
+
+    value
+        .split(',')
+        .map(str::trim)
+        .any(|candidate| candidate == "*" || candidate.trim_start_matches("W/") == etag)
+}
+
+/// Cabecera Content-Disposition con un nombre de archivo seguro
+fn content_disposition(file_name: &str, download: bool) -> String {
+    let safe_name: String = file_name
+        .chars()
+        .filter(|c| c.is_ascii_graphic() || *c == ' ')
+        .filter(|c| !matches!(c, '"' | '\\' | ';'))
+        .collect();
+    let safe_name = if safe_name.trim().is_empty() {
+        "ticket".to_string()
+    } else {
+        safe_name
+    };
+
+    let disposition = if download { "attachment" } else { "inline" };
+    format!("{}; filename=\"{}\"", disposition, safe_name)
+}
+
+/// Resultado de interpretar una cabecera `Range`
+#[derive(Debug, PartialEq)]
+enum ByteRange {
+    /// Rango inclusivo `start..=end` dentro del archivo
+    Satisfiable(u64, u64),
+    /// Rango bien formado pero fuera del archivo (416)
+    Unsatisfiable,
+    /// Cabecera no soportada (multiples rangos, otra unidad...): se sirve completo
+    Ignored,
+}
+
+/// Interpreta una cabecera `Range` de un unico rango de bytes
+fn parse_range(value: &str, total_len: u64) -> ByteRange {
+    let Some(spec) = value.trim().strip_prefix("bytes=") else {
+        return ByteRange::Ignored;
+    };
+
+    if spec.contains(',') {
+        return ByteRange::Ignored;
+    }
+
+    let Some((start, end)) = spec.trim().split_once('-') else {
+        return ByteRange::Ignored;
+    };
+
+    let (start, end) = match (start.trim(), end.trim()) {
+        ("", "") => return ByteRange::Ignored,
+        // Sufijo: ultimos N bytes
+        ("", suffix) => match suffix.parse::<u64>() {
+            Ok(0) => return ByteRange::Unsatisfiable,
+            Ok(n) => (total_len.saturating_sub(n), total_len.saturating_sub(1)),
+            Err(_) => return ByteRange::Ignored,
+        },
+        (start, "") => match start.parse::<u64>() {
+            Ok(s) => (s, total_len.saturating_sub(1)),
+            Err(_) => return ByteRange::Ignored,
+        },
+        (start, end) => match (start.parse::<u64>(), end.parse::<u64>()) {
+            (Ok(s), Ok(e)) if s <= e => (s, e.min(total_len.saturating_sub(1))),
+            _ => return ByteRange::Ignored,
+        },
+    };
+
+    if total_len == 0 || start >= total_len {
+        return ByteRange::Unsatisfiable;
+    }
+
+    ByteRange::Satisfiable(start, end)
+}
+
 /// Router para los endpoints de tickets
 pub fn tickets_router(state: AppState) -> Router {
     Router::new()
         .route("/history", get(get_user_tickets))
+        .route(
+            "/import",
+            post(import_tickets_batch).layer(DefaultBodyLimit::max(state.config.import_max_bytes)),
+        )
+        .route("/drafts", get(list_ticket_drafts))
+        .route(
+            "/drafts/:id",
+            get(get_ticket_draft_detail).delete(discard_draft),
+        )
+        .route("/drafts/:id/confirm", post(confirm_draft))
+        .route(
+            "/:numero_factura",
+            get(get_ticket_detail)
+                .patch(update_ticket)
+                .delete(delete_ticket),
+        )
+        .route("/:numero_factura/file", get(download_ticket_file))
         .with_state(state)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{db::insert_ticket_draft, models::TicketDraftInsert};
+    use sqlx::PgPool;
+
+    #[test]
+    fn test_parse_draft_edits() {
+        assert!(!parse_draft_edits(b"").unwrap().replace);
+        assert!(parse_draft_edits(b"{}").unwrap().productos.is_none());
+        assert_eq!(
+            parse_draft_edits(br#"{"total": 12.5}"#).unwrap().total,
+            Some(12.5)
+        );
+        assert!(matches!(
+            parse_draft_edits(br#"{"total": "doce"}"#),
+            Err(AppError::BadRequest(_))
+        ));
+    }
+
+    #[sqlx::test(migrations = "./migrations")]
+    async fn test_confirm_draft_rejects_invalid_edits(pool: PgPool) -> sqlx::Result<()> {
+        sqlx::query!(
+            "INSERT INTO usuarios (email, password_hash, nombre) VALUES ($1, $2, $3)",
+            "test@example.com",
+            "$2b$12$KpIEW.jQKvqXfN5nDwAXLub8RCRYjqNvCLKXfzHpFGK2FQJGmqQJi",
+            "Test User"
+        )
+        .execute(&pool)
+        .await?;
+
+        let draft = insert_ticket_draft(
+            &pool,
+            &TicketDraftInsert {
+                usuario_email: "test@example.com".to_string(),
+                resultado_ocr: serde_json::from_value(serde_json::json!({
+                    "ticket_id": "ticket_1",
+                    "raw_text": "MERCADONA",
+                    "numero_factura": "0001-001-000001",
+                    "fecha_hora": "2025-01-15 10:30:00",
+                    "total": 2.5,
+                    "productos": [{
+                        "nombre": "LECHE ENTERA",
+                        "cantidad": 2.0,
+                        "unidad": "unidad",
+                        "precio_unitario": 1.25,
+                        "precio_total": 2.5
+                    }]
+                }))
+                .unwrap(),
+                archivo: vec![0x25, 0x50, 0x44, 0x46],
+                nombre_archivo: "ticket.pdf".to_string(),
+                mime_type: Some("application/pdf".to_string()),
+                expira_en: Utc::now().naive_utc() + chrono::Duration::hours(24),
+            },
+        )
+        .await?;
+
+        let state = AppState::for_tests(pool.clone());
+        let user = AuthenticatedUser {
+            email: "test@example.com".to_string(),
+            is_demo: false,
+            is_operator: false,
+        };
+
+        // Un campo con el tipo equivocado no se confirma sin las correcciones
+        let err = confirm_draft(
+            State(state.clone()),
+            user.clone(),
+            Path(draft.id),
+            Bytes::from_static(br#"{"productos": [], "total": "2,50"}"#),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(err.status_code(), StatusCode::BAD_REQUEST);
+
+        assert!(get_ticket_draft(&pool, "test@example.com", draft.id)
+            .await?
+            .is_some());
+        assert!(get_purchase(&pool, "0001-001-000001").await?.is_none());
+
+        // Sin cuerpo se confirma el resultado del OCR tal cual
+        let ingestion = confirm_draft(State(state), user, Path(draft.id), Bytes::new())
+            .await
+            .expect("sin cuerpo se confirma el borrador");
+        assert_eq!(ingestion.numero_factura, "0001-001-000001");
+        assert!(get_ticket_draft(&pool, "test@example.com", draft.id)
+            .await?
+            .is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_range() {
+        assert_eq!(
+            parse_range("bytes=0-99", 1000),
+            ByteRange::Satisfiable(0, 99)
+        );
+        assert_eq!(
+            parse_range("bytes=900-", 1000),
+            ByteRange::Satisfiable(900, 999)
+        );
+        assert_eq!(
+            parse_range("bytes=-100", 1000),
+            ByteRange::Satisfiable(900, 999)
+        );
+        assert_eq!(
+            parse_range("bytes=500-5000", 1000),
+            ByteRange::Satisfiable(500, 999)
+        );
+        assert_eq!(parse_range("bytes=1000-", 1000), ByteRange::Unsatisfiable);
+        assert_eq!(parse_range("bytes=0-1,5-9", 1000), ByteRange::Ignored);
+        assert_eq!(parse_range("items=0-1", 1000), ByteRange::Ignored);
+        assert_eq!(parse_range("bytes=9-1", 1000), ByteRange::Ignored);
+    }
+}
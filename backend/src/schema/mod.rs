@@ -1,7 +1,22 @@
 pub mod auth;
+pub mod notifications;
 pub mod ocr;
+pub mod payment_cards;
+pub mod preferences;
+pub mod products;
+pub mod savings_goals;
 pub mod stats;
+pub mod tickets;
 
 pub use auth::{AuthResponse, LoginRequest, RegisterRequest, UserInfo};
-pub use ocr::TicketProcessPayload;
-pub use stats::{DashboardStatsResponse, MonthlyEvolutionResponse};
+pub use notifications::{NotificationListResponse, UnreadCountResponse};
+pub use ocr::{OcrJobPayload, TicketProcessPayload};
+pub use payment_cards::PaymentCardPayload;
+pub use preferences::UserPreferencesPayload;
+pub use products::{ProductCategoryPayload, ProductMergePayload};
+pub use savings_goals::{SavingsGoalCreatePayload, SavingsGoalUpdatePayload};
+pub use stats::{
+    CategoryStatsResponse, DashboardStatsResponse, IvaStatsResponse, MonthlyEvolutionResponse,
+    PaymentMethodStatsResponse, StoreStatsResponse,
+};
+pub use tickets::{TicketDraftConfirmPayload, TicketUpdatePayload};
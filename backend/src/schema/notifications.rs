@@ -0,0 +1,15 @@
+use crate::models::Notification;
+use serde::Serialize;
+
+/// Respuesta del listado de notificaciones
+#[derive(Debug, Clone, Serialize)]
+pub struct NotificationListResponse {
+    pub notificaciones: Vec<Notification>,
+    pub no_leidas: i64,
+}
+
+/// Número de notificaciones sin leer
+#[derive(Debug, Clone, Serialize)]
+pub struct UnreadCountResponse {
+    pub no_leidas: i64,
+}
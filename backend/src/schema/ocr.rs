@@ -19,6 +19,25 @@ pub struct TicketProcessPayload {
     /// Email del usuario (temporal hasta que se implemente autenticacion JWT)
     #[validate(email(message = "usuario_email debe ser un email valido"))]
     pub usuario_email: Option<String>,
+    /// Sustituye la compra existente con el mismo numero de factura en vez de
+    /// rechazarla como duplicada
+    #[serde(default)]
+    pub replace: bool,
+}
+
+/// Payload para encolar un ticket en la cola de trabajos de OCR.
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct OcrJobPayload {
+    #[validate(length(min = 1, message = "file_name es requerido"))]
+    pub file_name: String,
+    #[serde(alias = "pdf_b64")]
+    #[validate(length(min = 1, message = "file_content_b64 es requerido"))]
+    pub file_content_b64: String,
+    #[validate(length(min = 1, message = "mime_type no puede estar vacio"))]
+    pub mime_type: Option<String>,
+    /// Sustituye la compra existente con el mismo numero de factura
+    #[serde(default)]
+    pub replace: bool,
 }
 
 impl From<TicketProcessPayload> for OcrProcessTicketRequest {
@@ -0,0 +1,13 @@
+use serde::Deserialize;
+use validator::Validate;
+
+/// Payload para asociar un alias a un patrón de número de operación
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct PaymentCardPayload {
+    /// Nombre con el que el usuario reconoce la tarjeta
+    #[validate(length(min = 1, max = 100, message = "alias invalido"))]
+    pub alias: String,
+    /// Letras y dígitos del número de operación; `*` equivale a cualquier secuencia
+    #[validate(length(min = 1, max = 50, message = "patron invalido"))]
+    pub patron: String,
+}
@@ -0,0 +1,33 @@
+use rust_decimal::Decimal;
+use serde::Deserialize;
+
+/// Payload para guardar las preferencias del usuario
+///
+/// Sustituye las preferencias completas; los campos ausentes toman el valor
+/// por defecto de la tabla.
+#[derive(Debug, Clone, Deserialize)]
+pub struct UserPreferencesPayload {
+    #[serde(default = "default_true")]
+    pub alertas_gasto_activas: bool,
+    /// Gasto mensual a partir del cual se avisa; `null` desactiva el umbral
+    #[serde(default)]
+    pub umbral_alerta_gasto: Option<Decimal>,
+    #[serde(default = "default_true")]
+    pub notif_nuevos_logros: bool,
+    #[serde(default = "default_true")]
+    pub notif_inflacion: bool,
+    /// diaria, semanal, mensual o nunca
+    #[serde(default = "default_frecuencia")]
+    pub frecuencia_reportes: String,
+    /// Objeto JSON libre para ajustes del frontend
+    #[serde(default)]
+    pub configuracion_extra: Option<serde_json::Value>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_frecuencia() -> String {
+    "semanal".to_string()
+}
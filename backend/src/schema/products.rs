@@ -0,0 +1,21 @@
+use serde::Deserialize;
+use validator::Validate;
+
+/// Payload para fusionar un producto en otro
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct ProductMergePayload {
+    /// Producto que desaparece y queda como alias
+    #[validate(length(min = 1, max = 255, message = "nombre de producto invalido"))]
+    pub from: String,
+    /// Producto que se conserva
+    #[validate(length(min = 1, max = 255, message = "nombre de producto invalido"))]
+    pub into: String,
+}
+
+/// Payload para elegir la categoría de un producto
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct ProductCategoryPayload {
+    /// Categoría elegida; `null` vuelve a la categoría automática
+    #[validate(length(min = 1, max = 100, message = "categoria invalida"))]
+    pub categoria: Option<String>,
+}
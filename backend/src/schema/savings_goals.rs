@@ -0,0 +1,18 @@
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use serde::Deserialize;
+
+/// Payload para crear el objetivo de gasto de un mes
+#[derive(Debug, Clone, Deserialize)]
+pub struct SavingsGoalCreatePayload {
+    /// Cualquier día del mes del objetivo (se guarda el día 1)
+    pub mes: NaiveDate,
+    /// Gasto máximo del mes
+    pub objetivo_mensual: Decimal,
+}
+
+/// Payload para cambiar el importe de un objetivo
+#[derive(Debug, Clone, Deserialize)]
+pub struct SavingsGoalUpdatePayload {
+    pub objetivo_mensual: Decimal,
+}
@@ -1,4 +1,9 @@
-use crate::db::{DailySpendPoint, MonthlySpendPoint, TimeDistributionPoint, TopProductItem};
+use crate::db::{
+    CardSpendItem, CategoryMonthlyPoint, CategorySpendItem, DailySpendPoint, IvaMonthlyPoint,
+    MonthlySpendPoint, PaymentMethodMonthlyPoint, PaymentMethodSpendItem, StorePriceDifference,
+    StoreSpendItem, TimeDistributionPoint, TopProductItem,
+};
+use crate::services::{IvaYearlyPoint, PersonalInflationIndex, SavingsGoalProgress};
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 
@@ -37,6 +42,15 @@ pub struct DashboardStatsResponse {
 
     /// Distribución de compras por hora del día
     pub hourly_distribution: Vec<TimeDistributionPoint>,
+
+    /// Reparto del gasto por método de pago
+    pub payment_methods: Vec<PaymentMethodSpendItem>,
+
+    /// Progreso del objetivo de gasto del mes en curso, si lo hay
+    pub savings_goal: Option<SavingsGoalProgress>,
+
+    /// Inflación personal de la cesta del usuario
+    pub personal_inflation: PersonalInflationIndex,
 }
 
 /// Serie y métricas para la evolución mensual de gasto
@@ -49,3 +63,32 @@ pub struct MonthlyEvolutionResponse {
     pub year_to_date_total: Decimal,
     pub month_over_month: f64,
 }
+
+/// Gasto por categoría y su evolución mensual
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CategoryStatsResponse {
+    pub categorias: Vec<CategorySpendItem>,
+    pub evolucion: Vec<CategoryMonthlyPoint>,
+}
+
+/// Gasto por tienda y diferencias de precio entre tiendas
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoreStatsResponse {
+    pub tiendas: Vec<StoreSpendItem>,
+    pub diferencias_precio: Vec<StorePriceDifference>,
+}
+
+/// Gasto por método de pago, su evolución mensual y el gasto por tarjeta
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaymentMethodStatsResponse {
+    pub metodos: Vec<PaymentMethodSpendItem>,
+    pub evolucion: Vec<PaymentMethodMonthlyPoint>,
+    pub tarjetas: Vec<CardSpendItem>,
+}
+
+/// IVA pagado por tipo impositivo, por mes y por año
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IvaStatsResponse {
+    pub mensual: Vec<IvaMonthlyPoint>,
+    pub anual: Vec<IvaYearlyPoint>,
+}
@@ -0,0 +1,58 @@
+use chrono::NaiveDateTime;
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use validator::Validate;
+
+use crate::services::TicketProduct;
+
+/// Payload para corregir manualmente un ticket ya ingestado.
+/// Los campos ausentes conservan su valor actual.
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct TicketUpdatePayload {
+    /// Fecha y hora local de la compra
+    pub fecha_hora: Option<NaiveDateTime>,
+    /// Tienda (una cadena vacia la elimina)
+    #[validate(length(max = 255, message = "tienda no puede superar 255 caracteres"))]
+    pub tienda: Option<String>,
+    /// Metodo de pago (una cadena vacia lo elimina)
+    pub metodo_pago: Option<String>,
+    /// Lineas a anadir o modificar, identificadas por nombre de producto
+    #[serde(default)]
+    #[validate(nested)]
+    pub productos: Vec<TicketLineUpdate>,
+    /// Nombres de producto de las lineas a eliminar
+    #[serde(default)]
+    pub eliminar_productos: Vec<String>,
+}
+
+/// Linea de producto anadida o corregida manualmente
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct TicketLineUpdate {
+    #[validate(length(min = 1, max = 255, message = "nombre de producto invalido"))]
+    pub nombre: String,
+    pub cantidad: Decimal,
+    /// Unidad de la cantidad de una linea nueva (unidad, kg, g, l o ml); si se omite, unidad
+    #[validate(length(max = 20, message = "unidad invalida"))]
+    pub unidad: Option<String>,
+    pub precio_unitario: Decimal,
+    /// Si se omite se calcula como precio_unitario * cantidad - descuento
+    pub precio_total: Option<Decimal>,
+    pub descuento: Option<Decimal>,
+    /// Si se omite se conserva el de la linea existente
+    pub iva_porcentaje: Option<Decimal>,
+}
+
+/// Payload para confirmar un borrador de ticket.
+/// Los campos ausentes conservan el valor detectado por el OCR.
+#[derive(Debug, Clone, Default, Deserialize, Validate)]
+pub struct TicketDraftConfirmPayload {
+    /// Lista completa de productos revisada por el usuario
+    pub productos: Option<Vec<TicketProduct>>,
+    /// Fecha y hora local de la compra
+    pub fecha_hora: Option<NaiveDateTime>,
+    #[validate(range(min = 0.0, message = "total no puede ser negativo"))]
+    pub total: Option<f64>,
+    /// Sustituye la compra existente con el mismo numero de factura
+    #[serde(default)]
+    pub replace: bool,
+}
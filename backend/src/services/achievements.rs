@@ -0,0 +1,246 @@
+use crate::{
+    db::{self, AchievementCounters},
+    error::AppResult,
+    models::Achievement,
+    services::notifications,
+};
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::PgPool;
+use std::collections::HashMap;
+
+/// Regla con la que se desbloquea un logro
+///
+/// Las reglas se evalúan en Rust a partir de `codigo`; la columna
+/// `logros.condicion_sql` queda solo como documentación del catálogo.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AchievementRule {
+    /// Número mínimo de compras registradas
+    Compras(i64),
+    /// Número mínimo de objetivos de ahorro conseguidos
+    ObjetivosConseguidos(i64),
+    /// Días consecutivos con al menos una compra
+    RachaDiaria(i64),
+}
+
+impl AchievementRule {
+    fn for_code(codigo: &str) -> Option<Self> {
+        match codigo {
+            "PRIMERA_COMPRA" => Some(Self::Compras(1)),
+            "COMPRAS_10" => Some(Self::Compras(10)),
+            "COMPRAS_50" => Some(Self::Compras(50)),
+            "COMPRAS_100" => Some(Self::Compras(100)),
+            "AHORRO_MES" => Some(Self::ObjetivosConseguidos(1)),
+            "RACHA_SEMANAL" => Some(Self::RachaDiaria(7)),
+            _ => None,
+        }
+    }
+
+    fn objetivo(self) -> i64 {
+        match self {
+            Self::Compras(n) | Self::ObjetivosConseguidos(n) | Self::RachaDiaria(n) => n,
+        }
+    }
+
+    fn progreso(self, activity: &UserActivity) -> i64 {
+        match self {
+            Self::Compras(_) => activity.counters.compras,
+            Self::ObjetivosConseguidos(_) => activity.counters.objetivos_conseguidos,
+            Self::RachaDiaria(_) => activity.racha_maxima,
+        }
+    }
+
+    /// Datos que se guardan con el desbloqueo
+    fn metadata(self, activity: &UserActivity) -> serde_json::Value {
+        match self {
+            Self::Compras(_) => json!({ "compras": activity.counters.compras }),
+            Self::ObjetivosConseguidos(_) => {
+                json!({ "objetivos_conseguidos": activity.counters.objetivos_conseguidos })
+            }
+            Self::RachaDiaria(_) => json!({ "dias_consecutivos": activity.racha_maxima }),
+        }
+    }
+}
+
+/// Actividad del usuario con la que se evalúan todas las reglas
+#[derive(Debug, Clone, Default)]
+struct UserActivity {
+    counters: AchievementCounters,
+    racha_maxima: i64,
+}
+
+impl UserActivity {
+    async fn load(pool: &PgPool, user_email: &str) -> AppResult<Self> {
+        let counters = db::get_achievement_counters(pool, user_email).await?;
+        let fechas = db::get_purchase_dates(pool, user_email).await?;
+        Ok(Self {
+            counters,
+            racha_maxima: longest_streak(&fechas),
+        })
+    }
+}
+
+/// Estado de un logro para el usuario
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AchievementProgress {
+    #[serde(flatten)]
+    pub logro: Achievement,
+    pub desbloqueado: bool,
+    pub desbloqueado_en: Option<NaiveDateTime>,
+    pub metadata: Option<serde_json::Value>,
+    /// Valor actual de la métrica de la regla (limitado al objetivo)
+    pub progreso_actual: i64,
+    pub objetivo: i64,
+    pub porcentaje: f64,
+}
+
+/// Racha más larga de días consecutivos en una lista ordenada de fechas
+pub fn longest_streak(fechas: &[NaiveDate]) -> i64 {
+    let mut mejor = 0;
+    let mut actual = 0;
+    let mut anterior: Option<NaiveDate> = None;
+
+    for &fecha in fechas {
+        actual = match anterior {
+            Some(prev) if fecha == prev => actual,
+            Some(prev) if (fecha - prev).num_days() == 1 => actual + 1,
+            _ => 1,
+        };
+        mejor = mejor.max(actual);
+        anterior = Some(fecha);
+    }
+
+    mejor
+}
+
+/// Evalúa las reglas y registra los logros que el usuario acaba de desbloquear
+pub async fn evaluate_achievements(pool: &PgPool, user_email: &str) -> AppResult<Vec<Achievement>> {
+    let catalogo = db::list_achievements(pool).await?;
+    let desbloqueados: Vec<i32> = db::get_user_achievement_unlocks(pool, user_email)
+        .await?
+        .into_iter()
+        .map(|unlock| unlock.logro_id)
+        .collect();
+
+    let pendientes: Vec<(Achievement, AchievementRule)> = catalogo
+        .into_iter()
+        .filter(|logro| !desbloqueados.contains(&logro.id))
+        .filter_map(|logro| AchievementRule::for_code(&logro.codigo).map(|rule| (logro, rule)))
+        .collect();
+    if pendientes.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let activity = UserActivity::load(pool, user_email).await?;
+    let mut nuevos = Vec::new();
+
+    for (logro, rule) in pendientes {
+        if rule.progreso(&activity) < rule.objetivo() {
+            continue;
+        }
+        let metadata = rule.metadata(&activity);
+        if db::insert_user_achievement(pool, user_email, logro.id, &metadata).await? {
+            nuevos.push(logro);
+        }
+    }
+
+    if !nuevos.is_empty() {
+        tracing::info!("{} logros desbloqueados", nuevos.len());
+        if let Err(err) = notifications::notify_achievements(pool, user_email, &nuevos).await {
+            err.log();
+            tracing::warn!("No se pudieron notificar los logros desbloqueados");
+        }
+    }
+
+    Ok(nuevos)
+}
+
+/// Catálogo de logros con el estado y el progreso del usuario
+pub async fn get_user_achievements(
+    pool: &PgPool,
+    user_email: &str,
+) -> AppResult<Vec<AchievementProgress>> {
+    let catalogo = db::list_achievements(pool).await?;
+    let mut desbloqueados: HashMap<i32, _> = db::get_user_achievement_unlocks(pool, user_email)
+        .await?
+        .into_iter()
+        .map(|unlock| (unlock.logro_id, unlock))
+        .collect();
+    let activity = UserActivity::load(pool, user_email).await?;
+
+    let logros = catalogo
+        .into_iter()
+        .map(|logro| {
+            let unlock = desbloqueados.remove(&logro.id);
+            let rule = AchievementRule::for_code(&logro.codigo);
+            let objetivo = rule.map(AchievementRule::objetivo).unwrap_or(0);
+            let progreso_actual = match (&unlock, rule) {
+                (Some(_), _) => objetivo,
+                (None, Some(rule)) => rule.progreso(&activity).min(objetivo),
+                (None, None) => 0,
+            };
+            let porcentaje = if objetivo > 0 {
+                (progreso_actual as f64 * 100.0 / objetivo as f64 * 100.0).round() / 100.0
+            } else {
+                0.0
+            };
+
+            AchievementProgress {
+                logro,
+                desbloqueado: unlock.is_some(),
+                desbloqueado_en: unlock.as_ref().map(|u| u.desbloqueado_en),
+                metadata: unlock.and_then(|u| u.metadata).map(|m| m.0),
+                progreso_actual,
+                objetivo,
+                porcentaje,
+            }
+        })
+        .collect();
+
+    Ok(logros)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dia(d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, 3, d).unwrap()
+    }
+
+    #[test]
+    fn test_longest_streak() {
+        assert_eq!(longest_streak(&[]), 0);
+        assert_eq!(longest_streak(&[dia(5)]), 1);
+        assert_eq!(
+            longest_streak(&[dia(1), dia(2), dia(4), dia(5), dia(6), dia(7), dia(9)]),
+            4
+        );
+        // Fechas repetidas no rompen ni alargan la racha
+        assert_eq!(longest_streak(&[dia(1), dia(2), dia(2), dia(3)]), 3);
+        // La racha continúa al cambiar de mes
+        assert_eq!(
+            longest_streak(&[NaiveDate::from_ymd_opt(2025, 2, 28).unwrap(), dia(1)]),
+            2
+        );
+    }
+
+    #[test]
+    fn test_rules_cover_catalog() {
+        for codigo in [
+            "PRIMERA_COMPRA",
+            "COMPRAS_10",
+            "COMPRAS_50",
+            "COMPRAS_100",
+            "AHORRO_MES",
+            "RACHA_SEMANAL",
+        ] {
+            assert!(AchievementRule::for_code(codigo).is_some(), "{codigo}");
+        }
+        assert_eq!(
+            AchievementRule::for_code("RACHA_SEMANAL"),
+            Some(AchievementRule::RachaDiaria(7))
+        );
+    }
+}
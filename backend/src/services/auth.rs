@@ -11,6 +11,21 @@ pub struct JwtClaims {
     pub iat: usize,  // timestamp de emisión
 }
 
+/// Audiencia de los tokens que solo sirven para abrir el canal de eventos
+const EVENTS_AUDIENCE: &str = "ticket-events";
+
+/// Validez de un token de eventos: solo tiene que durar hasta abrir la conexión
+pub const EVENTS_TOKEN_SECS: i64 = 60;
+
+/// Claims del token de corta duración para el canal de eventos (SSE)
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EventsTokenClaims {
+    pub sub: String,
+    pub aud: String,
+    pub exp: usize,
+    pub iat: usize,
+}
+
 /// Hash una contraseña con bcrypt
 pub fn hash_password(password: &str) -> AppResult<String> {
     let hashed = bcrypt::hash(password, 12)?;
@@ -61,3 +76,68 @@ pub fn verify_jwt(token: &str, jwt_secret: &str) -> AppResult<JwtClaims> {
         }
     }
 }
+
+/// Genera el token de corta duración con el que `EventSource` abre el canal de eventos
+///
+/// Viaja en la URL, así que no vale como token de sesión: lleva su propia
+/// audiencia y caduca en `EVENTS_TOKEN_SECS`.
+pub fn generate_events_token(email: &str, jwt_secret: &str) -> AppResult<String> {
+    let now = Utc::now();
+    let claims = EventsTokenClaims {
+        sub: email.to_string(),
+        aud: EVENTS_AUDIENCE.to_string(),
+        exp: (now + Duration::seconds(EVENTS_TOKEN_SECS)).timestamp() as usize,
+        iat: now.timestamp() as usize,
+    };
+
+    let token = encode(
+        &Header::default(),
+        &claims,
+        &EncodingKey::from_secret(jwt_secret.as_bytes()),
+    )?;
+
+    Ok(token)
+}
+
+/// Verifica un token de eventos; rechaza los tokens de sesión
+pub fn verify_events_token(token: &str, jwt_secret: &str) -> AppResult<EventsTokenClaims> {
+    let mut validation = Validation::default();
+    validation.set_audience(&[EVENTS_AUDIENCE]);
+    validation.set_required_spec_claims(&["exp", "aud"]);
+    validation.leeway = 0;
+
+    match decode::<EventsTokenClaims>(
+        token,
+        &DecodingKey::from_secret(jwt_secret.as_bytes()),
+        &validation,
+    ) {
+        Ok(token_data) => Ok(token_data.claims),
+        Err(err) => {
+            tracing::warn!("Fallo al verificar el token de eventos: {}", err);
+            Err(AppError::Unauthorized(
+                "Token de eventos inválido o expirado".to_string(),
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_events_token_is_not_a_session_token() {
+        let secret = "test-secret";
+
+        let events = generate_events_token("test@example.com", secret).unwrap();
+        assert_eq!(
+            verify_events_token(&events, secret).unwrap().sub,
+            "test@example.com"
+        );
+        assert!(verify_jwt(&events, secret).is_err());
+
+        let session = generate_jwt("test@example.com", secret).unwrap();
+        assert!(verify_events_token(&session, secret).is_err());
+        assert!(verify_events_token(&events, "otro-secreto").is_err());
+    }
+}
@@ -0,0 +1,153 @@
+use crate::{db, error::AppResult};
+use serde::Deserialize;
+use sqlx::{PgConnection, PgPool};
+use std::sync::OnceLock;
+
+/// Reglas de categorización incluidas en el binario
+const BUNDLED_RULES: &str = include_str!("../../data/category_rules.json");
+
+/// Palabras clave que asignan una categoría
+#[derive(Debug, Clone, Deserialize)]
+pub struct CategoryRule {
+    pub categoria: String,
+    pub palabras: Vec<String>,
+}
+
+/// Conjunto de reglas de categorización por palabras clave
+#[derive(Debug, Clone)]
+pub struct CategoryRules {
+    rules: Vec<CategoryRule>,
+}
+
+impl CategoryRules {
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        let rules = serde_json::from_str(json)?;
+        Ok(Self { rules })
+    }
+
+    /// Reglas de `data/category_rules.json`
+    pub fn bundled() -> &'static CategoryRules {
+        static RULES: OnceLock<CategoryRules> = OnceLock::new();
+        RULES.get_or_init(|| {
+            CategoryRules::from_json(BUNDLED_RULES).expect("category_rules.json inválido")
+        })
+    }
+
+    /// Nombres de todas las categorías definidas
+    pub fn categories(&self) -> Vec<String> {
+        self.rules.iter().map(|r| r.categoria.clone()).collect()
+    }
+
+    /// Categoría de un producto según su nombre
+    ///
+    /// Una palabra clave coincide con palabras completas del nombre (admite
+    /// plurales en -S/-ES). Si coinciden varias gana la más larga, que es la
+    /// más específica ("TOMATE FRITO" frente a "TOMATE").
+    pub fn categorize(&self, nombre: &str) -> Option<&str> {
+        let tokens = tokenize(nombre);
+
+        self.rules
+            .iter()
+            .flat_map(|rule| rule.palabras.iter().map(move |p| (rule, p)))
+            .filter(|(_, palabra)| matches_keyword(&tokens, &tokenize(palabra)))
+            .fold(
+                None,
+                |best: Option<(&CategoryRule, usize)>, (rule, palabra)| match best {
+                    Some((_, len)) if len >= palabra.len() => best,
+                    _ => Some((rule, palabra.len())),
+                },
+            )
+            .map(|(rule, _)| rule.categoria.as_str())
+    }
+}
+
+/// Crea las categorías de las reglas y categoriza los productos pendientes
+///
+/// Se ejecuta al arrancar para que los productos anteriores a las reglas
+/// (o a una nueva versión de ellas) también tengan categoría.
+pub async fn seed_categories(pool: &PgPool) -> AppResult<u64> {
+    let rules = CategoryRules::bundled();
+    db::insert_categories(pool, &rules.categories()).await?;
+
+    let pendientes = db::get_uncategorized_products(pool, None).await?;
+    let mut asignados = 0;
+    for producto in &pendientes {
+        if let Some(categoria) = rules.categorize(producto) {
+            if db::assign_product_category(pool, producto, categoria).await? {
+                asignados += 1;
+            }
+        }
+    }
+
+    Ok(asignados)
+}
+
+/// Asigna categoría a los productos indicados que todavía no tienen
+pub async fn assign_categories(conn: &mut PgConnection, productos: &[String]) -> AppResult<()> {
+    let rules = CategoryRules::bundled();
+    let pendientes = db::get_uncategorized_products(&mut *conn, Some(productos)).await?;
+
+    for producto in &pendientes {
+        if let Some(categoria) = rules.categorize(producto) {
+            db::assign_product_category(&mut *conn, producto, categoria).await?;
+        }
+    }
+
+    Ok(())
+}
+
+fn tokenize(text: &str) -> Vec<String> {
+    text.to_uppercase()
+        .chars()
+        .map(|c| match c {
+            'Á' | 'À' | 'Ä' => 'A',
+            'É' | 'È' | 'Ë' => 'E',
+            'Í' | 'Ì' | 'Ï' => 'I',
+            'Ó' | 'Ò' | 'Ö' => 'O',
+            'Ú' | 'Ù' | 'Ü' => 'U',
+            c if c.is_alphanumeric() => c,
+            _ => ' ',
+        })
+        .collect::<String>()
+        .split_whitespace()
+        .map(str::to_string)
+        .collect()
+}
+
+fn matches_keyword(tokens: &[String], keyword: &[String]) -> bool {
+    let Some((last, rest)) = keyword.split_last() else {
+        return false;
+    };
+
+    tokens.windows(keyword.len()).any(|window| {
+        let (window_last, window_rest) = window.split_last().expect("ventana no vacía");
+        window_rest == rest
+            && (window_last == last
+                || window_last
+                    .strip_prefix(last.as_str())
+                    .is_some_and(|suffix| suffix == "S" || suffix == "ES"))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_categorize_with_bundled_rules() {
+        let rules = CategoryRules::bundled();
+
+        assert_eq!(rules.categorize("LECHE SEMIDESNATADA"), Some("Lácteos"));
+        assert_eq!(rules.categorize("PECHUGA POLLO FILETES"), Some("Carne"));
+        assert_eq!(rules.categorize("Jamón serrano"), Some("Charcutería"));
+        assert_eq!(rules.categorize("YOGURES GRIEGOS"), Some("Lácteos"));
+        // La regla más específica gana
+        assert_eq!(rules.categorize("TOMATE FRITO"), Some("Despensa"));
+        assert_eq!(rules.categorize("TOMATE PERA"), Some("Frutas y verduras"));
+        // Solo palabras completas: PANCETA no es pan
+        assert_eq!(rules.categorize("PANCETA TIRAS"), Some("Carne"));
+        // El sabor no es el producto: GELATINA es más específica que FRESA
+        assert_eq!(rules.categorize("GELATINA FRESA"), Some("Dulces y snacks"));
+        assert_eq!(rules.categorize("XYZ 123"), None);
+    }
+}
@@ -0,0 +1,203 @@
+use crate::{
+    db::{self, PersonalInflationData},
+    error::AppResult,
+    services::price_history::percentage_change,
+};
+use chrono::{Months, NaiveDate};
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+
+/// Peso y variación de precio de un producto dentro de la cesta personal
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InflationContribution {
+    pub producto: String,
+    /// Porcentaje del gasto de la cesta que representa el producto
+    pub peso: f64,
+    pub variacion_mensual: Option<f64>,
+    pub variacion_anual: Option<f64>,
+    /// Puntos porcentuales que aporta el producto a la inflación mensual
+    pub contribucion_mensual: Option<f64>,
+    /// Puntos porcentuales que aporta el producto a la inflación anual
+    pub contribucion_anual: Option<f64>,
+}
+
+/// Índice de inflación personal ponderado por el gasto del usuario
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PersonalInflationIndex {
+    pub inflacion_mensual: Option<f64>,
+    pub inflacion_anual: Option<f64>,
+    /// Inflación oficial de referencia configurada (`INFLATION_REFERENCE_RATE`)
+    pub inflacion_referencia: f64,
+    /// Inflación anual personal menos la de referencia, en puntos porcentuales
+    pub diferencia_vs_referencia: Option<f64>,
+    pub productos: Vec<InflationContribution>,
+}
+
+/// Calcula el índice de inflación personal del usuario a fecha `today`
+///
+/// La cesta son las compras desde `basket_from` o, si no se indica, las de
+/// los últimos 12 meses.
+pub async fn get_personal_inflation(
+    pool: &PgPool,
+    user_email: &str,
+    today: NaiveDate,
+    basket_from: Option<NaiveDate>,
+    reference_rate: f64,
+) -> AppResult<PersonalInflationIndex> {
+    let month_ago = today.checked_sub_months(Months::new(1)).unwrap_or(today);
+    let year_ago = today.checked_sub_months(Months::new(12)).unwrap_or(today);
+
+    let basket = db::get_personal_inflation_basket(
+        pool,
+        user_email,
+        today,
+        month_ago,
+        year_ago,
+        basket_from.unwrap_or(year_ago),
+    )
+    .await?;
+
+    Ok(compute_personal_inflation(&basket, reference_rate))
+}
+
+/// Calcula el índice a partir de la cesta del usuario
+///
+/// Cada variación se pondera por el gasto del producto. Los productos sin
+/// precio de referencia en el periodo no cuentan, y el peso del resto se
+/// reparte entre los que sí lo tienen.
+pub fn compute_personal_inflation(
+    basket: &[PersonalInflationData],
+    reference_rate: f64,
+) -> PersonalInflationIndex {
+    let total_gasto: Decimal = basket.iter().map(|item| item.gasto).sum();
+
+    let variaciones: Vec<_> = basket
+        .iter()
+        .map(|item| {
+            let current = item.precio_actual;
+            let change = |before: Option<Decimal>| percentage_change(before?, current?);
+            (
+                change(item.precio_mes_anterior),
+                change(item.precio_anio_anterior),
+            )
+        })
+        .collect();
+
+    // Gasto de los productos con precio de referencia en cada periodo
+    let (gasto_mensual, gasto_anual) = basket.iter().zip(&variaciones).fold(
+        (Decimal::ZERO, Decimal::ZERO),
+        |(mensual, anual), (item, (var_mensual, var_anual))| {
+            (
+                mensual + var_mensual.map_or(Decimal::ZERO, |_| item.gasto),
+                anual + var_anual.map_or(Decimal::ZERO, |_| item.gasto),
+            )
+        },
+    );
+
+    let contribution = |gasto: Decimal, covered: Decimal, variacion: Option<f64>| {
+        let share = ratio(gasto, covered)?;
+        variacion.map(|v| round2(share * v))
+    };
+
+    let productos: Vec<_> = basket
+        .iter()
+        .zip(&variaciones)
+        .map(|(item, &(mensual, anual))| InflationContribution {
+            producto: item.producto.clone(),
+            peso: ratio(item.gasto, total_gasto)
+                .map(|share| round2(share * 100.0))
+                .unwrap_or(0.0),
+            variacion_mensual: mensual,
+            variacion_anual: anual,
+            contribucion_mensual: contribution(item.gasto, gasto_mensual, mensual),
+            contribucion_anual: contribution(item.gasto, gasto_anual, anual),
+        })
+        .collect();
+
+    let index = |pick: fn(&InflationContribution) -> Option<f64>| {
+        let values: Vec<_> = productos.iter().filter_map(pick).collect();
+        (!values.is_empty()).then(|| round2(values.iter().sum()))
+    };
+    let inflacion_mensual = index(|p| p.contribucion_mensual);
+    let inflacion_anual = index(|p| p.contribucion_anual);
+
+    PersonalInflationIndex {
+        inflacion_mensual,
+        inflacion_anual,
+        inflacion_referencia: reference_rate,
+        diferencia_vs_referencia: inflacion_anual.map(|rate| round2(rate - reference_rate)),
+        productos,
+    }
+}
+
+fn ratio(part: Decimal, total: Decimal) -> Option<f64> {
+    if total.is_zero() {
+        return None;
+    }
+
+    (part / total).to_f64()
+}
+
+fn round2(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(
+        producto: &str,
+        gasto: i64,
+        actual: i64,
+        mes: Option<i64>,
+        anio: Option<i64>,
+    ) -> PersonalInflationData {
+        PersonalInflationData {
+            producto: producto.to_string(),
+            gasto: Decimal::new(gasto, 2),
+            precio_actual: Some(Decimal::new(actual, 2)),
+            precio_mes_anterior: mes.map(|c| Decimal::new(c, 2)),
+            precio_anio_anterior: anio.map(|c| Decimal::new(c, 2)),
+        }
+    }
+
+    #[test]
+    fn test_compute_personal_inflation() {
+        let basket = vec![
+            item("ACEITE", 3000, 110, Some(100), Some(100)),
+            item("LECHE", 1000, 100, Some(100), Some(80)),
+            item("PAN", 1000, 50, None, None),
+        ];
+
+        let index = compute_personal_inflation(&basket, 3.0);
+
+        // Mensual: solo ACEITE y LECHE tienen referencia (75% / 25%)
+        assert_eq!(index.inflacion_mensual, Some(7.5));
+        // Anual: 0.75 * 10 + 0.25 * 25
+        assert_eq!(index.inflacion_anual, Some(13.75));
+        assert_eq!(index.diferencia_vs_referencia, Some(10.75));
+
+        let aceite = &index.productos[0];
+        assert_eq!(aceite.peso, 60.0);
+        assert_eq!(aceite.contribucion_anual, Some(7.5));
+
+        let pan = &index.productos[2];
+        assert_eq!(pan.peso, 20.0);
+        assert_eq!(pan.contribucion_mensual, None);
+    }
+
+    #[test]
+    fn test_compute_personal_inflation_without_history() {
+        let basket = vec![item("PAN", 1000, 50, None, None)];
+
+        let index = compute_personal_inflation(&basket, 3.0);
+
+        assert_eq!(index.inflacion_mensual, None);
+        assert_eq!(index.inflacion_anual, None);
+        assert_eq!(index.diferencia_vs_referencia, None);
+        assert_eq!(index.productos.len(), 1);
+    }
+}
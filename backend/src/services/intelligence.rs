@@ -1,8 +1,9 @@
 use chrono::{Datelike, Timelike, Utc};
+use rust_decimal::prelude::ToPrimitive;
 use sqlx::PgPool;
 
 use crate::{
-    db::stats::get_top_products_by_quantity,
+    db::{stats::get_top_products_by_quantity, PurchaseFilter},
     services::intelligence_client::{
         IntelligenceClient, PredictRequest, PredictionResponse, SuggestedProduct, TicketFeature,
     },
@@ -108,7 +109,9 @@ impl IntelligenceService {
         let mut response = self.client.predict_next(req).await?;
 
         // Reemplazar/inyectar siempre con productos reales del usuario
-        let top_products = get_top_products_by_quantity(&self.pool, &user_email, 6).await?;
+        let top_products =
+            get_top_products_by_quantity(&self.pool, &user_email, 6, &PurchaseFilter::default())
+                .await?;
         tracing::info!(
             "Prediccion productos | usuario={} | top_items={}",
             user_email,
@@ -120,12 +123,13 @@ impl IntelligenceService {
                 .iter()
                 .filter_map(|p| p.cantidad_total)
                 .max()
-                .unwrap_or(1) as f64;
+                .and_then(|q| q.to_f64())
+                .unwrap_or(1.0);
 
             let suggestions: Vec<SuggestedProduct> = top_products
                 .into_iter()
                 .map(|p| {
-                    let qty = p.cantidad_total.unwrap_or(1) as f64;
+                    let qty = p.cantidad_total.and_then(|q| q.to_f64()).unwrap_or(1.0);
                     let probability = if max_qty > 0.0 { qty / max_qty } else { 0.5 };
 
                     // Usar precio_actual si existe, si no, usar precio_medio
@@ -104,9 +104,10 @@ impl IntelligenceClient {
 
             match request.send().await {
                 Ok(resp) => {
-                    if resp.status() == StatusCode::SERVICE_UNAVAILABLE
-                        && attempt < self.max_retries
-                    {
+                    if resp.status() == StatusCode::SERVICE_UNAVAILABLE {
+                        if attempt >= self.max_retries {
+                            return Err(IntelligenceClientError::ServiceUnavailable);
+                        }
                         attempt += 1;
                         sleep(Duration::from_millis(200 * attempt as u64)).await;
                         continue;
@@ -0,0 +1,82 @@
+use crate::db::IvaMonthlyPoint;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Base imponible y cuota de IVA pagadas en un año con un tipo impositivo
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IvaYearlyPoint {
+    pub year: String,
+    pub iva_porcentaje: Decimal,
+    pub base_imponible: Decimal,
+    pub cuota: Decimal,
+    pub total: Decimal,
+    pub tickets: i64,
+}
+
+/// Agrupa la serie mensual de IVA por año y tipo impositivo
+///
+/// Cada ticket pertenece a un único mes, así que sumar los tickets de los
+/// meses no cuenta ninguno dos veces.
+pub fn summarize_iva_by_year(mensual: &[IvaMonthlyPoint]) -> Vec<IvaYearlyPoint> {
+    let mut anual: BTreeMap<(&str, Decimal), IvaYearlyPoint> = BTreeMap::new();
+
+    for punto in mensual {
+        let year = punto.month.get(..4).unwrap_or(&punto.month);
+        let entry = anual
+            .entry((year, punto.iva_porcentaje))
+            .or_insert_with(|| IvaYearlyPoint {
+                year: year.to_string(),
+                iva_porcentaje: punto.iva_porcentaje,
+                base_imponible: Decimal::ZERO,
+                cuota: Decimal::ZERO,
+                total: Decimal::ZERO,
+                tickets: 0,
+            });
+        entry.base_imponible += punto.base_imponible;
+        entry.cuota += punto.cuota;
+        entry.total += punto.total;
+        entry.tickets += punto.tickets;
+    }
+
+    anual.into_values().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn punto(month: &str, iva: i64, cuota: i64, tickets: i64) -> IvaMonthlyPoint {
+        IvaMonthlyPoint {
+            month: month.to_string(),
+            iva_porcentaje: Decimal::new(iva, 0),
+            base_imponible: Decimal::new(cuota * 10, 2),
+            cuota: Decimal::new(cuota, 2),
+            total: Decimal::new(cuota * 11, 2),
+            tickets,
+        }
+    }
+
+    #[test]
+    fn test_summarize_iva_by_year() {
+        let anual = summarize_iva_by_year(&[
+            punto("2024-12", 21, 100, 1),
+            punto("2025-01", 4, 20, 2),
+            punto("2025-01", 21, 150, 2),
+            punto("2025-02", 21, 50, 1),
+        ]);
+
+        let resumen: Vec<_> = anual
+            .iter()
+            .map(|p| (p.year.as_str(), p.iva_porcentaje, p.cuota, p.tickets))
+            .collect();
+        assert_eq!(
+            resumen,
+            vec![
+                ("2024", Decimal::new(21, 0), Decimal::new(100, 2), 1),
+                ("2025", Decimal::new(4, 0), Decimal::new(20, 2), 2),
+                ("2025", Decimal::new(21, 0), Decimal::new(200, 2), 3),
+            ]
+        );
+    }
+}
@@ -1,10 +1,55 @@
+pub mod achievements;
 pub mod auth;
+pub mod categories;
+pub mod inflation;
 pub mod intelligence;
 pub mod intelligence_client;
+pub mod iva;
+pub mod notifications;
 pub mod ocr;
+pub mod ocr_jobs;
+pub mod package_sizes;
+pub mod period_comparison;
+pub mod preferences;
+pub mod price_history;
+pub mod product_merge;
+pub mod reports;
+pub mod savings_goals;
+pub mod ticket_correction;
+pub mod ticket_drafts;
+pub mod ticket_events;
+pub mod ticket_import;
 pub mod ticket_ingestion;
+pub mod ticket_storage;
 
-pub use auth::{generate_jwt, hash_password, verify_jwt, verify_password};
+pub use achievements::{evaluate_achievements, get_user_achievements, AchievementProgress};
+pub use auth::{
+    generate_events_token, generate_jwt, hash_password, verify_events_token, verify_jwt,
+    verify_password, EVENTS_TOKEN_SECS,
+};
+pub use categories::seed_categories;
+pub use inflation::{get_personal_inflation, PersonalInflationIndex};
 pub use intelligence_client::{IntelligenceClient, IntelligenceClientError};
+pub use iva::{summarize_iva_by_year, IvaYearlyPoint};
+pub use notifications::DraftExpiryNotifier;
 pub use ocr::{OcrProcessTicketRequest, OcrProcessTicketResponse, TicketProduct};
-pub use ticket_ingestion::{ingest_ticket, TicketIngestionResponse};
+pub use ocr_jobs::{enqueue_ocr_job, OcrJobWorker};
+pub use package_sizes::{backfill_package_sizes, ProductEquivalents, ShrinkflationAlert};
+pub use period_comparison::{compare_periods, PeriodComparison};
+pub use preferences::get_preferences;
+pub use price_history::{summarize_price_history, PriceSummary};
+pub use product_merge::ProductMergeResponse;
+pub use reports::ReportScheduler;
+pub use savings_goals::{get_current_goal_progress, SavingsGoalCloser, SavingsGoalProgress};
+pub use ticket_correction::correct_ticket;
+pub use ticket_drafts::{confirm_ticket_draft, create_ticket_draft};
+pub use ticket_events::{TicketEventBus, TicketEventMessage, TicketProgress, TicketStage};
+pub use ticket_import::{
+    expand_import_files, import_tickets, ImportFile, ImportLimits, TicketImportReport,
+};
+pub use ticket_ingestion::{
+    ingest_ocr_job_ticket, ingest_ticket, IvaMismatch, TicketFile, TicketIngestionResponse,
+};
+pub use ticket_storage::{
+    build_ticket_storage, migrate_ticket_files, StorageError, TicketStorages,
+};
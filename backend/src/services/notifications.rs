@@ -0,0 +1,252 @@
+use crate::{
+    db::{self, ExpiringTicketDraft, PriceJump},
+    error::AppResult,
+    models::{Achievement, Notification, NotificationInsert, NotificationKind, SpendingAlert},
+    services::preferences::get_preferences,
+};
+use chrono::Utc;
+use rust_decimal::Decimal;
+use serde_json::json;
+use sqlx::PgPool;
+use tokio::task::JoinHandle;
+
+/// Subida mínima (%) del precio unitario para avisar de un producto
+pub const PRICE_JUMP_MIN_PCT: Decimal = Decimal::from_parts(10, 0, 0, false, 0);
+
+/// Productos que se nombran en el aviso de subidas de precio
+const PRICE_JUMP_LISTED: usize = 3;
+
+/// Guarda una notificación para el usuario
+///
+/// Devuelve `None` si ya se había avisado de lo mismo (misma `clave`).
+pub async fn enqueue_notification(
+    pool: &PgPool,
+    notification: &NotificationInsert,
+) -> AppResult<Option<Notification>> {
+    let saved = db::insert_notification(pool, notification).await?;
+    if saved.is_some() {
+        tracing::debug!("Notificación {} encolada", notification.tipo.as_str());
+    }
+    Ok(saved)
+}
+
+/// Aviso de umbral de gasto mensual superado
+pub fn spending_alert_notification(user_email: &str, alert: &SpendingAlert) -> NotificationInsert {
+    NotificationInsert {
+        usuario_email: user_email.to_string(),
+        tipo: NotificationKind::AlertaGasto,
+        titulo: "Umbral de gasto superado".to_string(),
+        mensaje: format!(
+            "Llevas {:.2} € gastados en {}, por encima de tu umbral de {:.2} €.",
+            alert.gasto,
+            alert.mes.format("%m/%Y"),
+            alert.umbral
+        ),
+        datos: Some(json!({
+            "alerta_id": alert.id,
+            "mes": alert.mes,
+            "umbral": alert.umbral,
+            "gasto": alert.gasto,
+        })),
+        clave: Some(format!("alerta_gasto:{}", alert.id)),
+    }
+}
+
+/// Aviso de logro desbloqueado
+pub fn achievement_notification(user_email: &str, logro: &Achievement) -> NotificationInsert {
+    NotificationInsert {
+        usuario_email: user_email.to_string(),
+        tipo: NotificationKind::Logro,
+        titulo: format!("Nuevo logro: {}", logro.nombre),
+        mensaje: logro
+            .descripcion
+            .clone()
+            .unwrap_or_else(|| "Has desbloqueado un nuevo logro.".to_string()),
+        datos: Some(json!({ "logro_id": logro.id, "codigo": logro.codigo, "icono": logro.icono })),
+        clave: Some(format!("logro:{}", logro.codigo)),
+    }
+}
+
+/// Aviso con las subidas de precio de un ticket
+///
+/// Devuelve `None` si no hay subidas.
+pub fn price_jump_notification(
+    user_email: &str,
+    numero_factura: &str,
+    jumps: &[PriceJump],
+) -> Option<NotificationInsert> {
+    if jumps.is_empty() {
+        return None;
+    }
+
+    let mut listado: Vec<String> = jumps
+        .iter()
+        .take(PRICE_JUMP_LISTED)
+        .map(|jump| {
+            format!(
+                "{} (+{}%)",
+                jump.producto_nombre,
+                jump.variacion_porcentaje.round_dp(0)
+            )
+        })
+        .collect();
+    if jumps.len() > PRICE_JUMP_LISTED {
+        listado.push(format!("{} más", jumps.len() - PRICE_JUMP_LISTED));
+    }
+
+    let titulo = if jumps.len() == 1 {
+        "Un producto ha subido de precio".to_string()
+    } else {
+        format!("{} productos han subido de precio", jumps.len())
+    };
+
+    Some(NotificationInsert {
+        usuario_email: user_email.to_string(),
+        tipo: NotificationKind::SubidaPrecio,
+        titulo,
+        mensaje: format!("Respecto a tu compra anterior: {}.", listado.join(", ")),
+        datos: Some(json!({ "numero_factura": numero_factura, "productos": jumps })),
+        clave: Some(format!("subida_precio:{}", numero_factura)),
+    })
+}
+
+/// Aviso de borrador pendiente de confirmar que está a punto de caducar
+pub fn draft_expiry_notification(draft: &ExpiringTicketDraft) -> NotificationInsert {
+    NotificationInsert {
+        usuario_email: draft.usuario_email.clone(),
+        tipo: NotificationKind::BorradorCaduca,
+        titulo: "Borrador a punto de caducar".to_string(),
+        mensaje: format!(
+            "El ticket {} se descartará el {} (UTC) si no lo confirmas.",
+            draft.nombre_archivo,
+            draft.expira_en.format("%d/%m/%Y %H:%M")
+        ),
+        datos: Some(json!({ "borrador_id": draft.id, "expira_en": draft.expira_en })),
+        clave: Some(format!("borrador_caduca:{}", draft.id)),
+    }
+}
+
+/// Avisa de los logros recién desbloqueados si el usuario lo tiene activado
+pub async fn notify_achievements(
+    pool: &PgPool,
+    user_email: &str,
+    logros: &[Achievement],
+) -> AppResult<()> {
+    if logros.is_empty() || !get_preferences(pool, user_email).await?.notif_nuevos_logros {
+        return Ok(());
+    }
+
+    for logro in logros {
+        enqueue_notification(pool, &achievement_notification(user_email, logro)).await?;
+    }
+    Ok(())
+}
+
+/// Avisa de las subidas de precio de un ticket si el usuario lo tiene activado
+pub async fn notify_price_jumps(
+    pool: &PgPool,
+    user_email: &str,
+    numero_factura: &str,
+) -> AppResult<()> {
+    if !get_preferences(pool, user_email).await?.notif_inflacion {
+        return Ok(());
+    }
+
+    let jumps =
+        db::get_purchase_price_jumps(pool, user_email, numero_factura, PRICE_JUMP_MIN_PCT).await?;
+    if let Some(notification) = price_jump_notification(user_email, numero_factura, &jumps) {
+        enqueue_notification(pool, &notification).await?;
+    }
+    Ok(())
+}
+
+/// Tarea que avisa de los borradores de ticket a punto de caducar
+#[derive(Clone)]
+pub struct DraftExpiryNotifier {
+    pub pool: PgPool,
+    pub interval: std::time::Duration,
+    /// Antelación con la que se avisa antes de `expira_en`
+    pub notice: chrono::Duration,
+}
+
+impl DraftExpiryNotifier {
+    /// Arranca la tarea en tokio
+    pub fn spawn(self) -> JoinHandle<()> {
+        tokio::spawn(async move { self.run().await })
+    }
+
+    async fn run(self) {
+        tracing::info!("Avisos de borradores a punto de caducar iniciados");
+
+        loop {
+            if let Err(err) = self.notify_expiring().await {
+                err.log();
+                tracing::warn!("No se pudieron avisar los borradores a punto de caducar");
+            }
+
+            tokio::time::sleep(self.interval).await;
+        }
+    }
+
+    async fn notify_expiring(&self) -> AppResult<()> {
+        let hasta = Utc::now().naive_utc() + self.notice;
+        let drafts = db::get_expiring_ticket_drafts(&self.pool, hasta).await?;
+
+        let mut enviados = 0;
+        for draft in &drafts {
+            if enqueue_notification(&self.pool, &draft_expiry_notification(draft))
+                .await?
+                .is_some()
+            {
+                enviados += 1;
+            }
+        }
+        if enviados > 0 {
+            tracing::info!("{} avisos de borradores a punto de caducar", enviados);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn jump(nombre: &str, variacion: i64) -> PriceJump {
+        PriceJump {
+            producto_nombre: nombre.to_string(),
+            precio_anterior: Decimal::new(100, 2),
+            precio_actual: Decimal::new(100 + variacion, 2),
+            variacion_porcentaje: Decimal::new(variacion * 100, 2),
+        }
+    }
+
+    #[test]
+    fn test_price_jump_notification() {
+        assert!(price_jump_notification("a@example.com", "F-1", &[]).is_none());
+
+        let una = price_jump_notification("a@example.com", "F-1", &[jump("LECHE", 12)]).unwrap();
+        assert_eq!(una.tipo, NotificationKind::SubidaPrecio);
+        assert_eq!(una.titulo, "Un producto ha subido de precio");
+        assert_eq!(una.mensaje, "Respecto a tu compra anterior: LECHE (+12%).");
+        assert_eq!(una.clave.as_deref(), Some("subida_precio:F-1"));
+
+        let varias = price_jump_notification(
+            "a@example.com",
+            "F-2",
+            &[
+                jump("PAN", 30),
+                jump("LECHE", 20),
+                jump("HUEVOS", 15),
+                jump("ACEITE", 11),
+                jump("CAFE", 10),
+            ],
+        )
+        .unwrap();
+        assert_eq!(varias.titulo, "5 productos han subido de precio");
+        assert_eq!(
+            varias.mensaje,
+            "Respecto a tu compra anterior: PAN (+30%), LECHE (+20%), HUEVOS (+15%), 2 más."
+        );
+    }
+}
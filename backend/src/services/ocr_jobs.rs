@@ -0,0 +1,283 @@
+use crate::{
+    db,
+    error::{AppError, AppResult},
+    models::{AttachmentKind, OcrJob, OcrJobInsert, OcrJobStatus, OcrJobTask},
+    services::{
+        ingest_ocr_job_ticket, IntelligenceClient, IntelligenceClientError,
+        OcrProcessTicketRequest, TicketEventBus, TicketFile, TicketIngestionResponse,
+        TicketProgress, TicketStage, TicketStorages,
+    },
+};
+use base64::{engine::general_purpose, Engine as _};
+use chrono::{Duration, NaiveDateTime, Utc};
+use sqlx::PgPool;
+use tokio::task::JoinHandle;
+
+/// Espera máxima entre dos intentos de un mismo trabajo
+const MAX_RETRY_DELAY_SECS: i64 = 3600;
+
+/// Encola un ticket para procesarlo con OCR e ingestarlo en segundo plano
+pub async fn enqueue_ocr_job(
+    pool: &PgPool,
+    user_email: &str,
+    file: TicketFile,
+    replace_existing: bool,
+) -> AppResult<OcrJob> {
+    let kind = AttachmentKind::detect(
+        &file.nombre_archivo,
+        file.mime_type.as_deref(),
+        &file.contenido,
+    )
+    .map_err(AppError::InvalidTicketData)?;
+
+    if file.contenido.len() > kind.max_size() {
+        return Err(AppError::InvalidTicketData(format!(
+            "El archivo excede el tamaño máximo de {}MB para {} (tamaño actual: {} bytes)",
+            kind.max_size() / 1_048_576,
+            kind.mime_type(),
+            file.contenido.len()
+        )));
+    }
+
+    let job = db::insert_ocr_job(
+        pool,
+        &OcrJobInsert {
+            usuario_email: user_email.to_string(),
+            archivo: file.contenido,
+            nombre_archivo: file.nombre_archivo,
+            mime_type: Some(kind.mime_type().to_string()),
+            reemplazar: replace_existing,
+        },
+    )
+    .await?;
+
+    tracing::info!(trabajo = %job.id, "Trabajo de OCR encolado");
+
+    Ok(job)
+}
+
+/// Resultado de un intento fallido de procesar un trabajo
+enum JobFailure {
+    /// Fallo transitorio: el trabajo vuelve a la cola
+    Retry(AppError),
+    /// Fallo definitivo: el trabajo se marca como fallido
+    Fatal(AppError),
+}
+
+/// Worker que procesa la cola de trabajos de OCR
+#[derive(Clone)]
+pub struct OcrJobWorker {
+    pub pool: PgPool,
+    pub storage: TicketStorages,
+    pub client: IntelligenceClient,
+    pub events: TicketEventBus,
+    pub max_attempts: i32,
+    pub retry_base_secs: i64,
+    pub poll_interval: std::time::Duration,
+    /// Tiempo sin avanzar tras el que un trabajo en curso se da por interrumpido
+    pub lease: Duration,
+}
+
+impl OcrJobWorker {
+    /// Arranca el worker en una tarea de tokio
+    pub fn spawn(self) -> JoinHandle<()> {
+        tokio::spawn(async move { self.run().await })
+    }
+
+    async fn run(self) {
+        tracing::info!("Worker de trabajos de OCR iniciado");
+
+        loop {
+            match db::claim_next_ocr_job(&self.pool, Utc::now().naive_utc()).await {
+                Ok(Some(task)) => self.process(task).await,
+                Ok(None) => {
+                    self.requeue_stalled().await;
+                    tokio::time::sleep(self.poll_interval).await;
+                }
+                Err(err) => {
+                    tracing::error!("Error al leer la cola de trabajos de OCR: {}", err);
+                    tokio::time::sleep(self.poll_interval).await;
+                }
+            }
+        }
+    }
+
+    /// Devuelve a la cola los trabajos cuyo worker dejó de avanzar
+    async fn requeue_stalled(&self) {
+        let antes_de = Utc::now().naive_utc() - self.lease;
+        match db::requeue_stalled_ocr_jobs(&self.pool, antes_de, self.max_attempts).await {
+            Ok(stalled) => {
+                if stalled.reencolados > 0 {
+                    tracing::warn!(
+                        "{} trabajos de OCR interrumpidos vuelven a la cola",
+                        stalled.reencolados
+                    );
+                }
+                if stalled.fallidos > 0 {
+                    tracing::warn!(
+                        "{} trabajos de OCR interrumpidos agotaron sus intentos",
+                        stalled.fallidos
+                    );
+                }
+            }
+            Err(err) => tracing::error!("No se pudieron recuperar los trabajos de OCR: {}", err),
+        }
+    }
+
+    async fn process(&self, task: OcrJobTask) {
+        let id = task.id;
+        let attempt = task.intentos;
+        let progress =
+            self.events
+                .progress(&task.usuario_email, &id.to_string(), &task.nombre_archivo);
+
+        let result = match self.execute(task, &progress).await {
+            Ok(ingestion) => {
+                tracing::info!(trabajo = %id, "Trabajo de OCR completado");
+                db::complete_ocr_job(&self.pool, id, &ingestion).await
+            }
+            Err(JobFailure::Retry(err)) => {
+                let next = retry_at(Utc::now().naive_utc(), self.retry_base_secs, attempt);
+                tracing::warn!(
+                    trabajo = %id,
+                    intento = attempt,
+                    "Fallo transitorio en el trabajo de OCR; se reintentará"
+                );
+                db::schedule_ocr_job_retry(&self.pool, id, next, err.code(), &err.public_message())
+                    .await
+            }
+            Err(JobFailure::Fatal(err)) => {
+                err.log();
+                tracing::warn!(trabajo = %id, codigo = err.code(), "Trabajo de OCR fallido");
+                db::fail_ocr_job(&self.pool, id, err.code(), &err.public_message()).await
+            }
+        };
+
+        if let Err(err) = result {
+            tracing::error!("No se pudo actualizar el trabajo de OCR: {}", err);
+        }
+    }
+
+    /// Ejecuta un intento del trabajo
+    ///
+    /// Los fallos se publican en `progress` donde se producen; la ingesta
+    /// publica los suyos.
+    async fn execute(
+        &self,
+        mut task: OcrJobTask,
+        progress: &TicketProgress,
+    ) -> Result<TicketIngestionResponse, JobFailure> {
+        let fatal = |err: AppError| {
+            progress.failed(&err, false);
+            JobFailure::Fatal(err)
+        };
+
+        // Un intento anterior ya guardó la compra pero no llegó a cerrar el trabajo
+        if let Some(numero_factura) = task.numero_factura.as_deref() {
+            tracing::info!(trabajo = %task.id, "La compra del trabajo de OCR ya estaba ingestada");
+            let response = self
+                .already_ingested(&task, numero_factura)
+                .await
+                .map_err(fatal)?;
+            progress.stage(TicketStage::Ingested);
+            return Ok(response);
+        }
+
+        let contenido = task.archivo.take().ok_or_else(|| {
+            fatal(AppError::InternalError(
+                "Trabajo de OCR sin archivo".to_string(),
+            ))
+        })?;
+
+        let request = OcrProcessTicketRequest {
+            ticket_id: task.id.to_string(),
+            file_name: task.nombre_archivo.clone(),
+            file_content_b64: general_purpose::STANDARD.encode(&contenido),
+            mime_type: task.mime_type.clone(),
+        };
+
+        progress.stage(TicketStage::Ocr);
+        let ocr = match self.client.process_ticket(request).await {
+            Ok(ocr) => ocr,
+            Err(
+                err @ (IntelligenceClientError::Timeout
+                | IntelligenceClientError::ServiceUnavailable),
+            ) if task.intentos < self.max_attempts => {
+                let err = AppError::from(err);
+                progress.failed(&err, true);
+                return Err(JobFailure::Retry(err));
+            }
+            Err(err) => return Err(fatal(err.into())),
+        };
+        progress.ocr_completed(&ocr.warnings);
+
+        db::update_ocr_job_status(&self.pool, task.id, OcrJobStatus::Validating)
+            .await
+            .map_err(|e| fatal(e.into()))?;
+
+        let file = TicketFile {
+            contenido,
+            nombre_archivo: task.nombre_archivo.clone(),
+            mime_type: task.mime_type.clone(),
+        };
+
+        ingest_ocr_job_ticket(&self.pool, &self.storage, &task, file, ocr, progress)
+            .await
+            .map_err(JobFailure::Fatal)
+    }
+
+    /// Resultado de un trabajo cuya compra ya se guardó en un intento anterior
+    async fn already_ingested(
+        &self,
+        task: &OcrJobTask,
+        numero_factura: &str,
+    ) -> AppResult<TicketIngestionResponse> {
+        let purchase = db::get_purchase(&self.pool, numero_factura)
+            .await?
+            .filter(|purchase| purchase.usuario_email == task.usuario_email)
+            .ok_or_else(|| {
+                AppError::NotFound("La compra creada por el trabajo ya no existe".to_string())
+            })?;
+        let productos = db::get_purchase_products(&self.pool, numero_factura).await?;
+
+        Ok(TicketIngestionResponse {
+            ingested: true,
+            numero_factura: purchase.numero_factura,
+            total: purchase.total,
+            productos_insertados: productos.len(),
+            fecha_hora: purchase.fecha_hora,
+            replaced: task.reemplazar,
+            iva_descuadres: Vec::new(),
+            logros_desbloqueados: Vec::new(),
+            alerta_gasto: None,
+        })
+    }
+}
+
+/// Calcula el siguiente intento con espera exponencial
+fn retry_at(now: NaiveDateTime, base_secs: i64, attempt: i32) -> NaiveDateTime {
+    let exponent = attempt.saturating_sub(1).clamp(0, 16) as u32;
+    let delay = base_secs
+        .saturating_mul(2_i64.saturating_pow(exponent))
+        .min(MAX_RETRY_DELAY_SECS);
+
+    now + Duration::seconds(delay)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_backoff() {
+        let now = Utc::now().naive_utc();
+
+        assert_eq!(retry_at(now, 10, 1), now + Duration::seconds(10));
+        assert_eq!(retry_at(now, 10, 2), now + Duration::seconds(20));
+        assert_eq!(retry_at(now, 10, 4), now + Duration::seconds(80));
+        assert_eq!(
+            retry_at(now, 10, 30),
+            now + Duration::seconds(MAX_RETRY_DELAY_SECS)
+        );
+    }
+}
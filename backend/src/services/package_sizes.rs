@@ -0,0 +1,347 @@
+use crate::{
+    db::{self, ShrinkflationCandidate, UnitPriceItem},
+    error::{AppError, AppResult},
+    models::{ProductPackage, ProductUnit},
+    services::price_history::percentage_change,
+};
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use sqlx::{PgConnection, PgPool};
+use std::str::FromStr;
+
+/// Diferencia máxima entre precios de envase para considerarlos iguales
+const SHRINKFLATION_PRICE_TOLERANCE: Decimal = Decimal::from_parts(5, 0, 0, false, 2);
+
+/// Formato nuevo de un producto con menos contenido y el mismo precio
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ShrinkflationAlert {
+    pub nombre_base: String,
+    pub producto_anterior: String,
+    pub producto_nuevo: String,
+    pub unidad: String,
+    pub contenido_anterior: Decimal,
+    pub contenido_nuevo: Decimal,
+    pub precio_anterior: Decimal,
+    pub precio_nuevo: Decimal,
+    pub fecha_cambio: NaiveDate,
+    /// Reducción del contenido del envase (negativa)
+    pub variacion_contenido: Option<f64>,
+    /// Subida real del precio por kg o litro
+    pub variacion_precio_unidad_base: Option<f64>,
+}
+
+/// Comparativa de los formatos equivalentes a un producto
+#[derive(Debug, Clone, Serialize)]
+pub struct ProductEquivalents {
+    pub producto: String,
+    pub nombre_base: String,
+    pub unidad_base: Option<String>,
+    /// Formatos ordenados del más barato al más caro por kg o litro
+    pub equivalentes: Vec<UnitPriceItem>,
+    /// Formato más barato por kg o litro, si no es el propio producto
+    pub mas_barato: Option<String>,
+    /// Ahorro por kg o litro del formato más barato respecto al producto
+    pub ahorro_porcentaje: Option<f64>,
+}
+
+/// Extrae el tamaño de envase del nombre de un producto
+///
+/// Reconoce "AGUA 1,5L", "YOGUR 6X125G", "CERVEZA 33 CL" o "ATUN 3 X 80 G".
+/// El tamaño que aparece más a la derecha es el que cuenta.
+pub fn parse_package_size(nombre: &str) -> Option<ProductPackage> {
+    let tokens = tokenize(nombre);
+
+    for i in (0..tokens.len().saturating_sub(1)).rev() {
+        let Some(cantidad) = parse_number(&tokens[i].1) else {
+            continue;
+        };
+        let Some((unidad, factor)) = parse_size_unit(&tokens[i + 1].1) else {
+            continue;
+        };
+        if cantidad <= Decimal::ZERO {
+            continue;
+        }
+
+        // Multipack: "6 X 125 G"
+        let mut inicio = i;
+        let mut piezas = 1;
+        if i >= 2 && tokens[i - 1].1 == "X" {
+            if let Some(n) = tokens[i - 2].1.parse::<i32>().ok().filter(|n| *n > 0) {
+                piezas = n;
+                inicio = i - 2;
+            }
+        }
+
+        let contenido = (cantidad * factor * Decimal::from(piezas)).round_dp(3);
+        if contenido.is_zero() {
+            continue;
+        }
+
+        let nombre_base = join_tokens(
+            tokens[..inicio]
+                .iter()
+                .chain(&tokens[i + 2..])
+                .map(|(word, token)| (*word, token.as_str())),
+        );
+        if nombre_base.is_empty() {
+            return None;
+        }
+
+        return Some(ProductPackage {
+            nombre_base,
+            piezas,
+            contenido,
+            unidad,
+        });
+    }
+
+    None
+}
+
+/// Registra el tamaño de envase de todos los productos que aún no lo tienen
+///
+/// Se ejecuta al arrancar para los productos anteriores a esta extracción.
+pub async fn backfill_package_sizes(pool: &PgPool) -> AppResult<u64> {
+    let pendientes = db::get_products_without_package(pool, None).await?;
+
+    let mut registrados = 0;
+    for producto in &pendientes {
+        if let Some(envase) = parse_package_size(producto) {
+            if db::insert_product_package(pool, producto, &envase).await? {
+                registrados += 1;
+            }
+        }
+    }
+
+    Ok(registrados)
+}
+
+/// Registra el tamaño de envase de los productos indicados que no lo tienen
+pub async fn assign_package_sizes(conn: &mut PgConnection, productos: &[String]) -> AppResult<()> {
+    let pendientes = db::get_products_without_package(&mut *conn, Some(productos)).await?;
+
+    for producto in &pendientes {
+        if let Some(envase) = parse_package_size(producto) {
+            db::insert_product_package(&mut *conn, producto, &envase).await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Formatos de un producto en los que el envase ha encogido sin bajar de precio
+pub async fn get_shrinkflation_alerts(
+    pool: &PgPool,
+    user_email: &str,
+) -> AppResult<Vec<ShrinkflationAlert>> {
+    let candidates = db::get_shrinkflation_candidates(pool, user_email).await?;
+    Ok(detect_shrinkflation(candidates))
+}
+
+/// Filtra las parejas de formatos en las que el precio del envase se mantiene
+///
+/// El precio nuevo puede ser igual o mayor; si baja más de la tolerancia se
+/// entiende que es un formato distinto y no una reducción encubierta.
+pub fn detect_shrinkflation(candidates: Vec<ShrinkflationCandidate>) -> Vec<ShrinkflationAlert> {
+    candidates
+        .into_iter()
+        .filter(|c| {
+            c.precio_nuevo >= c.precio_anterior * (Decimal::ONE - SHRINKFLATION_PRICE_TOLERANCE)
+        })
+        .filter(|c| {
+            c.precio_nuevo <= c.precio_anterior * (Decimal::ONE + SHRINKFLATION_PRICE_TOLERANCE)
+        })
+        .map(|c| {
+            let precio_base_anterior = c.precio_anterior / c.contenido_anterior;
+            let precio_base_nuevo = c.precio_nuevo / c.contenido_nuevo;
+
+            ShrinkflationAlert {
+                variacion_contenido: percentage_change(c.contenido_anterior, c.contenido_nuevo),
+                variacion_precio_unidad_base: percentage_change(
+                    precio_base_anterior,
+                    precio_base_nuevo,
+                ),
+                nombre_base: c.nombre_base,
+                producto_anterior: c.producto_anterior,
+                producto_nuevo: c.producto_nuevo,
+                unidad: c.unidad,
+                contenido_anterior: c.contenido_anterior,
+                contenido_nuevo: c.contenido_nuevo,
+                precio_anterior: c.precio_anterior,
+                precio_nuevo: c.precio_nuevo,
+                fecha_cambio: c.fecha_cambio,
+            }
+        })
+        .collect()
+}
+
+/// Compara un producto con sus formatos equivalentes comprados por el usuario
+pub async fn get_product_equivalents(
+    pool: &PgPool,
+    user_email: &str,
+    producto: &str,
+) -> AppResult<ProductEquivalents> {
+    let equivalentes = db::get_equivalent_products(pool, user_email, producto).await?;
+
+    let referencia = equivalentes
+        .iter()
+        .find(|p| p.nombre == producto)
+        .cloned()
+        .ok_or_else(|| AppError::NotFound("Producto no encontrado".to_string()))?;
+
+    // La consulta devuelve primero el más barato
+    let mas_barato = equivalentes
+        .first()
+        .filter(|p| p.nombre != producto && p.precio_por_unidad_base.is_some());
+
+    let ahorro_porcentaje = mas_barato.and_then(|p| {
+        let change = percentage_change(
+            referencia.precio_por_unidad_base?,
+            p.precio_por_unidad_base?,
+        )?;
+        Some(-change)
+    });
+
+    Ok(ProductEquivalents {
+        producto: referencia.nombre,
+        nombre_base: referencia.nombre_base,
+        unidad_base: referencia.unidad_base,
+        mas_barato: mas_barato.map(|p| p.nombre.clone()),
+        ahorro_porcentaje,
+        equivalentes,
+    })
+}
+
+/// Unidades de tamaño admitidas y factor para pasar a kg o litros
+fn parse_size_unit(token: &str) -> Option<(ProductUnit, Decimal)> {
+    if token == "CL" {
+        return Some((ProductUnit::L, Decimal::new(1, 2)));
+    }
+
+    let unit = ProductUnit::parse(token)?;
+    unit.is_measured().then(|| unit.base())
+}
+
+fn parse_number(token: &str) -> Option<Decimal> {
+    if !token.starts_with(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+    Decimal::from_str(&token.replace(',', ".")).ok()
+}
+
+/// Separa el nombre en tokens recordando la palabra original de cada uno
+///
+/// Los números se separan de las letras ("6X125G" -> "6", "X", "125", "G")
+/// para poder reconstruir el resto del nombre tal y como venía.
+fn tokenize(nombre: &str) -> Vec<(usize, String)> {
+    let mut tokens = Vec::new();
+
+    for (word, text) in nombre.trim().to_uppercase().split_whitespace().enumerate() {
+        let mut current = String::new();
+        let mut numeric = false;
+        for c in text.chars() {
+            let is_number_part = c.is_ascii_digit() || (numeric && (c == ',' || c == '.'));
+            if !current.is_empty() && is_number_part != numeric {
+                tokens.push((word, std::mem::take(&mut current)));
+            }
+            numeric = is_number_part;
+            current.push(c);
+        }
+        if !current.is_empty() {
+            tokens.push((word, current));
+        }
+    }
+
+    tokens
+}
+
+/// Vuelve a unir los tokens respetando las palabras originales
+fn join_tokens<'a>(tokens: impl Iterator<Item = (usize, &'a str)>) -> String {
+    let mut nombre = String::new();
+    let mut last_word = None;
+
+    for (word, token) in tokens {
+        if last_word.is_some_and(|w| w != word) {
+            nombre.push(' ');
+        }
+        nombre.push_str(token);
+        last_word = Some(word);
+    }
+
+    nombre
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn package(
+        nombre_base: &str,
+        piezas: i32,
+        milesimas: i64,
+        unidad: ProductUnit,
+    ) -> ProductPackage {
+        ProductPackage {
+            nombre_base: nombre_base.to_string(),
+            piezas,
+            contenido: Decimal::new(milesimas, 3),
+            unidad,
+        }
+    }
+
+    #[test]
+    fn test_parse_package_size() {
+        assert_eq!(
+            parse_package_size("AGUA MINERAL 1,5L"),
+            Some(package("AGUA MINERAL", 1, 1500, ProductUnit::L))
+        );
+        assert_eq!(
+            parse_package_size("YOGUR GRIEGO 6X125G"),
+            Some(package("YOGUR GRIEGO", 6, 750, ProductUnit::Kg))
+        );
+        assert_eq!(
+            parse_package_size("CERVEZA 33 CL LATA"),
+            Some(package("CERVEZA LATA", 1, 330, ProductUnit::L))
+        );
+        assert_eq!(
+            parse_package_size("ATUN CLARO 3 X 80 G"),
+            Some(package("ATUN CLARO", 3, 240, ProductUnit::Kg))
+        );
+        // Las palabras con números que no son tamaños se conservan
+        assert_eq!(
+            parse_package_size("7UP ZERO 500ML"),
+            Some(package("7UP ZERO", 1, 500, ProductUnit::L))
+        );
+        assert_eq!(parse_package_size("PAN BARRA"), None);
+        assert_eq!(parse_package_size("HUEVOS L 12"), None);
+        assert_eq!(parse_package_size("1L"), None);
+    }
+
+    fn candidate(precio_anterior: i64, precio_nuevo: i64) -> ShrinkflationCandidate {
+        ShrinkflationCandidate {
+            nombre_base: "CAFE MOLIDO".to_string(),
+            producto_anterior: "CAFE MOLIDO 250G".to_string(),
+            producto_nuevo: "CAFE MOLIDO 220G".to_string(),
+            unidad: "kg".to_string(),
+            contenido_anterior: Decimal::new(250, 3),
+            contenido_nuevo: Decimal::new(220, 3),
+            precio_anterior: Decimal::new(precio_anterior, 2),
+            precio_nuevo: Decimal::new(precio_nuevo, 2),
+            fecha_cambio: NaiveDate::from_ymd_opt(2025, 3, 1).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_detect_shrinkflation() {
+        let alerts = detect_shrinkflation(vec![
+            candidate(220, 220),
+            // El envase pequeño es proporcionalmente más barato
+            candidate(220, 190),
+        ]);
+
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].variacion_contenido, Some(-12.0));
+        assert_eq!(alerts[0].variacion_precio_unidad_base, Some(13.64));
+    }
+}
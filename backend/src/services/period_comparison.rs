@@ -0,0 +1,206 @@
+use crate::{
+    db::{self, PurchaseFilter, TopProductItem, UserStats},
+    error::AppResult,
+    services::price_history::percentage_change,
+};
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use sqlx::PgPool;
+use std::collections::HashMap;
+
+/// Productos que se consultan por periodo antes de recortar la comparativa
+const PRODUCTS_PER_PERIOD: i64 = 1000;
+
+/// Métricas de un periodo
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PeriodSummary {
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+    pub total: Decimal,
+    pub tickets: i64,
+    pub ticket_medio: Option<Decimal>,
+    pub productos_unicos: i64,
+}
+
+/// Diferencias del periodo B respecto al periodo A
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PeriodDelta {
+    pub total: Decimal,
+    pub total_porcentaje: Option<f64>,
+    pub tickets: i64,
+    pub ticket_medio: Option<Decimal>,
+    pub ticket_medio_porcentaje: Option<f64>,
+}
+
+/// Gasto en un producto en cada periodo
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ProductPeriodComparison {
+    pub nombre: String,
+    pub gasto_a: Decimal,
+    pub gasto_b: Decimal,
+    pub diferencia: Decimal,
+    pub diferencia_porcentaje: Option<f64>,
+}
+
+/// Comparativa de dos periodos arbitrarios
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PeriodComparison {
+    pub a: PeriodSummary,
+    pub b: PeriodSummary,
+    pub diferencia: PeriodDelta,
+    /// Productos con más gasto en alguno de los dos periodos
+    pub productos: Vec<ProductPeriodComparison>,
+}
+
+/// Compara el gasto del usuario en los periodos `a` y `b`
+pub async fn compare_periods(
+    pool: &PgPool,
+    user_email: &str,
+    a: &PurchaseFilter,
+    b: &PurchaseFilter,
+    limit: usize,
+) -> AppResult<PeriodComparison> {
+    let stats_a = db::get_user_stats(pool, user_email, a).await?;
+    let stats_b = db::get_user_stats(pool, user_email, b).await?;
+    let products_a =
+        db::get_top_products_by_spending(pool, user_email, PRODUCTS_PER_PERIOD, a).await?;
+    let products_b =
+        db::get_top_products_by_spending(pool, user_email, PRODUCTS_PER_PERIOD, b).await?;
+
+    Ok(build_comparison(
+        summarize(a, &stats_a),
+        summarize(b, &stats_b),
+        &products_a,
+        &products_b,
+        limit,
+    ))
+}
+
+/// Calcula las diferencias entre dos periodos ya resumidos
+///
+/// Los productos se ordenan por el mayor gasto en cualquiera de los dos
+/// periodos; un producto ausente en uno de ellos cuenta con gasto cero.
+pub fn build_comparison(
+    a: PeriodSummary,
+    b: PeriodSummary,
+    products_a: &[TopProductItem],
+    products_b: &[TopProductItem],
+    limit: usize,
+) -> PeriodComparison {
+    let diferencia = PeriodDelta {
+        total: b.total - a.total,
+        total_porcentaje: percentage_change(a.total, b.total),
+        tickets: b.tickets - a.tickets,
+        ticket_medio: a.ticket_medio.zip(b.ticket_medio).map(|(a, b)| b - a),
+        ticket_medio_porcentaje: a
+            .ticket_medio
+            .zip(b.ticket_medio)
+            .and_then(|(a, b)| percentage_change(a, b)),
+    };
+
+    let spend = |p: &TopProductItem| p.gasto_total.unwrap_or(Decimal::ZERO);
+    let mut gastos: HashMap<&str, (Decimal, Decimal)> = HashMap::new();
+    for product in products_a {
+        gastos.entry(&product.nombre).or_default().0 += spend(product);
+    }
+    for product in products_b {
+        gastos.entry(&product.nombre).or_default().1 += spend(product);
+    }
+
+    let mut productos: Vec<_> = gastos
+        .into_iter()
+        .map(|(nombre, (gasto_a, gasto_b))| ProductPeriodComparison {
+            nombre: nombre.to_string(),
+            gasto_a,
+            gasto_b,
+            diferencia: gasto_b - gasto_a,
+            diferencia_porcentaje: percentage_change(gasto_a, gasto_b),
+        })
+        .collect();
+    productos.sort_by(|x, y| {
+        y.gasto_a
+            .max(y.gasto_b)
+            .cmp(&x.gasto_a.max(x.gasto_b))
+            .then_with(|| x.nombre.cmp(&y.nombre))
+    });
+    productos.truncate(limit);
+
+    PeriodComparison {
+        a,
+        b,
+        diferencia,
+        productos,
+    }
+}
+
+fn summarize(filter: &PurchaseFilter, stats: &UserStats) -> PeriodSummary {
+    PeriodSummary {
+        from: filter.from,
+        to: filter.to,
+        total: stats.total_gastado.unwrap_or(Decimal::ZERO),
+        tickets: stats.total_tickets.unwrap_or(0),
+        ticket_medio: stats.gasto_medio,
+        productos_unicos: stats.productos_unicos.unwrap_or(0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn summary(total: i64, tickets: i64) -> PeriodSummary {
+        PeriodSummary {
+            from: None,
+            to: None,
+            total: Decimal::new(total, 2),
+            tickets,
+            ticket_medio: (tickets > 0).then(|| Decimal::new(total / tickets, 2)),
+            productos_unicos: 0,
+        }
+    }
+
+    fn product(nombre: &str, centimos: i64) -> TopProductItem {
+        TopProductItem {
+            nombre: nombre.to_string(),
+            cantidad_total: Some(Decimal::ONE),
+            gasto_total: Some(Decimal::new(centimos, 2)),
+            precio_medio: None,
+            precio_actual: None,
+            unidad: None,
+            precio_unidad_medida: None,
+            unidad_base: None,
+            precio_por_unidad_base: None,
+        }
+    }
+
+    #[test]
+    fn test_build_comparison() {
+        let comparison = build_comparison(
+            summary(10000, 4),
+            summary(12000, 4),
+            &[product("LECHE", 2000), product("PAN", 500)],
+            &[product("LECHE", 2500), product("ACEITE", 3000)],
+            10,
+        );
+
+        assert_eq!(comparison.diferencia.total, Decimal::new(2000, 2));
+        assert_eq!(comparison.diferencia.total_porcentaje, Some(20.0));
+        assert_eq!(comparison.diferencia.tickets, 0);
+        assert_eq!(comparison.diferencia.ticket_medio_porcentaje, Some(20.0));
+
+        let nombres: Vec<_> = comparison
+            .productos
+            .iter()
+            .map(|p| p.nombre.as_str())
+            .collect();
+        assert_eq!(nombres, vec!["ACEITE", "LECHE", "PAN"]);
+
+        let pan = &comparison.productos[2];
+        assert_eq!(pan.gasto_b, Decimal::ZERO);
+        assert_eq!(pan.diferencia_porcentaje, Some(-100.0));
+
+        // Un producto nuevo no tiene variación porcentual
+        assert_eq!(comparison.productos[0].diferencia_porcentaje, None);
+    }
+}
@@ -0,0 +1,50 @@
+use crate::{
+    db,
+    error::AppResult,
+    models::{SavingsGoal, SpendingAlert, UserPreferences},
+    services::notifications,
+};
+use chrono::NaiveDate;
+use sqlx::PgPool;
+
+/// Preferencias del usuario, con los valores por defecto si nunca las guardó
+pub async fn get_preferences(pool: &PgPool, user_email: &str) -> AppResult<UserPreferences> {
+    Ok(db::get_user_preferences(pool, user_email)
+        .await?
+        .unwrap_or_default())
+}
+
+/// Comprueba si el gasto del mes de `fecha` ha superado el umbral del usuario
+///
+/// Devuelve la alerta creada, o `None` si las alertas están desactivadas, no
+/// hay umbral, no se ha superado o ese umbral ya avisó este mes.
+pub async fn check_spending_threshold(
+    pool: &PgPool,
+    user_email: &str,
+    fecha: NaiveDate,
+) -> AppResult<Option<SpendingAlert>> {
+    let preferences = get_preferences(pool, user_email).await?;
+    let Some(umbral) = preferences
+        .umbral_alerta_gasto
+        .filter(|_| preferences.alertas_gasto_activas)
+    else {
+        return Ok(None);
+    };
+
+    let mes = SavingsGoal::month_start(fecha);
+    let gasto = db::get_month_spending(pool, user_email, mes).await?;
+    if gasto < umbral {
+        return Ok(None);
+    }
+
+    let alert = db::insert_spending_alert(pool, user_email, mes, umbral, gasto).await?;
+    if let Some(alert) = &alert {
+        let notification = notifications::spending_alert_notification(user_email, alert);
+        if let Err(err) = notifications::enqueue_notification(pool, &notification).await {
+            err.log();
+            tracing::warn!("No se pudo notificar la alerta de gasto");
+        }
+    }
+
+    Ok(alert)
+}
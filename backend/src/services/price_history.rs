@@ -0,0 +1,120 @@
+use crate::db::PriceHistoryPoint;
+use chrono::{Months, NaiveDate};
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use serde::Serialize;
+
+/// Último cambio de precio registrado
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PriceChange {
+    pub fecha: NaiveDate,
+    pub precio_anterior: Decimal,
+    pub precio_nuevo: Decimal,
+    pub variacion_porcentaje: Option<f64>,
+}
+
+/// Resumen de la evolución del precio de un producto
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct PriceSummary {
+    pub precio_actual: Option<Decimal>,
+    pub precio_minimo: Option<Decimal>,
+    pub precio_maximo: Option<Decimal>,
+    pub ultimo_cambio: Option<PriceChange>,
+    pub variacion_3m: Option<f64>,
+    pub variacion_6m: Option<f64>,
+    pub variacion_12m: Option<f64>,
+}
+
+/// Calcula el resumen de una serie de precios ordenada por fecha
+///
+/// La variación a N meses compara el precio actual con el que estaba
+/// vigente hace N meses; es `None` si la serie no llega tan atrás.
+pub fn summarize_price_history(points: &[PriceHistoryPoint], today: NaiveDate) -> PriceSummary {
+    let Some(last) = points.last() else {
+        return PriceSummary::default();
+    };
+
+    let ultimo_cambio = points
+        .windows(2)
+        .rev()
+        .find(|pair| pair[0].precio != pair[1].precio)
+        .map(|pair| PriceChange {
+            fecha: pair[1].fecha,
+            precio_anterior: pair[0].precio,
+            precio_nuevo: pair[1].precio,
+            variacion_porcentaje: percentage_change(pair[0].precio, pair[1].precio),
+        });
+
+    let variacion = |months: u32| {
+        let cutoff = today.checked_sub_months(Months::new(months))?;
+        let reference = points.iter().rev().find(|p| p.fecha <= cutoff)?;
+        percentage_change(reference.precio, last.precio)
+    };
+
+    PriceSummary {
+        precio_actual: Some(last.precio),
+        precio_minimo: points.iter().map(|p| p.precio).min(),
+        precio_maximo: points.iter().map(|p| p.precio).max(),
+        ultimo_cambio,
+        variacion_3m: variacion(3),
+        variacion_6m: variacion(6),
+        variacion_12m: variacion(12),
+    }
+}
+
+/// Variación porcentual redondeada a dos decimales
+pub fn percentage_change(before: Decimal, after: Decimal) -> Option<f64> {
+    if before.is_zero() {
+        return None;
+    }
+
+    let change = ((after - before) / before * Decimal::ONE_HUNDRED).round_dp(2);
+    change.to_f64()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(fecha: &str, centimos: i64) -> PriceHistoryPoint {
+        PriceHistoryPoint {
+            fecha: NaiveDate::parse_from_str(fecha, "%Y-%m-%d").unwrap(),
+            precio: Decimal::new(centimos, 2),
+            fuente: Some("ticket".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_summarize_price_history() {
+        let today = NaiveDate::from_ymd_opt(2025, 6, 15).unwrap();
+        let points = vec![
+            point("2024-12-01", 100),
+            point("2025-02-10", 110),
+            point("2025-04-01", 110),
+            point("2025-06-01", 121),
+        ];
+
+        let summary = summarize_price_history(&points, today);
+
+        assert_eq!(summary.precio_actual, Some(Decimal::new(121, 2)));
+        assert_eq!(summary.precio_minimo, Some(Decimal::new(100, 2)));
+        assert_eq!(summary.precio_maximo, Some(Decimal::new(121, 2)));
+
+        let cambio = summary.ultimo_cambio.unwrap();
+        assert_eq!(cambio.fecha, point("2025-06-01", 0).fecha);
+        assert_eq!(cambio.precio_anterior, Decimal::new(110, 2));
+        assert_eq!(cambio.variacion_porcentaje, Some(10.0));
+
+        // Hace 3 meses (15 de marzo) estaba vigente el precio de febrero
+        assert_eq!(summary.variacion_3m, Some(10.0));
+        assert_eq!(summary.variacion_6m, Some(21.0));
+        // La serie no llega a 12 meses atrás
+        assert_eq!(summary.variacion_12m, None);
+    }
+
+    #[test]
+    fn test_summarize_empty_history() {
+        let today = NaiveDate::from_ymd_opt(2025, 6, 15).unwrap();
+        assert_eq!(summarize_price_history(&[], today), PriceSummary::default());
+    }
+}
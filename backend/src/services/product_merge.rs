@@ -0,0 +1,58 @@
+use crate::{
+    db::{self, ProductMergeStats},
+    error::{AppError, AppResult},
+    models::ProductUpsert,
+};
+use serde::Serialize;
+use sqlx::PgPool;
+
+/// Resultado de fusionar un producto en otro
+#[derive(Debug, Clone, Serialize)]
+pub struct ProductMergeResponse {
+    /// Producto que se conserva
+    pub producto: String,
+    /// Nombre fusionado, que queda como alias del producto
+    pub alias: String,
+    #[serde(flatten)]
+    pub stats: ProductMergeStats,
+}
+
+/// Fusiona el producto `origen` en `destino` dentro de una transacción
+pub async fn merge_products(
+    pool: &PgPool,
+    origen: &str,
+    destino: &str,
+) -> AppResult<ProductMergeResponse> {
+    let origen = ProductUpsert::normalize_name(origen);
+    let destino = ProductUpsert::normalize_name(destino);
+
+    if origen == destino {
+        return Err(AppError::BadRequest(
+            "No se puede fusionar un producto consigo mismo".to_string(),
+        ));
+    }
+
+    let mut tx = pool.begin().await?;
+
+    let existentes = db::lock_products(&mut tx, &[origen.clone(), destino.clone()]).await?;
+    if existentes.len() != 2 {
+        return Err(AppError::NotFound("Producto no encontrado".to_string()));
+    }
+
+    let stats = db::merge_products(&mut tx, &origen, &destino).await?;
+
+    tx.commit().await?;
+
+    tracing::info!(
+        lineas_movidas = stats.lineas_movidas,
+        lineas_fusionadas = stats.lineas_fusionadas,
+        precios_movidos = stats.precios_movidos,
+        "Productos fusionados"
+    );
+
+    Ok(ProductMergeResponse {
+        producto: destino,
+        alias: origen,
+        stats,
+    })
+}
@@ -0,0 +1,140 @@
+use crate::{db, models::ReportFrequency};
+use chrono::{Datelike, Days, Months, NaiveDate, Utc};
+use sqlx::PgPool;
+use tokio::task::JoinHandle;
+
+/// Periodo cerrado que cubre un informe
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReportPeriod {
+    pub desde: NaiveDate,
+    pub hasta: NaiveDate,
+    /// Inicio del periodo anterior, con el que se compara el gasto
+    pub anterior_desde: NaiveDate,
+}
+
+/// Último periodo completo anterior a `today` para una frecuencia
+///
+/// Las semanas van de lunes a domingo. `Nunca` no tiene periodo.
+pub fn report_period(frecuencia: ReportFrequency, today: NaiveDate) -> Option<ReportPeriod> {
+    match frecuencia {
+        ReportFrequency::Diaria => {
+            let desde = today.pred_opt()?;
+            Some(ReportPeriod {
+                desde,
+                hasta: desde,
+                anterior_desde: desde.pred_opt()?,
+            })
+        }
+        ReportFrequency::Semanal => {
+            let lunes = today - Days::new(u64::from(today.weekday().num_days_from_monday()));
+            let desde = lunes - Days::new(7);
+            Some(ReportPeriod {
+                desde,
+                hasta: lunes.pred_opt()?,
+                anterior_desde: desde - Days::new(7),
+            })
+        }
+        ReportFrequency::Mensual => {
+            let mes_actual = today.with_day(1)?;
+            let desde = mes_actual.checked_sub_months(Months::new(1))?;
+            Some(ReportPeriod {
+                desde,
+                hasta: mes_actual.pred_opt()?,
+                anterior_desde: desde.checked_sub_months(Months::new(1))?,
+            })
+        }
+        ReportFrequency::Nunca => None,
+    }
+}
+
+/// Tarea que genera los informes de gasto según `frecuencia_reportes`
+#[derive(Clone)]
+pub struct ReportScheduler {
+    pub pool: PgPool,
+    pub interval: std::time::Duration,
+}
+
+impl ReportScheduler {
+    /// Arranca la tarea en tokio
+    pub fn spawn(self) -> JoinHandle<()> {
+        tokio::spawn(async move { self.run().await })
+    }
+
+    async fn run(self) {
+        tracing::info!("Generación de informes periódicos iniciada");
+
+        loop {
+            let today = Utc::now().date_naive();
+            for frecuencia in [
+                ReportFrequency::Diaria,
+                ReportFrequency::Semanal,
+                ReportFrequency::Mensual,
+            ] {
+                let Some(periodo) = report_period(frecuencia, today) else {
+                    continue;
+                };
+                match db::generate_user_reports(
+                    &self.pool,
+                    frecuencia,
+                    periodo.desde,
+                    periodo.hasta,
+                    periodo.anterior_desde,
+                )
+                .await
+                {
+                    Ok(0) => {}
+                    Ok(n) => tracing::info!("{} informes {} generados", n, frecuencia.as_str()),
+                    Err(err) => tracing::error!("Error al generar los informes: {}", err),
+                }
+            }
+
+            tokio::time::sleep(self.interval).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fecha(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn test_report_period() {
+        // Miércoles 19 de marzo de 2025
+        let today = fecha(2025, 3, 19);
+
+        assert_eq!(
+            report_period(ReportFrequency::Diaria, today),
+            Some(ReportPeriod {
+                desde: fecha(2025, 3, 18),
+                hasta: fecha(2025, 3, 18),
+                anterior_desde: fecha(2025, 3, 17),
+            })
+        );
+        assert_eq!(
+            report_period(ReportFrequency::Semanal, today),
+            Some(ReportPeriod {
+                desde: fecha(2025, 3, 10),
+                hasta: fecha(2025, 3, 16),
+                anterior_desde: fecha(2025, 3, 3),
+            })
+        );
+        // Un lunes ya cierra la semana anterior
+        assert_eq!(
+            report_period(ReportFrequency::Semanal, fecha(2025, 3, 17)).map(|p| p.desde),
+            Some(fecha(2025, 3, 10))
+        );
+        assert_eq!(
+            report_period(ReportFrequency::Mensual, fecha(2025, 3, 1)),
+            Some(ReportPeriod {
+                desde: fecha(2025, 2, 1),
+                hasta: fecha(2025, 2, 28),
+                anterior_desde: fecha(2025, 1, 1),
+            })
+        );
+        assert_eq!(report_period(ReportFrequency::Nunca, today), None);
+    }
+}
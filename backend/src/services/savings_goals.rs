@@ -0,0 +1,142 @@
+use crate::{db, error::AppResult, models::SavingsGoal};
+use chrono::{Datelike, Months, NaiveDate, Utc};
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use tokio::task::JoinHandle;
+
+/// Progreso del objetivo de gasto del mes en curso
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavingsGoalProgress {
+    pub mes: NaiveDate,
+    pub objetivo_mensual: Decimal,
+    /// Gasto del mes hasta hoy
+    pub gastado: Decimal,
+    /// Lo que queda hasta el objetivo (negativo si ya se ha superado)
+    pub restante: Decimal,
+    /// Gasto estimado a final de mes si se mantiene el ritmo diario actual
+    pub proyeccion_fin_mes: Decimal,
+    /// Días que quedan del mes, hoy incluido
+    pub dias_restantes: i64,
+    /// Gasto diario que se puede permitir el resto del mes
+    pub disponible_diario: Decimal,
+    pub porcentaje_consumido: f64,
+    /// Indica si la proyección queda dentro del objetivo
+    pub en_camino: bool,
+}
+
+/// Calcula el progreso de un objetivo con el gasto acumulado a `today`
+pub fn goal_progress(
+    goal: &SavingsGoal,
+    gastado: Decimal,
+    today: NaiveDate,
+) -> SavingsGoalProgress {
+    let mes = SavingsGoal::month_start(today);
+    let dias_mes = mes
+        .checked_add_months(Months::new(1))
+        .map(|siguiente| (siguiente - mes).num_days())
+        .unwrap_or(30);
+    let dias_transcurridos = i64::from(today.day());
+    let dias_restantes = dias_mes - dias_transcurridos + 1;
+
+    let restante = goal.objetivo_mensual - gastado;
+    let proyeccion_fin_mes =
+        (gastado / Decimal::from(dias_transcurridos) * Decimal::from(dias_mes)).round_dp(2);
+    let disponible_diario =
+        (restante.max(Decimal::ZERO) / Decimal::from(dias_restantes)).round_dp(2);
+    let porcentaje_consumido = (gastado * Decimal::from(100) / goal.objetivo_mensual)
+        .round_dp(2)
+        .to_f64()
+        .unwrap_or(0.0);
+
+    SavingsGoalProgress {
+        mes: goal.mes,
+        objetivo_mensual: goal.objetivo_mensual,
+        gastado,
+        restante,
+        proyeccion_fin_mes,
+        dias_restantes,
+        disponible_diario,
+        porcentaje_consumido,
+        en_camino: proyeccion_fin_mes <= goal.objetivo_mensual,
+    }
+}
+
+/// Progreso del objetivo del mes de `today`, si el usuario lo tiene
+pub async fn get_current_goal_progress(
+    pool: &PgPool,
+    user_email: &str,
+    today: NaiveDate,
+) -> AppResult<Option<SavingsGoalProgress>> {
+    let mes = SavingsGoal::month_start(today);
+    let Some(goal) = db::get_savings_goal_for_month(pool, user_email, mes).await? else {
+        return Ok(None);
+    };
+
+    let gastado = db::get_month_spending(pool, user_email, mes).await?;
+    Ok(Some(goal_progress(&goal, gastado, today)))
+}
+
+/// Tarea que cierra periódicamente los objetivos de los meses terminados
+#[derive(Clone)]
+pub struct SavingsGoalCloser {
+    pub pool: PgPool,
+    pub interval: std::time::Duration,
+}
+
+impl SavingsGoalCloser {
+    /// Arranca la tarea en tokio
+    pub fn spawn(self) -> JoinHandle<()> {
+        tokio::spawn(async move { self.run().await })
+    }
+
+    async fn run(self) {
+        tracing::info!("Cierre de objetivos de ahorro iniciado");
+
+        loop {
+            let current_month = SavingsGoal::month_start(Utc::now().date_naive());
+            match db::close_savings_goals(&self.pool, current_month).await {
+                Ok(0) => {}
+                Ok(n) => tracing::info!("{} objetivos de ahorro cerrados", n),
+                Err(err) => tracing::error!("Error al cerrar los objetivos de ahorro: {}", err),
+            }
+
+            tokio::time::sleep(self.interval).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_goal_progress() {
+        let goal = SavingsGoal {
+            id: 1,
+            mes: NaiveDate::from_ymd_opt(2025, 4, 1).unwrap(),
+            objetivo_mensual: Decimal::new(30000, 2),
+            conseguido: false,
+            ahorro_real: None,
+            created_at: NaiveDate::from_ymd_opt(2025, 4, 1)
+                .unwrap()
+                .and_hms_opt(0, 0, 0)
+                .unwrap(),
+        };
+        let today = NaiveDate::from_ymd_opt(2025, 4, 10).unwrap();
+
+        let progress = goal_progress(&goal, Decimal::new(12000, 2), today);
+        assert_eq!(progress.restante, Decimal::new(18000, 2));
+        assert_eq!(progress.proyeccion_fin_mes, Decimal::new(36000, 2));
+        assert_eq!(progress.dias_restantes, 21);
+        assert_eq!(progress.disponible_diario, Decimal::new(857, 2));
+        assert_eq!(progress.porcentaje_consumido, 40.0);
+        assert!(!progress.en_camino);
+
+        // Objetivo superado: no queda nada disponible
+        let progress = goal_progress(&goal, Decimal::new(31000, 2), today);
+        assert_eq!(progress.restante, Decimal::new(-1000, 2));
+        assert_eq!(progress.disponible_diario, Decimal::ZERO);
+    }
+}
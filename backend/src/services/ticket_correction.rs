@@ -0,0 +1,707 @@
+use crate::{
+    db,
+    error::{AppError, AppResult},
+    models::{ProductUnit, ProductUpsert, PurchaseInsert, PurchaseProductInsert},
+    schema::{tickets::TicketLineUpdate, TicketUpdatePayload},
+    services::{
+        ticket_ingestion::{
+            check_iva_breakdown, parse_productos, to_utc_naive, upsert_line_products,
+            validate_line_prices, validate_totals, IvaMismatch, TicketLine,
+        },
+        TicketProduct,
+    },
+};
+use chrono::Utc;
+use rust_decimal::prelude::*;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Resumen de la corrección manual de un ticket
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TicketCorrectionSummary {
+    pub lineas_anadidas: usize,
+    pub lineas_modificadas: usize,
+    pub lineas_eliminadas: usize,
+    /// Tipos de IVA en los que las líneas corregidas no cuadran con el desglose del ticket
+    pub iva_descuadres: Vec<IvaMismatch>,
+}
+
+/// Aplica una corrección manual sobre un ticket ya ingestado
+///
+/// 1. Carga la compra y sus líneas actuales
+/// 2. Aplica en memoria los cambios de cabecera y de líneas; las líneas nuevas
+///    siguen el camino de la ingesta (alias, detalle de peso y unidades)
+///    Una línea nueva que resulta ser un alias de otra del ticket se suma a ella
+/// 3. Repite las validaciones de la ingesta (coherencia de precios, totales y
+///    desglose de IVA guardado)
+/// 4. Persiste en una transacción:
+///    - Cabecera de la compra
+///    - Líneas eliminadas, modificadas y añadidas
+///    - Recálculo del precio actual y del histórico derivados de las líneas
+///    - Histórico de precios con fuente `manual` para las líneas corregidas
+pub async fn correct_ticket(
+    pool: &PgPool,
+    user_email: &str,
+    numero_factura: &str,
+    payload: TicketUpdatePayload,
+) -> AppResult<TicketCorrectionSummary> {
+    let numero_factura = PurchaseInsert::normalize_invoice_number(numero_factura);
+
+    let mut tx = pool.begin().await?;
+
+    // 1. Cargar estado actual
+    let purchase = db::get_purchase(&mut *tx, &numero_factura)
+        .await?
+        .filter(|p| p.usuario_email == user_email)
+        .ok_or_else(|| AppError::NotFound("Ticket no encontrado".to_string()))?;
+
+    let mut lineas: BTreeMap<String, PurchaseProductInsert> =
+        db::get_purchase_products(&mut *tx, &numero_factura)
+            .await?
+            .into_iter()
+            .map(|p| {
+                (
+                    p.producto_nombre.clone(),
+                    PurchaseProductInsert {
+                        producto_nombre: p.producto_nombre,
+                        cantidad: p.cantidad,
+                        precio_unitario: p.precio_unitario,
+                        precio_total: p.precio_total,
+                        descuento: p.descuento,
+                        iva_porcentaje: p.iva_porcentaje,
+                        iva_importe: p.iva_importe,
+                    },
+                )
+            })
+            .collect();
+    let originales: BTreeSet<String> = lineas.keys().cloned().collect();
+
+    // 2. Aplicar cambios de líneas
+    for nombre in &payload.eliminar_productos {
+        let nombre = ProductUpsert::normalize_name(nombre);
+        if lineas.remove(&nombre).is_none() {
+            return Err(AppError::BadRequest(format!(
+                "La linea {} no existe en el ticket",
+                nombre
+            )));
+        }
+    }
+
+    let (existentes, nuevas): (Vec<&TicketLineUpdate>, Vec<&TicketLineUpdate>) = payload
+        .productos
+        .iter()
+        .partition(|l| lineas.contains_key(&ProductUpsert::normalize_name(&l.nombre)));
+
+    let mut editadas = BTreeSet::new();
+    for linea in existentes {
+        let producto = build_line(
+            linea,
+            lineas.get(&ProductUpsert::normalize_name(&linea.nombre)),
+        )?;
+
+        if !editadas.insert(producto.producto_nombre.clone()) {
+            return Err(AppError::BadRequest(format!(
+                "La linea {} aparece repetida",
+                producto.producto_nombre
+            )));
+        }
+
+        lineas.insert(producto.producto_nombre.clone(), producto);
+    }
+
+    let nuevas = nuevas
+        .into_iter()
+        .map(new_ticket_product)
+        .collect::<AppResult<Vec<_>>>()?;
+    let mut unidades: BTreeMap<String, ProductUnit> = BTreeMap::new();
+    for linea in parse_productos(pool, &nuevas).await? {
+        let nombre = linea.producto.producto_nombre.clone();
+
+        // Igual que en la ingesta, dos líneas del mismo producto se suman
+        match lineas.get_mut(&nombre) {
+            Some(existente) => existente.absorb(&linea.producto),
+            None => {
+                unidades.insert(nombre.clone(), linea.unidad);
+                lineas.insert(nombre.clone(), linea.producto);
+            }
+        }
+
+        editadas.insert(nombre);
+    }
+
+    let finales: BTreeSet<String> = lineas.keys().cloned().collect();
+    let eliminadas: Vec<String> = originales.difference(&finales).cloned().collect();
+    let anadidas: Vec<String> = finales.difference(&originales).cloned().collect();
+    let modificadas: Vec<String> = editadas.intersection(&originales).cloned().collect();
+
+    // Cabecera
+    let fecha_hora = payload
+        .fecha_hora
+        .map(to_utc_naive)
+        .unwrap_or(purchase.fecha_hora);
+    if fecha_hora > Utc::now().naive_utc() {
+        return Err(AppError::InvalidTicketData(
+            "La fecha del ticket no puede ser futura".to_string(),
+        ));
+    }
+
+    let tienda = match payload.tienda {
+        Some(tienda) => Some(tienda.trim().to_string()).filter(|t| !t.is_empty()),
+        None => purchase.tienda.clone(),
+    };
+
+    let metodo_pago = match payload.metodo_pago {
+        Some(metodo) if metodo.trim().is_empty() => None,
+        Some(metodo) => Some(
+            PurchaseInsert::normalize_payment_method(&metodo).ok_or_else(|| {
+                AppError::BadRequest(format!("Metodo de pago no valido: {}", metodo))
+            })?,
+        ),
+        None => purchase.metodo_pago.clone(),
+    };
+
+    // 3. Mismas validaciones que la ingesta
+    let productos: Vec<PurchaseProductInsert> = lineas.into_values().collect();
+
+    if productos.is_empty() && purchase.total > Decimal::ZERO {
+        return Err(AppError::InvalidTicketData(
+            "El ticket tiene un total mayor a 0 pero no contiene productos".to_string(),
+        ));
+    }
+
+    validate_line_prices(&productos)?;
+    validate_totals(&productos, purchase.total)?;
+
+    let desglose_iva = db::get_purchase_iva_breakdown(&mut *tx, &numero_factura).await?;
+    let iva_descuadres = check_iva_breakdown(&productos, &desglose_iva);
+    if !iva_descuadres.is_empty() {
+        tracing::warn!(
+            "El IVA de las lineas corregidas no cuadra con el desglose del ticket en {} tipos",
+            iva_descuadres.len()
+        );
+    }
+
+    // 4. Persistir cambios
+    let fecha_anterior = purchase.fecha_hora.date();
+    let fecha_nueva = fecha_hora.date();
+    let cambia_fecha = fecha_anterior != fecha_nueva;
+
+    db::update_purchase_header(
+        &mut *tx,
+        &numero_factura,
+        fecha_hora,
+        tienda.as_deref(),
+        metodo_pago.as_deref(),
+    )
+    .await?;
+
+    if !eliminadas.is_empty() {
+        db::delete_purchase_products(&mut *tx, &numero_factura, &eliminadas).await?;
+    }
+
+    for producto in productos
+        .iter()
+        .filter(|p| modificadas.contains(&p.producto_nombre))
+    {
+        db::update_purchase_product(&mut *tx, &numero_factura, producto).await?;
+    }
+
+    let nuevas: Vec<TicketLine> = productos
+        .iter()
+        .filter(|p| anadidas.contains(&p.producto_nombre))
+        .map(|p| TicketLine {
+            producto: p.clone(),
+            unidad: unidades
+                .get(&p.producto_nombre)
+                .copied()
+                .unwrap_or(ProductUnit::Unidad),
+        })
+        .collect();
+
+    upsert_line_products(&mut tx, &nuevas).await?;
+
+    let nuevas: Vec<PurchaseProductInsert> = nuevas.into_iter().map(|l| l.producto).collect();
+    db::insert_purchase_products(&mut tx, &numero_factura, &nuevas).await?;
+
+    // Si cambia la fecha todas las líneas afectan al histórico de ambos días
+    let afectados: Vec<String> = if cambia_fecha {
+        originales.union(&finales).cloned().collect()
+    } else {
+        eliminadas
+            .iter()
+            .chain(&anadidas)
+            .chain(&modificadas)
+            .cloned()
+            .collect()
+    };
+
+    db::refresh_derived_prices(&mut tx, &afectados, fecha_anterior).await?;
+
+    for producto in productos
+        .iter()
+        .filter(|p| cambia_fecha || editadas.contains(&p.producto_nombre))
+    {
+        db::upsert_price_history(
+            &mut *tx,
+            &producto.producto_nombre,
+            fecha_nueva,
+            producto.precio_unitario,
+            "manual",
+        )
+        .await?;
+    }
+
+    tx.commit().await?;
+
+    tracing::info!(
+        "Ticket corregido manualmente ({} anadidas, {} modificadas, {} eliminadas)",
+        anadidas.len(),
+        modificadas.len(),
+        eliminadas.len()
+    );
+
+    Ok(TicketCorrectionSummary {
+        lineas_anadidas: anadidas.len(),
+        lineas_modificadas: modificadas.len(),
+        lineas_eliminadas: eliminadas.len(),
+        iva_descuadres,
+    })
+}
+
+/// Convierte una línea nueva en un producto de ticket como los que devuelve el OCR
+///
+/// Sin precio total se calcula sobre la cantidad en kg o litros, que es a lo
+/// que se refiere el precio unitario de las líneas a peso.
+fn new_ticket_product(linea: &TicketLineUpdate) -> AppResult<TicketProduct> {
+    let unidad = linea.unidad.as_deref().unwrap_or_default();
+    let (_, factor) = ProductUnit::parse(unidad)
+        .ok_or_else(|| AppError::BadRequest(format!("Unidad no valida: {}", unidad)))?
+        .base();
+
+    if linea.precio_unitario < Decimal::ZERO {
+        return Err(AppError::InvalidTicketData(format!(
+            "Precio unitario inválido: {}",
+            linea.precio_unitario
+        )));
+    }
+
+    let descuento = linea.descuento.unwrap_or(Decimal::ZERO);
+    if descuento < Decimal::ZERO {
+        return Err(AppError::InvalidTicketData(format!(
+            "Descuento inválido: {}",
+            descuento
+        )));
+    }
+
+    let precio_total = linea.precio_total.unwrap_or_else(|| {
+        (linea.precio_unitario * linea.cantidad * factor - descuento).round_dp(2)
+    });
+
+    let to_f64 = |valor: Decimal| valor.to_f64().unwrap_or_default();
+
+    Ok(TicketProduct {
+        nombre: linea.nombre.clone(),
+        cantidad: to_f64(linea.cantidad),
+        unidad: unidad.to_string(),
+        precio_unitario: to_f64(linea.precio_unitario),
+        precio_total: to_f64(precio_total),
+        descuento: to_f64(descuento),
+        iva_porcentaje: to_f64(linea.iva_porcentaje.unwrap_or(Decimal::ZERO)),
+        // Se calcula a partir del porcentaje igual que en la ingesta
+        iva_importe: 0.0,
+    })
+}
+
+/// Construye una línea validada a partir de la corrección y la línea existente
+fn build_line(
+    linea: &TicketLineUpdate,
+    existente: Option<&PurchaseProductInsert>,
+) -> AppResult<PurchaseProductInsert> {
+    let nombre = ProductUpsert::normalize_name(&linea.nombre);
+
+    if nombre.is_empty() {
+        return Err(AppError::InvalidTicketData(
+            "Producto con nombre vacío".to_string(),
+        ));
+    }
+
+    if linea.cantidad <= Decimal::ZERO {
+        return Err(AppError::InvalidTicketData(format!(
+            "Cantidad inválida: {}",
+            linea.cantidad
+        )));
+    }
+
+    if linea.precio_unitario < Decimal::ZERO {
+        return Err(AppError::InvalidTicketData(format!(
+            "Precio unitario inválido: {}",
+            linea.precio_unitario
+        )));
+    }
+
+    let descuento = linea
+        .descuento
+        .or(existente.map(|e| e.descuento))
+        .unwrap_or(Decimal::ZERO);
+
+    if descuento < Decimal::ZERO {
+        return Err(AppError::InvalidTicketData(format!(
+            "Descuento inválido: {}",
+            descuento
+        )));
+    }
+
+    let precio_total = linea
+        .precio_total
+        .unwrap_or_else(|| (linea.precio_unitario * linea.cantidad - descuento).round_dp(2));
+
+    let iva_porcentaje = PurchaseProductInsert::normalize_iva_percentage(
+        linea
+            .iva_porcentaje
+            .or(existente.map(|e| e.iva_porcentaje))
+            .unwrap_or(Decimal::ZERO),
+    );
+
+    // Mismo cálculo que la ingesta cuando el OCR no aporta el importe de IVA
+    let iva_importe = PurchaseProductInsert::calculate_iva_importe(precio_total, iva_porcentaje);
+
+    let producto = PurchaseProductInsert {
+        producto_nombre: nombre,
+        cantidad: linea.cantidad,
+        precio_unitario: linea.precio_unitario,
+        precio_total,
+        descuento,
+        iva_porcentaje,
+        iva_importe,
+    };
+
+    if !producto.validate_price_coherence() {
+        return Err(AppError::InvalidTicketData(format!(
+            "Precios incoherentes en {}: {} x {} - {} != {}",
+            producto.producto_nombre,
+            producto.precio_unitario,
+            producto.cantidad,
+            producto.descuento,
+            producto.precio_total
+        )));
+    }
+
+    Ok(producto)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::PurchaseIvaInsert;
+    use chrono::{Duration, NaiveDate, NaiveDateTime};
+
+    fn linea(unidad: Option<&str>) -> TicketLineUpdate {
+        TicketLineUpdate {
+            nombre: "naranja".to_string(),
+            cantidad: Decimal::from(500),
+            unidad: unidad.map(str::to_string),
+            precio_unitario: Decimal::new(748, 2),
+            precio_total: None,
+            descuento: None,
+            iva_porcentaje: None,
+        }
+    }
+
+    #[test]
+    fn test_new_ticket_product_measured_total() {
+        let producto = new_ticket_product(&linea(Some("g"))).unwrap();
+
+        assert_eq!(producto.unidad, "g");
+        assert_eq!(producto.cantidad, 500.0);
+        assert_eq!(producto.precio_total, 3.74);
+
+        let producto = new_ticket_product(&linea(None)).unwrap();
+        assert_eq!(producto.precio_total, 3740.0);
+
+        assert!(matches!(
+            new_ticket_product(&linea(Some("caja"))),
+            Err(AppError::BadRequest(_))
+        ));
+    }
+
+    fn linea_ticket(nombre: &str, cantidad: i64, centimos: i64, iva: i64) -> PurchaseProductInsert {
+        let precio_total = Decimal::new(cantidad * centimos, 2);
+        let iva_porcentaje = Decimal::new(iva, 0);
+        PurchaseProductInsert {
+            producto_nombre: nombre.to_string(),
+            cantidad: Decimal::from(cantidad),
+            precio_unitario: Decimal::new(centimos, 2),
+            precio_total,
+            descuento: Decimal::ZERO,
+            iva_porcentaje,
+            iva_importe: PurchaseProductInsert::calculate_iva_importe(precio_total, iva_porcentaje),
+        }
+    }
+
+    fn correccion(nombre: &str, cantidad: i64, centimos: i64) -> TicketLineUpdate {
+        TicketLineUpdate {
+            nombre: nombre.to_string(),
+            cantidad: Decimal::from(cantidad),
+            unidad: None,
+            precio_unitario: Decimal::new(centimos, 2),
+            precio_total: None,
+            descuento: None,
+            iva_porcentaje: None,
+        }
+    }
+
+    fn payload(productos: Vec<TicketLineUpdate>, eliminar: &[&str]) -> TicketUpdatePayload {
+        TicketUpdatePayload {
+            fecha_hora: None,
+            tienda: None,
+            metodo_pago: None,
+            productos,
+            eliminar_productos: eliminar.iter().map(|n| n.to_string()).collect(),
+        }
+    }
+
+    /// Ticket de 6,00 € ingestado hace diez días con su histórico y desglose de IVA
+    async fn setup_ticket(pool: &PgPool) -> sqlx::Result<NaiveDateTime> {
+        sqlx::query!(
+            "INSERT INTO usuarios (email, password_hash, nombre) VALUES ($1, $2, $3)",
+            "test@example.com",
+            "$2b$12$KpIEW.jQKvqXfN5nDwAXLub8RCRYjqNvCLKXfzHpFGK2FQJGmqQJi",
+            "Test User"
+        )
+        .execute(pool)
+        .await?;
+
+        let fecha_hora = (Utc::now() - Duration::days(10))
+            .date_naive()
+            .and_hms_opt(12, 0, 0)
+            .unwrap();
+        let lineas = vec![
+            linea_ticket("LECHE SEMIDESNATADA", 2, 100, 4),
+            linea_ticket("PAN", 1, 150, 4),
+            linea_ticket("HUEVOS", 1, 250, 10),
+        ];
+
+        let mut tx = pool.begin().await?;
+        db::insert_purchase(
+            &mut *tx,
+            &PurchaseInsert {
+                numero_factura: "F-1".to_string(),
+                usuario_email: "test@example.com".to_string(),
+                fecha_hora,
+                total: Decimal::new(600, 2),
+                tienda: None,
+                ubicacion: None,
+                metodo_pago: None,
+                numero_operacion: None,
+            },
+        )
+        .await?;
+        for linea in &lineas {
+            db::upsert_product(
+                &mut *tx,
+                &ProductUpsert {
+                    nombre: linea.producto_nombre.clone(),
+                    marca: None,
+                    unidad: "unidad".to_string(),
+                    precio_actual: Some(linea.precio_unitario),
+                },
+            )
+            .await?;
+            db::upsert_price_history(
+                &mut *tx,
+                &linea.producto_nombre,
+                fecha_hora.date(),
+                linea.precio_unitario,
+                "ticket",
+            )
+            .await?;
+        }
+        db::insert_purchase_products(&mut tx, "F-1", &lineas).await?;
+        db::insert_purchase_iva(
+            &mut tx,
+            "F-1",
+            &[
+                PurchaseIvaInsert {
+                    iva_porcentaje: Decimal::new(4, 0),
+                    base_imponible: Decimal::new(337, 2),
+                    cuota: Decimal::new(13, 2),
+                },
+                PurchaseIvaInsert {
+                    iva_porcentaje: Decimal::new(10, 0),
+                    base_imponible: Decimal::new(227, 2),
+                    cuota: Decimal::new(23, 2),
+                },
+            ],
+        )
+        .await?;
+        tx.commit().await?;
+
+        Ok(fecha_hora)
+    }
+
+    async fn historico(pool: &PgPool, nombre: &str) -> sqlx::Result<Vec<(NaiveDate, String)>> {
+        Ok(sqlx::query!(
+            "SELECT fecha_vigencia, fuente FROM historico_precios WHERE producto_nombre = $1 ORDER BY fecha_vigencia",
+            nombre
+        )
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .map(|r| (r.fecha_vigencia, r.fuente.unwrap_or_default()))
+        .collect())
+    }
+
+    async fn linea_guardada(
+        pool: &PgPool,
+        nombre: &str,
+    ) -> sqlx::Result<Option<crate::models::PurchaseProduct>> {
+        Ok(db::get_purchase_products(pool, "F-1")
+            .await?
+            .into_iter()
+            .find(|p| p.producto_nombre == nombre))
+    }
+
+    #[sqlx::test(migrations = "./migrations")]
+    async fn test_correct_ticket_lines(pool: PgPool) -> sqlx::Result<()> {
+        let fecha_hora = setup_ticket(&pool).await?;
+        let dia = fecha_hora.date();
+
+        // PAN pasa a 2 unidades, HUEVOS se elimina y se añaden 500 g de tomate a 2 €/kg
+        let mut tomate = correccion("TOMATE", 500, 200);
+        tomate.unidad = Some("g".to_string());
+        tomate.iva_porcentaje = Some(Decimal::new(4, 0));
+        let resumen = correct_ticket(
+            &pool,
+            "test@example.com",
+            "F-1",
+            payload(vec![correccion("pan", 2, 150), tomate], &["HUEVOS"]),
+        )
+        .await
+        .expect("la corrección cuadra con el total");
+
+        assert_eq!(resumen.lineas_anadidas, 1);
+        assert_eq!(resumen.lineas_modificadas, 1);
+        assert_eq!(resumen.lineas_eliminadas, 1);
+
+        let pan = linea_guardada(&pool, "PAN").await?.unwrap();
+        assert_eq!(pan.cantidad, Decimal::from(2));
+        assert_eq!(pan.precio_total, Decimal::new(300, 2));
+
+        // La línea nueva sigue el camino de la ingesta: gramos a kg
+        let tomate = linea_guardada(&pool, "TOMATE").await?.unwrap();
+        assert_eq!(tomate.cantidad, Decimal::new(5, 1));
+        assert_eq!(tomate.precio_unitario, Decimal::new(200, 2));
+        assert_eq!(tomate.precio_total, Decimal::new(100, 2));
+        let producto = db::get_product(&pool, "TOMATE").await?.unwrap();
+        assert_eq!(producto.unidad.as_deref(), Some("kg"));
+
+        assert!(linea_guardada(&pool, "HUEVOS").await?.is_none());
+        assert!(historico(&pool, "HUEVOS").await?.is_empty());
+        assert!(db::get_product(&pool, "HUEVOS").await?.is_none());
+
+        assert_eq!(
+            historico(&pool, "PAN").await?,
+            vec![(dia, "manual".to_string())]
+        );
+        assert_eq!(
+            historico(&pool, "LECHE SEMIDESNATADA").await?,
+            vec![(dia, "ticket".to_string())]
+        );
+
+        // Sin HUEVOS no queda IVA al 10 % en las líneas y al 4 % sobra cuota
+        let tipos: Vec<Decimal> = resumen
+            .iva_descuadres
+            .iter()
+            .map(|d| d.iva_porcentaje)
+            .collect();
+        assert_eq!(tipos, vec![Decimal::new(4, 0), Decimal::new(10, 0)]);
+
+        // Una corrección que no cuadra con el total no toca nada
+        let err = correct_ticket(
+            &pool,
+            "test@example.com",
+            "F-1",
+            payload(vec![correccion("PAN", 3, 150)], &[]),
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(err, AppError::InvalidTotals(_)));
+        assert_eq!(
+            linea_guardada(&pool, "PAN").await?.unwrap().cantidad,
+            Decimal::from(2)
+        );
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrations = "./migrations")]
+    async fn test_correct_ticket_date_change(pool: PgPool) -> sqlx::Result<()> {
+        let fecha_hora = setup_ticket(&pool).await?;
+        let nueva = fecha_hora + Duration::days(3);
+
+        let mut cambio = payload(Vec::new(), &[]);
+        cambio.fecha_hora = Some(nueva);
+        let resumen = correct_ticket(&pool, "test@example.com", "F-1", cambio)
+            .await
+            .expect("cambio de fecha válido");
+        assert_eq!(resumen.lineas_modificadas, 0);
+        assert!(resumen.iva_descuadres.is_empty());
+
+        let compra = db::get_purchase(&pool, "F-1").await?.unwrap();
+        let dia_nuevo = compra.fecha_hora.date();
+        assert_ne!(dia_nuevo, fecha_hora.date());
+
+        // El histórico del día anterior se retira y todas las líneas pasan al nuevo
+        for nombre in ["LECHE SEMIDESNATADA", "PAN", "HUEVOS"] {
+            assert_eq!(
+                historico(&pool, nombre).await?,
+                vec![(dia_nuevo, "manual".to_string())]
+            );
+        }
+
+        Ok(())
+    }
+
+    #[sqlx::test(migrations = "./migrations")]
+    async fn test_correct_ticket_alias_merges_into_line(pool: PgPool) -> sqlx::Result<()> {
+        setup_ticket(&pool).await?;
+        sqlx::query!(
+            "INSERT INTO productos_alias (alias, producto_nombre) VALUES ($1, $2)",
+            "LECHE SEMI",
+            "LECHE SEMIDESNATADA"
+        )
+        .execute(&pool)
+        .await?;
+
+        // Dos bricks más con el nombre corto, sin PAN y con HUEVOS más baratos
+        let resumen = correct_ticket(
+            &pool,
+            "test@example.com",
+            "F-1",
+            payload(
+                vec![
+                    correccion("LECHE SEMI", 2, 100),
+                    correccion("HUEVOS", 1, 200),
+                ],
+                &["PAN"],
+            ),
+        )
+        .await
+        .expect("la corrección cuadra con el total");
+
+        assert_eq!(resumen.lineas_anadidas, 0);
+        assert_eq!(resumen.lineas_modificadas, 2);
+        assert_eq!(resumen.lineas_eliminadas, 1);
+
+        let leche = linea_guardada(&pool, "LECHE SEMIDESNATADA").await?.unwrap();
+        assert_eq!(leche.cantidad, Decimal::from(4));
+        assert_eq!(leche.precio_unitario, Decimal::new(100, 2));
+        assert_eq!(leche.precio_total, Decimal::new(400, 2));
+        assert!(linea_guardada(&pool, "LECHE SEMI").await?.is_none());
+        assert!(db::get_product(&pool, "LECHE SEMI").await?.is_none());
+
+        Ok(())
+    }
+}
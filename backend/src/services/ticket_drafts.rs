@@ -0,0 +1,100 @@
+use crate::{
+    db,
+    error::{AppError, AppResult},
+    models::{AttachmentKind, TicketDraftInsert, TicketDraftSummary},
+    schema::TicketDraftConfirmPayload,
+    services::{
+        ticket_ingestion::{decode_file_base64, ingest_ticket_draft},
+        OcrProcessTicketResponse, TicketEventBus, TicketIngestionResponse, TicketStorages,
+    },
+};
+use chrono::{Duration, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// Guarda el resultado del OCR y el archivo original como borrador
+///
+/// Aprovecha la llamada para purgar los borradores caducados.
+pub async fn create_ticket_draft(
+    pool: &PgPool,
+    user_email: &str,
+    file_b64: &str,
+    file_name: &str,
+    mime_type: Option<String>,
+    ocr_response: OcrProcessTicketResponse,
+    ttl_hours: i64,
+) -> AppResult<TicketDraftSummary> {
+    let archivo = decode_file_base64(file_b64)?;
+
+    let kind = AttachmentKind::detect(file_name, mime_type.as_deref(), &archivo)
+        .map_err(AppError::InvalidTicketData)?;
+
+    if archivo.len() > kind.max_size() {
+        return Err(AppError::InvalidTicketData(format!(
+            "El archivo excede el tamaño máximo de {}MB para {} (tamaño actual: {} bytes)",
+            kind.max_size() / 1_048_576,
+            kind.mime_type(),
+            archivo.len()
+        )));
+    }
+
+    let purged = db::delete_expired_ticket_drafts(pool).await?;
+    if purged > 0 {
+        tracing::debug!("Eliminados {} borradores caducados", purged);
+    }
+
+    let draft = TicketDraftInsert {
+        usuario_email: user_email.to_string(),
+        resultado_ocr: ocr_response,
+        archivo,
+        nombre_archivo: file_name.to_string(),
+        mime_type: Some(kind.mime_type().to_string()),
+        expira_en: Utc::now().naive_utc() + Duration::hours(ttl_hours),
+    };
+
+    let summary = db::insert_ticket_draft(pool, &draft).await?;
+
+    tracing::info!("Borrador de ticket guardado");
+
+    Ok(summary)
+}
+
+/// Confirma un borrador aplicando las correcciones del usuario y lo ingesta
+pub async fn confirm_ticket_draft(
+    pool: &PgPool,
+    storage: &TicketStorages,
+    user_email: &str,
+    draft_id: Uuid,
+    payload: TicketDraftConfirmPayload,
+    events: &TicketEventBus,
+) -> AppResult<TicketIngestionResponse> {
+    let mut draft = db::get_ticket_draft(pool, user_email, draft_id)
+        .await?
+        .filter(|d| !d.is_expired(Utc::now().naive_utc()))
+        .ok_or_else(|| AppError::NotFound("Borrador no encontrado o caducado".to_string()))?;
+
+    let replace = payload.replace;
+    apply_draft_edits(&mut draft.resultado_ocr, payload);
+
+    tracing::info!("Confirmando borrador de ticket");
+
+    let progress = events.progress(user_email, &draft.id.to_string(), &draft.nombre_archivo);
+
+    ingest_ticket_draft(pool, storage, user_email, draft, replace, &progress).await
+}
+
+/// Sustituye en el resultado del OCR los campos corregidos por el usuario
+fn apply_draft_edits(ocr: &mut OcrProcessTicketResponse, payload: TicketDraftConfirmPayload) {
+    if let Some(productos) = payload.productos {
+        ocr.productos = productos;
+    }
+
+    if let Some(fecha_hora) = payload.fecha_hora {
+        ocr.fecha = Some(fecha_hora.format("%Y-%m-%d").to_string());
+        ocr.fecha_hora = Some(fecha_hora.format("%Y-%m-%d %H:%M:%S").to_string());
+    }
+
+    if let Some(total) = payload.total {
+        ocr.total = Some(total);
+    }
+}
@@ -0,0 +1,227 @@
+use crate::error::AppError;
+use serde::Serialize;
+use tokio::sync::broadcast;
+
+/// Eventos que se conservan para un suscriptor lento antes de descartarlos
+const EVENT_BUFFER: usize = 256;
+
+/// Fase por la que pasa un ticket durante su procesamiento
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TicketStage {
+    Ocr,
+    OcrCompleted,
+    Validating,
+    DuplicateCheck,
+    Committing,
+    Ingested,
+    Draft,
+    Retrying,
+    Failed,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TicketEventError {
+    pub code: &'static str,
+    pub message: String,
+}
+
+/// Evento de progreso enviado al usuario por SSE
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TicketProgressEvent {
+    /// Identificador del ticket: el `ticket_id` del cliente, el del borrador o el del trabajo
+    pub ticket_id: String,
+    pub file_name: String,
+    pub stage: TicketStage,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<TicketEventError>,
+}
+
+/// Mensaje del canal de eventos de un usuario
+#[derive(Debug, Clone, PartialEq)]
+pub enum TicketEventMessage {
+    Progress(TicketProgressEvent),
+    /// Se descartaron eventos por ir retrasado: el cliente debe volver a
+    /// consultar el estado de sus tickets
+    Resync {
+        skipped: u64,
+    },
+}
+
+#[derive(Debug, Clone)]
+struct UserTicketEvent {
+    usuario_email: String,
+    event: TicketProgressEvent,
+}
+
+/// Canal en memoria con los eventos de progreso de todos los usuarios
+#[derive(Debug, Clone)]
+pub struct TicketEventBus {
+    sender: broadcast::Sender<UserTicketEvent>,
+}
+
+impl TicketEventBus {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(EVENT_BUFFER);
+        Self { sender }
+    }
+
+    /// Se suscribe a los eventos de un único usuario
+    pub fn subscribe(&self, user_email: &str) -> TicketEventSubscription {
+        TicketEventSubscription {
+            receiver: self.sender.subscribe(),
+            usuario_email: user_email.to_string(),
+        }
+    }
+
+    /// Crea el emisor de progreso de un ticket concreto
+    pub fn progress(&self, user_email: &str, ticket_id: &str, file_name: &str) -> TicketProgress {
+        TicketProgress {
+            bus: self.clone(),
+            usuario_email: user_email.to_string(),
+            ticket_id: ticket_id.to_string(),
+            file_name: file_name.to_string(),
+        }
+    }
+}
+
+impl Default for TicketEventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Suscripción a los eventos de progreso de un usuario
+pub struct TicketEventSubscription {
+    receiver: broadcast::Receiver<UserTicketEvent>,
+    usuario_email: String,
+}
+
+impl TicketEventSubscription {
+    /// Espera al siguiente mensaje del usuario; `None` si el canal se ha cerrado
+    pub async fn recv(&mut self) -> Option<TicketEventMessage> {
+        loop {
+            match self.receiver.recv().await {
+                Ok(message) if message.usuario_email == self.usuario_email => {
+                    return Some(TicketEventMessage::Progress(message.event))
+                }
+                Ok(_) => continue,
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    tracing::warn!("Suscriptor SSE retrasado: {} eventos descartados", skipped);
+                    return Some(TicketEventMessage::Resync { skipped });
+                }
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    }
+}
+
+/// Emisor de los eventos de progreso de un ticket
+#[derive(Debug, Clone)]
+pub struct TicketProgress {
+    bus: TicketEventBus,
+    usuario_email: String,
+    ticket_id: String,
+    file_name: String,
+}
+
+impl TicketProgress {
+    pub fn stage(&self, stage: TicketStage) {
+        self.send(stage, Vec::new(), None);
+    }
+
+    /// Publica el fin del OCR junto con los avisos que haya devuelto
+    pub fn ocr_completed(&self, warnings: &[String]) {
+        self.send(TicketStage::OcrCompleted, warnings.to_vec(), None);
+    }
+
+    /// Publica un fallo; `retrying` indica que el ticket volverá a intentarse
+    pub fn failed(&self, err: &AppError, retrying: bool) {
+        let stage = if retrying {
+            TicketStage::Retrying
+        } else {
+            TicketStage::Failed
+        };
+
+        let error = TicketEventError {
+            code: err.code(),
+            message: err.public_message(),
+        };
+
+        self.send(stage, Vec::new(), Some(error));
+    }
+
+    fn send(&self, stage: TicketStage, warnings: Vec<String>, error: Option<TicketEventError>) {
+        let message = UserTicketEvent {
+            usuario_email: self.usuario_email.clone(),
+            event: TicketProgressEvent {
+                ticket_id: self.ticket_id.clone(),
+                file_name: self.file_name.clone(),
+                stage,
+                warnings,
+                error,
+            },
+        };
+
+        // Sin suscriptores el envío falla y el evento simplemente se pierde
+        let _ = self.bus.sender.send(message);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_events_are_scoped_to_user() {
+        let bus = TicketEventBus::new();
+        let mut subscription = bus.subscribe("a@example.com");
+
+        bus.progress("b@example.com", "t2", "otro.pdf")
+            .stage(TicketStage::Ocr);
+        let progress = bus.progress("a@example.com", "t1", "ticket.pdf");
+        progress.ocr_completed(&["Total dudoso".to_string()]);
+        progress.failed(&AppError::InvalidTotals("Totales".to_string()), false);
+
+        let Some(TicketEventMessage::Progress(event)) = subscription.recv().await else {
+            panic!("se esperaba un evento de progreso");
+        };
+        assert_eq!(event.ticket_id, "t1");
+        assert_eq!(event.stage, TicketStage::OcrCompleted);
+        assert_eq!(event.warnings, vec!["Total dudoso".to_string()]);
+
+        let Some(TicketEventMessage::Progress(event)) = subscription.recv().await else {
+            panic!("se esperaba un evento de progreso");
+        };
+        assert_eq!(event.stage, TicketStage::Failed);
+        assert_eq!(event.error.unwrap().code, "invalid_totals");
+    }
+
+    #[tokio::test]
+    async fn test_lagged_subscriber_gets_resync() {
+        let bus = TicketEventBus::new();
+        let mut subscription = bus.subscribe("a@example.com");
+
+        let progress = bus.progress("a@example.com", "t1", "ticket.pdf");
+        for _ in 0..EVENT_BUFFER + 10 {
+            progress.stage(TicketStage::Ocr);
+        }
+        progress.stage(TicketStage::Ingested);
+
+        assert_eq!(
+            subscription.recv().await,
+            Some(TicketEventMessage::Resync { skipped: 11 })
+        );
+
+        // Tras el aviso siguen llegando los eventos que quedan en el buffer
+        let mut ultimo = None;
+        for _ in 0..EVENT_BUFFER {
+            if let Some(TicketEventMessage::Progress(event)) = subscription.recv().await {
+                ultimo = Some(event.stage);
+            }
+        }
+        assert_eq!(ultimo, Some(TicketStage::Ingested));
+    }
+}
@@ -0,0 +1,526 @@
+use crate::{
+    error::AppError,
+    models::AttachmentKind,
+    services::{
+        ingest_ticket, IntelligenceClient, IntelligenceClientError, OcrProcessTicketRequest,
+        TicketEventBus, TicketFile, TicketProgress, TicketStage, TicketStorages,
+    },
+};
+use base64::{engine::general_purpose, Engine as _};
+use serde::Serialize;
+use sqlx::PgPool;
+use std::io::{Cursor, Read};
+use std::sync::Arc;
+use tokio::{sync::Semaphore, task::JoinSet};
+use uuid::Uuid;
+
+/// Cabecera local de un archivo ZIP
+const ZIP_MAGIC: &[u8] = b"PK\x03\x04";
+
+/// Archivo recibido en una importación masiva
+#[derive(Debug, Clone)]
+pub struct ImportFile {
+    pub nombre_archivo: String,
+    pub contenido: Vec<u8>,
+    pub mime_type: Option<String>,
+}
+
+impl ImportFile {
+    /// Indica si el archivo es un ZIP que hay que expandir
+    pub fn is_zip(&self) -> bool {
+        self.nombre_archivo.to_ascii_lowercase().ends_with(".zip")
+            || matches!(
+                self.mime_type.as_deref(),
+                Some("application/zip" | "application/x-zip-compressed")
+            )
+            || self.contenido.starts_with(ZIP_MAGIC)
+    }
+}
+
+/// Resultado de la importación de un archivo concreto
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportFileStatus {
+    Ingested,
+    Duplicate,
+    InvalidTotals,
+    OcrFailed,
+    Invalid,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportFileResult {
+    pub file_name: String,
+    pub status: ImportFileStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub numero_factura: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub productos_insertados: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Informe completo de una importación masiva
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TicketImportReport {
+    pub total: usize,
+    pub ingested: usize,
+    pub duplicates: usize,
+    pub invalid_totals: usize,
+    pub ocr_failures: usize,
+    pub invalid: usize,
+    pub files: Vec<ImportFileResult>,
+}
+
+impl TicketImportReport {
+    fn push(&mut self, result: ImportFileResult) {
+        self.total += 1;
+        match result.status {
+            ImportFileStatus::Ingested => self.ingested += 1,
+            ImportFileStatus::Duplicate => self.duplicates += 1,
+            ImportFileStatus::InvalidTotals => self.invalid_totals += 1,
+            ImportFileStatus::OcrFailed => self.ocr_failures += 1,
+            ImportFileStatus::Invalid => self.invalid += 1,
+        }
+        self.files.push(result);
+    }
+}
+
+/// Límites de una importación masiva, aplicados al expandir los ZIP
+#[derive(Debug, Clone, Copy)]
+pub struct ImportLimits {
+    /// Número máximo de tickets tras expandir los ZIP
+    pub max_files: usize,
+    /// Tamaño máximo del conjunto de tickets ya descomprimidos, en bytes
+    pub max_bytes: usize,
+}
+
+/// Tickets y bytes que todavía admite la importación
+struct ImportBudget {
+    limits: ImportLimits,
+    files: usize,
+    bytes: usize,
+}
+
+impl ImportBudget {
+    fn new(limits: ImportLimits) -> Self {
+        Self {
+            limits,
+            files: 0,
+            bytes: 0,
+        }
+    }
+
+    /// Bytes que aún pueden leerse antes de agotar el presupuesto
+    fn remaining_bytes(&self) -> usize {
+        self.limits.max_bytes.saturating_sub(self.bytes)
+    }
+
+    /// Cuenta un ticket más y falla en cuanto se supera algún límite
+    fn add(&mut self, size: usize) -> Result<(), String> {
+        self.files += 1;
+        self.bytes += size;
+
+        if self.files > self.limits.max_files {
+            return Err(format!(
+                "Se admiten como máximo {} tickets por importación",
+                self.limits.max_files
+            ));
+        }
+        if self.bytes > self.limits.max_bytes {
+            return Err(format!(
+                "Los tickets descomprimidos superan el máximo de {} MB por importación",
+                self.limits.max_bytes / (1024 * 1024)
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Expande los ZIP recibidos y devuelve la lista plana de tickets
+///
+/// Las entradas que no son tickets soportados se ignoran. Cada entrada se
+/// lee como máximo hasta el tamaño permitido para su tipo y sin pasar del
+/// presupuesto de `limits`, que se comprueba mientras se recorre el ZIP: un
+/// ZIP con miles de entradas o muy comprimido falla antes de descomprimirse
+/// entero.
+pub fn expand_import_files(
+    files: Vec<ImportFile>,
+    limits: ImportLimits,
+) -> Result<Vec<ImportFile>, String> {
+    let mut budget = ImportBudget::new(limits);
+    let mut expanded = Vec::with_capacity(files.len().min(limits.max_files));
+
+    for file in files {
+        if file.is_zip() {
+            extract_zip(&file, &mut budget, &mut expanded)?;
+        } else {
+            budget.add(file.contenido.len())?;
+            expanded.push(file);
+        }
+    }
+
+    Ok(expanded)
+}
+
+fn extract_zip(
+    file: &ImportFile,
+    budget: &mut ImportBudget,
+    files: &mut Vec<ImportFile>,
+) -> Result<(), String> {
+    let mut archive = zip::ZipArchive::new(Cursor::new(file.contenido.as_slice()))
+        .map_err(|_| format!("El archivo {} no es un ZIP válido", file.nombre_archivo))?;
+
+    for index in 0..archive.len() {
+        let entry = archive
+            .by_index(index)
+            .map_err(|_| format!("No se pudo leer el ZIP {}", file.nombre_archivo))?;
+
+        if entry.is_dir() {
+            continue;
+        }
+
+        let entry_name = entry.name().to_string();
+        if entry_name.starts_with("__MACOSX/") {
+            continue;
+        }
+
+        let nombre_archivo = entry_name
+            .rsplit('/')
+            .next()
+            .unwrap_or(&entry_name)
+            .to_string();
+        if nombre_archivo.starts_with('.') {
+            continue;
+        }
+
+        let Some(kind) = AttachmentKind::from_file_name(&nombre_archivo) else {
+            continue;
+        };
+
+        // Se lee un byte más del máximo para detectar archivos demasiado
+        // grandes, sin pasar de lo que queda del presupuesto de la importación
+        let limit = kind.max_size().min(budget.remaining_bytes()) as u64 + 1;
+        let mut contenido = Vec::new();
+        entry
+            .take(limit)
+            .read_to_end(&mut contenido)
+            .map_err(|_| format!("No se pudo descomprimir {}", nombre_archivo))?;
+
+        budget.add(contenido.len())?;
+        files.push(ImportFile {
+            nombre_archivo,
+            contenido,
+            mime_type: Some(kind.mime_type().to_string()),
+        });
+    }
+
+    Ok(())
+}
+
+/// Procesa con OCR e ingesta un lote de tickets
+///
+/// Las llamadas al servicio de inteligencia se limitan a `concurrency`
+/// simultáneas. Los fallos de un archivo no detienen el resto del lote.
+pub async fn import_tickets(
+    pool: PgPool,
+    storage: TicketStorages,
+    client: IntelligenceClient,
+    user_email: &str,
+    files: Vec<ImportFile>,
+    concurrency: usize,
+    events: TicketEventBus,
+) -> TicketImportReport {
+    let semaphore = Arc::new(Semaphore::new(concurrency.max(1)));
+    let mut tasks = JoinSet::new();
+
+    tracing::info!(archivos = files.len(), "Iniciando importación masiva");
+
+    for (index, file) in files.into_iter().enumerate() {
+        let pool = pool.clone();
+        let storage = storage.clone();
+        let client = client.clone();
+        let semaphore = Arc::clone(&semaphore);
+        let ticket_id = Uuid::new_v4().to_string();
+        let progress = events.progress(user_email, &ticket_id, &file.nombre_archivo);
+        let user_email = user_email.to_string();
+
+        tasks.spawn(async move {
+            let result = match semaphore.acquire_owned().await {
+                Ok(_permit) => {
+                    import_single_file(
+                        &pool,
+                        &storage,
+                        &client,
+                        &user_email,
+                        file,
+                        &ticket_id,
+                        &progress,
+                    )
+                    .await
+                }
+                Err(_) => failed(
+                    file.nombre_archivo,
+                    ImportFileStatus::Invalid,
+                    "Importación cancelada".to_string(),
+                ),
+            };
+            (index, result)
+        });
+    }
+
+    let mut results = Vec::new();
+    while let Some(joined) = tasks.join_next().await {
+        match joined {
+            Ok(result) => results.push(result),
+            Err(err) => tracing::error!("Tarea de importación abortada: {}", err),
+        }
+    }
+
+    // Mantener el orden en que se recibieron los archivos
+    results.sort_by_key(|(index, _)| *index);
+
+    let mut report = TicketImportReport::default();
+    for (_, result) in results {
+        report.push(result);
+    }
+
+    tracing::info!(
+        total = report.total,
+        ingestados = report.ingested,
+        duplicados = report.duplicates,
+        totales_invalidos = report.invalid_totals,
+        fallos_ocr = report.ocr_failures,
+        invalidos = report.invalid,
+        "Importación masiva completada"
+    );
+
+    report
+}
+
+async fn import_single_file(
+    pool: &PgPool,
+    storage: &TicketStorages,
+    client: &IntelligenceClient,
+    user_email: &str,
+    file: ImportFile,
+    ticket_id: &str,
+    progress: &TicketProgress,
+) -> ImportFileResult {
+    let ImportFile {
+        nombre_archivo,
+        contenido,
+        mime_type,
+    } = file;
+
+    let kind = AttachmentKind::detect(&nombre_archivo, mime_type.as_deref(), &contenido).and_then(
+        |kind| {
+            if contenido.len() > kind.max_size() {
+                Err(format!(
+                    "El archivo excede el tamaño máximo de {}MB para {}",
+                    kind.max_size() / 1_048_576,
+                    kind.mime_type()
+                ))
+            } else {
+                Ok(kind)
+            }
+        },
+    );
+
+    let kind = match kind {
+        Ok(kind) => kind,
+        Err(msg) => {
+            progress.failed(&AppError::InvalidTicketData(msg.clone()), false);
+            return failed(nombre_archivo, ImportFileStatus::Invalid, msg);
+        }
+    };
+
+    let request = OcrProcessTicketRequest {
+        ticket_id: ticket_id.to_string(),
+        file_name: nombre_archivo.clone(),
+        file_content_b64: general_purpose::STANDARD.encode(&contenido),
+        mime_type: Some(kind.mime_type().to_string()),
+    };
+
+    progress.stage(TicketStage::Ocr);
+    let ocr = match client.process_ticket(request).await {
+        Ok(ocr) => ocr,
+        Err(err) => {
+            let motivo = match &err {
+                IntelligenceClientError::UnexpectedStatus { status, .. } => status.to_string(),
+                other => other.to_string(),
+            };
+            tracing::warn!("Fallo de OCR en importación masiva: {}", motivo);
+            progress.failed(&err.into(), false);
+            return failed(
+                nombre_archivo,
+                ImportFileStatus::OcrFailed,
+                "No se pudo procesar el ticket con OCR".to_string(),
+            );
+        }
+    };
+
+    progress.ocr_completed(&ocr.warnings);
+
+    let ticket = TicketFile {
+        contenido,
+        nombre_archivo: nombre_archivo.clone(),
+        mime_type: Some(kind.mime_type().to_string()),
+    };
+
+    match ingest_ticket(pool, storage, user_email, ticket, ocr, false, progress).await {
+        Ok(ingestion) => ImportFileResult {
+            file_name: nombre_archivo,
+            status: ImportFileStatus::Ingested,
+            numero_factura: Some(ingestion.numero_factura),
+            productos_insertados: Some(ingestion.productos_insertados),
+            error: None,
+        },
+        Err(AppError::DuplicatePurchase(numero_factura)) => ImportFileResult {
+            file_name: nombre_archivo,
+            status: ImportFileStatus::Duplicate,
+            numero_factura: Some(numero_factura),
+            productos_insertados: None,
+            error: Some("El ticket ya estaba registrado".to_string()),
+        },
+        Err(AppError::InvalidTotals(msg)) => {
+            failed(nombre_archivo, ImportFileStatus::InvalidTotals, msg)
+        }
+        Err(AppError::MissingInvoiceNumber) => failed(
+            nombre_archivo,
+            ImportFileStatus::Invalid,
+            "El ticket no contiene numero de factura".to_string(),
+        ),
+        Err(AppError::InvalidTicketData(msg) | AppError::BadRequest(msg)) => {
+            failed(nombre_archivo, ImportFileStatus::Invalid, msg)
+        }
+        Err(err) => {
+            tracing::error!("Fallo al ingerir ticket en importación masiva: {:?}", err);
+            failed(
+                nombre_archivo,
+                ImportFileStatus::Invalid,
+                "Error interno al guardar el ticket".to_string(),
+            )
+        }
+    }
+}
+
+fn failed(file_name: String, status: ImportFileStatus, error: String) -> ImportFileResult {
+    ImportFileResult {
+        file_name,
+        status,
+        numero_factura: None,
+        productos_insertados: None,
+        error: Some(error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use zip::write::SimpleFileOptions;
+
+    const LIMITS: ImportLimits = ImportLimits {
+        max_files: 10,
+        max_bytes: 1024 * 1024,
+    };
+
+    #[test]
+    fn test_expand_import_files_from_zip() {
+        let mut buffer = Cursor::new(Vec::new());
+        {
+            let mut writer = zip::ZipWriter::new(&mut buffer);
+            let options = SimpleFileOptions::default();
+            writer.add_directory("tickets/", options).unwrap();
+            writer.start_file("tickets/ticket1.pdf", options).unwrap();
+            writer.write_all(b"%PDF-1.4 test").unwrap();
+            writer
+                .start_file("__MACOSX/._ticket1.pdf", options)
+                .unwrap();
+            writer.write_all(b"basura").unwrap();
+            writer.start_file("notas.txt", options).unwrap();
+            writer.write_all(b"no es un ticket").unwrap();
+            writer.start_file("ticket2.jpg", options).unwrap();
+            writer.write_all(&[0xFF, 0xD8, 0xFF, 0xE0]).unwrap();
+            writer.finish().unwrap();
+        }
+
+        let files = vec![
+            ImportFile {
+                nombre_archivo: "tickets.zip".to_string(),
+                contenido: buffer.into_inner(),
+                mime_type: Some("application/zip".to_string()),
+            },
+            ImportFile {
+                nombre_archivo: "suelto.png".to_string(),
+                contenido: b"\x89PNG\r\n\x1a\n".to_vec(),
+                mime_type: Some("image/png".to_string()),
+            },
+        ];
+
+        let expanded = expand_import_files(files, LIMITS).unwrap();
+        let names: Vec<_> = expanded.iter().map(|f| f.nombre_archivo.as_str()).collect();
+
+        assert_eq!(names, vec!["ticket1.pdf", "ticket2.jpg", "suelto.png"]);
+        assert_eq!(expanded[0].mime_type.as_deref(), Some("application/pdf"));
+    }
+
+    #[test]
+    fn test_expand_import_files_rejects_corrupt_zip() {
+        let files = vec![ImportFile {
+            nombre_archivo: "roto.zip".to_string(),
+            contenido: b"PK\x03\x04 no es un zip".to_vec(),
+            mime_type: None,
+        }];
+
+        assert!(expand_import_files(files, LIMITS).is_err());
+    }
+
+    fn zip_with(entries: &[(&str, Vec<u8>)]) -> ImportFile {
+        let mut buffer = Cursor::new(Vec::new());
+        {
+            let mut writer = zip::ZipWriter::new(&mut buffer);
+            let options = SimpleFileOptions::default();
+            for (name, contenido) in entries {
+                writer.start_file(*name, options).unwrap();
+                writer.write_all(contenido).unwrap();
+            }
+            writer.finish().unwrap();
+        }
+
+        ImportFile {
+            nombre_archivo: "tickets.zip".to_string(),
+            contenido: buffer.into_inner(),
+            mime_type: Some("application/zip".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_expand_import_files_enforces_limits() {
+        let limits = ImportLimits {
+            max_files: 2,
+            max_bytes: 1024,
+        };
+
+        // Más entradas de las permitidas
+        let entries = ["a.pdf", "b.pdf", "c.pdf"].map(|name| (name, b"%PDF-1.4".to_vec()));
+        let err = expand_import_files(vec![zip_with(&entries)], limits).unwrap_err();
+        assert!(err.contains("como máximo 2 tickets"));
+
+        // Entrada muy comprimida que supera el presupuesto descomprimido
+        let bomba = zip_with(&[("ticket.pdf", vec![0u8; 64 * 1024])]);
+        assert!(bomba.contenido.len() < limits.max_bytes);
+        let err = expand_import_files(vec![bomba], limits).unwrap_err();
+        assert!(err.contains("descomprimidos"));
+
+        // Los archivos sueltos también cuentan
+        let suelto = ImportFile {
+            nombre_archivo: "suelto.pdf".to_string(),
+            contenido: vec![0u8; 800],
+            mime_type: Some("application/pdf".to_string()),
+        };
+        let zip = zip_with(&[("ticket.pdf", vec![0u8; 400])]);
+        assert!(expand_import_files(vec![suelto, zip], limits).is_err());
+    }
+}
@@ -1,16 +1,32 @@
 use crate::{
     db,
     error::{AppError, AppResult},
-    models::{ProductUpsert, PurchaseInsert, PurchaseProductInsert, TicketPdfInsert},
-    services::{OcrProcessTicketResponse as ProcessTicketResponse, TicketProduct},
+    models::{
+        Achievement, AttachmentKind, OcrJobTask, ProductUnit, ProductUpsert, PurchaseInsert,
+        PurchaseIvaInsert, PurchaseProductInsert, SpendingAlert, TicketAttachmentInsert,
+        TicketDraft,
+    },
+    services::{
+        achievements,
+        categories::assign_categories,
+        notifications,
+        ocr::IvaBreakdown,
+        package_sizes::assign_package_sizes,
+        preferences,
+        ticket_storage::{new_storage_key, remove_stored_file},
+        OcrProcessTicketResponse as ProcessTicketResponse, TicketProduct, TicketProgress,
+        TicketStage, TicketStorages,
+    },
 };
 use base64::{engine::general_purpose, Engine as _};
 use chrono::{Local, NaiveDateTime, TimeZone, Utc};
 use rust_decimal::prelude::*;
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
-use sqlx::PgPool;
+use sqlx::{PgConnection, PgPool};
+use std::collections::{BTreeMap, HashMap};
 use std::str::FromStr;
+use uuid::Uuid;
 
 /// Respuesta de la ingesta de un ticket
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -20,28 +36,249 @@ pub struct TicketIngestionResponse {
     pub total: Decimal,
     pub productos_insertados: usize,
     pub fecha_hora: NaiveDateTime,
+    /// Indica si se sustituyó una compra existente con el mismo número de factura
+    #[serde(default)]
+    pub replaced: bool,
+    /// Tipos de IVA en los que las líneas no cuadran con el desglose del ticket
+    #[serde(default)]
+    pub iva_descuadres: Vec<IvaMismatch>,
+    /// Logros que el usuario ha desbloqueado con este ticket
+    #[serde(default)]
+    pub logros_desbloqueados: Vec<Achievement>,
+    /// Alerta creada si este ticket hace superar el umbral de gasto mensual
+    #[serde(default)]
+    pub alerta_gasto: Option<SpendingAlert>,
 }
 
-/// Procesa e ingesta un ticket completo en la base de datos
+/// Diferencia entre la cuota de IVA de las líneas y la del pie del ticket
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IvaMismatch {
+    pub iva_porcentaje: Decimal,
+    pub cuota_lineas: Decimal,
+    pub cuota_ticket: Decimal,
+    pub diferencia: Decimal,
+}
+
+/// Archivo original del ticket pendiente de guardar
+#[derive(Debug, Clone)]
+pub struct TicketFile {
+    pub contenido: Vec<u8>,
+    pub nombre_archivo: String,
+    pub mime_type: Option<String>,
+}
+
+impl TicketFile {
+    /// Decodifica un archivo recibido en base64
+    pub fn from_base64(
+        file_b64: &str,
+        file_name: &str,
+        mime_type: Option<&str>,
+    ) -> AppResult<Self> {
+        Ok(Self {
+            contenido: decode_file_base64(file_b64)?,
+            nombre_archivo: file_name.to_string(),
+            mime_type: mime_type.map(str::to_string),
+        })
+    }
+}
+
+/// Procesa e ingesta un ticket completo (PDF o imagen)
+pub async fn ingest_ticket(
+    pool: &PgPool,
+    storage: &TicketStorages,
+    user_email: &str,
+    file: TicketFile,
+    ocr_response: ProcessTicketResponse,
+    replace_existing: bool,
+    progress: &TicketProgress,
+) -> AppResult<TicketIngestionResponse> {
+    let options = IngestOptions {
+        replace_existing,
+        draft_id: None,
+        ocr_job_id: None,
+    };
+
+    ingest_ticket_file(
+        pool,
+        storage,
+        user_email,
+        file,
+        ocr_response,
+        options,
+        progress,
+    )
+    .await
+}
+
+/// Ingesta un borrador de ticket ya revisado por el usuario
+///
+/// Usa el archivo y el resultado del OCR guardados en el borrador, que se
+/// elimina en la misma transacción que inserta la compra.
+pub async fn ingest_ticket_draft(
+    pool: &PgPool,
+    storage: &TicketStorages,
+    user_email: &str,
+    draft: TicketDraft,
+    replace_existing: bool,
+    progress: &TicketProgress,
+) -> AppResult<TicketIngestionResponse> {
+    let file = TicketFile {
+        contenido: draft.archivo,
+        nombre_archivo: draft.nombre_archivo,
+        mime_type: draft.mime_type,
+    };
+
+    let options = IngestOptions {
+        replace_existing,
+        draft_id: Some(draft.id),
+        ocr_job_id: None,
+    };
+
+    ingest_ticket_file(
+        pool,
+        storage,
+        user_email,
+        file,
+        draft.resultado_ocr.0,
+        options,
+        progress,
+    )
+    .await
+}
+
+/// Ingesta el ticket de un trabajo de OCR
+///
+/// La compra queda asociada al trabajo en la misma transacción, de modo que
+/// un reintento del trabajo sabe si la ingesta ya se completó.
+pub async fn ingest_ocr_job_ticket(
+    pool: &PgPool,
+    storage: &TicketStorages,
+    task: &OcrJobTask,
+    file: TicketFile,
+    ocr_response: ProcessTicketResponse,
+    progress: &TicketProgress,
+) -> AppResult<TicketIngestionResponse> {
+    let options = IngestOptions {
+        replace_existing: task.reemplazar,
+        draft_id: None,
+        ocr_job_id: Some(task.id),
+    };
+
+    ingest_ticket_file(
+        pool,
+        storage,
+        &task.usuario_email,
+        file,
+        ocr_response,
+        options,
+        progress,
+    )
+    .await
+}
+
+/// Opciones de una ingesta
+struct IngestOptions {
+    /// Sustituir la compra propia con el mismo número de factura
+    replace_existing: bool,
+    /// Borrador que se consume al confirmar la ingesta
+    draft_id: Option<Uuid>,
+    /// Trabajo de OCR al que se asocia la compra creada
+    ocr_job_id: Option<Uuid>,
+}
+
+/// Ingesta un ticket y publica el resultado final en `progress`
+async fn ingest_ticket_file(
+    pool: &PgPool,
+    storage: &TicketStorages,
+    user_email: &str,
+    file: TicketFile,
+    ocr_response: ProcessTicketResponse,
+    options: IngestOptions,
+    progress: &TicketProgress,
+) -> AppResult<TicketIngestionResponse> {
+    let mut result = persist_ticket(
+        pool,
+        storage,
+        user_email,
+        file,
+        ocr_response,
+        options,
+        progress,
+    )
+    .await;
+
+    // Un fallo en las comprobaciones posteriores no invalida un ticket ya guardado
+    if let Ok(response) = &mut result {
+        match achievements::evaluate_achievements(pool, user_email).await {
+            Ok(nuevos) => response.logros_desbloqueados = nuevos,
+            Err(err) => {
+                err.log();
+                tracing::warn!("No se pudieron evaluar los logros tras la ingesta");
+            }
+        }
+
+        match preferences::check_spending_threshold(pool, user_email, response.fecha_hora.date())
+            .await
+        {
+            Ok(alerta) => response.alerta_gasto = alerta,
+            Err(err) => {
+                err.log();
+                tracing::warn!("No se pudo comprobar el umbral de gasto tras la ingesta");
+            }
+        }
+
+        if let Err(err) =
+            notifications::notify_price_jumps(pool, user_email, &response.numero_factura).await
+        {
+            err.log();
+            tracing::warn!("No se pudieron notificar las subidas de precio tras la ingesta");
+        }
+    }
+
+    match &result {
+        Ok(_) => progress.stage(TicketStage::Ingested),
+        Err(err) => progress.failed(err, false),
+    }
+
+    result
+}
+
+/// Ingesta un ticket completo en la base de datos
 ///
 /// Esta función orquesta todo el proceso:
 /// 1. Valida los datos obligatorios del ticket
 /// 2. Normaliza los datos
-/// 3. Verifica que no exista duplicado
-/// 4. Decodifica el PDF
-/// 5. Abre una transacción y ejecuta:
+/// 3. Verifica que no exista duplicado (o que pueda reemplazarse)
+/// 4. Valida el archivo original (formato y tamaño)
+/// 5. Guarda el archivo original en el `TicketStorage` configurado
+/// 6. Abre una transacción y ejecuta:
+///    - Borrado de la compra anterior (solo en modo reemplazo)
 ///    - Upsert de productos
 ///    - Insert de la compra
 ///    - Insert de compras_productos
-///    - Insert del PDF
-/// 6. Retorna resumen de la operación
-pub async fn ingest_ticket(
+///    - Insert del desglose de IVA del ticket
+///    - Insert de los metadatos del archivo original
+///    - Borrado del borrador confirmado (si procede)
+///    - Asociación de la compra al trabajo de OCR (si procede)
+/// 7. Borra el archivo sustituido (o el nuevo si la transacción falla)
+/// 8. Retorna resumen de la operación
+async fn persist_ticket(
     pool: &PgPool,
+    storage: &TicketStorages,
     user_email: &str,
-    pdf_b64: &str,
-    file_name: &str,
+    file: TicketFile,
     ocr_response: ProcessTicketResponse,
+    options: IngestOptions,
+    progress: &TicketProgress,
 ) -> AppResult<TicketIngestionResponse> {
+    let IngestOptions {
+        replace_existing,
+        draft_id,
+        ocr_job_id,
+    } = options;
+
+    progress.stage(TicketStage::Validating);
+
     // 1. Validar campos obligatorios
     let numero_factura = ocr_response
         .numero_factura
@@ -53,10 +290,19 @@ pub async fn ingest_ticket(
     tracing::info!("Iniciando ingesta de ticket");
 
     // 2. Verificar si ya existe (idempotencia)
-    if let Some(_existing) = db::get_purchase(pool, &numero_factura).await? {
-        tracing::warn!("La compra ya existe en la base de datos");
-        return Err(AppError::DuplicatePurchase(numero_factura));
-    }
+    progress.stage(TicketStage::DuplicateCheck);
+    // Solo se puede reemplazar una compra propia; las ajenas siguen siendo duplicados
+    let replaced = match db::get_purchase(pool, &numero_factura).await? {
+        Some(existing) if replace_existing && existing.usuario_email == user_email => {
+            tracing::info!("La compra existente sera reemplazada");
+            true
+        }
+        Some(_) => {
+            tracing::warn!("La compra ya existe en la base de datos");
+            return Err(AppError::DuplicatePurchase(numero_factura));
+        }
+        None => false,
+    };
 
     // 3. Extraer y validar datos principales
     let fecha_hora = parse_fecha_hora(&ocr_response)?;
@@ -69,16 +315,21 @@ pub async fn ingest_ticket(
         ));
     }
 
-    // 5. Decodificar el PDF
-    let pdf_bytes = decode_pdf_base64(pdf_b64)?;
+    // 5. Validar el archivo original
+    let kind = AttachmentKind::detect(
+        &file.nombre_archivo,
+        file.mime_type.as_deref(),
+        &file.contenido,
+    )
+    .map_err(AppError::InvalidTicketData)?;
 
-    // Validar el PDF
-    let ticket_pdf = TicketPdfInsert {
+    let attachment = TicketAttachmentInsert {
         numero_factura: numero_factura.clone(),
-        ticket_pdf: pdf_bytes,
-        ticket_nombre_archivo: file_name.to_string(),
+        contenido: file.contenido,
+        nombre_archivo: file.nombre_archivo,
+        kind,
     };
-    ticket_pdf.validate().map_err(AppError::InvalidTicketData)?;
+    attachment.validate().map_err(AppError::InvalidTicketData)?;
 
     // 6. Preparar datos de la compra
     let purchase_data = PurchaseInsert {
@@ -94,78 +345,116 @@ pub async fn ingest_ticket(
         numero_operacion: ocr_response.numero_operacion.map(|n| n.trim().to_string()),
     };
 
-    // 7. Preparar productos
-    let productos: Vec<PurchaseProductInsert> = ocr_response
-        .productos
-        .iter()
-        .map(|p| parse_producto(p))
-        .collect::<AppResult<Vec<_>>>()?;
+    // 7. Preparar productos resolviendo los alias al producto canónico
+    let lineas = parse_productos(pool, &ocr_response.productos).await?;
+    let lineas = merge_duplicate_lines(lineas);
+    let productos: Vec<PurchaseProductInsert> = lineas.iter().map(|l| l.producto.clone()).collect();
 
-    // 8. Validar coherencia de totales
+    // 8. Validar coherencia de precios, totales y del desglose de IVA
+    validate_line_prices(&productos)?;
     validate_totals(&productos, total)?;
 
-    // 9. Ejecutar inserción en transacción
-    let mut tx = pool.begin().await?;
+    let desglose_iva = parse_iva_breakdown(&ocr_response.iva_desglose);
+    let iva_descuadres = check_iva_breakdown(&productos, &desglose_iva);
+    if !iva_descuadres.is_empty() {
+        tracing::warn!(
+            "El IVA de las lineas no cuadra con el desglose del ticket en {} tipos",
+            iva_descuadres.len()
+        );
+    }
 
-    tracing::debug!("Procesando {} productos", productos.len());
+    // 9. Guardar el archivo original fuera de la transacción
+    progress.stage(TicketStage::Committing);
+    let storage_key = new_storage_key(&numero_factura);
+    tracing::debug!(
+        "Guardando archivo {} ({} bytes)",
+        attachment.kind.mime_type(),
+        attachment.contenido.len()
+    );
+    storage
+        .active()
+        .put(&storage_key, &attachment.contenido, kind.mime_type())
+        .await?;
 
-    // Upsert de productos en el catálogo
-    for producto in &productos {
-        let product_upsert = ProductUpsert {
-            nombre: producto.producto_nombre.clone(),
-            marca: None,                  // El OCR actual no extrae marca
-            unidad: "unidad".to_string(), // TODO: extraer del OCR
-            precio_actual: Some(producto.precio_unitario),
-        };
+    let previous_attachment = if replaced {
+        db::get_ticket_attachment(pool, &numero_factura).await?
+    } else {
+        None
+    };
 
-        db::upsert_product(&mut *tx, &product_upsert).await?;
-    }
+    // 10. Ejecutar inserción en transacción
+    let persisted: AppResult<u64> = async {
+        let mut tx = pool.begin().await?;
 
-    // Insertar compra
-    tracing::debug!("Insertando compra");
-    let _purchase = db::insert_purchase(&mut *tx, &purchase_data).await?;
-
-    // Insertar productos de la compra
-    tracing::debug!("Insertando {} productos de la compra", productos.len());
-
-    // Insertar productos uno por uno dentro de la transacción
-    let mut rows_inserted = 0u64;
-    for producto in &productos {
-        let result = sqlx::query!(
-            r#"
-            INSERT INTO compras_productos (
-                compra_numero_factura,
-                producto_nombre,
-                cantidad,
-                precio_unitario,
-                precio_total,
-                descuento,
-                iva_porcentaje,
-                iva_importe
-            )
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
-            "#,
-            numero_factura,
-            producto.producto_nombre,
-            producto.cantidad,
-            producto.precio_unitario,
-            producto.precio_total,
-            producto.descuento,
-            producto.iva_porcentaje,
-            producto.iva_importe
+        if replaced {
+            tracing::debug!("Eliminando la compra anterior");
+            db::delete_purchase(&mut tx, &numero_factura).await?;
+        }
+
+        tracing::debug!("Procesando {} productos", productos.len());
+
+        // Upsert de productos en el catálogo
+        upsert_line_products(&mut tx, &lineas).await?;
+
+        // Insertar compra
+        tracing::debug!("Insertando compra");
+        let _purchase = db::insert_purchase(&mut *tx, &purchase_data).await?;
+
+        // Insertar productos de la compra
+        tracing::debug!("Insertando {} productos de la compra", productos.len());
+
+        let rows_inserted =
+            db::insert_purchase_products(&mut tx, &numero_factura, &productos).await?;
+        db::insert_purchase_iva(&mut tx, &numero_factura, &desglose_iva).await?;
+
+        // Registrar el archivo original del ticket
+        db::insert_ticket_attachment(
+            &mut *tx,
+            &attachment,
+            storage.active().backend().as_str(),
+            &storage_key,
         )
-        .execute(&mut *tx)
         .await?;
 
-        rows_inserted += result.rows_affected();
-    }
+        // Consumir el borrador confirmado
+        if let Some(draft_id) = draft_id {
+            if !db::delete_ticket_draft(&mut *tx, draft_id).await? {
+                return Err(AppError::NotFound(
+                    "Borrador no encontrado o ya confirmado".to_string(),
+                ));
+            }
+        }
 
-    // Insertar PDF del ticket
-    tracing::debug!("Guardando PDF ({} bytes)", ticket_pdf.ticket_pdf.len());
-    db::insert_ticket_pdf(&mut *tx, &ticket_pdf).await?;
+        // Asociar la compra al trabajo de OCR que la ha creado
+        if let Some(ocr_job_id) = ocr_job_id {
+            db::link_ocr_job_purchase(&mut *tx, ocr_job_id, &numero_factura).await?;
+        }
+
+        // Commit de la transacción
+        tx.commit().await?;
 
-    // Commit de la transacción
-    tx.commit().await?;
+        Ok(rows_inserted)
+    }
+    .await;
+
+    // 11. Limpiar el archivo que ya no está referenciado
+    let rows_inserted = match persisted {
+        Ok(rows_inserted) => {
+            if let Some(previous) = previous_attachment {
+                remove_stored_file(storage, &previous).await;
+            }
+            rows_inserted
+        }
+        Err(err) => {
+            if let Err(storage_err) = storage.active().delete(&storage_key).await {
+                tracing::warn!(
+                    "No se pudo borrar el archivo de una ingesta fallida: {}",
+                    storage_err
+                );
+            }
+            return Err(err);
+        }
+    };
 
     tracing::info!(
         "Ticket ingestado correctamente ({} productos)",
@@ -178,21 +467,25 @@ pub async fn ingest_ticket(
         total,
         productos_insertados: rows_inserted as usize,
         fecha_hora,
+        replaced,
+        iva_descuadres,
+        logros_desbloqueados: Vec::new(),
+        alerta_gasto: None,
     })
 }
 
+/// Convierte un NaiveDateTime (interpretado como hora local) a UTC para guardar en DB
+pub fn to_utc_naive(dt: NaiveDateTime) -> NaiveDateTime {
+    Local
+        .from_local_datetime(&dt)
+        .single()
+        .or_else(|| Local.from_local_datetime(&dt).earliest())
+        .map(|dt_local| dt_local.with_timezone(&Utc).naive_utc())
+        .unwrap_or(dt)
+}
+
 /// Parsea la fecha y hora del ticket
 fn parse_fecha_hora(response: &ProcessTicketResponse) -> AppResult<NaiveDateTime> {
-    // Convierte un NaiveDateTime (interpretado como hora local) a UTC para guardar en DB
-    fn to_utc_naive(dt: NaiveDateTime) -> NaiveDateTime {
-        Local
-            .from_local_datetime(&dt)
-            .single()
-            .or_else(|| Local.from_local_datetime(&dt).earliest())
-            .map(|dt_local| dt_local.with_timezone(&Utc).naive_utc())
-            .unwrap_or(dt)
-    }
-
     // Intentar con fecha_hora primero
     if let Some(ref fecha_hora_str) = response.fecha_hora {
         if let Ok(dt) = NaiveDateTime::parse_from_str(fecha_hora_str, "%Y-%m-%d %H:%M:%S") {
@@ -235,9 +528,78 @@ fn parse_total(response: &ProcessTicketResponse) -> AppResult<Decimal> {
         .map_err(|_| AppError::InvalidTicketData(format!("Total inválido: {}", total_f64)))
 }
 
-/// Parsea un producto del ticket OCR a PurchaseProductInsert
-fn parse_producto(producto: &TicketProduct) -> AppResult<PurchaseProductInsert> {
-    let nombre = ProductUpsert::normalize_name(&producto.nombre);
+/// Línea de producto del ticket junto a la unidad en que se mide su cantidad
+#[derive(Debug, Clone)]
+pub(crate) struct TicketLine {
+    pub(crate) producto: PurchaseProductInsert,
+    pub(crate) unidad: ProductUnit,
+}
+
+/// Convierte los productos de un ticket en líneas, resolviendo los alias
+pub(crate) async fn parse_productos(
+    pool: &PgPool,
+    productos: &[TicketProduct],
+) -> AppResult<Vec<TicketLine>> {
+    let nombres: Vec<String> = productos
+        .iter()
+        .map(|p| split_weighted_detail(&p.nombre).0)
+        .collect();
+    let aliases = db::get_product_aliases(pool, &nombres).await?;
+
+    productos
+        .iter()
+        .map(|p| parse_producto(p, &aliases))
+        .collect()
+}
+
+/// Da de alta en el catálogo los productos de las líneas
+///
+/// Guarda la unidad de cada línea y asigna la categoría automática y el
+/// tamaño de envase de los productos que aún no los tienen.
+pub(crate) async fn upsert_line_products(
+    conn: &mut PgConnection,
+    lineas: &[TicketLine],
+) -> AppResult<()> {
+    for linea in lineas {
+        let product_upsert = ProductUpsert {
+            nombre: linea.producto.producto_nombre.clone(),
+            marca: None, // El OCR actual no extrae marca
+            unidad: linea.unidad.as_str().to_string(),
+            precio_actual: Some(linea.producto.precio_unitario),
+        };
+
+        db::upsert_product(&mut *conn, &product_upsert).await?;
+    }
+
+    let nombres: Vec<String> = lineas
+        .iter()
+        .map(|l| l.producto.producto_nombre.clone())
+        .collect();
+    assign_categories(&mut *conn, &nombres).await?;
+    assign_package_sizes(&mut *conn, &nombres).await?;
+
+    Ok(())
+}
+
+/// Detalle de una línea a peso o volumen ("1,234 kg x 2,99 €/kg")
+#[derive(Debug, Clone, PartialEq)]
+struct WeightedDetail {
+    cantidad: Decimal,
+    unidad: ProductUnit,
+    precio_unitario: Decimal,
+}
+
+/// Parsea un producto del ticket OCR
+///
+/// Si el nombre es un alias conocido se sustituye por el producto canónico.
+/// Las cantidades en g o ml se guardan en kg o l, con el precio unitario por
+/// kg o litro.
+fn parse_producto(
+    producto: &TicketProduct,
+    aliases: &HashMap<String, String>,
+) -> AppResult<TicketLine> {
+    let (nombre, detalle) = split_weighted_detail(&producto.nombre);
+    let nombre = aliases.get(&nombre).cloned().unwrap_or(nombre);
 
     if nombre.is_empty() {
         return Err(AppError::InvalidTicketData(
@@ -245,22 +607,58 @@ fn parse_producto(producto: &TicketProduct) -> AppResult<PurchaseProductInsert>
         ));
     }
 
-    let cantidad = Decimal::from_f64(producto.cantidad).ok_or_else(|| {
-        AppError::InvalidTicketData(format!("Cantidad inválida: {}", producto.cantidad))
-    })?;
+    let mut cantidad = Decimal::from_f64(producto.cantidad)
+        .filter(|c| *c > Decimal::ZERO)
+        .ok_or_else(|| {
+            AppError::InvalidTicketData(format!("Cantidad inválida: {}", producto.cantidad))
+        })?
+        .round_dp(3);
+
+    let mut precio_unitario = Decimal::from_f64(producto.precio_unitario)
+        .ok_or_else(|| {
+            AppError::InvalidTicketData(format!(
+                "Precio unitario inválido: {}",
+                producto.precio_unitario
+            ))
+        })?
+        .round_dp(2);
 
-    let precio_unitario = Decimal::from_f64(producto.precio_unitario).ok_or_else(|| {
-        AppError::InvalidTicketData(format!(
-            "Precio unitario inválido: {}",
-            producto.precio_unitario
-        ))
-    })?;
+    let precio_total = Decimal::from_f64(producto.precio_total)
+        .ok_or_else(|| {
+            AppError::InvalidTicketData(format!("Precio total inválido: {}", producto.precio_total))
+        })?
+        .round_dp(2);
 
-    let precio_total = Decimal::from_f64(producto.precio_total).ok_or_else(|| {
-        AppError::InvalidTicketData(format!("Precio total inválido: {}", producto.precio_total))
-    })?;
+    let descuento = Decimal::from_f64(producto.descuento)
+        .unwrap_or(Decimal::ZERO)
+        .round_dp(2);
 
-    let descuento = Decimal::from_f64(producto.descuento).unwrap_or(Decimal::ZERO);
+    let mut unidad = ProductUnit::parse(&producto.unidad).unwrap_or_else(|| {
+        tracing::warn!("Unidad desconocida en un producto del ticket; se usa 'unidad'");
+        ProductUnit::Unidad
+    });
+
+    // El detalle de peso del ticket prevalece sobre lo que haya deducido el OCR
+    if let Some(detalle) = detalle {
+        cantidad = detalle.cantidad;
+        unidad = detalle.unidad;
+        precio_unitario = detalle.precio_unitario;
+    }
+
+    let (base, factor) = unidad.base();
+    if base != unidad {
+        cantidad = (cantidad * factor).round_dp(3);
+        unidad = base;
+    }
+
+    // Líneas a peso que el OCR devuelve como una unidad: el peso se deduce del importe
+    if unidad.is_measured()
+        && cantidad == Decimal::ONE
+        && precio_unitario > Decimal::ZERO
+        && (precio_unitario - descuento - precio_total).abs() > Decimal::new(1, 2)
+    {
+        cantidad = ((precio_total + descuento) / precio_unitario).round_dp(3);
+    }
 
     let iva_porcentaje = Decimal::from_f64(producto.iva_porcentaje).unwrap_or(Decimal::ZERO);
     let iva_porcentaje = PurchaseProductInsert::normalize_iva_percentage(iva_porcentaje);
@@ -269,11 +667,10 @@ fn parse_producto(producto: &TicketProduct) -> AppResult<PurchaseProductInsert>
         Decimal::from_f64(producto.iva_importe).unwrap_or(Decimal::ZERO)
     } else {
         // Calcular IVA si no está presente
-        let base = precio_total - descuento;
-        PurchaseProductInsert::calculate_iva_importe(base, iva_porcentaje)
+        PurchaseProductInsert::calculate_iva_importe(precio_total, iva_porcentaje)
     };
 
-    let product_insert = PurchaseProductInsert {
+    let mut product_insert = PurchaseProductInsert {
         producto_nombre: nombre,
         cantidad,
         precio_unitario,
@@ -283,16 +680,129 @@ fn parse_producto(producto: &TicketProduct) -> AppResult<PurchaseProductInsert>
         iva_importe,
     };
 
+    // En las líneas a peso el importe manda: el precio por kg o litro se
+    // recalcula si el redondeo de la cantidad o de la conversión lo descuadra
+    if unidad.is_measured() && !product_insert.validate_price_coherence() {
+        product_insert.derive_unit_price();
+    }
+
     // Validar coherencia de precios
     if !product_insert.validate_price_coherence() {
-        tracing::warn!("Incoherencia de precios detectada en un producto del ticket");
+        return Err(AppError::InvalidTicketData(format!(
+            "Precios incoherentes en {}: {} x {} - {} != {}",
+            product_insert.producto_nombre,
+            product_insert.precio_unitario,
+            product_insert.cantidad,
+            product_insert.descuento,
+            product_insert.precio_total
+        )));
+    }
+
+    Ok(TicketLine {
+        producto: product_insert,
+        unidad,
+    })
+}
+
+/// Separa el nombre normalizado del detalle de peso que algunos OCR dejan en él
+///
+/// Reconoce "PLATANO 1,234 kg x 2,99 €/kg" y variantes como "0,250KG X 12,90".
+fn split_weighted_detail(nombre: &str) -> (String, Option<WeightedDetail>) {
+    let normalized = ProductUpsert::normalize_name(nombre);
+
+    // Separar números pegados a la unidad ("1,234KG") y los símbolos de precio
+    let mut spaced = String::with_capacity(normalized.len());
+    let mut previous: Option<char> = None;
+    for c in normalized.chars() {
+        if previous.is_some_and(|p| p.is_ascii_digit()) && c.is_alphabetic() {
+            spaced.push(' ');
+        }
+        match c {
+            '€' | '/' | '*' => {
+                spaced.push(' ');
+                spaced.push(if c == '*' { 'X' } else { c });
+                spaced.push(' ');
+            }
+            _ => spaced.push(c),
+        }
+        previous = Some(c);
+    }
+
+    let tokens: Vec<&str> = spaced.split_whitespace().collect();
+    let parse_number = |token: &str| Decimal::from_str(&token.replace(',', ".")).ok();
+
+    for i in 0..tokens.len() {
+        let Some(cantidad) = parse_number(tokens[i]) else {
+            continue;
+        };
+        let Some(unidad) = tokens.get(i + 1).and_then(|t| ProductUnit::parse(t)) else {
+            continue;
+        };
+        if !unidad.is_measured() || tokens.get(i + 2) != Some(&"X") {
+            continue;
+        }
+        let Some(precio_unitario) = tokens.get(i + 3).and_then(|t| parse_number(t)) else {
+            continue;
+        };
+        if cantidad <= Decimal::ZERO {
+            continue;
+        }
+
+        let detalle = WeightedDetail {
+            cantidad: cantidad.round_dp(3),
+            unidad,
+            precio_unitario: precio_unitario.round_dp(2),
+        };
+        return (tokens[..i].join(" "), Some(detalle));
     }
 
-    Ok(product_insert)
+    (normalized, None)
+}
+
+/// Junta en una sola línea las que se refieren al mismo producto
+///
+/// Ocurre cuando el ticket repite un artículo o cuando dos variantes del
+/// nombre resuelven al mismo alias; cada compra solo admite una línea por
+/// producto.
+fn merge_duplicate_lines(lineas: Vec<TicketLine>) -> Vec<TicketLine> {
+    let mut merged: Vec<TicketLine> = Vec::with_capacity(lineas.len());
+
+    for linea in lineas {
+        match merged
+            .iter_mut()
+            .find(|l| l.producto.producto_nombre == linea.producto.producto_nombre)
+        {
+            Some(existing) => existing.producto.absorb(&linea.producto),
+            None => merged.push(linea),
+        }
+    }
+
+    merged
+}
+
+/// Valida que cada línea cumpla `precio_unitario * cantidad - descuento ≈ precio_total`
+///
+/// Se comprueba antes de abrir la transacción para no depender del CHECK
+/// `precio_coherente` de la base de datos.
+pub fn validate_line_prices(productos: &[PurchaseProductInsert]) -> AppResult<()> {
+    match productos.iter().find(|p| !p.validate_price_coherence()) {
+        Some(producto) => Err(AppError::InvalidTicketData(format!(
+            "Precios incoherentes en {}: {} x {} - {} != {}",
+            producto.producto_nombre,
+            producto.precio_unitario,
+            producto.cantidad,
+            producto.descuento,
+            producto.precio_total
+        ))),
+        None => Ok(()),
+    }
 }
 
 /// Valida que la suma de productos coincida con el total del ticket
-fn validate_totals(productos: &[PurchaseProductInsert], expected_total: Decimal) -> AppResult<()> {
+pub fn validate_totals(
+    productos: &[PurchaseProductInsert],
+    expected_total: Decimal,
+) -> AppResult<()> {
     let suma_productos: Decimal = productos.iter().map(|p| p.precio_total).sum();
 
     let diff = (suma_productos - expected_total).abs();
@@ -312,9 +822,237 @@ fn validate_totals(productos: &[PurchaseProductInsert], expected_total: Decimal)
     Ok(())
 }
 
-/// Decodifica el PDF en base64 a bytes
-fn decode_pdf_base64(pdf_b64: &str) -> AppResult<Vec<u8>> {
+/// Convierte el desglose de IVA del OCR, agrupando por tipo normalizado
+///
+/// Se descartan las filas con importes negativos o no numéricos.
+fn parse_iva_breakdown(desglose: &[IvaBreakdown]) -> Vec<PurchaseIvaInsert> {
+    let mut por_tipo: BTreeMap<Decimal, PurchaseIvaInsert> = BTreeMap::new();
+
+    for fila in desglose {
+        let (Some(porcentaje), Some(base), Some(cuota)) = (
+            Decimal::from_f64(fila.porcentaje),
+            Decimal::from_f64(fila.base_imponible),
+            Decimal::from_f64(fila.cuota),
+        ) else {
+            tracing::warn!("Fila del desglose de IVA con importes no numericos");
+            continue;
+        };
+        if base < Decimal::ZERO || cuota < Decimal::ZERO {
+            tracing::warn!("Fila del desglose de IVA con importes negativos");
+            continue;
+        }
+
+        let iva_porcentaje = PurchaseProductInsert::normalize_iva_percentage(porcentaje);
+        let entry = por_tipo
+            .entry(iva_porcentaje)
+            .or_insert_with(|| PurchaseIvaInsert {
+                iva_porcentaje,
+                base_imponible: Decimal::ZERO,
+                cuota: Decimal::ZERO,
+            });
+        entry.base_imponible += base.round_dp(2);
+        entry.cuota += cuota.round_dp(2);
+    }
+
+    por_tipo.into_values().collect()
+}
+
+/// Compara la cuota de IVA de las líneas con el desglose del ticket
+///
+/// Cada línea redondea su cuota por separado, así que se admite un céntimo
+/// de diferencia por línea (dos como mínimo). Sin desglose no hay nada que
+/// comparar.
+pub fn check_iva_breakdown(
+    productos: &[PurchaseProductInsert],
+    desglose: &[PurchaseIvaInsert],
+) -> Vec<IvaMismatch> {
+    if desglose.is_empty() {
+        return Vec::new();
+    }
+
+    // Tipo -> (cuota de las líneas, número de líneas, cuota del ticket)
+    let mut por_tipo: BTreeMap<Decimal, (Decimal, i64, Decimal)> = BTreeMap::new();
+    for producto in productos {
+        let entry = por_tipo.entry(producto.iva_porcentaje).or_default();
+        entry.0 += producto.iva_importe;
+        entry.1 += 1;
+    }
+    for fila in desglose {
+        por_tipo.entry(fila.iva_porcentaje).or_default().2 += fila.cuota;
+    }
+
+    por_tipo
+        .into_iter()
+        .filter_map(|(iva_porcentaje, (cuota_lineas, lineas, cuota_ticket))| {
+            let diferencia = cuota_lineas - cuota_ticket;
+            let tolerancia = Decimal::new(lineas.max(2), 2);
+            (diferencia.abs() > tolerancia).then_some(IvaMismatch {
+                iva_porcentaje,
+                cuota_lineas,
+                cuota_ticket,
+                diferencia,
+            })
+        })
+        .collect()
+}
+
+/// Decodifica el archivo en base64 a bytes
+pub fn decode_file_base64(file_b64: &str) -> AppResult<Vec<u8>> {
     general_purpose::STANDARD
-        .decode(pdf_b64)
-        .map_err(|e| AppError::InvalidTicketData(format!("PDF base64 inválido: {}", e)))
+        .decode(file_b64)
+        .map_err(|e| AppError::InvalidTicketData(format!("Archivo base64 inválido: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ticket_product(
+        nombre: &str,
+        cantidad: f64,
+        unidad: &str,
+        unitario: f64,
+        total: f64,
+    ) -> TicketProduct {
+        TicketProduct {
+            nombre: nombre.to_string(),
+            cantidad,
+            unidad: unidad.to_string(),
+            precio_unitario: unitario,
+            precio_total: total,
+            descuento: 0.0,
+            iva_porcentaje: 4.0,
+            iva_importe: 0.0,
+        }
+    }
+
+    fn linea(iva_porcentaje: i64, precio_total: i64) -> PurchaseProductInsert {
+        let iva_porcentaje = Decimal::new(iva_porcentaje, 0);
+        let precio_total = Decimal::new(precio_total, 2);
+        PurchaseProductInsert {
+            producto_nombre: "PRODUCTO".to_string(),
+            cantidad: Decimal::ONE,
+            precio_unitario: precio_total,
+            precio_total,
+            descuento: Decimal::ZERO,
+            iva_porcentaje,
+            iva_importe: PurchaseProductInsert::calculate_iva_importe(precio_total, iva_porcentaje),
+        }
+    }
+
+    #[test]
+    fn test_check_iva_breakdown() {
+        let desglose = parse_iva_breakdown(&[
+            IvaBreakdown {
+                porcentaje: 21.0,
+                base_imponible: 8.26,
+                cuota: 1.74,
+            },
+            IvaBreakdown {
+                porcentaje: 4.0,
+                base_imponible: 4.81,
+                cuota: 0.19,
+            },
+            IvaBreakdown {
+                porcentaje: 10.0,
+                base_imponible: -1.0,
+                cuota: 0.1,
+            },
+        ]);
+        assert_eq!(desglose.len(), 2);
+        assert_eq!(desglose[0].iva_porcentaje, Decimal::new(4, 0));
+
+        // Las cuotas de las líneas cuadran con el pie del ticket
+        let productos = [linea(21, 600), linea(21, 400), linea(4, 500)];
+        assert_eq!(productos[0].iva_importe, Decimal::new(104, 2));
+        assert!(check_iva_breakdown(&productos, &desglose).is_empty());
+
+        // Falta una línea al 4%
+        let descuadres = check_iva_breakdown(&productos[..2], &desglose);
+        assert_eq!(descuadres.len(), 1);
+        assert_eq!(descuadres[0].iva_porcentaje, Decimal::new(4, 0));
+        assert_eq!(descuadres[0].diferencia, Decimal::new(-19, 2));
+
+        // Sin desglose no se compara
+        assert!(check_iva_breakdown(&productos, &[]).is_empty());
+    }
+
+    #[test]
+    fn test_split_weighted_detail() {
+        let (nombre, detalle) = split_weighted_detail("Plátano 1,234 kg x 2,99 €/kg");
+        assert_eq!(nombre, "PLÁTANO");
+        assert_eq!(
+            detalle,
+            Some(WeightedDetail {
+                cantidad: Decimal::new(1234, 3),
+                unidad: ProductUnit::Kg,
+                precio_unitario: Decimal::new(299, 2),
+            })
+        );
+
+        let (nombre, detalle) = split_weighted_detail("SALMON 0,250KG * 12,90");
+        assert_eq!(nombre, "SALMON");
+        assert_eq!(detalle.map(|d| d.cantidad), Some(Decimal::new(250, 3)));
+
+        // Sin detalle de peso el nombre no cambia
+        let (nombre, detalle) = split_weighted_detail("LECHE 1L");
+        assert_eq!(nombre, "LECHE 1L");
+        assert_eq!(detalle, None);
+    }
+
+    #[test]
+    fn test_parse_weighted_products() {
+        let aliases = HashMap::new();
+
+        let linea = parse_producto(
+            &ticket_product(
+                "MANZANA GOLDEN 1,234 kg x 2,99 €/kg",
+                1.0,
+                "unidad",
+                3.69,
+                3.69,
+            ),
+            &aliases,
+        )
+        .unwrap();
+        assert_eq!(linea.unidad, ProductUnit::Kg);
+        assert_eq!(linea.producto.producto_nombre, "MANZANA GOLDEN");
+        assert_eq!(linea.producto.cantidad, Decimal::new(1234, 3));
+        assert_eq!(linea.producto.precio_unitario, Decimal::new(299, 2));
+
+        // Gramos a kg con el precio por kg
+        let linea =
+            parse_producto(&ticket_product("JAMON", 250.0, "g", 0.02, 4.5), &aliases).unwrap();
+        assert_eq!(linea.unidad, ProductUnit::Kg);
+        assert_eq!(linea.producto.cantidad, Decimal::new(250, 3));
+        assert_eq!(linea.producto.precio_unitario, Decimal::new(1800, 2));
+
+        // 2500 g por 7,49 €: 3,00 €/kg daría 7,50 €
+        let linea = parse_producto(
+            &ticket_product("NARANJA", 2500.0, "g", 0.003, 7.49),
+            &aliases,
+        )
+        .unwrap();
+        assert_eq!(linea.unidad, ProductUnit::Kg);
+        assert_eq!(linea.producto.cantidad, Decimal::new(2500, 3));
+        assert_eq!(linea.producto.precio_total, Decimal::new(749, 2));
+        assert!(linea.producto.validate_price_coherence());
+
+        // Peso deducido del importe cuando el OCR devuelve cantidad 1
+        let linea =
+            parse_producto(&ticket_product("MERLUZA", 1.0, "kg", 10.0, 5.0), &aliases).unwrap();
+        assert_eq!(linea.producto.cantidad, Decimal::new(500, 3));
+
+        // Las líneas por unidades incoherentes se rechazan antes de la transacción
+        assert!(matches!(
+            parse_producto(&ticket_product("LECHE", 3.0, "unidad", 1.0, 2.5), &aliases),
+            Err(AppError::InvalidTicketData(_))
+        ));
+
+        // Unidad desconocida
+        let linea =
+            parse_producto(&ticket_product("PAN", 2.0, "pack", 0.5, 1.0), &aliases).unwrap();
+        assert_eq!(linea.unidad, ProductUnit::Unidad);
+        assert_eq!(linea.producto.cantidad, Decimal::new(2, 0));
+    }
 }
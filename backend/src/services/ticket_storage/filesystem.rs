@@ -0,0 +1,102 @@
+use std::path::PathBuf;
+
+use async_trait::async_trait;
+use uuid::Uuid;
+
+use super::{validate_key, StorageError, TicketStorage};
+use crate::config::StorageBackend;
+
+/// Guarda los archivos en un directorio local, usando la clave como ruta relativa
+pub struct FilesystemTicketStorage {
+    root: PathBuf,
+}
+
+impl FilesystemTicketStorage {
+    pub fn new(root: PathBuf) -> Self {
+        Self { root }
+    }
+
+    fn path_for(&self, key: &str) -> Result<PathBuf, StorageError> {
+        validate_key(key)?;
+        Ok(self.root.join(key))
+    }
+}
+
+#[async_trait]
+impl TicketStorage for FilesystemTicketStorage {
+    fn backend(&self) -> StorageBackend {
+        StorageBackend::Filesystem
+    }
+
+    async fn put(&self, key: &str, content: &[u8], _mime_type: &str) -> Result<(), StorageError> {
+        let path = self.path_for(key)?;
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+
+        // Escritura atómica: nunca se sirve un archivo a medio escribir
+        let tmp_path = path.with_extension(format!("tmp-{}", Uuid::new_v4()));
+        tokio::fs::write(&tmp_path, content).await?;
+        if let Err(err) = tokio::fs::rename(&tmp_path, &path).await {
+            let _ = tokio::fs::remove_file(&tmp_path).await;
+            return Err(err.into());
+        }
+
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, StorageError> {
+        let path = self.path_for(key)?;
+
+        match tokio::fs::read(&path).await {
+            Ok(content) => Ok(Some(content)),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), StorageError> {
+        let path = self.path_for(key)?;
+
+        match tokio::fs::remove_file(&path).await {
+            Ok(()) => Ok(()),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_filesystem_storage_roundtrip() {
+        let root = std::env::temp_dir().join(format!("mercastats-storage-{}", Uuid::new_v4()));
+        let storage = FilesystemTicketStorage::new(root.clone());
+        let key = "tickets/0001-001-000001/original";
+
+        storage
+            .put(key, b"%PDF-1", "application/pdf")
+            .await
+            .unwrap();
+        storage
+            .put(key, b"%PDF-2", "application/pdf")
+            .await
+            .unwrap();
+        assert_eq!(storage.get(key).await.unwrap(), Some(b"%PDF-2".to_vec()));
+
+        storage.delete(key).await.unwrap();
+        storage.delete(key).await.unwrap();
+        assert_eq!(storage.get(key).await.unwrap(), None);
+
+        // Las claves no pueden salir del directorio raiz
+        assert!(storage
+            .put("../fuera", b"x", "application/pdf")
+            .await
+            .is_err());
+        assert!(storage.get("/etc/passwd").await.is_err());
+
+        let _ = tokio::fs::remove_dir_all(root).await;
+    }
+}
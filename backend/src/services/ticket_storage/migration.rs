@@ -0,0 +1,87 @@
+use serde::Serialize;
+use sqlx::PgPool;
+
+use super::{StorageError, TicketStorage};
+use crate::db;
+
+/// Resultado de mover los archivos de tickets entre backends
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct StorageMigrationReport {
+    pub migrados: usize,
+    pub no_encontrados: usize,
+    pub fallidos: usize,
+}
+
+/// Mueve todos los archivos guardados en `source` a `target`
+///
+/// Cada archivo se copia, se actualiza su fila en `tickets_adjuntos` y solo
+/// entonces se borra del origen, por lo que el proceso puede relanzarse si se
+/// interrumpe.
+pub async fn migrate_ticket_files(
+    pool: &PgPool,
+    source: &dyn TicketStorage,
+    target: &dyn TicketStorage,
+) -> Result<StorageMigrationReport, StorageError> {
+    let mut report = StorageMigrationReport::default();
+
+    if source.backend() == target.backend() {
+        return Ok(report);
+    }
+
+    let attachments =
+        db::get_ticket_attachments_by_storage(pool, source.backend().as_str()).await?;
+
+    tracing::info!(
+        "Migrando {} archivos de tickets de {} a {}",
+        attachments.len(),
+        source.backend().as_str(),
+        target.backend().as_str()
+    );
+
+    for attachment in attachments {
+        let key = &attachment.clave_almacenamiento;
+
+        let content = match source.get(key).await {
+            Ok(Some(content)) => content,
+            Ok(None) => {
+                tracing::warn!("Archivo de ticket no encontrado en el origen");
+                report.no_encontrados += 1;
+                continue;
+            }
+            Err(err) => {
+                tracing::error!("No se pudo leer un archivo de ticket: {}", err);
+                report.fallidos += 1;
+                continue;
+            }
+        };
+
+        if let Err(err) = target.put(key, &content, &attachment.mime_type).await {
+            tracing::error!("No se pudo escribir un archivo de ticket: {}", err);
+            report.fallidos += 1;
+            continue;
+        }
+
+        db::update_ticket_attachment_storage(
+            pool,
+            &attachment.numero_factura,
+            target.backend().as_str(),
+            key,
+        )
+        .await?;
+
+        if let Err(err) = source.delete(key).await {
+            tracing::warn!("No se pudo borrar el archivo migrado del origen: {}", err);
+        }
+
+        report.migrados += 1;
+    }
+
+    tracing::info!(
+        "Migracion completada: {} migrados, {} no encontrados, {} fallidos",
+        report.migrados,
+        report.no_encontrados,
+        report.fallidos
+    );
+
+    Ok(report)
+}
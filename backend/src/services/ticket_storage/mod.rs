@@ -0,0 +1,242 @@
+//! Almacenamiento intercambiable de los archivos originales de los tickets.
+//!
+//! `tickets_adjuntos` guarda los metadatos (backend y clave); el contenido se
+//! escribe y se lee a través de [`TicketStorage`].
+
+mod filesystem;
+mod migration;
+mod postgres;
+mod s3;
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use sqlx::PgPool;
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::{
+    config::{StorageBackend, TicketStorageConfig},
+    models::TicketAttachment,
+};
+
+pub use filesystem::FilesystemTicketStorage;
+pub use migration::migrate_ticket_files;
+pub use postgres::PostgresTicketStorage;
+pub use s3::S3TicketStorage;
+
+#[derive(Debug, Error)]
+pub enum StorageError {
+    #[error("clave de almacenamiento invalida")]
+    InvalidKey,
+    #[error("configuracion de almacenamiento invalida: {0}")]
+    Config(String),
+    #[error("error de entrada/salida: {0}")]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Database(#[from] sqlx::Error),
+    #[error("respuesta inesperada del almacenamiento S3 ({0})")]
+    UnexpectedStatus(reqwest::StatusCode),
+    #[error(transparent)]
+    Request(#[from] reqwest::Error),
+}
+
+/// Backend de almacenamiento de archivos de tickets
+#[async_trait]
+pub trait TicketStorage: Send + Sync {
+    /// Backend que implementa, tal y como se registra en `tickets_adjuntos`
+    fn backend(&self) -> StorageBackend;
+
+    /// Guarda el contenido bajo `key`, sustituyéndolo si ya existía
+    async fn put(&self, key: &str, content: &[u8], mime_type: &str) -> Result<(), StorageError>;
+
+    /// Recupera el contenido guardado bajo `key`
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, StorageError>;
+
+    /// Elimina el contenido guardado bajo `key`. No falla si no existe
+    async fn delete(&self, key: &str) -> Result<(), StorageError>;
+}
+
+/// Construye el backend indicado a partir de la configuración
+pub fn build_ticket_storage(
+    backend: StorageBackend,
+    config: &TicketStorageConfig,
+    pool: &PgPool,
+) -> Result<Arc<dyn TicketStorage>, StorageError> {
+    let storage: Arc<dyn TicketStorage> = match backend {
+        StorageBackend::Postgres => Arc::new(PostgresTicketStorage::new(pool.clone())),
+        StorageBackend::Filesystem => {
+            Arc::new(FilesystemTicketStorage::new(config.filesystem_path.clone()))
+        }
+        StorageBackend::S3 => {
+            let s3 = config.s3.as_ref().ok_or_else(|| {
+                StorageError::Config("faltan S3_ENDPOINT y S3_BUCKET".to_string())
+            })?;
+            Arc::new(S3TicketStorage::new(s3)?)
+        }
+    };
+
+    Ok(storage)
+}
+
+/// Backends de almacenamiento disponibles
+///
+/// Los archivos nuevos se guardan en el backend activo (`TICKET_STORAGE`).
+/// El resto de backends configurados se conservan para leer y borrar los
+/// archivos que se guardaron antes de cambiar de backend, según la columna
+/// `almacenamiento` de cada adjunto.
+#[derive(Clone)]
+pub struct TicketStorages {
+    active: Arc<dyn TicketStorage>,
+    others: Vec<Arc<dyn TicketStorage>>,
+}
+
+impl TicketStorages {
+    /// Construye el backend activo y los demás que estén configurados
+    ///
+    /// S3 solo está disponible si hay `S3_ENDPOINT` y `S3_BUCKET`.
+    pub fn build(config: &TicketStorageConfig, pool: &PgPool) -> Result<Self, StorageError> {
+        let active = build_ticket_storage(config.backend, config, pool)?;
+
+        let mut others = Vec::new();
+        for backend in [
+            StorageBackend::Postgres,
+            StorageBackend::Filesystem,
+            StorageBackend::S3,
+        ] {
+            if backend == config.backend {
+                continue;
+            }
+            match build_ticket_storage(backend, config, pool) {
+                Ok(storage) => others.push(storage),
+                Err(StorageError::Config(reason)) => {
+                    tracing::debug!("Backend {} no disponible: {}", backend.as_str(), reason)
+                }
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(Self { active, others })
+    }
+
+    /// Backend donde se guardan los archivos nuevos
+    pub fn active(&self) -> &dyn TicketStorage {
+        self.active.as_ref()
+    }
+
+    /// Backend en el que está guardado un adjunto
+    pub fn for_attachment(&self, attachment: &TicketAttachment) -> Option<&dyn TicketStorage> {
+        std::iter::once(&self.active)
+            .chain(&self.others)
+            .find(|storage| storage.backend().as_str() == attachment.almacenamiento)
+            .map(|storage| storage.as_ref())
+    }
+}
+
+/// Genera una clave nueva para el archivo de un ticket
+///
+/// Cada versión del archivo tiene su propia clave, de modo que reemplazar un
+/// ticket no pisa el archivo anterior hasta que la transacción se confirma.
+pub fn new_storage_key(numero_factura: &str) -> String {
+    format!(
+        "tickets/{}/{}",
+        sanitize_key_segment(numero_factura),
+        Uuid::new_v4()
+    )
+}
+
+/// Borra el archivo de un ticket que ha dejado de estar referenciado
+///
+/// Es un borrado de limpieza: los fallos se registran pero no se propagan.
+pub async fn remove_stored_file(storages: &TicketStorages, attachment: &TicketAttachment) {
+    let Some(storage) = storages.for_attachment(attachment) else {
+        tracing::warn!(
+            "Archivo de ticket en el backend {}, que no está configurado; no se borra",
+            attachment.almacenamiento
+        );
+        return;
+    };
+
+    if let Err(err) = storage.delete(&attachment.clave_almacenamiento).await {
+        tracing::warn!("No se pudo borrar un archivo de ticket: {}", err);
+    }
+}
+
+/// Comprueba que una clave solo contenga segmentos seguros
+pub(crate) fn validate_key(key: &str) -> Result<(), StorageError> {
+    let valid = !key.is_empty()
+        && key.split('/').all(|segment| {
+            !segment.is_empty()
+                && segment != "."
+                && segment != ".."
+                && segment
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        });
+
+    if valid {
+        Ok(())
+    } else {
+        Err(StorageError::InvalidKey)
+    }
+}
+
+fn sanitize_key_segment(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '-' | '_') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    #[sqlx::test(migrations = "./migrations")]
+    async fn test_storages_dispatch_on_attachment_backend(pool: PgPool) -> sqlx::Result<()> {
+        let root = std::env::temp_dir().join(format!("mercastats-storages-{}", Uuid::new_v4()));
+        let config = TicketStorageConfig {
+            backend: StorageBackend::Filesystem,
+            filesystem_path: root,
+            s3: None,
+        };
+        let storages = TicketStorages::build(&config, &pool).unwrap();
+        assert_eq!(storages.active().backend(), StorageBackend::Filesystem);
+
+        // Archivo guardado en Postgres antes de cambiar de backend
+        let key = new_storage_key("0001-001-000001");
+        PostgresTicketStorage::new(pool.clone())
+            .put(&key, b"%PDF-1", "application/pdf")
+            .await
+            .unwrap();
+        let mut attachment = TicketAttachment {
+            numero_factura: "0001-001-000001".to_string(),
+            nombre_archivo: "ticket.pdf".to_string(),
+            mime_type: "application/pdf".to_string(),
+            tamano_bytes: 6,
+            almacenamiento: "postgres".to_string(),
+            clave_almacenamiento: key.clone(),
+            created_at: Utc::now().naive_utc(),
+        };
+
+        let storage = storages.for_attachment(&attachment).unwrap();
+        assert_eq!(storage.backend(), StorageBackend::Postgres);
+        assert_eq!(storage.get(&key).await.unwrap(), Some(b"%PDF-1".to_vec()));
+
+        remove_stored_file(&storages, &attachment).await;
+        assert_eq!(storage.get(&key).await.unwrap(), None);
+
+        // S3 no está configurado
+        attachment.almacenamiento = "s3".to_string();
+        assert!(storages.for_attachment(&attachment).is_none());
+
+        Ok(())
+    }
+}
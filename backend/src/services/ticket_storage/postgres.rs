@@ -0,0 +1,67 @@
+use async_trait::async_trait;
+use sqlx::PgPool;
+
+use super::{validate_key, StorageError, TicketStorage};
+use crate::{config::StorageBackend, db};
+
+/// Guarda los archivos en la tabla `tickets_blobs` (comportamiento por defecto)
+pub struct PostgresTicketStorage {
+    pool: PgPool,
+}
+
+impl PostgresTicketStorage {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl TicketStorage for PostgresTicketStorage {
+    fn backend(&self) -> StorageBackend {
+        StorageBackend::Postgres
+    }
+
+    async fn put(&self, key: &str, content: &[u8], _mime_type: &str) -> Result<(), StorageError> {
+        validate_key(key)?;
+        db::upsert_ticket_blob(&self.pool, key, content).await?;
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, StorageError> {
+        validate_key(key)?;
+        Ok(db::get_ticket_blob(&self.pool, key).await?)
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), StorageError> {
+        validate_key(key)?;
+        db::delete_ticket_blob(&self.pool, key).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[sqlx::test(migrations = "./migrations")]
+    async fn test_postgres_storage_roundtrip(pool: PgPool) -> sqlx::Result<()> {
+        let storage = PostgresTicketStorage::new(pool);
+        let key = "tickets/0001-001-000001/original";
+
+        storage
+            .put(key, b"%PDF-1", "application/pdf")
+            .await
+            .unwrap();
+        storage
+            .put(key, b"%PDF-2", "application/pdf")
+            .await
+            .unwrap();
+        assert_eq!(storage.get(key).await.unwrap(), Some(b"%PDF-2".to_vec()));
+
+        storage.delete(key).await.unwrap();
+        assert_eq!(storage.get(key).await.unwrap(), None);
+        assert!(storage.get("../etc/passwd").await.is_err());
+
+        Ok(())
+    }
+}
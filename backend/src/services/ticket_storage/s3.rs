@@ -0,0 +1,154 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use reqwest::{header, Client, StatusCode};
+use rusty_s3::{Bucket, Credentials, S3Action, UrlStyle};
+
+use super::{validate_key, StorageError, TicketStorage};
+use crate::config::{S3StorageConfig, StorageBackend};
+
+/// Validez de las URLs firmadas para cada petición
+const SIGNATURE_TTL: Duration = Duration::from_secs(300);
+
+/// Guarda los archivos en un bucket compatible con S3 (AWS, MinIO...)
+pub struct S3TicketStorage {
+    bucket: Bucket,
+    credentials: Credentials,
+    http: Client,
+}
+
+impl S3TicketStorage {
+    pub fn new(config: &S3StorageConfig) -> Result<Self, StorageError> {
+        let endpoint = config
+            .endpoint
+            .parse()
+            .map_err(|_| StorageError::Config("S3_ENDPOINT no es una URL valida".to_string()))?;
+        let url_style = if config.path_style {
+            UrlStyle::Path
+        } else {
+            UrlStyle::VirtualHost
+        };
+
+        let bucket = Bucket::new(
+            endpoint,
+            url_style,
+            config.bucket.clone(),
+            config.region.clone(),
+        )
+        .map_err(|_| StorageError::Config("bucket S3 invalido".to_string()))?;
+
+        let http = Client::builder().timeout(Duration::from_secs(60)).build()?;
+
+        Ok(Self {
+            bucket,
+            credentials: Credentials::new(config.access_key.clone(), config.secret_key.clone()),
+            http,
+        })
+    }
+}
+
+#[async_trait]
+impl TicketStorage for S3TicketStorage {
+    fn backend(&self) -> StorageBackend {
+        StorageBackend::S3
+    }
+
+    async fn put(&self, key: &str, content: &[u8], mime_type: &str) -> Result<(), StorageError> {
+        validate_key(key)?;
+        let url = self
+            .bucket
+            .put_object(Some(&self.credentials), key)
+            .sign(SIGNATURE_TTL);
+
+        let response = self
+            .http
+            .put(url)
+            .header(header::CONTENT_TYPE, mime_type)
+            .body(content.to_vec())
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(StorageError::UnexpectedStatus(response.status()));
+        }
+
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> Result<Option<Vec<u8>>, StorageError> {
+        validate_key(key)?;
+        let url = self
+            .bucket
+            .get_object(Some(&self.credentials), key)
+            .sign(SIGNATURE_TTL);
+
+        let response = self.http.get(url).send().await?;
+
+        match response.status() {
+            StatusCode::NOT_FOUND => Ok(None),
+            status if status.is_success() => Ok(Some(response.bytes().await?.to_vec())),
+            status => Err(StorageError::UnexpectedStatus(status)),
+        }
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), StorageError> {
+        validate_key(key)?;
+        let url = self
+            .bucket
+            .delete_object(Some(&self.credentials), key)
+            .sign(SIGNATURE_TTL);
+
+        let response = self.http.delete(url).send().await?;
+
+        match response.status() {
+            StatusCode::NOT_FOUND => Ok(()),
+            status if status.is_success() => Ok(()),
+            status => Err(StorageError::UnexpectedStatus(status)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Necesita un MinIO local y `S3_TEST_ENDPOINT`, así que no se ejecuta por defecto:
+    ///
+    /// ```text
+    /// docker run -p 9000:9000 minio/minio server /data
+    /// S3_TEST_ENDPOINT=http://127.0.0.1:9000 cargo test s3_storage -- --ignored
+    /// ```
+    #[tokio::test]
+    #[ignore = "necesita un servidor S3 en S3_TEST_ENDPOINT"]
+    async fn test_s3_storage_roundtrip() {
+        let endpoint =
+            std::env::var("S3_TEST_ENDPOINT").expect("S3_TEST_ENDPOINT apunta al servidor S3");
+
+        let config = S3StorageConfig {
+            endpoint,
+            bucket: std::env::var("S3_TEST_BUCKET").unwrap_or_else(|_| "mercastats-test".into()),
+            region: "us-east-1".to_string(),
+            access_key: std::env::var("S3_TEST_ACCESS_KEY").unwrap_or_else(|_| "minioadmin".into()),
+            secret_key: std::env::var("S3_TEST_SECRET_KEY").unwrap_or_else(|_| "minioadmin".into()),
+            path_style: true,
+        };
+        let storage = S3TicketStorage::new(&config).unwrap();
+
+        // El bucket puede existir de ejecuciones anteriores
+        let create_url = storage
+            .bucket
+            .create_bucket(&storage.credentials)
+            .sign(SIGNATURE_TTL);
+        storage.http.put(create_url).send().await.unwrap();
+
+        let key = format!("tickets/test/{}", uuid::Uuid::new_v4());
+        storage
+            .put(&key, b"%PDF-1", "application/pdf")
+            .await
+            .unwrap();
+        assert_eq!(storage.get(&key).await.unwrap(), Some(b"%PDF-1".to_vec()));
+
+        storage.delete(&key).await.unwrap();
+        assert_eq!(storage.get(&key).await.unwrap(), None);
+    }
+}
@@ -1,4 +1,5 @@
 pub mod auth;
+pub mod notifications;
 pub mod prediction;
 pub mod stats;
 pub mod tickets;
@@ -12,6 +13,19 @@ pub const API_BASE_URL: &str = "/api";
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ApiError {
     pub error: String,
+    /// Codigo estable del error (`AppError::code` en el backend)
+    #[serde(default)]
+    pub code: String,
+}
+
+impl ApiError {
+    /// Error producido en el cliente, sin respuesta del servidor
+    pub fn local(error: impl Into<String>) -> Self {
+        Self {
+            error: error.into(),
+            code: String::new(),
+        }
+    }
 }
 
 /// Obtener el token de autenticación del localStorage
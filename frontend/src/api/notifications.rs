@@ -0,0 +1,104 @@
+use super::{get_auth_token, ApiError, API_BASE_URL};
+use gloo_net::http::{Request, Response};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+/// Notificación del usuario
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Notification {
+    pub id: i32,
+    /// alerta_gasto, logro, subida_precio o borrador_caduca
+    pub tipo: String,
+    pub titulo: String,
+    pub mensaje: String,
+    pub leida: bool,
+    pub created_at: String,
+}
+
+/// Respuesta del listado de notificaciones
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotificationListResponse {
+    pub notificaciones: Vec<Notification>,
+    pub no_leidas: i64,
+}
+
+/// Número de notificaciones sin leer
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnreadCountResponse {
+    pub no_leidas: i64,
+}
+
+/// Obtener las últimas notificaciones
+pub async fn get_notifications(limit: u32) -> Result<NotificationListResponse, String> {
+    let token = get_auth_token().ok_or_else(|| "No hay sesion activa".to_string())?;
+    let url = format!("{}/notifications?limit={}", API_BASE_URL, limit);
+
+    let response = Request::get(&url)
+        .header("Authorization", &format!("Bearer {}", token))
+        .send()
+        .await
+        .map_err(|e| format!("Error de conexion: {}", e))?;
+
+    parse_response(response, "No se pudieron obtener las notificaciones").await
+}
+
+/// Obtener el número de notificaciones sin leer
+pub async fn get_unread_count() -> Result<UnreadCountResponse, String> {
+    let token = get_auth_token().ok_or_else(|| "No hay sesion activa".to_string())?;
+    let url = format!("{}/notifications/unread-count", API_BASE_URL);
+
+    let response = Request::get(&url)
+        .header("Authorization", &format!("Bearer {}", token))
+        .send()
+        .await
+        .map_err(|e| format!("Error de conexion: {}", e))?;
+
+    parse_response(response, "No se pudo obtener el número de notificaciones").await
+}
+
+/// Marcar una notificación como leída
+pub async fn mark_notification_read(id: i32) -> Result<UnreadCountResponse, String> {
+    let token = get_auth_token().ok_or_else(|| "No hay sesion activa".to_string())?;
+    let url = format!("{}/notifications/{}/read", API_BASE_URL, id);
+
+    let response = Request::post(&url)
+        .header("Authorization", &format!("Bearer {}", token))
+        .send()
+        .await
+        .map_err(|e| format!("Error de conexion: {}", e))?;
+
+    parse_response(response, "No se pudo marcar la notificación").await
+}
+
+/// Marcar todas las notificaciones como leídas
+pub async fn mark_all_notifications_read() -> Result<UnreadCountResponse, String> {
+    let token = get_auth_token().ok_or_else(|| "No hay sesion activa".to_string())?;
+    let url = format!("{}/notifications/read-all", API_BASE_URL);
+
+    let response = Request::post(&url)
+        .header("Authorization", &format!("Bearer {}", token))
+        .send()
+        .await
+        .map_err(|e| format!("Error de conexion: {}", e))?;
+
+    parse_response(response, "No se pudieron marcar las notificaciones").await
+}
+
+async fn parse_response<T: DeserializeOwned>(
+    response: Response,
+    fallback: &str,
+) -> Result<T, String> {
+    if response.ok() {
+        response
+            .json::<T>()
+            .await
+            .map_err(|e| format!("Error al procesar respuesta: {}", e))
+    } else {
+        let status = response.status();
+        let error = response
+            .json::<ApiError>()
+            .await
+            .map(|e| e.error)
+            .unwrap_or_else(|_| format!("Error {}: {}", status, fallback));
+        Err(error)
+    }
+}
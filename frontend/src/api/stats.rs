@@ -13,9 +13,17 @@ pub struct DailySpendPoint {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TopProductItem {
     pub nombre: String,
-    pub cantidad_total: Option<i64>,
+    pub cantidad_total: Option<String>,
     pub gasto_total: Option<String>,
     pub precio_medio: Option<String>,
+    #[serde(default)]
+    pub unidad: Option<String>,
+    #[serde(default)]
+    pub precio_unidad_medida: Option<String>,
+    #[serde(default)]
+    pub unidad_base: Option<String>,
+    #[serde(default)]
+    pub precio_por_unidad_base: Option<String>,
 }
 
 /// Punto de distribución temporal
@@ -1,8 +1,12 @@
 use super::{get_auth_token, ApiError, API_BASE_URL};
 use gloo_net::http::Request;
+use gloo_timers::future::TimeoutFuture;
 use serde::{Deserialize, Serialize};
-use wasm_bindgen::JsValue;
-use web_sys::File;
+use std::cell::{Cell, RefCell};
+use std::rc::{Rc, Weak};
+use wasm_bindgen::{closure::Closure, JsCast, JsValue};
+use wasm_bindgen_futures::spawn_local;
+use web_sys::{EventSource, File, MessageEvent};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UploadResponse {
@@ -100,10 +104,228 @@ pub struct TicketIngestionResponse {
     pub productos_insertados: Option<usize>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TicketDraftSummary {
+    pub id: String,
+    pub nombre_archivo: String,
+    pub expira_en: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct ProcessTicketResponse {
     pub ocr: OcrResponseSummary,
     pub ingestion: Option<TicketIngestionResponse>,
+    /// Borrador guardado en el servidor, pendiente de confirmar
+    #[serde(default)]
+    pub draft: Option<TicketDraftSummary>,
+}
+
+// ===== Eventos de progreso (SSE) =====
+
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+pub struct TicketEventError {
+    pub code: String,
+    pub message: String,
+}
+
+/// Fase de un ticket publicada por el servidor mientras lo procesa
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+pub struct TicketProgressEvent {
+    pub ticket_id: String,
+    pub file_name: String,
+    pub stage: String,
+    #[serde(default)]
+    pub warnings: Vec<String>,
+    #[serde(default)]
+    pub error: Option<TicketEventError>,
+}
+
+/// Espera antes de reabrir el canal de eventos tras perder la conexion
+const EVENTS_RECONNECT_MS: u32 = 3_000;
+
+#[derive(Debug, Clone, Deserialize)]
+struct EventsTokenResponse {
+    token: String,
+}
+
+/// Conexion abierta con `/api/ocr/events`; se cierra al soltarla
+pub struct TicketEventsSubscription {
+    connection: Rc<EventsConnection>,
+}
+
+struct EventsConnection {
+    on_event: Box<dyn Fn(TicketProgressEvent)>,
+    on_resync: Box<dyn Fn()>,
+    source: RefCell<Option<EventSource>>,
+    listeners: RefCell<Vec<Closure<dyn FnMut(web_sys::Event)>>>,
+    closed: Cell<bool>,
+}
+
+impl EventsConnection {
+    fn close_source(&self) {
+        if let Some(source) = self.source.borrow_mut().take() {
+            source.close();
+        }
+        self.listeners.borrow_mut().clear();
+    }
+}
+
+impl Drop for TicketEventsSubscription {
+    fn drop(&mut self) {
+        self.connection.closed.set(true);
+        self.connection.close_source();
+    }
+}
+
+/// Pedir el token de corta duracion con el que se abre el canal de eventos
+async fn create_events_token() -> Result<String, String> {
+    let token = get_auth_token().ok_or_else(|| "No hay sesion activa".to_string())?;
+    let url = format!("{}/ocr/events/token", API_BASE_URL);
+
+    let response = Request::post(&url)
+        .header("Authorization", &format!("Bearer {}", token))
+        .send()
+        .await
+        .map_err(|e| format!("Error de conexion: {}", e))?;
+
+    if response.ok() {
+        response
+            .json::<EventsTokenResponse>()
+            .await
+            .map(|r| r.token)
+            .map_err(|e| format!("Error al procesar respuesta: {}", e))
+    } else {
+        let status = response.status();
+        let error = response
+            .json::<ApiError>()
+            .await
+            .map(|e| e.error)
+            .unwrap_or_else(|_| format!("Error {}: No se pudo abrir el canal de eventos", status));
+        Err(error)
+    }
+}
+
+/// Escuchar los eventos de progreso de los tickets del usuario
+///
+/// El token de sesion no viaja en la URL: cada conexion usa un token de
+/// eventos nuevo, tambien al reconectar cuando el servidor cierra el canal.
+/// `on_resync` se llama cuando el servidor avisa de que se han perdido
+/// eventos y el progreso mostrado puede estar desfasado.
+pub fn subscribe_ticket_events(
+    on_event: impl Fn(TicketProgressEvent) + 'static,
+    on_resync: impl Fn() + 'static,
+) -> Result<TicketEventsSubscription, String> {
+    get_auth_token().ok_or_else(|| "No hay sesion activa".to_string())?;
+
+    let connection = Rc::new(EventsConnection {
+        on_event: Box::new(on_event),
+        on_resync: Box::new(on_resync),
+        source: RefCell::new(None),
+        listeners: RefCell::new(Vec::new()),
+        closed: Cell::new(false),
+    });
+
+    spawn_local(connect_ticket_events(Rc::downgrade(&connection), 0));
+
+    Ok(TicketEventsSubscription { connection })
+}
+
+async fn connect_ticket_events(connection: Weak<EventsConnection>, delay_ms: u32) {
+    if delay_ms > 0 {
+        TimeoutFuture::new(delay_ms).await;
+    }
+
+    let token = match create_events_token().await {
+        Ok(token) => token,
+        Err(err) => {
+            log::warn!("Sin eventos de progreso: {}", err);
+            if get_auth_token().is_some() {
+                reconnect_ticket_events(connection);
+            }
+            return;
+        }
+    };
+
+    let Some(conn) = connection.upgrade().filter(|c| !c.closed.get()) else {
+        return;
+    };
+
+    // EventSource no permite cabeceras, el token de eventos viaja en la query
+    let url = format!(
+        "{}/ocr/events?token={}",
+        API_BASE_URL,
+        String::from(js_sys::encode_uri_component(&token))
+    );
+    let source = match EventSource::new(&url) {
+        Ok(source) => source,
+        Err(_) => {
+            log::warn!("No se pudo abrir el canal de eventos");
+            return;
+        }
+    };
+
+    let weak = connection.clone();
+    let on_ticket = Closure::<dyn FnMut(web_sys::Event)>::new(move |event: web_sys::Event| {
+        let data = event
+            .dyn_ref::<MessageEvent>()
+            .and_then(|e| e.data().as_string());
+        if let (Some(conn), Some(data)) = (weak.upgrade(), data) {
+            if let Ok(progress) = serde_json::from_str::<TicketProgressEvent>(&data) {
+                (conn.on_event)(progress);
+            }
+        }
+    });
+
+    let weak = connection.clone();
+    let on_resync = Closure::<dyn FnMut(web_sys::Event)>::new(move |_: web_sys::Event| {
+        if let Some(conn) = weak.upgrade() {
+            (conn.on_resync)();
+        }
+    });
+
+    // El navegador reintenta solo los cortes de red; si el servidor rechaza
+    // el token (ya caducado) el canal queda cerrado y hay que pedir otro
+    let weak = connection.clone();
+    let on_error = Closure::<dyn FnMut(web_sys::Event)>::new(move |_: web_sys::Event| {
+        let Some(conn) = weak.upgrade() else {
+            return;
+        };
+        let cerrado = conn
+            .source
+            .borrow()
+            .as_ref()
+            .map(|s| s.ready_state() == EventSource::CLOSED)
+            .unwrap_or(false);
+        // Los listeners se sustituyen al reconectar, no desde su propio callback
+        if cerrado && !conn.closed.get() {
+            if let Some(source) = conn.source.borrow_mut().take() {
+                source.close();
+            }
+            reconnect_ticket_events(weak.clone());
+        }
+    });
+
+    let listening = source
+        .add_event_listener_with_callback("ticket", on_ticket.as_ref().unchecked_ref())
+        .and_then(|_| {
+            source.add_event_listener_with_callback("resync", on_resync.as_ref().unchecked_ref())
+        })
+        .and_then(|_| {
+            source.add_event_listener_with_callback("error", on_error.as_ref().unchecked_ref())
+        });
+    if listening.is_err() {
+        log::warn!("No se pudo escuchar el canal de eventos");
+        source.close();
+        return;
+    }
+
+    conn.close_source();
+    *conn.source.borrow_mut() = Some(source);
+    *conn.listeners.borrow_mut() = vec![on_ticket, on_resync, on_error];
+}
+
+fn reconnect_ticket_events(connection: Weak<EventsConnection>) {
+    spawn_local(connect_ticket_events(connection, EVENTS_RECONNECT_MS));
 }
 
 // Legacy response para compatibilidad con codigo existente
@@ -194,12 +416,15 @@ pub async fn get_user_ticket_history() -> Result<TicketHistoryResponse, String>
 }
 
 /// Procesar ticket con OCR (PDF o imagen) e ingestarlo en la base de datos
-pub async fn process_ticket_ocr(file: File, ingest: bool) -> Result<ProcessTicketResponse, String> {
+///
+/// `ticket_id` identifica el ticket en los eventos de progreso del servidor.
+pub async fn process_ticket_ocr(
+    file: File,
+    ticket_id: String,
+    ingest: bool,
+) -> Result<ProcessTicketResponse, String> {
     let url = format!("{}/ocr/process", API_BASE_URL);
     let token = get_auth_token().ok_or_else(|| "No hay sesion activa".to_string())?;
-
-    // Generar ID unico para el ticket
-    let ticket_id = format!("ticket_{}", js_sys::Date::now() as u64);
     let file_name = file.name();
     let mime_type = {
         let mt = file.type_();
@@ -262,6 +487,128 @@ pub async fn process_ticket_ocr(file: File, ingest: bool) -> Result<ProcessTicke
     }
 }
 
+/// Confirmar un borrador procesado por OCR sin volver a subir el archivo
+///
+/// Devuelve el error estructurado para distinguir un borrador caducado
+/// (`not_found`) del resto de fallos.
+pub async fn confirm_ticket_draft(draft_id: &str) -> Result<TicketIngestionResponse, ApiError> {
+    let url = format!("{}/tickets/drafts/{}/confirm", API_BASE_URL, draft_id);
+    let token = get_auth_token().ok_or_else(|| ApiError::local("No hay sesion activa"))?;
+
+    let response = Request::post(&url)
+        .header("Authorization", &format!("Bearer {}", token))
+        .json(&serde_json::json!({}))
+        .map_err(|e| ApiError::local(format!("Error al preparar peticion: {}", e)))?
+        .send()
+        .await
+        .map_err(|e| ApiError::local(format!("Error de conexion: {}", e)))?;
+
+    if response.ok() {
+        response
+            .json::<TicketIngestionResponse>()
+            .await
+            .map_err(|e| ApiError::local(format!("Error al procesar respuesta: {}", e)))
+    } else {
+        let status = response.status();
+        Err(response.json::<ApiError>().await.unwrap_or_else(|_| {
+            ApiError::local(format!("Error {}: No se pudo confirmar el ticket", status))
+        }))
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct TicketDraftListResponse {
+    borradores: Vec<TicketDraftSummary>,
+}
+
+/// Borrador pendiente con el resultado del OCR
+#[derive(Debug, Clone, Deserialize)]
+pub struct TicketDraftDetail {
+    pub id: String,
+    pub nombre_archivo: String,
+    pub expira_en: String,
+    pub ocr: OcrResponseSummary,
+}
+
+/// Obtener los borradores pendientes de confirmar del usuario
+pub async fn get_ticket_drafts() -> Result<Vec<TicketDraftSummary>, String> {
+    let url = format!("{}/tickets/drafts", API_BASE_URL);
+    let token = get_auth_token().ok_or_else(|| "No hay sesion activa".to_string())?;
+
+    let response = Request::get(&url)
+        .header("Authorization", &format!("Bearer {}", token))
+        .send()
+        .await
+        .map_err(|e| format!("Error de conexion: {}", e))?;
+
+    if response.ok() {
+        response
+            .json::<TicketDraftListResponse>()
+            .await
+            .map(|r| r.borradores)
+            .map_err(|e| format!("Error al procesar respuesta: {}", e))
+    } else {
+        let status = response.status();
+        let error = response
+            .json::<ApiError>()
+            .await
+            .map(|e| e.error)
+            .unwrap_or_else(|_| format!("Error {}: No se pudieron obtener los borradores", status));
+        Err(error)
+    }
+}
+
+/// Recuperar un borrador para revisarlo de nuevo
+pub async fn get_ticket_draft(draft_id: &str) -> Result<TicketDraftDetail, String> {
+    let url = format!("{}/tickets/drafts/{}", API_BASE_URL, draft_id);
+    let token = get_auth_token().ok_or_else(|| "No hay sesion activa".to_string())?;
+
+    let response = Request::get(&url)
+        .header("Authorization", &format!("Bearer {}", token))
+        .send()
+        .await
+        .map_err(|e| format!("Error de conexion: {}", e))?;
+
+    if response.ok() {
+        response
+            .json::<TicketDraftDetail>()
+            .await
+            .map_err(|e| format!("Error al procesar respuesta: {}", e))
+    } else {
+        let status = response.status();
+        let error = response
+            .json::<ApiError>()
+            .await
+            .map(|e| e.error)
+            .unwrap_or_else(|_| format!("Error {}: No se pudo recuperar el borrador", status));
+        Err(error)
+    }
+}
+
+/// Descartar un borrador en el servidor
+pub async fn discard_ticket_draft(draft_id: &str) -> Result<(), String> {
+    let url = format!("{}/tickets/drafts/{}", API_BASE_URL, draft_id);
+    let token = get_auth_token().ok_or_else(|| "No hay sesion activa".to_string())?;
+
+    let response = Request::delete(&url)
+        .header("Authorization", &format!("Bearer {}", token))
+        .send()
+        .await
+        .map_err(|e| format!("Error de conexion: {}", e))?;
+
+    if response.ok() {
+        Ok(())
+    } else {
+        let status = response.status();
+        let error = response
+            .json::<ApiError>()
+            .await
+            .map(|e| e.error)
+            .unwrap_or_else(|_| format!("Error {}: No se pudo descartar el borrador", status));
+        Err(error)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TicketHistoryItem {
     pub numero_factura: String,
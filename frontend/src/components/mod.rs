@@ -5,6 +5,7 @@ pub mod card;
 pub mod chart;
 pub mod fade_transition;
 pub mod input;
+pub mod notification_bell;
 pub mod prediction_card;
 pub mod product_list_modal;
 pub mod sidebar;
@@ -13,5 +14,6 @@ pub use animated_counter::KpiCard;
 pub use button::{Button, ButtonVariant};
 pub use card::Card;
 pub use chart::{Chart, ChartSeriesData, ChartType};
+pub use notification_bell::NotificationBell;
 pub use product_list_modal::ProductListModal;
 pub use sidebar::Sidebar;
@@ -0,0 +1,182 @@
+use crate::api::notifications::{
+    get_notifications, get_unread_count, mark_all_notifications_read, mark_notification_read,
+    Notification,
+};
+use leptos::*;
+use std::time::Duration;
+
+/// Cada cuánto se consulta el número de notificaciones sin leer
+const POLL_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Notificaciones que se muestran al abrir el panel
+const PANEL_LIMIT: u32 = 10;
+
+/// Color del indicador según el tipo de notificación
+fn kind_color(tipo: &str) -> &'static str {
+    match tipo {
+        "alerta_gasto" => "bg-red-500",
+        "logro" => "bg-amber-400",
+        "subida_precio" => "bg-orange-500",
+        "borrador_caduca" => "bg-blue-500",
+        _ => "bg-gray-400",
+    }
+}
+
+/// Campana con el número de notificaciones sin leer y el panel con las últimas
+#[component]
+pub fn NotificationBell(#[prop(into)] is_demo: Signal<bool>) -> impl IntoView {
+    let (unread, set_unread) = create_signal(0i64);
+    let (open, set_open) = create_signal(false);
+    let (items, set_items) = create_signal(Vec::<Notification>::new());
+    let (loading, set_loading) = create_signal(false);
+    let (error, set_error) = create_signal(None::<String>);
+
+    let refresh_count = move || {
+        spawn_local(async move {
+            if let Ok(response) = get_unread_count().await {
+                set_unread.set(response.no_leidas);
+            }
+        });
+    };
+
+    // Consultar al montar y después periódicamente
+    refresh_count();
+    if let Ok(handle) = set_interval_with_handle(refresh_count, POLL_INTERVAL) {
+        on_cleanup(move || handle.clear());
+    }
+
+    let load_notifications = move || {
+        set_loading.set(true);
+        spawn_local(async move {
+            match get_notifications(PANEL_LIMIT).await {
+                Ok(response) => {
+                    set_unread.set(response.no_leidas);
+                    set_items.set(response.notificaciones);
+                    set_error.set(None);
+                }
+                Err(e) => set_error.set(Some(e)),
+            }
+            set_loading.set(false);
+        });
+    };
+
+    let toggle_panel = move |_| {
+        let abrir = !open.get_untracked();
+        set_open.set(abrir);
+        if abrir {
+            load_notifications();
+        }
+    };
+
+    // El usuario demo puede ver las notificaciones pero no modificarlas
+    let mark_read = move |id: i32| {
+        if is_demo.get_untracked() {
+            return;
+        }
+        spawn_local(async move {
+            if let Ok(response) = mark_notification_read(id).await {
+                set_unread.set(response.no_leidas);
+                set_items.update(|items| {
+                    if let Some(item) = items.iter_mut().find(|n| n.id == id) {
+                        item.leida = true;
+                    }
+                });
+            }
+        });
+    };
+
+    let mark_all_read = move |_| {
+        spawn_local(async move {
+            if let Ok(response) = mark_all_notifications_read().await {
+                set_unread.set(response.no_leidas);
+                set_items.update(|items| items.iter_mut().for_each(|n| n.leida = true));
+            }
+        });
+    };
+
+    view! {
+        <div class="relative">
+            <button
+                class="relative p-2 text-gray-500 hover:text-gray-700 hover:bg-gray-100 rounded-lg transition-colors"
+                on:click=toggle_panel
+                title="Notificaciones"
+            >
+                <svg class="w-5 h-5" fill="none" stroke="currentColor" viewBox="0 0 24 24">
+                    <path stroke-linecap="round" stroke-linejoin="round" stroke-width="2" d="M15 17h5l-1.405-1.405A2.032 2.032 0 0118 14.158V11a6.002 6.002 0 00-4-5.659V5a2 2 0 10-4 0v.341C7.67 6.165 6 8.388 6 11v3.159c0 .538-.214 1.055-.595 1.436L4 17h5m6 0v1a3 3 0 11-6 0v-1m6 0H9"></path>
+                </svg>
+                {move || if unread.get() > 0 {
+                    let label = if unread.get() > 9 { "9+".to_string() } else { unread.get().to_string() };
+                    view! {
+                        <span class="absolute -top-0.5 -right-0.5 min-w-[1.125rem] h-[1.125rem] px-1 flex items-center justify-center rounded-full bg-red-500 text-white text-[10px] font-semibold">
+                            {label}
+                        </span>
+                    }.into_view()
+                } else {
+                    view! {}.into_view()
+                }}
+            </button>
+
+            {move || if open.get() {
+                view! {
+                    <div class="absolute left-0 top-full mt-2 w-80 bg-white border border-gray-200 rounded-xl shadow-lg z-50">
+                        <div class="flex items-center justify-between px-4 py-3 border-b border-gray-100">
+                            <p class="text-sm font-semibold text-gray-900">"Notificaciones"</p>
+                            {move || if unread.get() > 0 && !is_demo.get() {
+                                view! {
+                                    <button
+                                        class="text-xs font-medium text-primary-600 hover:text-primary-700"
+                                        on:click=mark_all_read
+                                    >
+                                        "Marcar todas como leídas"
+                                    </button>
+                                }.into_view()
+                            } else {
+                                view! {}.into_view()
+                            }}
+                        </div>
+
+                        <div class="max-h-96 overflow-y-auto">
+                            {move || if loading.get() && items.with(|items| items.is_empty()) {
+                                view! {
+                                    <p class="px-4 py-6 text-sm text-center text-gray-500">"Cargando..."</p>
+                                }.into_view()
+                            } else if let Some(e) = error.get() {
+                                view! {
+                                    <p class="px-4 py-6 text-sm text-center text-red-600">{e}</p>
+                                }.into_view()
+                            } else if items.with(|items| items.is_empty()) {
+                                view! {
+                                    <p class="px-4 py-6 text-sm text-center text-gray-500">"No tienes notificaciones"</p>
+                                }.into_view()
+                            } else {
+                                items.get().into_iter().map(|item| {
+                                    let id = item.id;
+                                    let fecha = item.created_at.replace('T', " ").chars().take(16).collect::<String>();
+                                    let class = if item.leida {
+                                        "w-full text-left flex gap-3 px-4 py-3 border-b border-gray-50 hover:bg-gray-50"
+                                    } else {
+                                        "w-full text-left flex gap-3 px-4 py-3 border-b border-gray-50 bg-primary-50/40 hover:bg-gray-50"
+                                    };
+                                    view! {
+                                        <button class=class on:click=move |_| mark_read(id)>
+                                            <span class=format!("mt-1.5 w-2 h-2 flex-shrink-0 rounded-full {}", kind_color(&item.tipo))></span>
+                                            <div class="min-w-0">
+                                                <p class=if item.leida { "text-sm text-gray-700" } else { "text-sm font-semibold text-gray-900" }>
+                                                    {item.titulo}
+                                                </p>
+                                                <p class="text-xs text-gray-500 mt-0.5">{item.mensaje}</p>
+                                                <p class="text-[11px] text-gray-400 mt-1">{fecha}</p>
+                                            </div>
+                                        </button>
+                                    }
+                                }).collect_view()
+                            }}
+                        </div>
+                    </div>
+                }.into_view()
+            } else {
+                view! {}.into_view()
+            }}
+        </div>
+    }
+}
@@ -48,7 +48,10 @@ pub fn ProductListModal(
                         .parse::<f64>()
                         .unwrap_or(0.0)
                 } else {
-                    p.cantidad_total.unwrap_or(0) as f64
+                    p.cantidad_total
+                        .as_deref()
+                        .and_then(|c| c.parse::<f64>().ok())
+                        .unwrap_or(0.0)
                 }
             })
             .collect();
@@ -1,3 +1,4 @@
+use crate::components::NotificationBell;
 use leptos::*;
 
 #[derive(Debug, Clone, PartialEq)]
@@ -61,6 +62,9 @@ pub fn Sidebar(
                             view! {}.into_view()
                         }}
                     </div>
+                    <div class="ml-auto">
+                        <NotificationBell is_demo=is_demo_user />
+                    </div>
                 </div>
             </div>
 
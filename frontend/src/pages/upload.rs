@@ -1,4 +1,11 @@
-use crate::api::tickets::{process_ticket_ocr, ProcessTicketResponse};
+use crate::api::{
+    tickets::{
+        confirm_ticket_draft, discard_ticket_draft, get_ticket_draft, get_ticket_drafts,
+        process_ticket_ocr, subscribe_ticket_events, ProcessTicketResponse, TicketDraftSummary,
+        TicketProgressEvent,
+    },
+    ApiError,
+};
 use crate::components::{Button, ButtonVariant, Card};
 use leptos::*;
 use std::collections::HashSet;
@@ -18,11 +25,17 @@ enum UploadStatus {
 #[derive(Clone, PartialEq)]
 struct FileStatus {
     id: String,
-    file: File,
+    name: String,
+    /// `None` en los borradores recuperados del servidor
+    file: Option<File>,
     status: UploadStatus,
     result: Option<ProcessTicketResponse>,
     error: Option<String>,
     preview_url: Option<String>,
+    /// Fase actual segun los eventos del servidor
+    stage: Option<String>,
+    /// Avisos del OCR recibidos por eventos
+    warnings: Vec<String>,
 }
 
 #[component]
@@ -34,6 +47,7 @@ pub fn Upload() -> impl IntoView {
     let (is_demo, set_is_demo) = create_signal(false);
     let (upload_error, set_upload_error) = create_signal(None::<String>);
     let (review_modal_open, set_review_modal_open) = create_signal(false);
+    let (pending_drafts, set_pending_drafts) = create_signal(Vec::<TicketDraftSummary>::new());
 
     let review_files = create_memo(move |_| {
         files
@@ -57,10 +71,65 @@ pub fn Upload() -> impl IntoView {
         }
     });
 
+    // Progreso real de cada ticket publicado por el servidor (SSE)
+    let apply_progress = move |event: TicketProgressEvent| {
+        set_files.update(|files| {
+            let target = files.iter_mut().find(|f| {
+                f.id == event.ticket_id
+                    || f.result
+                        .as_ref()
+                        .and_then(|r| r.draft.as_ref())
+                        .map(|d| d.id == event.ticket_id)
+                        .unwrap_or(false)
+            });
+
+            if let Some(f) = target {
+                f.stage = stage_label(&event.stage).map(str::to_string);
+                if !event.warnings.is_empty() {
+                    f.warnings = event.warnings;
+                }
+            }
+        });
+    };
+
+    // Borradores que quedaron sin confirmar en otra sesion
+    spawn_local(async move {
+        match get_ticket_drafts().await {
+            Ok(drafts) => set_pending_drafts.set(drafts),
+            Err(err) => log::warn!("No se pudieron obtener los borradores: {}", err),
+        }
+    });
+
+    // El servidor descarto eventos: las etapas mostradas pueden estar
+    // desfasadas, se vuelve al estado generico y se recargan los borradores
+    let resync_progress = move || {
+        set_files.update(|files| {
+            for f in files.iter_mut() {
+                f.stage = None;
+            }
+        });
+        spawn_local(async move {
+            match get_ticket_drafts().await {
+                Ok(drafts) => set_pending_drafts.set(drafts),
+                Err(err) => log::warn!("No se pudieron obtener los borradores: {}", err),
+            }
+        });
+    };
+
+    match subscribe_ticket_events(apply_progress, resync_progress) {
+        Ok(subscription) => on_cleanup(move || drop(subscription)),
+        Err(err) => log::warn!("Sin eventos de progreso: {}", err),
+    }
+
     let file_input_ref = create_node_ref::<leptos::html::Input>();
 
     let build_file_status = |file: File| {
-        let id = format!("{}-{}", file.name(), js_sys::Date::now());
+        // Tambien es el ticket_id de los eventos de progreso
+        let id = format!(
+            "ticket_{}_{}",
+            js_sys::Date::now() as u64,
+            (js_sys::Math::random() * 1_000_000_000.0) as u64
+        );
         let mut preview_url = None;
         if file.type_().starts_with("image/") {
             preview_url = create_object_url(&file).ok();
@@ -68,11 +137,14 @@ pub fn Upload() -> impl IntoView {
 
         FileStatus {
             id,
-            file,
+            name: file.name(),
+            file: Some(file),
             status: UploadStatus::Pending,
             result: None,
             error: None,
             preview_url,
+            stage: None,
+            warnings: Vec::new(),
         }
     };
 
@@ -185,6 +257,11 @@ pub fn Upload() -> impl IntoView {
 
         let id = file_status.id.clone();
         let file_clone = file_status.file.clone();
+        // Si el OCR dejo un borrador en el servidor se confirma sin volver a subir el archivo
+        let draft_result = file_status
+            .result
+            .clone()
+            .filter(|r| ingest && r.draft.is_some());
 
         spawn_local(async move {
             // Update status to processing or ingesting
@@ -196,10 +273,37 @@ pub fn Upload() -> impl IntoView {
                         UploadStatus::Processing
                     };
                     f.error = None;
+                    f.stage = None;
                 }
             });
 
-            match process_ticket_ocr(file_clone, ingest).await {
+            let from_draft = draft_result.is_some();
+            let outcome = match draft_result {
+                Some(previous) => {
+                    let draft_id = previous
+                        .draft
+                        .as_ref()
+                        .map(|d| d.id.clone())
+                        .unwrap_or_default();
+                    confirm_ticket_draft(&draft_id)
+                        .await
+                        .map(|ingestion| ProcessTicketResponse {
+                            ingestion: Some(ingestion),
+                            draft: None,
+                            ..previous
+                        })
+                }
+                None => match file_clone {
+                    Some(file) => process_ticket_ocr(file, id.clone(), ingest)
+                        .await
+                        .map_err(ApiError::local),
+                    None => Err(ApiError::local(
+                        "El borrador ha caducado; vuelve a subir el ticket",
+                    )),
+                },
+            };
+
+            match outcome {
                 Ok(response) => {
                     set_files.update(|files| {
                         if let Some(f) = files.iter_mut().find(|f| f.id == id) {
@@ -209,21 +313,31 @@ pub fn Upload() -> impl IntoView {
                                 UploadStatus::Review
                             };
                             f.result = Some(response.clone());
+                            f.stage = None;
                         }
                     });
 
                     refresh_review_modal();
                 }
                 Err(err) => {
-                    let error_msg = if err.contains("ya existe") || err.contains("Conflict") {
-                        format!("Duplicado: {}", err)
+                    // Borrador caducado o ya confirmado: el reintento vuelve a pasar por el OCR
+                    let draft_gone = from_draft && err.code == "not_found";
+                    let error_msg = if err.code == "duplicate_purchase"
+                        || err.error.contains("ya existe")
+                        || err.error.contains("Conflict")
+                    {
+                        format!("Duplicado: {}", err.error)
                     } else {
-                        err
+                        err.error
                     };
 
                     set_files.update(|files| {
                         if let Some(f) = files.iter_mut().find(|f| f.id == id) {
                             f.status = UploadStatus::Error;
+                            f.stage = None;
+                            if draft_gone {
+                                f.result = None;
+                            }
                             f.error = Some(error_msg);
                         }
                     });
@@ -273,6 +387,20 @@ pub fn Upload() -> impl IntoView {
         set_files.update(|files| {
             if let Some(index) = files.iter().position(|f| f.id == id) {
                 let file = &files[index];
+                // Descartar un ticket en revision descarta tambien su borrador
+                let draft_id = file
+                    .result
+                    .as_ref()
+                    .and_then(|r| r.draft.as_ref())
+                    .filter(|_| file.status == UploadStatus::Review)
+                    .map(|d| d.id.clone());
+                if let Some(draft_id) = draft_id {
+                    spawn_local(async move {
+                        if let Err(err) = discard_ticket_draft(&draft_id).await {
+                            log::warn!("No se pudo descartar el borrador: {}", err);
+                        }
+                    });
+                }
                 if let Some(url) = &file.preview_url {
                     let _ = web_sys::Url::revoke_object_url(url);
                 }
@@ -288,6 +416,53 @@ pub fn Upload() -> impl IntoView {
         check_and_reset_batch();
     };
 
+    // Vuelve a poner un borrador pendiente en revision, sin repetir el OCR
+    let restore_draft = move |draft_id: String| {
+        spawn_local(async move {
+            match get_ticket_draft(&draft_id).await {
+                Ok(detail) => {
+                    let draft = TicketDraftSummary {
+                        id: detail.id.clone(),
+                        nombre_archivo: detail.nombre_archivo.clone(),
+                        expira_en: detail.expira_en,
+                    };
+                    set_files.update(|files| {
+                        files.push(FileStatus {
+                            id: detail.id,
+                            name: detail.nombre_archivo,
+                            file: None,
+                            status: UploadStatus::Review,
+                            result: Some(ProcessTicketResponse {
+                                ocr: detail.ocr,
+                                ingestion: None,
+                                draft: Some(draft),
+                            }),
+                            error: None,
+                            preview_url: None,
+                            stage: None,
+                            warnings: Vec::new(),
+                        });
+                    });
+                    set_upload_error.set(None);
+                    refresh_review_modal();
+                }
+                Err(err) => set_upload_error.set(Some(err)),
+            }
+            set_pending_drafts.update(|drafts| drafts.retain(|d| d.id != draft_id));
+        });
+    };
+
+    let discard_draft = move |draft_id: String| {
+        spawn_local(async move {
+            match discard_ticket_draft(&draft_id).await {
+                Ok(()) => {
+                    set_pending_drafts.update(|drafts| drafts.retain(|d| d.id != draft_id));
+                }
+                Err(err) => set_upload_error.set(Some(err)),
+            }
+        });
+    };
+
     let clear_completed = move |_| {
         // Collect IDs of completed files before removing them
         let completed_ids: Vec<String> = files.with(|files| {
@@ -364,7 +539,7 @@ pub fn Upload() -> impl IntoView {
                         <input
                             node_ref=file_input_ref
                             type="file"
-                            accept="image/*,.pdf"
+                            accept="image/jpeg,image/png,image/webp,image/heic,image/heif,.heic,.heif,.pdf"
                             class="hidden"
                             multiple=true
                             on:change=handle_file_select
@@ -393,6 +568,50 @@ pub fn Upload() -> impl IntoView {
                         })}
                     </div>
 
+                    // Borradores pendientes de otra sesion
+                    {move || if !pending_drafts.with(|d| d.is_empty()) {
+                        view! {
+                            <div class="p-4 bg-amber-50 border border-amber-200 rounded-lg space-y-3">
+                                <p class="text-sm font-medium text-amber-800">
+                                    "Tienes tickets procesados pendientes de confirmar"
+                                </p>
+                                <For
+                                    each=move || pending_drafts.get()
+                                    key=|d| d.id.clone()
+                                    children=move |draft| {
+                                        let id_restore = draft.id.clone();
+                                        let id_discard = draft.id.clone();
+                                        let expira = draft.expira_en.replace('T', " ").chars().take(16).collect::<String>();
+                                        view! {
+                                            <div class="flex items-center justify-between gap-4">
+                                                <div class="min-w-0">
+                                                    <p class="text-sm text-gray-900 truncate">{draft.nombre_archivo}</p>
+                                                    <p class="text-xs text-gray-500">{format!("Caduca el {}", expira)}</p>
+                                                </div>
+                                                <div class="flex gap-3 flex-shrink-0">
+                                                    <button
+                                                        class="text-sm font-medium text-primary-600 hover:text-primary-700"
+                                                        on:click=move |_| restore_draft(id_restore.clone())
+                                                    >
+                                                        "Recuperar"
+                                                    </button>
+                                                    <button
+                                                        class="text-sm text-gray-500 hover:text-red-600"
+                                                        on:click=move |_| discard_draft(id_discard.clone())
+                                                    >
+                                                        "Descartar"
+                                                    </button>
+                                                </div>
+                                            </div>
+                                        }
+                                    }
+                                />
+                            </div>
+                        }.into_view()
+                    } else {
+                        view! { <div></div> }.into_view()
+                    }}
+
                     // File List
                     {move || if !files.get().is_empty() {
                         view! {
@@ -424,6 +643,15 @@ pub fn Upload() -> impl IntoView {
                                             let status = file.status.clone();
                                             let error = file.error.clone();
                                             let result = file.result.clone();
+                                            let live_id = file.id.clone();
+                                            let live = create_memo(move |_| {
+                                                files.with(|fs| {
+                                                    fs.iter()
+                                                        .find(|f| f.id == live_id)
+                                                        .map(|f| (f.stage.clone(), f.warnings.clone()))
+                                                        .unwrap_or_default()
+                                                })
+                                            });
 
                                             view! {
                                                 <div class="flex items-center p-3 bg-gray-50 rounded-lg border border-gray-200 group hover:border-gray-300 transition-colors">
@@ -443,14 +671,14 @@ pub fn Upload() -> impl IntoView {
                                                     // Info
                                                     <div class="flex-grow min-w-0 mr-4">
                                                         <p class="text-sm font-medium text-gray-900 truncate">
-                                                            {file.file.name()}
+                                                            {file.name.clone()}
                                                         </p>
                                                         <div class="text-xs mt-1">
                                                             {match status {
                                                                 UploadStatus::Pending => view! { <span class="text-gray-500">"Pendiente"</span> }.into_view(),
-                                                                UploadStatus::Processing => view! { <span class="text-blue-600 animate-pulse">"Procesando..."</span> }.into_view(),
+                                                                UploadStatus::Processing => view! { <span class="text-blue-600 animate-pulse">{move || live.get().0.unwrap_or_else(|| "Procesando...".to_string())}</span> }.into_view(),
                                                                 UploadStatus::Review => view! { <span class="text-amber-600 font-medium">"Listo para confirmar"</span> }.into_view(),
-                                                                UploadStatus::Ingesting => view! { <span class="text-blue-600 animate-pulse">"Guardando..."</span> }.into_view(),
+                                                                UploadStatus::Ingesting => view! { <span class="text-blue-600 animate-pulse">{move || live.get().0.unwrap_or_else(|| "Guardando...".to_string())}</span> }.into_view(),
                                                                 UploadStatus::Success => {
                                                                     if let Some(ref res) = result {
                                                                         if let Some(ingestion) = &res.ingestion {
@@ -469,16 +697,27 @@ pub fn Upload() -> impl IntoView {
                                                                 UploadStatus::Error => view! { <span class="text-red-600">{error.unwrap_or_default()}</span> }.into_view(),
                                                             }}
                                                         </div>
-                                                        {match &result {
-                                                            Some(res) if !res.ocr.warnings.is_empty() => {
-                                                                view! {
-                                                                    <p class="text-xs text-amber-600 mt-1">
-                                                                        {format!("Avisos OCR: {}", res.ocr.warnings.join(" | "))}
-                                                                    </p>
-                                                                }.into_view()
+                                                        {
+                                                            let result_warnings = result
+                                                                .as_ref()
+                                                                .map(|res| res.ocr.warnings.clone())
+                                                                .unwrap_or_default();
+                                                            move || {
+                                                                let mut warnings = live.get().1;
+                                                                if warnings.is_empty() {
+                                                                    warnings = result_warnings.clone();
+                                                                }
+                                                                if warnings.is_empty() {
+                                                                    view! { <div></div> }.into_view()
+                                                                } else {
+                                                                    view! {
+                                                                        <p class="text-xs text-amber-600 mt-1">
+                                                                            {format!("Avisos OCR: {}", warnings.join(" | "))}
+                                                                        </p>
+                                                                    }.into_view()
+                                                                }
                                                             }
-                                                            _ => view! { <div></div> }.into_view(),
-                                                        }}
+                                                        }
                                                     </div>
 
                                                     // Status Icon / Action
@@ -703,7 +942,7 @@ pub fn Upload() -> impl IntoView {
                                                         <div class="flex-1 space-y-3">
                                                             <div class="flex items-start justify-between gap-2 flex-wrap">
                                                                 <div>
-                                                                    <p class="text-sm font-semibold text-gray-900">{file.file.name()}</p>
+                                                                    <p class="text-sm font-semibold text-gray-900">{file.name.clone()}</p>
                                                                     <p class="text-xs text-gray-500">{format!("{} productos detectados", productos.len())}</p>
                                                                 </div>
                                                                 {match summary.as_ref().and_then(|s| s.processing_profile.clone()) {
@@ -821,6 +1060,19 @@ pub fn Upload() -> impl IntoView {
     }
 }
 
+/// Texto para cada fase que publica el servidor; `None` en las fases finales
+fn stage_label(stage: &str) -> Option<&'static str> {
+    match stage {
+        "ocr" => Some("Leyendo ticket (OCR)..."),
+        "ocr_completed" => Some("Ticket leido"),
+        "validating" => Some("Validando datos..."),
+        "duplicate_check" => Some("Comprobando duplicados..."),
+        "committing" => Some("Guardando..."),
+        "retrying" => Some("Reintentando..."),
+        _ => None,
+    }
+}
+
 fn create_object_url(file: &File) -> Result<String, String> {
     web_sys::Url::create_object_url_with_blob(file)
         .map_err(|_| "Error al crear preview".to_string())
@@ -828,7 +1080,15 @@ fn create_object_url(file: &File) -> Result<String, String> {
 
 fn is_allowed_file(file: &File) -> bool {
     let mime = file.type_();
-    if mime.starts_with("image/") || mime == "application/pdf" {
+    let allowed_mimes = [
+        "application/pdf",
+        "image/jpeg",
+        "image/png",
+        "image/webp",
+        "image/heic",
+        "image/heif",
+    ];
+    if allowed_mimes.contains(&mime.as_str()) {
         return true;
     }
 